@@ -4,44 +4,633 @@ use winreg::enums::*;
 ///! Reads settings from the Windows registry
 use winreg::RegKey;
 
+use std::path::Path;
+
+use super::{CoverStrategy, CoverVerification, NaturalSortMode, SortKey};
+use crate::image_processor::thumbnail::{ResizeFilter, DEFAULT_THUMBNAIL_SIZE};
+
 const CONFIG_KEY_PATH: &str = "Software\\CBXShell-rs\\{9E6ECB90-5A61-42BD-B851-D3297D9C7F39}";
-const NO_SORT_VALUE: &str = "NoSort";
+pub const ENABLED_VALUE: &str = "Enabled";
+pub const NO_SORT_VALUE: &str = "NoSort";
+pub const MAX_ARCHIVE_SIZE_VALUE: &str = "MaxArchiveSizeMB";
+pub const COVER_STRATEGY_VALUE: &str = "CoverStrategy";
+pub const COVER_STRATEGY_BY_EXTENSION_VALUE: &str = "CoverStrategyByExtension";
+pub const FORCE_THUMBNAIL_SIZE_VALUE: &str = "ForceThumbnailSize";
+pub const MAX_THUMBNAIL_DIM_VALUE: &str = "MaxThumbnailDim";
+pub const DECODE_TIMEOUT_VALUE: &str = "DecodeTimeoutMs";
+pub const VERIFY_FIRST_IMAGE_VALUE: &str = "VerifyFirstImage";
+pub const NEXT_IMAGE_ON_MISSING_CODEC_VALUE: &str = "NextImageOnMissingCodec";
+pub const EVENT_LOG_VALUE: &str = "EventLog";
+pub const COVER_SKIP_STEMS_VALUE: &str = "CoverSkipStems";
+pub const GDI_HALFTONE_VALUE: &str = "GdiHalftone";
+pub const WARM_CODECS_VALUE: &str = "WarmCodecs";
+pub const EMBEDDED_PREVIEW_VALUE: &str = "EmbeddedPreview";
+pub const EMBEDDED_PREVIEW_NAMES_VALUE: &str = "EmbeddedPreviewNames";
+const PASSWORD_VALUE: &str = "Password";
+pub const NATURAL_SORT_MODE_VALUE: &str = "NaturalSortMode";
+pub const ALLOW_UPSCALE_TO_VALUE: &str = "AllowUpscaleTo";
+pub const LENIENT_DECODE_VALUE: &str = "LenientDecode";
+pub const MAX_NON_IMAGE_SCAN_VALUE: &str = "MaxNonImageScan";
+pub const SORT_BY_VALUE: &str = "SortBy";
+pub const GRAYSCALE_VALUE: &str = "Grayscale";
+pub const BOOK_EFFECT_VALUE: &str = "BookEffect";
+
+/// Per-folder password file, checked next to the archive before the registry
+pub const PASSWORD_FILE_NAME: &str = ".cbxpass";
+
+/// Built-in per-extension cover strategies, overridable per extension with
+/// `CoverStrategyByExtension`
+pub const DEFAULT_EXTENSION_COVER_STRATEGIES: &[(&str, CoverStrategy)] =
+    &[("epub", CoverStrategy::Opf)];
+
+/// Pre-made cover thumbnails some tools embed in archives, in order of preference
+pub const DEFAULT_EMBEDDED_PREVIEW_NAMES: &[&str] =
+    &["__cover.jpg", "__cover.png", "preview.jpg", "preview.png"];
+
+/// Entry name used when CBXManager embeds a preview into an archive
+pub const EMBEDDED_PREVIEW_FILE_NAME: &str = "__cover.jpg";
+
+/// Decode watchdog timeout used when DecodeTimeoutMs is missing
+pub(crate) const DEFAULT_DECODE_TIMEOUT_MS: u32 = 5000;
+
+/// Non-image files scanned before giving up, used when MaxNonImageScan is missing
+pub(crate) const DEFAULT_MAX_NON_IMAGE_SCAN: u32 = 500;
+
+/// Subkey holding per-archive cover overrides (value name = archive path)
+const COVER_OVERRIDES_SUBKEY: &str = "CoverOverrides";
+
+/// Source of DWORD configuration values
+///
+/// Production code reads the registry through `ConfigKey`; tests
+/// use `MemoryConfigSource` so they don't depend on (or mutate) HKCU.
+pub trait ConfigSource {
+    /// Read a DWORD value by name, `None` if the key or value is missing
+    fn get_dword(&self, name: &str) -> Option<u32>;
+
+    /// Read a string value by name, `None` if the key or value is missing
+    fn get_string(&self, _name: &str) -> Option<String> {
+        None
+    }
+
+    /// Read a REG_BINARY value by name, `None` if missing or of another type
+    fn get_binary(&self, _name: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Destination for configuration values
+///
+/// `Settings::save` writes through this, so tests can round-trip settings
+/// without touching HKCU.
+pub trait ConfigSink {
+    /// Write a DWORD value by name
+    fn set_dword(&mut self, name: &str, value: u32) -> std::io::Result<()>;
+
+    /// Write a string value by name
+    fn set_string(&mut self, name: &str, value: &str) -> std::io::Result<()>;
+}
+
+/// HKCU\Software\CBXShell-rs\{GUID}, opened once for many values
+pub struct ConfigKey(Option<RegKey>);
+
+impl ConfigKey {
+    /// Open the key for reading; a missing key reads as all values missing
+    pub fn open() -> Self {
+        Self(
+            RegKey::predef(HKEY_CURRENT_USER)
+                .open_subkey(CONFIG_KEY_PATH)
+                .ok(),
+        )
+    }
+
+    /// Open the key for writing, creating it if needed
+    pub fn create() -> std::io::Result<Self> {
+        let (key, _) = RegKey::predef(HKEY_CURRENT_USER).create_subkey(CONFIG_KEY_PATH)?;
+        Ok(Self(Some(key)))
+    }
+
+    /// The key for writing; one opened read-only from a missing key has none
+    fn writable_key(&self) -> std::io::Result<&RegKey> {
+        self.0.as_ref().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Configuration key is not open",
+            )
+        })
+    }
+}
+
+impl ConfigSource for ConfigKey {
+    fn get_dword(&self, name: &str) -> Option<u32> {
+        self.0.as_ref()?.get_value::<u32, _>(name).ok()
+    }
+
+    fn get_string(&self, name: &str) -> Option<String> {
+        self.0.as_ref()?.get_value::<String, _>(name).ok()
+    }
+
+    fn get_binary(&self, name: &str) -> Option<Vec<u8>> {
+        let raw = self.0.as_ref()?.get_raw_value(name).ok()?;
+        (raw.vtype == REG_BINARY).then_some(raw.bytes)
+    }
+}
+
+impl ConfigSink for ConfigKey {
+    fn set_dword(&mut self, name: &str, value: u32) -> std::io::Result<()> {
+        self.writable_key()?.set_value(name, &value)
+    }
+
+    fn set_string(&mut self, name: &str, value: &str) -> std::io::Result<()> {
+        self.writable_key()?.set_value(name, &value.to_string())
+    }
+}
+
+/// In-memory configuration for tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryConfigSource {
+    values: std::collections::HashMap<String, u32>,
+    strings: std::collections::HashMap<String, String>,
+}
+
+#[cfg(test)]
+impl MemoryConfigSource {
+    /// Builder-style setter
+    pub fn with(mut self, name: &str, value: u32) -> Self {
+        self.values.insert(name.to_string(), value);
+        self
+    }
+
+    /// Builder-style setter for string values
+    pub fn with_string(mut self, name: &str, value: &str) -> Self {
+        self.strings.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+impl ConfigSource for MemoryConfigSource {
+    fn get_dword(&self, name: &str) -> Option<u32> {
+        self.values.get(name).copied()
+    }
+
+    fn get_string(&self, name: &str) -> Option<String> {
+        self.strings.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+impl ConfigSink for MemoryConfigSource {
+    fn set_dword(&mut self, name: &str, value: u32) -> std::io::Result<()> {
+        self.values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn set_string(&mut self, name: &str, value: &str) -> std::io::Result<()> {
+        self.strings.insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+/// Read the master thumbnail switch from a configuration source
+///
+/// Lets users pause thumbnailing (e.g. during mass copies) without
+/// unregistering the DLL.
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\Enabled
+/// - Value 1 or missing = thumbnails are generated (default)
+/// - Value 0 = archives are not touched; Explorer shows its default icons
+pub fn thumbnails_enabled_from(source: &dyn ConfigSource) -> bool {
+    source
+        .get_dword(ENABLED_VALUE)
+        .map(|value| value != 0)
+        .unwrap_or(true)
+}
 
 /// Read the sorting preference from the registry
 ///
+/// Registry-backed form of `should_sort_images_from`, the read counterpart of
+/// `set_should_sort_images`.
+#[allow(dead_code)] // Runtime code reads `Settings::sort`; kept for callers outside a `Settings` load
+pub fn should_sort_images() -> bool {
+    should_sort_images_from(&ConfigKey::open())
+}
+
+/// Read the sorting preference from a configuration source
+///
 /// Returns `true` if images should be sorted alphabetically.
 /// Returns `false` if the first image encountered should be used (default for performance).
 ///
 /// Registry location: HKCU\Software\CBXShell-rs\{GUID}\NoSort
 /// - Value 0 = sort enabled (true)
 /// - Value 1 or missing = sort disabled (false, default)
-pub fn should_sort_images() -> bool {
-    match read_no_sort_setting() {
-        Ok(no_sort) => !no_sort, // Invert: NoSort=0 means sort=true
-        Err(_) => {
+pub fn should_sort_images_from(source: &dyn ConfigSource) -> bool {
+    match source.get_dword(NO_SORT_VALUE) {
+        Some(no_sort) => no_sort == 0, // Invert: NoSort=0 means sort=true
+        None => {
             // Default to NOT sorting for better performance with large archives
-            tracing::debug!("Failed to read NoSort setting, defaulting to unsorted mode (fast)");
+            tracing::debug!("NoSort setting missing, defaulting to unsorted mode (fast)");
             false
         }
     }
 }
 
-/// Read the NoSort registry value
+/// Read the archive size limit (in megabytes) from a configuration source
 ///
-/// Returns `Ok(true)` if NoSort=1 or missing (sorting disabled, default)
-/// Returns `Ok(false)` if NoSort=0 (sorting enabled)
-fn read_no_sort_setting() -> Result<bool, std::io::Error> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+/// Archives larger than this are skipped entirely and Explorer falls back to the
+/// default icon. This is a whole-archive limit, separate from the per-entry
+/// `MAX_ENTRY_SIZE` guard.
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\MaxArchiveSizeMB
+/// - Value 0 or missing = unlimited (default)
+/// - Value N = skip archives larger than N MB
+pub fn max_archive_size_mb_from(source: &dyn ConfigSource) -> u32 {
+    source.get_dword(MAX_ARCHIVE_SIZE_VALUE).unwrap_or(0) // Missing key or value = unlimited
+}
+
+/// Decide whether an archive of `size_bytes` exceeds the configured limit
+///
+/// A limit of 0 means unlimited.
+pub fn exceeds_max_archive_size(size_bytes: u64, max_mb: u32) -> bool {
+    max_mb != 0 && size_bytes > u64::from(max_mb) * 1024 * 1024
+}
+
+/// Read the forced thumbnail size (in pixels) from a configuration source
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\ForceThumbnailSize
+/// - Value 0 or missing = honor the size requested by Explorer (default)
+/// - Value N = always render N x N thumbnails
+pub fn force_thumbnail_size_from(source: &dyn ConfigSource) -> u32 {
+    source.get_dword(FORCE_THUMBNAIL_SIZE_VALUE).unwrap_or(0) // Missing key or value = honor cx
+}
+
+/// Read the upper bound on thumbnail size (in pixels) from a configuration source
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\MaxThumbnailDim
+/// - Value 0 or missing = no cap (default)
+/// - Value N = render at most N x N, even when Explorer asks for more
+///
+/// Unlike `ForceThumbnailSize`, the cap never raises the size.
+pub fn max_thumbnail_dim_from(source: &dyn ConfigSource) -> u32 {
+    source.get_dword(MAX_THUMBNAIL_DIM_VALUE).unwrap_or(0) // Missing key or value = uncapped
+}
+
+/// Lower `size` to `cap`, leaving it unchanged when `cap` is 0
+pub fn cap_thumbnail_size(size: u32, cap: u32) -> u32 {
+    if cap == 0 {
+        size
+    } else {
+        size.min(cap)
+    }
+}
+
+/// Choose the thumbnail size from Explorer's `cx` and the forced size
+///
+/// A nonzero `forced` size wins; otherwise `cx` is used, falling back to
+/// `DEFAULT_THUMBNAIL_SIZE` (256) when Explorer passes 0.
+pub fn select_thumbnail_size(cx: u32, forced: u32) -> u32 {
+    if forced != 0 {
+        forced
+    } else if cx == 0 {
+        DEFAULT_THUMBNAIL_SIZE
+    } else {
+        cx
+    }
+}
+
+/// Read the image decode timeout (in milliseconds) from a configuration source
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\DecodeTimeoutMs
+/// - Value missing = 5000 ms (default)
+/// - Value 0 = no timeout (decode on the calling thread)
+/// - Value N = abandon decodes that take longer than N ms
+pub fn decode_timeout_ms_from(source: &dyn ConfigSource) -> u32 {
+    source
+        .get_dword(DECODE_TIMEOUT_VALUE)
+        .unwrap_or(DEFAULT_DECODE_TIMEOUT_MS)
+}
+
+/// Read how the first-image fast path should verify candidates
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\VerifyFirstImage
+/// - Value 0 or missing = use the first image by name (default, fastest)
+/// - Value 1 = extract and check magic bytes, skipping misnamed non-images
+/// - Value 2 = also read the image dimensions, skipping corrupt images
+pub fn verify_first_image_from(source: &dyn ConfigSource) -> CoverVerification {
+    source
+        .get_dword(VERIFY_FIRST_IMAGE_VALUE)
+        .map(CoverVerification::from_registry_value)
+        .unwrap_or_default()
+}
+
+/// Read how many non-image files the unsorted first-image scan passes over
+///
+/// Archives that match a registered extension but hold no comic (a `.7z` of
+/// DLLs, a renamed installer) would otherwise be scanned to the end.
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\MaxNonImageScan
+/// - Value missing = 500 (default)
+/// - Value 0 = scan every entry
+/// - Value N = report no images after N non-image files without an image
+pub fn max_non_image_scan_from(source: &dyn ConfigSource) -> u32 {
+    source
+        .get_dword(MAX_NON_IMAGE_SCAN_VALUE)
+        .unwrap_or(DEFAULT_MAX_NON_IMAGE_SCAN)
+}
+
+/// Images tried after a cover that needs a missing codec
+pub const MISSING_CODEC_FALLBACK_LIMIT: usize = 3;
+
+/// Read whether a cover needing a missing codec falls back to later pages
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\NextImageOnMissingCodec
+/// - Value 0 or missing = fail the thumbnail (default)
+/// - Value 1 = try up to `MISSING_CODEC_FALLBACK_LIMIT` other images in
+///   reading order (e.g. JPEG pages after an AVIF cover)
+pub fn next_image_on_missing_codec_from(source: &dyn ConfigSource) -> bool {
+    source
+        .get_dword(NEXT_IMAGE_ON_MISSING_CODEC_VALUE)
+        .map(|value| value != 0)
+        .unwrap_or(false)
+}
+
+/// Read whether failures are reported to the Windows Event Log
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\EventLog
+/// - Value 0 or missing = no event log entries (default)
+/// - Value 1 = write a warning event for every failed thumbnail
+pub fn event_log_enabled_from(source: &dyn ConfigSource) -> bool {
+    source
+        .get_dword(EVENT_LOG_VALUE)
+        .map(|value| value != 0)
+        .unwrap_or(false)
+}
+
+/// Read whether truncated covers are rendered partially
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\LenientDecode
+/// - Value 0 or missing = a cover that fails to decode gets no thumbnail (default)
+/// - Value 1 = a truncated JPEG/PNG (e.g. an interrupted download) shows the
+///   rows that could be decoded
+pub fn lenient_decode_from(source: &dyn ConfigSource) -> bool {
+    source
+        .get_dword(LENIENT_DECODE_VALUE)
+        .map(|value| value != 0)
+        .unwrap_or(false)
+}
+
+/// Read whether grayscale covers are resized in a single luma channel
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\Grayscale
+/// - Value 0 or missing = every cover is resized as RGBA (default)
+/// - Value 1 = black-and-white scans keep a chroma-free gray ramp
+pub fn grayscale_from(source: &dyn ConfigSource) -> bool {
+    source
+        .get_dword(GRAYSCALE_VALUE)
+        .map(|value| value != 0)
+        .unwrap_or(false)
+}
+
+/// Read whether thumbnails are framed like a physical book
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\BookEffect
+/// - Value 0 or missing = the cover fills the thumbnail (default)
+/// - Value 1 = a border and drop shadow, returned to Explorer with alpha
+pub fn book_effect_from(source: &dyn ConfigSource) -> bool {
+    source
+        .get_dword(BOOK_EFFECT_VALUE)
+        .map(|value| value != 0)
+        .unwrap_or(false)
+}
+
+/// Read the filter used to resize thumbnails
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\GdiHalftone
+/// - Value 0 or missing = Bilinear via fast_image_resize (default)
+/// - Value 1 = GDI StretchBlt in HALFTONE mode, identical to the C++ version (Windows only)
+pub fn resize_filter_from(source: &dyn ConfigSource) -> ResizeFilter {
+    let gdi_halftone = source
+        .get_dword(GDI_HALFTONE_VALUE)
+        .is_some_and(|value| value != 0);
+    if gdi_halftone {
+        ResizeFilter::GdiHalftone
+    } else {
+        ResizeFilter::Triangle
+    }
+}
+
+/// Read whether OS image codecs are warmed up on the first thumbnail request
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\WarmCodecs
+/// - Value 0 or missing = codecs load on first use (default)
+/// - Value 1 = load HEIF/AVIF and WebP decoders on a background thread and log availability
+pub fn warm_codecs_enabled_from(source: &dyn ConfigSource) -> bool {
+    source
+        .get_dword(WARM_CODECS_VALUE)
+        .map(|value| value != 0)
+        .unwrap_or(false)
+}
+
+/// Read the intro/TOC page names skipped during cover selection
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\CoverSkipStems
+/// - REG_SZ list separated by `;` or `,`, e.g. `intro;toc;credits;blank`
+/// - Missing or empty = nothing is skipped (default)
+pub fn cover_skip_stems_from(source: &dyn ConfigSource) -> Vec<String> {
+    source
+        .get_string(COVER_SKIP_STEMS_VALUE)
+        .map(|list| split_name_list(&list))
+        .unwrap_or_default()
+}
+
+/// Read the embedded preview file names checked before cover selection
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\EmbeddedPreview
+/// - Value 0 or missing = no preview lookup (default)
+/// - Value 1 = use an embedded preview image when the archive has one
+///
+/// `EmbeddedPreviewNames` (REG_SZ, `;` or `,` separated) replaces
+/// `DEFAULT_EMBEDDED_PREVIEW_NAMES`. Empty when the lookup is disabled.
+pub fn embedded_preview_names_from(source: &dyn ConfigSource) -> Vec<String> {
+    if source.get_dword(EMBEDDED_PREVIEW_VALUE).unwrap_or(0) == 0 {
+        return Vec::new();
+    }
+
+    let names = source
+        .get_string(EMBEDDED_PREVIEW_NAMES_VALUE)
+        .map(|list| split_name_list(&list))
+        .unwrap_or_default();
+    if names.is_empty() {
+        DEFAULT_EMBEDDED_PREVIEW_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    } else {
+        names
+    }
+}
 
-    match hkcu.open_subkey(CONFIG_KEY_PATH) {
-        Ok(key) => {
-            match key.get_value::<u32, _>(NO_SORT_VALUE) {
-                Ok(value) => Ok(value != 0), // NonZero = true (don't sort)
-                Err(_) => Ok(true),          // Missing value = true (don't sort, for performance)
-            }
+/// Split a `;` or `,` separated REG_SZ list, dropping empty items
+fn split_name_list(list: &str) -> Vec<String> {
+    list.split([';', ','])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Pick the strategy for `path` from a `CoverStrategyByExtension` list,
+/// the built-in extension defaults and then `strategy`
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\CoverStrategyByExtension
+/// - REG_SZ list of `extension=strategy` pairs separated by `;` or `,`,
+///   e.g. `cbz=first;zip=largest;epub=opf`
+/// - Strategies: `first`, `largest`, `portrait`, `opf` or a `CoverStrategy` value
+///
+/// Extensions not listed use `DEFAULT_EXTENSION_COVER_STRATEGIES`, then:
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\CoverStrategy
+/// - Value 0 or missing = first image (default, honours NoSort)
+/// - Value 1 = largest image by uncompressed size
+/// - Value 2 = first portrait image (passes over landscape banners)
+/// - Value 3 = cover declared by an EPUB package document (OPF)
+pub fn resolve_cover_strategy(
+    by_extension: &str,
+    strategy: CoverStrategy,
+    path: Option<&Path>,
+) -> CoverStrategy {
+    let extension = path
+        .and_then(|p| p.extension())
+        .and_then(|ext| ext.to_str());
+    if let Some(extension) = extension {
+        let configured = extension_strategy(by_extension, extension);
+        let built_in = DEFAULT_EXTENSION_COVER_STRATEGIES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+            .map(|(_, strategy)| *strategy);
+        if let Some(strategy) = configured.or(built_in) {
+            return strategy;
         }
-        Err(_) => Ok(true), // Missing key = true (don't sort, for performance)
     }
+
+    strategy
+}
+
+/// Strategy for `extension` in a `CoverStrategyByExtension` list
+///
+/// Pairs with an unknown strategy are ignored; a leading `.` on the extension
+/// is allowed.
+fn extension_strategy(map: &str, extension: &str) -> Option<CoverStrategy> {
+    map.split([';', ','])
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(ext, _)| {
+            ext.trim()
+                .trim_start_matches('.')
+                .eq_ignore_ascii_case(extension)
+        })
+        .find_map(|(_, strategy)| CoverStrategy::from_name(strategy))
+}
+
+/// Read the size up to which covers smaller than the request are enlarged
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\AllowUpscaleTo
+/// - Value 0 or missing = never upscale (default, C++ behavior)
+/// - Value N = enlarge small covers (at most 2x, with Lanczos3) until the
+///   longer side reaches N pixels or the requested size
+pub fn allow_upscale_to_from(source: &dyn ConfigSource) -> Option<u32> {
+    source
+        .get_dword(ALLOW_UPSCALE_TO_VALUE)
+        .filter(|&size| size != 0)
+}
+
+/// Read how entry names are compared when sorting is enabled
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\NaturalSortMode
+/// - Value 0 or missing = built-in natural sort, the same on every machine
+///   (full-width digits count as digits) (default)
+/// - Value 1 = Windows `StrCmpLogicalW`, following the user's locale like
+///   Explorer's own file list
+pub fn natural_sort_mode_from(source: &dyn ConfigSource) -> NaturalSortMode {
+    source
+        .get_dword(NATURAL_SORT_MODE_VALUE)
+        .map(NaturalSortMode::from_registry_value)
+        .unwrap_or_default()
+}
+
+/// Read which part of entry names is sorted on
+///
+/// Matters for archives with chapter folders, e.g. `ch1/01.jpg`, `ch2/01.jpg`.
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\SortBy
+/// - Value 0 or missing = full path, folders first (default, C++ behavior)
+/// - Value 1 = file name only, ignoring folders; equal file names keep
+///   full path order
+pub fn sort_by_from(source: &dyn ConfigSource) -> SortKey {
+    source
+        .get_dword(SORT_BY_VALUE)
+        .map(SortKey::from_registry_value)
+        .unwrap_or_default()
+}
+
+/// Registry value name used for an archive's cover override
+///
+/// Windows paths are case-insensitive, so the name is the lowercased path.
+fn cover_override_value_name(archive_path: &Path) -> String {
+    archive_path.to_string_lossy().to_lowercase()
+}
+
+/// Read the user-selected cover entry for an archive, if any
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\CoverOverrides
+/// - Value name = archive path, data = entry name inside the archive
+pub fn cover_override(archive_path: &Path) -> Option<String> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+
+    hkcu.open_subkey(format!("{}\\{}", CONFIG_KEY_PATH, COVER_OVERRIDES_SUBKEY))
+        .and_then(|key| key.get_value::<String, _>(cover_override_value_name(archive_path)))
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+/// Store the user-selected cover entry for an archive
+///
+/// Written by the "Set as cover" context menu command.
+pub fn set_cover_override(archive_path: &Path, entry_name: &str) -> Result<(), std::io::Error> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let (key, _) =
+        hkcu.create_subkey(format!("{}\\{}", CONFIG_KEY_PATH, COVER_OVERRIDES_SUBKEY))?;
+
+    key.set_value(
+        cover_override_value_name(archive_path),
+        &entry_name.to_string(),
+    )
+}
+
+/// Read the password for opening encrypted archives, if one is configured
+///
+/// A `.cbxpass` file in the archive's folder wins over this value (see
+/// `Settings::archive_password_for`).
+///
+/// Registry location: HKCU\Software\CBXShell-rs\{GUID}\Password
+/// - REG_BINARY = password encrypted for the current user with DPAPI (see `utils::dpapi`)
+/// - REG_SZ = plain-text password, for deployment by script or policy
+/// - Missing = encrypted archives are not opened (default)
+pub fn password_from(source: &dyn ConfigSource) -> Option<String> {
+    let password = match source.get_binary(PASSWORD_VALUE) {
+        Some(blob) => String::from_utf8(crate::utils::dpapi::unprotect(&blob)?).ok()?,
+        None => source.get_string(PASSWORD_VALUE)?,
+    };
+    (!password.is_empty()).then_some(password)
+}
+
+/// Read the first line of a folder's `.cbxpass` file
+///
+/// Only the first line is used, without surrounding whitespace, so a series
+/// can carry its own password.
+pub fn folder_password(folder: &Path) -> Option<String> {
+    let contents = std::fs::read_to_string(folder.join(PASSWORD_FILE_NAME)).ok()?;
+    let password = contents.lines().next()?.trim();
+    (!password.is_empty()).then(|| password.to_string())
 }
 
 /// Set the sorting preference in the registry (for testing/configuration)
@@ -62,6 +651,8 @@ pub fn set_should_sort_images(sort: bool) -> Result<(), std::io::Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::archive::SortOrder;
+    use crate::settings::Settings;
 
     #[test]
     fn test_read_no_sort_default() {
@@ -85,4 +676,251 @@ mod tests {
         // Cleanup: restore to default (sorting disabled for performance)
         let _ = set_should_sort_images(false);
     }
+
+    #[test]
+    fn test_should_sort_images_from_source() {
+        // Missing value = unsorted (fast path)
+        assert!(!should_sort_images_from(&MemoryConfigSource::default()));
+
+        let source = MemoryConfigSource::default().with(NO_SORT_VALUE, 0);
+        assert!(should_sort_images_from(&source));
+
+        let source = MemoryConfigSource::default().with(NO_SORT_VALUE, 1);
+        assert!(!should_sort_images_from(&source));
+
+        // Any non-zero value disables sorting
+        let source = MemoryConfigSource::default().with(NO_SORT_VALUE, 7);
+        assert!(!should_sort_images_from(&source));
+    }
+
+    #[test]
+    fn test_thumbnails_enabled_from_source() {
+        // Missing value = enabled
+        assert!(thumbnails_enabled_from(&MemoryConfigSource::default()));
+
+        let source = MemoryConfigSource::default().with(ENABLED_VALUE, 0);
+        assert!(!thumbnails_enabled_from(&source));
+
+        let source = MemoryConfigSource::default().with(ENABLED_VALUE, 1);
+        assert!(thumbnails_enabled_from(&source));
+    }
+
+    #[test]
+    fn test_verify_first_image_from_source() {
+        // Missing value = no verification (fast path)
+        assert_eq!(
+            verify_first_image_from(&MemoryConfigSource::default()),
+            CoverVerification::Off
+        );
+
+        for (value, level) in [
+            (0, CoverVerification::Off),
+            (1, CoverVerification::Header),
+            (2, CoverVerification::Decode),
+        ] {
+            let source = MemoryConfigSource::default().with(VERIFY_FIRST_IMAGE_VALUE, value);
+            assert_eq!(verify_first_image_from(&source), level);
+        }
+    }
+
+    #[test]
+    fn test_max_non_image_scan_from_source() {
+        assert_eq!(max_non_image_scan_from(&MemoryConfigSource::default()), 500);
+
+        for value in [0, 20, 10_000] {
+            let source = MemoryConfigSource::default().with(MAX_NON_IMAGE_SCAN_VALUE, value);
+            assert_eq!(max_non_image_scan_from(&source), value);
+        }
+    }
+
+    #[test]
+    fn test_sort_order_from_source() {
+        let sort_order_from =
+            |source: &MemoryConfigSource| Settings::load_from(source).sort_order();
+        assert_eq!(
+            sort_order_from(&MemoryConfigSource::default()),
+            SortOrder::default()
+        );
+
+        let source = MemoryConfigSource::default()
+            .with(SORT_BY_VALUE, 1)
+            .with(NATURAL_SORT_MODE_VALUE, 1);
+        let order = sort_order_from(&source);
+        assert_eq!(order.sort_by, SortKey::BaseName);
+        assert_eq!(order.mode, NaturalSortMode::Windows);
+
+        let source = MemoryConfigSource::default().with(SORT_BY_VALUE, 7);
+        assert_eq!(sort_order_from(&source).sort_by, SortKey::FullPath);
+    }
+
+    #[test]
+    fn test_cover_skip_stems_from_source() {
+        assert!(cover_skip_stems_from(&MemoryConfigSource::default()).is_empty());
+
+        let source = MemoryConfigSource::default()
+            .with_string(COVER_SKIP_STEMS_VALUE, "intro; toc,credits;;blank ");
+        assert_eq!(
+            cover_skip_stems_from(&source),
+            vec!["intro", "toc", "credits", "blank"]
+        );
+    }
+
+    #[test]
+    fn test_embedded_preview_names_from_source() {
+        // Disabled unless EmbeddedPreview is set, even with names configured
+        let source =
+            MemoryConfigSource::default().with_string(EMBEDDED_PREVIEW_NAMES_VALUE, "thumb.jpg");
+        assert!(embedded_preview_names_from(&source).is_empty());
+
+        let source = MemoryConfigSource::default().with(EMBEDDED_PREVIEW_VALUE, 1);
+        assert_eq!(
+            embedded_preview_names_from(&source),
+            DEFAULT_EMBEDDED_PREVIEW_NAMES
+        );
+
+        let source = source.with_string(EMBEDDED_PREVIEW_NAMES_VALUE, " thumb.jpg;cover.webp ");
+        assert_eq!(
+            embedded_preview_names_from(&source),
+            vec!["thumb.jpg", "cover.webp"]
+        );
+    }
+
+    #[test]
+    fn test_cover_strategy_from_registry_value() {
+        assert_eq!(
+            CoverStrategy::from_registry_value(0),
+            CoverStrategy::FirstSorted
+        );
+        assert_eq!(
+            CoverStrategy::from_registry_value(1),
+            CoverStrategy::Largest
+        );
+        assert_eq!(
+            CoverStrategy::from_registry_value(2),
+            CoverStrategy::Portrait
+        );
+        assert_eq!(CoverStrategy::from_registry_value(3), CoverStrategy::Opf);
+        assert_eq!(
+            CoverStrategy::from_registry_value(42),
+            CoverStrategy::FirstSorted
+        );
+        assert_eq!(CoverStrategy::default(), CoverStrategy::FirstSorted);
+    }
+
+    #[test]
+    fn test_cover_strategy_for_extension() {
+        let cover_strategy_for_from = |source: &MemoryConfigSource, path: Option<&Path>| {
+            Settings::load_from(source).cover_strategy_for(path)
+        };
+        let strategy = |source: &MemoryConfigSource, name: &str| {
+            cover_strategy_for_from(source, Some(Path::new(name)))
+        };
+
+        // Built-in defaults: EPUB uses its OPF, everything else the first image
+        let source = MemoryConfigSource::default();
+        assert_eq!(
+            strategy(&source, "C:\\Books\\novel.EPUB"),
+            CoverStrategy::Opf
+        );
+        assert_eq!(strategy(&source, "comic.cbz"), CoverStrategy::FirstSorted);
+        assert_eq!(
+            cover_strategy_for_from(&source, None),
+            CoverStrategy::FirstSorted
+        );
+
+        // CoverStrategy applies to extensions without an entry
+        let source = MemoryConfigSource::default().with(COVER_STRATEGY_VALUE, 2);
+        assert_eq!(strategy(&source, "comic.cbz"), CoverStrategy::Portrait);
+        assert_eq!(strategy(&source, "novel.epub"), CoverStrategy::Opf);
+        assert_eq!(
+            cover_strategy_for_from(&source, None),
+            CoverStrategy::Portrait
+        );
+
+        // Per-extension entries win over both
+        let source = MemoryConfigSource::default()
+            .with(COVER_STRATEGY_VALUE, 2)
+            .with_string(
+                COVER_STRATEGY_BY_EXTENSION_VALUE,
+                " .zip = Largest; epub=first, cbz=bogus;cb7=1",
+            );
+        assert_eq!(strategy(&source, "album.zip"), CoverStrategy::Largest);
+        assert_eq!(strategy(&source, "novel.epub"), CoverStrategy::FirstSorted);
+        assert_eq!(strategy(&source, "comic.cb7"), CoverStrategy::Largest);
+        // Unknown strategy names are ignored
+        assert_eq!(strategy(&source, "comic.cbz"), CoverStrategy::Portrait);
+    }
+
+    #[test]
+    fn test_cover_override_value_name_is_case_insensitive() {
+        assert_eq!(
+            cover_override_value_name(Path::new("C:\\Comics\\Book.CBZ")),
+            cover_override_value_name(Path::new("c:\\comics\\book.cbz"))
+        );
+    }
+
+    #[test]
+    fn test_password_from_source() {
+        assert_eq!(password_from(&MemoryConfigSource::default()), None);
+
+        let plain = MemoryConfigSource::default().with_string(PASSWORD_VALUE, "secret");
+        assert_eq!(password_from(&plain), Some("secret".to_string()));
+
+        let empty = MemoryConfigSource::default().with_string(PASSWORD_VALUE, "");
+        assert_eq!(password_from(&empty), None);
+    }
+
+    #[test]
+    fn test_folder_password_reads_first_line() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(folder_password(dir.path()), None);
+
+        std::fs::write(dir.path().join(PASSWORD_FILE_NAME), "  secret \nignored\n").unwrap();
+        assert_eq!(folder_password(dir.path()), Some("secret".to_string()));
+
+        std::fs::write(dir.path().join(PASSWORD_FILE_NAME), "\n").unwrap();
+        assert_eq!(folder_password(dir.path()), None);
+    }
+
+    #[test]
+    fn test_select_thumbnail_size() {
+        // No override: honor cx, 0 falls back to the default
+        assert_eq!(select_thumbnail_size(96, 0), 96);
+        assert_eq!(select_thumbnail_size(1024, 0), 1024);
+        assert_eq!(select_thumbnail_size(0, 0), 256);
+
+        // Override set: always use it
+        assert_eq!(select_thumbnail_size(1024, 256), 256);
+        assert_eq!(select_thumbnail_size(96, 512), 512);
+        assert_eq!(select_thumbnail_size(0, 128), 128);
+    }
+
+    #[test]
+    fn test_cap_thumbnail_size() {
+        // Uncapped: the selected size is kept
+        assert_eq!(cap_thumbnail_size(1024, 0), 1024);
+        // The cap only lowers
+        assert_eq!(cap_thumbnail_size(1024, 256), 256);
+        assert_eq!(cap_thumbnail_size(96, 256), 96);
+        assert_eq!(cap_thumbnail_size(256, 256), 256);
+        // Applies to forced sizes too
+        assert_eq!(cap_thumbnail_size(select_thumbnail_size(96, 512), 384), 384);
+    }
+
+    #[test]
+    fn test_exceeds_max_archive_size() {
+        const MB: u64 = 1024 * 1024;
+
+        // 0 = unlimited
+        assert!(!exceeds_max_archive_size(0, 0));
+        assert!(!exceeds_max_archive_size(u64::MAX, 0));
+
+        // Exactly at the limit is allowed
+        assert!(!exceeds_max_archive_size(100 * MB, 100));
+        assert!(exceeds_max_archive_size(100 * MB + 1, 100));
+        assert!(!exceeds_max_archive_size(5 * MB, 100));
+
+        // Large limits must not overflow
+        assert!(!exceeds_max_archive_size(4 * 1024 * MB, u32::MAX));
+    }
 }
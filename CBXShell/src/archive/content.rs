@@ -0,0 +1,178 @@
+//! What an opened archive holds
+//!
+//! Comics and e-books count pages and pick covers differently, so the
+//! kind is decided once when the archive is opened and travels with it.
+use super::manifest::EPUB_CONTAINER_FILE;
+use super::utils::is_image_file;
+use super::{open_archive, Archive, ArchiveEntry};
+use crate::utils::error::Result;
+use std::path::Path;
+
+/// Kind of content inside an archive
+///
+/// PDF joins once a backend opens PDFs; `open_archive` rejects them today.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentKind {
+    /// Image pages (CBZ/CBR/CB7 and plain archives)
+    #[default]
+    ImageArchive,
+    /// EPUB e-book (a ZIP with `META-INF/container.xml`)
+    Epub,
+}
+
+impl ContentKind {
+    /// Classify an archive from its listing (`Archive::list_entries`)
+    ///
+    /// The `.epub` extension decides; otherwise an EPUB container file marks
+    /// an e-book whatever the archive is called.
+    pub fn classify(entries: &[ArchiveEntry], path: Option<&Path>) -> Self {
+        let is_epub_path = path
+            .and_then(|p| p.extension())
+            .is_some_and(|e| e.eq_ignore_ascii_case("epub"));
+        let has_container = entries.iter().any(|e| {
+            !e.is_directory
+                && e.name
+                    .replace('\\', "/")
+                    .eq_ignore_ascii_case(EPUB_CONTAINER_FILE)
+        });
+        if is_epub_path || has_container {
+            Self::Epub
+        } else {
+            Self::ImageArchive
+        }
+    }
+
+    /// Short lowercase label shown to users
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::ImageArchive => "comic",
+            Self::Epub => "e-book",
+        }
+    }
+
+    /// Label with the page count where images are pages, e.g. "comic, 24
+    /// pages"
+    ///
+    /// An e-book's images are illustrations, not pages, so it gets the label
+    /// alone.
+    pub fn describe(self, image_count: usize) -> String {
+        match self {
+            Self::ImageArchive => {
+                let noun = if image_count == 1 { "page" } else { "pages" };
+                format!("{}, {} {}", self.label(), image_count, noun)
+            }
+            Self::Epub => self.label().to_string(),
+        }
+    }
+}
+
+/// An opened archive together with its content kind
+pub struct OpenedArchive {
+    pub archive: Box<dyn Archive>,
+    pub kind: ContentKind,
+    /// Listing the kind was decided from, kept for callers that need one
+    pub entries: Vec<ArchiveEntry>,
+}
+
+impl OpenedArchive {
+    /// What the archive holds, e.g. "comic, 24 pages", from the kept listing
+    pub fn summary(&self) -> String {
+        let images = self
+            .entries
+            .iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .count();
+        self.kind.describe(images)
+    }
+}
+
+/// Open the archive at `path`, list it once and classify its content
+pub fn open_classified(path: &Path) -> Result<OpenedArchive> {
+    classify_archive(open_archive(path)?, Some(path))
+}
+
+/// List an opened archive once and classify its content
+///
+/// For archives read through a stream; `path` is the file behind it, if known.
+pub fn classify_archive(archive: Box<dyn Archive>, path: Option<&Path>) -> Result<OpenedArchive> {
+    let entries = archive.list_entries()?;
+    let kind = ContentKind::classify(&entries, path);
+    Ok(OpenedArchive {
+        archive,
+        kind,
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::{FileOptions, ZipWriter};
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_classify_epub_and_cbz() {
+        let dir = tempfile::tempdir().unwrap();
+        let png: &[u8] = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+        let cbz = dir.path().join("book.cbz");
+        write_zip(&cbz, &[("01.png", png), ("02.png", png)]);
+        let opened = open_classified(&cbz).unwrap();
+        assert_eq!(opened.kind, ContentKind::ImageArchive);
+        assert_eq!(opened.summary(), "comic, 2 pages");
+
+        // Recognized by its container file even under a comic extension
+        let epub_files: &[(&str, &[u8])] = &[
+            ("mimetype", b"application/epub+zip"),
+            ("META-INF/container.xml", b"<container/>"),
+            ("OEBPS/cover.png", png),
+        ];
+        let renamed = dir.path().join("novel.cbz");
+        write_zip(&renamed, epub_files);
+        let opened = open_classified(&renamed).unwrap();
+        assert_eq!(opened.kind, ContentKind::Epub);
+        assert_eq!(opened.summary(), "e-book");
+
+        let epub = dir.path().join("novel.epub");
+        write_zip(&epub, epub_files);
+        assert_eq!(open_classified(&epub).unwrap().kind, ContentKind::Epub);
+
+        // The extension alone marks an e-book
+        assert_eq!(
+            ContentKind::classify(&[], Some(Path::new("novel.EPUB"))),
+            ContentKind::Epub
+        );
+        assert_eq!(ContentKind::classify(&[], None), ContentKind::ImageArchive);
+    }
+
+    #[test]
+    fn test_classify_archive_from_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let png: &[u8] = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+        let cbz = dir.path().join("book.cbz");
+        write_zip(&cbz, &[("01.png", png), ("02.png", png), ("03.png", png)]);
+
+        let reader = std::io::Cursor::new(std::fs::read(&cbz).unwrap());
+        let archive = crate::archive::open_archive_from_stream(reader).unwrap();
+        let opened = classify_archive(archive, None).unwrap();
+        assert_eq!(opened.kind, ContentKind::ImageArchive);
+        assert_eq!(opened.summary(), "comic, 3 pages");
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(ContentKind::ImageArchive.describe(24), "comic, 24 pages");
+        assert_eq!(ContentKind::ImageArchive.describe(1), "comic, 1 page");
+        // Images in an e-book are not its pages
+        assert_eq!(ContentKind::Epub.describe(142), "e-book");
+    }
+}
@@ -0,0 +1,259 @@
+///! Directory "archive" implementation
+///!
+///! Treats a folder of extracted images as an archive so folder-based comics
+///! get the same cover behavior as CBZ/CBR/CB7
+use std::path::{Path, PathBuf};
+
+use super::utils::{find_first_image, is_image_file, MAX_ENTRY_SIZE};
+use crate::archive::{
+    likely_photo_album, Archive, ArchiveEntry, ArchiveMetadata, ArchiveType, ListOptions,
+};
+use crate::utils::error::{CbxError, Result};
+
+/// Directory handler (top-level files only)
+pub struct DirectoryArchive {
+    path: PathBuf,
+    /// Sort order and scan limit from the settings
+    options: ListOptions,
+}
+
+impl DirectoryArchive {
+    /// Open a directory as an archive
+    pub fn open(path: &Path) -> Result<Self> {
+        tracing::debug!("Opening directory as archive: {:?}", path);
+
+        if !path.is_dir() {
+            return Err(CbxError::Archive(format!(
+                "Not a directory: {}",
+                path.display()
+            )));
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            options: ListOptions::default(),
+        })
+    }
+
+    /// List with `options` instead of the defaults (see `open_archive_with`)
+    pub fn with_list_options(mut self, options: ListOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl Archive for DirectoryArchive {
+    fn open(path: &Path) -> Result<Box<dyn Archive>> {
+        Ok(Box::new(Self::open(path)?))
+    }
+
+    fn list_options(&self) -> ListOptions {
+        self.options
+    }
+
+    fn find_first_image(&self, sort: bool) -> Result<ArchiveEntry> {
+        tracing::debug!("Finding first image in directory (sort={})", sort);
+
+        let entries: Vec<ArchiveEntry> = self
+            .list_entries()?
+            .into_iter()
+            .filter(|e| !e.is_directory)
+            .collect();
+
+        if entries.is_empty() {
+            return Err(CbxError::Archive("Directory is empty".to_string()));
+        }
+
+        let names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
+
+        let image_name = find_first_image(
+            names.iter().map(|s| s.as_str()),
+            sort.then_some(self.options.order),
+        )
+        .ok_or(CbxError::NoImages)?;
+
+        tracing::info!("Found first image in directory: {}", image_name);
+
+        entries
+            .into_iter()
+            .find(|e| e.name == image_name)
+            .ok_or_else(|| CbxError::Archive("Image entry not found".to_string()))
+    }
+
+    fn find_images(&self) -> Result<Vec<ArchiveEntry>> {
+        Ok(self
+            .list_entries()?
+            .into_iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .collect())
+    }
+
+    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let read_dir = std::fs::read_dir(&self.path)
+            .map_err(|e| CbxError::Archive(format!("Failed to read directory: {}", e)))?;
+
+        let mut entries = Vec::new();
+
+        for dir_entry in read_dir.flatten() {
+            let Ok(metadata) = dir_entry.metadata() else {
+                continue;
+            };
+
+            entries.push(ArchiveEntry {
+                name: dir_entry.file_name().to_string_lossy().to_string(),
+                size: metadata.len(),
+                is_directory: metadata.is_dir(),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    fn extract_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
+        tracing::debug!("Reading file: {} ({} bytes)", entry.name, entry.size);
+
+        // Safety check: prevent memory exhaustion (same limit as archive entries)
+        if entry.size > MAX_ENTRY_SIZE {
+            tracing::warn!(
+                "Entry too large: {} bytes (max {})",
+                entry.size,
+                MAX_ENTRY_SIZE
+            );
+            return Err(CbxError::Archive(format!(
+                "Entry too large: {} bytes (max 32MB)",
+                entry.size
+            )));
+        }
+
+        // Entry names are bare file names; reject anything that could escape the folder
+        let file_name = Path::new(&entry.name);
+        if file_name.components().count() != 1 || file_name.file_name().is_none() {
+            return Err(CbxError::Archive(format!(
+                "Invalid entry name: {}",
+                entry.name
+            )));
+        }
+
+        std::fs::read(self.path.join(file_name))
+            .map_err(|e| CbxError::Archive(format!("Failed to read {}: {}", entry.name, e)))
+    }
+
+    fn get_metadata(&self) -> Result<ArchiveMetadata> {
+        let entries = self.list_entries()?;
+        let files: Vec<&ArchiveEntry> = entries.iter().filter(|e| !e.is_directory).collect();
+        let total_files = files.len();
+        let image_count = files.iter().filter(|e| is_image_file(&e.name)).count();
+        let compressed_size = files.iter().map(|e| e.size).sum();
+
+        tracing::debug!(
+            "Directory metadata: {} files, {} images, {} bytes",
+            total_files,
+            image_count,
+            compressed_size
+        );
+
+        Ok(ArchiveMetadata {
+            total_files,
+            image_count,
+            compressed_size,
+            archive_type: ArchiveType::Directory,
+            likely_photo_album: likely_photo_album(self, image_count),
+        })
+    }
+
+    fn archive_type(&self) -> ArchiveType {
+        ArchiveType::Directory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_dir(files: &[(&str, &[u8])]) -> TempDir {
+        let dir = tempfile::Builder::new()
+            .prefix("test_dir_archive_")
+            .tempdir()
+            .unwrap();
+        for (name, content) in files {
+            std::fs::write(dir.path().join(name), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_open_directory() {
+        let dir = create_test_dir(&[("page1.jpg", b"image 1")]);
+        let archive = DirectoryArchive::open(dir.path()).unwrap();
+        assert_eq!(archive.archive_type(), ArchiveType::Directory);
+    }
+
+    #[test]
+    fn test_open_file_is_error() {
+        let dir = create_test_dir(&[("page1.jpg", b"image 1")]);
+        let result = DirectoryArchive::open(&dir.path().join("page1.jpg"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_find_first_image_sorted() {
+        let dir = create_test_dir(&[
+            ("readme.txt", b"text file"),
+            ("page10.jpg", b"image 10"),
+            ("page2.jpg", b"image 2"),
+            ("page1.jpg", b"image 1"),
+        ]);
+        std::fs::create_dir(dir.path().join("extras.jpg")).unwrap();
+
+        let archive = DirectoryArchive::open(dir.path()).unwrap();
+        let entry = archive.find_first_image(true).unwrap();
+
+        // Natural sort: page1.jpg < page2.jpg < page10.jpg (subdirectories ignored)
+        assert_eq!(entry.name, "page1.jpg");
+    }
+
+    #[test]
+    fn test_no_images_found() {
+        let dir = create_test_dir(&[("readme.txt", b"text")]);
+        let archive = DirectoryArchive::open(dir.path()).unwrap();
+        assert!(matches!(
+            archive.find_first_image(true),
+            Err(CbxError::NoImages)
+        ));
+    }
+
+    #[test]
+    fn test_extract_entry() {
+        let content = b"fake jpeg data";
+        let dir = create_test_dir(&[("image.jpg", content)]);
+
+        let archive = DirectoryArchive::open(dir.path()).unwrap();
+        let entry = archive.find_first_image(true).unwrap();
+        let extracted = archive.extract_entry(&entry).unwrap();
+
+        assert_eq!(extracted, content);
+    }
+
+    #[test]
+    fn test_extract_entry_rejects_traversal() {
+        let dir = create_test_dir(&[("image.jpg", b"data")]);
+        let archive = DirectoryArchive::open(dir.path()).unwrap();
+
+        let entry = ArchiveEntry {
+            name: "../image.jpg".to_string(),
+            size: 4,
+            is_directory: false,
+        };
+        assert!(archive.extract_entry(&entry).is_err());
+    }
+
+    #[test]
+    fn test_open_archive_detects_directory() {
+        let dir = create_test_dir(&[("page2.png", b"image 2"), ("page1.png", b"image 1")]);
+
+        let archive = crate::archive::open_archive(dir.path()).unwrap();
+        assert_eq!(archive.archive_type(), ArchiveType::Directory);
+        assert_eq!(archive.find_first_image(true).unwrap().name, "page1.png");
+    }
+}
@@ -0,0 +1,239 @@
+//! In-archive cover manifests
+//!
+//! Publishers can name the intended cover inside the archive, either with a
+//! `cbxcover` manifest holding an entry name or with a ComicInfo.xml
+//! `<Page Type="FrontCover" Image="N"/>` element. EPUBs name theirs in the
+//! package document (OPF) that `META-INF/container.xml` points to.
+
+/// ComicInfo metadata file (ComicRack schema)
+pub const COMIC_INFO_FILE: &str = "ComicInfo.xml";
+
+/// Manifest files naming the cover entry
+pub const COVER_MANIFEST_FILES: &[&str] = &["cbxcover", "cbxcover.txt"];
+
+/// EPUB container file locating the package document (inside `META-INF/`)
+pub const EPUB_CONTAINER_FILE: &str = "META-INF/container.xml";
+
+/// Entry name from a `cbxcover` manifest (first non-empty, non-`#` line)
+pub fn parse_cover_manifest(text: &str) -> Option<String> {
+    text.trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+/// Page index of the front cover declared in ComicInfo.xml
+///
+/// `Image` is the 0-based page index. Only `<Page>` attributes are read, so no
+/// full XML parser is needed.
+pub fn comic_info_front_cover(xml: &str) -> Option<usize> {
+    let mut rest = xml;
+
+    while let Some(start) = rest.find("<Page") {
+        let tag = &rest[start + "<Page".len()..];
+        let end = tag.find('>')?;
+        let (attributes, after) = tag.split_at(end);
+        rest = after;
+
+        // Skip <Pages> and other tags sharing the prefix
+        if !attributes.starts_with(|c: char| c.is_whitespace() || c == '/') {
+            continue;
+        }
+
+        let is_front_cover = attribute(attributes, "Type")
+            .is_some_and(|value| value.eq_ignore_ascii_case("FrontCover"));
+        if is_front_cover {
+            if let Some(index) = attribute(attributes, "Image").and_then(|v| v.parse().ok()) {
+                return Some(index);
+            }
+        }
+    }
+
+    None
+}
+
+/// Path of the package document (OPF) declared in `META-INF/container.xml`
+pub fn epub_package_path(container_xml: &str) -> Option<String> {
+    tag_attributes(container_xml, "rootfile")
+        .find_map(|attributes| attribute(attributes, "full-path"))
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+}
+
+/// Archive path of the cover image declared in an EPUB package document
+///
+/// An EPUB 3 manifest item with the `cover-image` property wins over the
+/// EPUB 2 `<meta name="cover" content="item-id"/>`. The item's `href` is
+/// relative to the package document at `package_path`.
+pub fn opf_cover_path(opf: &str, package_path: &str) -> Option<String> {
+    let items = || tag_attributes(opf, "item");
+    let is_cover_image = |attributes: &str| {
+        attribute(attributes, "properties")
+            .is_some_and(|value| value.split_whitespace().any(|p| p == "cover-image"))
+    };
+
+    let href = items()
+        .find(|attributes| is_cover_image(attributes))
+        .or_else(|| {
+            let id = tag_attributes(opf, "meta")
+                .find(|attributes| attribute(attributes, "name") == Some("cover"))
+                .and_then(|attributes| attribute(attributes, "content"))?;
+            items().find(|attributes| attribute(attributes, "id") == Some(id))
+        })
+        .and_then(|attributes| attribute(attributes, "href"))?;
+
+    resolve_href(package_path, href)
+}
+
+/// Join `href` onto the folder of `base`, resolving `.` and `..` segments
+///
+/// Fragments are dropped and `%20` decoded; `None` if `..` climbs above the
+/// archive root.
+fn resolve_href(base: &str, href: &str) -> Option<String> {
+    let href = href.split('#').next()?.replace("%20", " ");
+    let mut segments: Vec<&str> = base.split(['/', '\\']).collect();
+    segments.pop();
+
+    for segment in href.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    Some(segments.join("/"))
+}
+
+/// Attribute text of every `<name ...>` tag in `xml`
+///
+/// Tags that merely share the prefix (`<items>` for `item`) are skipped.
+fn tag_attributes<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    let open = format!("<{}", name);
+    let mut rest = xml;
+
+    std::iter::from_fn(move || loop {
+        let start = rest.find(&open)?;
+        let tag = &rest[start + open.len()..];
+        let end = tag.find('>')?;
+        let (attributes, after) = tag.split_at(end);
+        rest = after;
+
+        if attributes.starts_with(|c: char| c.is_whitespace() || c == '/') {
+            return Some(attributes);
+        }
+    })
+}
+
+/// Value of a quoted attribute in the inside of a tag
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().rsplit(char::is_whitespace).next()?;
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+        let close = value.find(quote)?;
+
+        if key == name {
+            return Some(value[..close].trim());
+        }
+        rest = &value[close + 1..];
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comic_info_front_cover() {
+        let xml = r#"<?xml version="1.0"?>
+<ComicInfo>
+  <Title>Example</Title>
+  <Pages>
+    <Page Image="0" ImageSize="1000" />
+    <Page Image="1" Type="InnerCover"/>
+    <Page Type='FrontCover' Image='2' ImageWidth="1200"/>
+  </Pages>
+</ComicInfo>"#;
+        assert_eq!(comic_info_front_cover(xml), Some(2));
+    }
+
+    #[test]
+    fn test_comic_info_without_front_cover() {
+        assert_eq!(comic_info_front_cover("<ComicInfo/>"), None);
+        assert_eq!(
+            comic_info_front_cover(r#"<Pages><Page Image="0" Type="Story"/></Pages>"#),
+            None
+        );
+        assert_eq!(
+            comic_info_front_cover(r#"<Page Type="FrontCover" Image="x"/>"#),
+            None
+        );
+        // Truncated tag
+        assert_eq!(
+            comic_info_front_cover(r#"<Page Type="FrontCover" Ima"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_epub_package_path() {
+        let container = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+        assert_eq!(
+            epub_package_path(container),
+            Some("OEBPS/content.opf".to_string())
+        );
+        assert_eq!(epub_package_path("<container/>"), None);
+    }
+
+    #[test]
+    fn test_opf_cover_path() {
+        // EPUB 3 cover-image property
+        let opf = r#"<package><manifest>
+  <item id="css" href="style.css" media-type="text/css"/>
+  <item id="c" href="images/cover%20art.jpg" properties="nav cover-image"/>
+</manifest></package>"#;
+        assert_eq!(
+            opf_cover_path(opf, "OEBPS/content.opf"),
+            Some("OEBPS/images/cover art.jpg".to_string())
+        );
+
+        // EPUB 2 <meta name="cover">, href relative to the package folder
+        let opf = r#"<package><metadata><meta name="cover" content="img1"/></metadata>
+<manifest>
+  <item href="../Text/p1.xhtml" id="p1"/>
+  <item href="../Images/front.png" id="img1" media-type="image/png"/>
+</manifest></package>"#;
+        assert_eq!(
+            opf_cover_path(opf, "OPS/Package/book.opf"),
+            Some("OPS/Images/front.png".to_string())
+        );
+
+        assert_eq!(opf_cover_path("<package/>", "content.opf"), None);
+        // Escaping the archive root is refused
+        let opf = r#"<item properties="cover-image" href="../../cover.jpg"/>"#;
+        assert_eq!(opf_cover_path(opf, "content.opf"), None);
+    }
+
+    #[test]
+    fn test_parse_cover_manifest() {
+        assert_eq!(
+            parse_cover_manifest("\u{feff}# cover page\n\n  art/cover.png  \nignored.jpg"),
+            Some("art/cover.png".to_string())
+        );
+        assert_eq!(parse_cover_manifest("\n# nothing\n"), None);
+    }
+}
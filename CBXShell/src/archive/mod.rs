@@ -1,10 +1,16 @@
+use crate::image_processor::decoder::probe_dimensions;
+use crate::image_processor::magic::{verify_image_format, ImageFormat};
+use crate::settings::Settings;
 use crate::utils::error::{CbxError, Result};
 ///! Archive format handling
 ///!
 ///! Supports ZIP, RAR, and 7z formats for comic book archives
 use std::path::Path;
 
-mod config;
+pub(crate) mod config;
+mod content;
+mod directory;
+mod manifest;
 mod rar;
 mod sevenz;
 pub mod stream_reader;
@@ -12,15 +18,31 @@ mod utils;
 mod zip;
 
 // Re-export utilities for internal use only (not used in public API)
-pub use config::should_sort_images;
+pub use config::{
+    cap_thumbnail_size, cover_override, exceeds_max_archive_size, select_thumbnail_size,
+    set_cover_override, EMBEDDED_PREVIEW_FILE_NAME, MISSING_CODEC_FALLBACK_LIMIT,
+};
 
+pub use content::{classify_archive, open_classified, ContentKind, OpenedArchive};
+
+use manifest::{
+    comic_info_front_cover, epub_package_path, opf_cover_path, parse_cover_manifest,
+    COMIC_INFO_FILE, COVER_MANIFEST_FILES, EPUB_CONTAINER_FILE,
+};
+use utils::{
+    find_first_image_skipping, is_jpeg_name, is_portrait, is_skipped_page, looks_like_photo_album,
+    select_largest_image,
+};
 // Re-export image verification function (used by COM shell extension)
-pub use utils::verify_image_data;
+pub use utils::{verify_image_data, ListOptions, NaturalSortMode, SortKey, SortOrder};
 
+#[allow(dead_code)] // Used by open_archive function and part of public API
+pub use directory::DirectoryArchive;
 #[allow(dead_code)] // Used by open_archive function and part of public API
 pub use rar::RarArchive;
 #[allow(dead_code)] // Used by open_archive function and part of public API
 pub use sevenz::SevenZipArchive;
+pub use zip::write_entry as write_zip_entry;
 #[allow(dead_code)] // Used by open_archive function and part of public API
 pub use zip::ZipArchive;
 
@@ -36,14 +58,25 @@ pub struct ArchiveEntry {
     pub is_directory: bool,
 }
 
+/// Image page listed by `Archive::list_pages`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageInfo {
+    pub name: String,
+    /// Uncompressed size in bytes
+    pub size: u64,
+    /// Position in archive order, as accepted by `Archive::page_dimensions`
+    pub index: usize,
+}
+
 /// Archive metadata
 #[derive(Debug, Clone)]
-#[allow(dead_code)] // Part of public API, may be used in future
 pub struct ArchiveMetadata {
     pub total_files: usize,
     pub image_count: usize,
     pub compressed_size: u64,
     pub archive_type: ArchiveType,
+    /// Looks like a photo dump rather than a comic (see `likely_photo_album`)
+    pub likely_photo_album: bool,
 }
 
 /// Archive type
@@ -52,11 +85,12 @@ pub enum ArchiveType {
     Zip,
     Rar,
     SevenZip,
+    /// Folder of extracted images (not a real archive)
+    Directory,
 }
 
 impl ArchiveType {
     /// Detect archive type from file extension
-    #[allow(dead_code)] // Part of public API, may be used in future
     pub fn from_extension(ext: &str) -> Option<Self> {
         match ext.to_lowercase().as_str() {
             "zip" | "cbz" | "epub" | "phz" => Some(Self::Zip),
@@ -72,10 +106,143 @@ impl ArchiveType {
             Self::Zip => "ZIP",
             Self::Rar => "RAR",
             Self::SevenZip => "7-Zip",
+            Self::Directory => "Folder",
         }
     }
 }
 
+/// Archive types this build can open
+///
+/// Every backend is compiled in unconditionally; a backend behind a cargo
+/// feature would be listed here only when that feature is enabled.
+pub fn supported_archive_types() -> &'static [ArchiveType] {
+    &[
+        ArchiveType::Zip,
+        ArchiveType::Rar,
+        ArchiveType::SevenZip,
+        ArchiveType::Directory,
+    ]
+}
+
+/// Strategy used to pick the cover image of an archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoverStrategy {
+    /// First image in archive order, or natural order when sorting is enabled
+    #[default]
+    FirstSorted,
+    /// Image with the greatest uncompressed size (requires a full listing pass)
+    Largest,
+    /// First portrait image, so landscape banners are passed over
+    ///
+    /// Only the first `PORTRAIT_PROBE_LIMIT` candidates are probed; falls back
+    /// to the first image when none of them is portrait.
+    Portrait,
+    /// Cover declared by an EPUB package document (OPF), else the first image
+    Opf,
+}
+
+/// Images probed by `CoverStrategy::Portrait` before giving up
+///
+/// Each probe extracts the entry, so this bounds the cost on archives without
+/// any portrait page.
+const PORTRAIT_PROBE_LIMIT: usize = 8;
+
+/// Archives with fewer images are never reported as photo albums
+const PHOTO_ALBUM_MIN_IMAGES: usize = 100;
+
+/// Leading images probed for the photo album signal
+const PHOTO_ALBUM_PROBE_LIMIT: usize = 4;
+
+impl CoverStrategy {
+    /// Map the `CoverStrategy` registry DWORD to a strategy
+    ///
+    /// Unknown values fall back to the default.
+    pub fn from_registry_value(value: u32) -> Self {
+        match value {
+            1 => Self::Largest,
+            2 => Self::Portrait,
+            3 => Self::Opf,
+            _ => Self::FirstSorted,
+        }
+    }
+
+    /// The `CoverStrategy` registry DWORD for this strategy
+    pub fn registry_value(self) -> u32 {
+        match self {
+            Self::FirstSorted => 0,
+            Self::Largest => 1,
+            Self::Portrait => 2,
+            Self::Opf => 3,
+        }
+    }
+
+    /// Parse a strategy name from `CoverStrategyByExtension`
+    ///
+    /// Accepts `first`, `largest`, `portrait` and `opf` (ASCII
+    /// case-insensitive) or the `CoverStrategy` DWORD value as digits.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if let Ok(value) = name.parse() {
+            return Some(Self::from_registry_value(value));
+        }
+        [
+            ("first", Self::FirstSorted),
+            ("largest", Self::Largest),
+            ("portrait", Self::Portrait),
+            ("opf", Self::Opf),
+        ]
+        .into_iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map(|(_, strategy)| strategy)
+    }
+}
+
+/// How thoroughly the first-image fast path checks a candidate before
+/// taking it as the cover
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoverVerification {
+    /// Take the first image by name without extracting others (fastest)
+    #[default]
+    Off,
+    /// Require image magic bytes, skipping misnamed non-images
+    Header,
+    /// Also read the dimensions from the image header, skipping corrupt or
+    /// truncated images that carry valid magic bytes
+    Decode,
+}
+
+impl CoverVerification {
+    /// Map the `VerifyFirstImage` registry DWORD to a level
+    pub fn from_registry_value(value: u32) -> Self {
+        match value {
+            0 => Self::Off,
+            1 => Self::Header,
+            _ => Self::Decode,
+        }
+    }
+
+    /// The `VerifyFirstImage` registry DWORD for this level
+    pub fn registry_value(self) -> u32 {
+        match self {
+            Self::Off => 0,
+            Self::Header => 1,
+            Self::Decode => 2,
+        }
+    }
+
+    /// Check extracted candidate data at this level
+    fn check(self, data: &[u8], name: &str) -> Result<()> {
+        if self == Self::Off {
+            return Ok(());
+        }
+        verify_image_data(data, name)?;
+        if self == Self::Decode {
+            probe_dimensions(data)?;
+        }
+        Ok(())
+    }
+}
+
 /// Archive trait for different archive formats
 #[allow(dead_code)] // Part of public API, used by archive implementations
 pub trait Archive {
@@ -84,22 +251,418 @@ pub trait Archive {
     where
         Self: Sized;
 
+    /// Sort order and scan limit this archive was opened with
+    fn list_options(&self) -> ListOptions;
+
     /// Find the first image in the archive (optionally sorted alphabetically)
     fn find_first_image(&self, sort: bool) -> Result<ArchiveEntry>;
 
+    /// List all image entries in archive order
+    fn find_images(&self) -> Result<Vec<ArchiveEntry>>;
+
+    /// List image pages in archive order (same order as `find_images`)
+    ///
+    /// Nothing is extracted; use `page_dimensions` to size a page on demand.
+    fn list_pages(&self) -> Result<Vec<PageInfo>> {
+        Ok(self
+            .find_images()?
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| PageInfo {
+                name: entry.name,
+                size: entry.size,
+                index,
+            })
+            .collect())
+    }
+
+    /// Width and height of the page at `index` in `list_pages` order
+    ///
+    /// Extracts only that page and reads its header with `probe_dimensions`.
+    fn page_dimensions(&self, index: usize) -> Result<(u32, u32)> {
+        let images = self.find_images()?;
+        let page_count = images.len();
+        let entry = images.into_iter().nth(index).ok_or_else(|| {
+            CbxError::Archive(format!(
+                "Page index {} out of range ({} pages)",
+                index, page_count
+            ))
+        })?;
+
+        let data = self.extract_entry(&entry)?;
+        probe_dimensions(&data)
+    }
+
+    /// List every entry, folders included, in archive order
+    fn list_entries(&self) -> Result<Vec<ArchiveEntry>>;
+
+    /// List file entries whose file name (in any folder) is one of `file_names`
+    ///
+    /// Names are compared ASCII case-insensitively; results are in archive order.
+    fn find_entries(&self, file_names: &[&str]) -> Result<Vec<ArchiveEntry>> {
+        Ok(self
+            .list_entries()?
+            .into_iter()
+            .filter(|e| {
+                !e.is_directory && file_names.iter().any(|f| utils::has_file_name(&e.name, f))
+            })
+            .collect())
+    }
+
+    /// Cover entry named by the archive itself, if any
+    ///
+    /// Looks the manifests up in `entries` (from `list_entries`), so only a
+    /// manifest that is present gets extracted. A `cbxcover` manifest wins over
+    /// a ComicInfo.xml front cover page, whose index counts images in the same
+    /// order as `find_first_image` (sorted with `order` when given). Missing or
+    /// unreadable manifests return `None` so cover selection falls back to its
+    /// heuristics.
+    fn manifest_cover_name(
+        &self,
+        entries: &[ArchiveEntry],
+        order: Option<SortOrder>,
+    ) -> Option<String> {
+        let read_text = |file_name: &str| -> Option<String> {
+            let entry = entries
+                .iter()
+                .find(|e| !e.is_directory && utils::has_file_name(&e.name, file_name))?;
+            match self.extract_entry(entry) {
+                Ok(data) => Some(String::from_utf8_lossy(&data).into_owned()),
+                Err(e) => {
+                    tracing::warn!("Failed to read {}: {}", entry.name, e);
+                    None
+                }
+            }
+        };
+
+        for file_name in COVER_MANIFEST_FILES {
+            if let Some(name) = read_text(file_name)
+                .as_deref()
+                .and_then(parse_cover_manifest)
+            {
+                tracing::info!("Cover named by {}: {}", file_name, name);
+                return Some(name);
+            }
+        }
+
+        let index = read_text(COMIC_INFO_FILE)
+            .as_deref()
+            .and_then(comic_info_front_cover)?;
+        let mut images: Vec<&ArchiveEntry> = entries
+            .iter()
+            .filter(|e| !e.is_directory && utils::is_image_file(&e.name))
+            .collect();
+        if let Some(order) = order {
+            images.sort_by(|a, b| order.compare(&a.name, &b.name));
+        }
+        let entry = images.into_iter().nth(index)?;
+        tracing::info!("ComicInfo.xml front cover: page {} ({})", index, entry.name);
+        Some(entry.name.clone())
+    }
+
+    /// Find a pre-made preview image embedded in the archive
+    ///
+    /// `file_names` are tried in order and looked up in `entries` (from
+    /// `list_entries`, matched in any folder as by `find_entries`). A preview
+    /// that can't be extracted or fails `verify_image_data` is skipped. `None`
+    /// means cover selection should run as usual.
+    ///
+    /// This is the only stored thumbnail the cover lookup reads. Thumbnails
+    /// kept outside the entries, in a ZIP extra field or an NTFS `:Thumbs`
+    /// stream, have no documented format and no writer, so they are not
+    /// looked for.
+    fn find_embedded_preview(
+        &self,
+        entries: &[ArchiveEntry],
+        file_names: &[String],
+    ) -> Option<(ArchiveEntry, Vec<u8>)> {
+        for file_name in file_names {
+            let Some(entry) = entries
+                .iter()
+                .find(|e| !e.is_directory && utils::has_file_name(&e.name, file_name))
+            else {
+                continue;
+            };
+
+            let verified = self
+                .extract_entry(entry)
+                .and_then(|data| verify_image_data(&data, &entry.name).map(|()| data));
+            match verified {
+                Ok(data) => {
+                    tracing::info!("Using embedded preview: {}", entry.name);
+                    return Some((entry.clone(), data));
+                }
+                Err(e) => tracing::warn!("Ignoring embedded preview {}: {}", entry.name, e),
+            }
+        }
+
+        None
+    }
+
+    /// Find the cover image using the given strategy
+    ///
+    /// `FirstSorted` keeps the fast path of `find_first_image`; only strategies
+    /// that need every entry pay for a full listing pass.
+    fn find_cover(&self, strategy: CoverStrategy, sort: bool) -> Result<ArchiveEntry> {
+        match strategy {
+            CoverStrategy::FirstSorted => self.find_first_image(sort),
+            CoverStrategy::Largest => {
+                let entry = select_largest_image(self.find_images()?).ok_or(CbxError::NoImages)?;
+                tracing::info!("Found largest image: {} ({} bytes)", entry.name, entry.size);
+                Ok(entry)
+            }
+            CoverStrategy::Portrait => self.find_portrait_image(sort),
+            CoverStrategy::Opf => match self.find_opf_cover() {
+                Some(entry) => Ok(entry),
+                None => self.find_first_image(sort),
+            },
+        }
+    }
+
+    /// Find the cover image declared by an EPUB package document
+    ///
+    /// Follows `META-INF/container.xml` to the OPF and looks its cover path
+    /// up among the images. `None` when any step is missing, so callers fall
+    /// back to another strategy.
+    fn find_opf_cover(&self) -> Option<ArchiveEntry> {
+        let read_text = |path: &str| -> Option<String> {
+            let file_name = path.rsplit('/').next()?;
+            let entry = self
+                .find_entries(&[file_name])
+                .ok()?
+                .into_iter()
+                .find(|e| e.name.replace('\\', "/").eq_ignore_ascii_case(path))?;
+            match self.extract_entry(&entry) {
+                Ok(data) => Some(String::from_utf8_lossy(&data).into_owned()),
+                Err(e) => {
+                    tracing::warn!("Failed to read {}: {}", entry.name, e);
+                    None
+                }
+            }
+        };
+
+        let package_path = epub_package_path(&read_text(EPUB_CONTAINER_FILE)?)?;
+        let cover_path = opf_cover_path(&read_text(&package_path)?, &package_path)?;
+        let entry = self
+            .find_images()
+            .ok()?
+            .into_iter()
+            .find(|e| e.name.replace('\\', "/").eq_ignore_ascii_case(&cover_path));
+        match &entry {
+            Some(entry) => tracing::info!("Cover named by {}: {}", package_path, entry.name),
+            None => tracing::warn!("OPF cover not found in archive: {}", cover_path),
+        }
+        entry
+    }
+
+    /// Find the first image whose height exceeds its width
+    ///
+    /// Candidates (archive order, or natural order when `sort`) are extracted
+    /// and only their headers read via `probe_dimensions`. Falls back to the
+    /// first image when none of the first `PORTRAIT_PROBE_LIMIT` is portrait.
+    fn find_portrait_image(&self, sort: bool) -> Result<ArchiveEntry> {
+        let mut candidates = self.find_images()?;
+        if sort {
+            let order = self.list_options().order;
+            candidates.sort_by(|a, b| order.compare(&a.name, &b.name));
+        }
+
+        for entry in candidates.iter().take(PORTRAIT_PROBE_LIMIT) {
+            let dimensions = self
+                .extract_entry(entry)
+                .and_then(|data| probe_dimensions(&data));
+            match dimensions {
+                Ok((width, height)) if is_portrait(width, height) => {
+                    tracing::info!(
+                        "Found portrait image: {} ({}x{})",
+                        entry.name,
+                        width,
+                        height
+                    );
+                    return Ok(entry.clone());
+                }
+                Ok((width, height)) => {
+                    tracing::debug!(
+                        "Passing over landscape image {} ({}x{})",
+                        entry.name,
+                        width,
+                        height
+                    )
+                }
+                Err(e) => tracing::warn!("Failed to probe {}: {}", entry.name, e),
+            }
+        }
+
+        let first = candidates.into_iter().next().ok_or(CbxError::NoImages)?;
+        tracing::info!("No portrait image found, using {}", first.name);
+        Ok(first)
+    }
+
+    /// Find the first image, passing over intro/TOC pages
+    ///
+    /// Keeps the `find_first_image` fast path when the first image is not
+    /// matched by `skip_stems`; otherwise lists all images and takes the first
+    /// one that isn't skipped (or the first image if all are).
+    fn find_first_image_skipping(&self, sort: bool, skip_stems: &[String]) -> Result<ArchiveEntry> {
+        let first = self.find_first_image(sort)?;
+        if !is_skipped_page(&first.name, skip_stems) {
+            return Ok(first);
+        }
+
+        let images = self.find_images()?;
+        let order = sort.then_some(self.list_options().order);
+        let chosen =
+            find_first_image_skipping(images.iter().map(|e| e.name.as_str()), order, skip_stems);
+
+        match chosen.and_then(|name| images.into_iter().find(|e| e.name == name)) {
+            Some(entry) => {
+                tracing::info!("Skipped intro page {}, using {}", first.name, entry.name);
+                Ok(entry)
+            }
+            None => Ok(first),
+        }
+    }
+
+    /// Find the first image and extract it
+    ///
+    /// Unless `verify` is `Off`, each candidate (archive order, or natural
+    /// order when `sort`) is extracted and checked at that level; candidates
+    /// that fail (e.g. a text file named `page1.jpg`, or a truncated JPEG
+    /// under `Decode`) are skipped. This costs an extra listing pass, so it is
+    /// opt-in.
+    ///
+    /// Pages matched by `skip_stems` are only used when no other image is.
+    fn find_first_image_data(
+        &self,
+        sort: bool,
+        verify: CoverVerification,
+        skip_stems: &[String],
+    ) -> Result<(ArchiveEntry, Vec<u8>)> {
+        if verify == CoverVerification::Off {
+            let entry = self.find_first_image_skipping(sort, skip_stems)?;
+            let data = self.extract_entry(&entry)?;
+            return Ok((entry, data));
+        }
+
+        let mut candidates = self.find_images()?;
+        if sort {
+            let order = self.list_options().order;
+            candidates.sort_by(|a, b| order.compare(&a.name, &b.name));
+        }
+        // Stable: skipped pages move to the end, keeping their order
+        candidates.sort_by_key(|e| is_skipped_page(&e.name, skip_stems));
+
+        for entry in candidates {
+            let data = match self.extract_entry(&entry) {
+                Ok(data) => data,
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable image {}: {}", entry.name, e);
+                    continue;
+                }
+            };
+
+            match verify.check(&data, &entry.name) {
+                Ok(()) => return Ok((entry, data)),
+                Err(e) => tracing::warn!("Skipping invalid image {}: {}", entry.name, e),
+            }
+        }
+
+        Err(CbxError::Archive(
+            "No valid images found in archive".to_string(),
+        ))
+    }
+
+    /// Find the cover image, preferring a user-selected entry when present
+    ///
+    /// `override_name` comes from the "Set as cover" command. If it no longer
+    /// names an image in the archive, falls back to `find_cover`. An exact
+    /// match wins over entries differing only in case.
+    fn find_cover_with_override(
+        &self,
+        override_name: Option<&str>,
+        strategy: CoverStrategy,
+        sort: bool,
+    ) -> Result<ArchiveEntry> {
+        if let Some(name) = override_name {
+            let wanted = name.replace('\\', "/");
+            let images = self.find_images()?;
+            let normalized = |e: &&ArchiveEntry| e.name.replace('\\', "/");
+            let found = images
+                .iter()
+                .find(|e| normalized(e) == wanted)
+                .or_else(|| {
+                    images
+                        .iter()
+                        .find(|e| normalized(e).eq_ignore_ascii_case(&wanted))
+                })
+                .cloned();
+
+            match found {
+                Some(entry) => {
+                    tracing::info!("Using cover override: {}", entry.name);
+                    return Ok(entry);
+                }
+                None => tracing::warn!("Cover override not found in archive: {}", name),
+            }
+        }
+
+        self.find_cover(strategy, sort)
+    }
+
     /// Extract an entry to a byte vector
     fn extract_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>>;
 
+    /// Find the first image, extract it and detect its format in one call
+    ///
+    /// Fails like `verify_image_data` when the entry has an image extension
+    /// but not an image header. Backends that can find and extract in a single
+    /// pass may override this.
+    fn read_cover(&self, sort: bool) -> Result<(Vec<u8>, ImageFormat)> {
+        let entry = self.find_first_image(sort)?;
+        let data = self.extract_entry(&entry)?;
+        verify_image_data(&data, &entry.name)?;
+        let format = verify_image_format(&data)?;
+        Ok((data, format))
+    }
+
     /// Get archive metadata
+    ///
+    /// Implementations fill `likely_photo_album` with `likely_photo_album`.
     fn get_metadata(&self) -> Result<ArchiveMetadata>;
 
+    /// Check whether entries are encrypted and cannot be read without a password
+    fn is_password_protected(&self) -> bool {
+        false
+    }
+
+    /// Get the archive-level comment (often title/credits), if any
+    fn get_comment(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
     /// Get archive type
     fn archive_type(&self) -> ArchiveType;
 }
 
 /// Open an archive of any supported type from a file path
-#[allow(dead_code)] // Part of public API, may be used in future
 pub fn open_archive(path: &Path) -> Result<Box<dyn Archive>> {
+    open_archive_with(path, &Settings::load())
+}
+
+/// Open an archive from a file path, listing it as `settings` say
+///
+/// The sort order and `MaxNonImageScan` are fixed for the archive's lifetime,
+/// so a request that has loaded its `Settings` doesn't read them again.
+pub fn open_archive_with(path: &Path, settings: &Settings) -> Result<Box<dyn Archive>> {
+    let options = settings.list_options();
+
+    // Extracted comics: treat the folder itself as the archive
+    if path.is_dir() {
+        return Ok(Box::new(
+            DirectoryArchive::open(path)?.with_list_options(options),
+        ));
+    }
+
     let extension = path
         .extension()
         .and_then(|s| s.to_str())
@@ -108,11 +671,125 @@ pub fn open_archive(path: &Path) -> Result<Box<dyn Archive>> {
     let archive_type = ArchiveType::from_extension(extension)
         .ok_or_else(|| CbxError::UnsupportedFormat(extension.to_string()))?;
 
+    // The extension only gates support; the backend opens the
+    // symlink/junction target, which unrar in particular needs
+    let original_path = path;
+    let resolved = crate::utils::file::resolve_archive_path(path);
+    let path = resolved.as_path();
+    let archive_type = sniff_archive_type(path, archive_type);
+
+    // Password lookup uses the name Explorer sees, so `.cbxpass` is read
+    // from the folder the user put it in
+    let password = settings.archive_password_for(Some(original_path));
+
     match archive_type {
-        ArchiveType::Zip => <ZipArchive as Archive>::open(path),
-        ArchiveType::Rar => <RarArchive as Archive>::open(path),
-        ArchiveType::SevenZip => <SevenZipArchive as Archive>::open(path),
+        ArchiveType::Zip => Ok(Box::new(
+            ZipArchive::open(path)?
+                .with_password(password)
+                .with_list_options(options),
+        )),
+        ArchiveType::Rar => Ok(Box::new(
+            RarArchive::open_with_password(path, password)?.with_list_options(options),
+        )),
+        ArchiveType::SevenZip => Ok(Box::new(
+            SevenZipArchive::open_with_password(path, password)?.with_list_options(options),
+        )),
+        ArchiveType::Directory => Ok(Box::new(
+            DirectoryArchive::open(path)?.with_list_options(options),
+        )),
+    }
+}
+
+/// Bytes read to confirm an archive's type (RAR 5 has the longest signature)
+const ARCHIVE_MAGIC_LEN: u64 = 8;
+
+/// Type of the archive at `path`, trusting its header over the extension
+///
+/// `guess` comes from the extension and is kept when the header can't be
+/// read or isn't recognized (e.g. a ZIP with a self-extractor stub). A RAR
+/// named `.cbz` thus opens with the RAR backend instead of failing as a ZIP.
+pub fn sniff_archive_type(path: &Path, guess: ArchiveType) -> ArchiveType {
+    use std::io::Read;
+
+    let mut header = Vec::new();
+    let read = std::fs::File::open(path)
+        .and_then(|file| file.take(ARCHIVE_MAGIC_LEN).read_to_end(&mut header));
+    match read
+        .ok()
+        .and_then(|_| detect_archive_type_from_bytes(&header).ok())
+    {
+        Some(detected) if detected != guess => {
+            tracing::info!(
+                "{:?} is a {:?} archive despite its extension",
+                path,
+                detected
+            );
+            detected
+        }
+        _ => guess,
+    }
+}
+
+/// Whether `archive` looks like a photo album rather than a comic
+///
+/// Many same-size landscape JPEGs are probably a photo dump. Below
+/// `PHOTO_ALBUM_MIN_IMAGES` images (`image_count`, as `get_metadata` counted
+/// them) nothing is extracted; otherwise the first `PHOTO_ALBUM_PROBE_LIMIT`
+/// images (archive order) are, and must all be JPEGs of the same landscape
+/// size.
+fn likely_photo_album<A: Archive + ?Sized>(archive: &A, image_count: usize) -> bool {
+    if image_count < PHOTO_ALBUM_MIN_IMAGES {
+        return false;
+    }
+    let Ok(images) = archive.find_images() else {
+        return false;
+    };
+
+    let mut sample = Vec::with_capacity(PHOTO_ALBUM_PROBE_LIMIT);
+    for entry in images.iter().take(PHOTO_ALBUM_PROBE_LIMIT) {
+        if !is_jpeg_name(&entry.name) {
+            return false;
+        }
+        match archive
+            .extract_entry(entry)
+            .and_then(|data| probe_dimensions(&data))
+        {
+            Ok(dimensions) => sample.push(dimensions),
+            Err(e) => {
+                tracing::debug!("Photo album probe failed for {}: {}", entry.name, e);
+                return false;
+            }
+        }
+    }
+
+    looks_like_photo_album(&sample)
+}
+
+/// Check that a file is a usable comic archive without extracting a thumbnail
+///
+/// Opens and lists the archive; nothing is decoded, and only archives large
+/// enough for the photo album probe have a few images decompressed.
+///
+/// # Arguments
+/// * `path` - Path to a supported archive (.cbz, .cbr, .cb7, ...)
+///
+/// # Returns
+/// * `Ok(ArchiveMetadata)` - Archive type and entry/image counts
+/// * `Err(CbxError::PasswordProtected)` - Entries are encrypted
+/// * `Err(CbxError::NoImages)` - Archive holds no images
+/// * `Err(CbxError)` - Unsupported or corrupt
+pub fn validate_archive(path: &Path) -> Result<ArchiveMetadata> {
+    let archive = open_archive(path)?;
+    if archive.is_password_protected() {
+        return Err(CbxError::PasswordProtected);
     }
+
+    let metadata = archive.get_metadata()?;
+    if metadata.image_count == 0 {
+        return Err(CbxError::NoImages);
+    }
+
+    Ok(metadata)
 }
 
 /// Open an archive from a stream (OPTIMIZED for IStream)
@@ -129,6 +806,9 @@ pub fn open_archive(path: &Path) -> Result<Box<dyn Archive>> {
 /// - **RAR**: Streaming write to temp file (2-3x faster, temp file still required)
 /// - **7z**: Streaming with RefCell pattern (19-28x faster for large archives)
 ///
+/// Streams that cannot seek back to the start are still accepted: RAR is
+/// streamed forward-only, ZIP and 7z are buffered to memory first.
+///
 /// # Arguments
 /// * `reader` - Any Read implementer (IStreamReader, File, etc.)
 ///
@@ -148,11 +828,24 @@ pub fn open_archive(path: &Path) -> Result<Box<dyn Archive>> {
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
 pub fn open_archive_from_stream<R: std::io::Read + std::io::Seek + 'static>(
+    reader: R,
+) -> Result<Box<dyn Archive>> {
+    open_archive_from_stream_with(reader, &Settings::load())
+}
+
+/// Open an archive from a stream, listing it as `settings` say
+///
+/// Like `open_archive_with`; `MaxArchiveSizeMB` also bounds streams that
+/// have to be buffered to memory.
+pub fn open_archive_from_stream_with<R: std::io::Read + std::io::Seek + 'static>(
     mut reader: R,
+    settings: &Settings,
 ) -> Result<Box<dyn Archive>> {
     use std::io::SeekFrom;
 
-    crate::utils::debug_log::debug_log(">>>>> open_archive_from_stream STARTING (OPTIMIZED) <<<<<");
+    let options = settings.list_options();
+
+    crate::debug_log!(">>>>> open_archive_from_stream STARTING (OPTIMIZED) <<<<<");
 
     // Read first 16 bytes for magic byte detection
     let mut magic_bytes = [0u8; 16];
@@ -162,29 +855,275 @@ pub fn open_archive_from_stream<R: std::io::Read + std::io::Seek + 'static>(
 
     // Detect archive type
     let archive_type = detect_archive_type_from_bytes(&magic_bytes)?;
-    crate::utils::debug_log::debug_log(&format!("Detected archive type: {:?}", archive_type));
+    crate::debug_log!("Detected archive type: {:?}", archive_type);
 
-    // Seek back to beginning
-    reader
-        .seek(SeekFrom::Start(0))
-        .map_err(|e| CbxError::Archive(format!("Failed to seek to start: {}", e)))?;
+    // ZIP locates its directory from the end and 7z takes the length up
+    // front; a stream that reports no size (Seek(End) failing, or 0 although
+    // bytes were just read) is buffered to memory instead. RAR needs neither.
+    let needs_len = archive_type != ArchiveType::Rar;
+    let stream_len = if needs_len {
+        reader
+            .seek(SeekFrom::End(0))
+            .ok()
+            .filter(|&len| len >= magic_bytes.len() as u64)
+    } else {
+        None
+    };
+    // Streams carry no folder, so only the configured password applies
+    let password = settings.archive_password_for(None);
+
+    // Position the stream at 0 exactly once; constructors assume it and do
+    // not re-seek. The zip crate seeks to the end itself, so a sized ZIP
+    // stream needs no rewind. Forward-only streams (e.g. pipe-backed IStream)
+    // fail here and are handled without rewinding.
+    if archive_type != ArchiveType::Zip || stream_len.is_none() {
+        if let Err(e) = reader.seek(SeekFrom::Start(0)) {
+            tracing::debug!("Stream cannot seek back ({}), opening forward-only", e);
+            crate::debug_log!("Stream is forward-only: {}", e);
+            return open_forward_only_stream(archive_type, magic_bytes, reader, password, settings);
+        }
+    }
+
+    if needs_len && stream_len.is_none() {
+        tracing::info!("Stream reports no size, buffering it to memory");
+        crate::debug_log!("Stream size unknown, buffering to memory");
+        let data = buffer_stream(reader, stream_buffer_limit(settings.max_archive_size_mb))?;
+        return open_archive_from_stream_with(std::io::Cursor::new(data), settings);
+    }
 
     match archive_type {
         ArchiveType::Zip => {
             // ZIP: Direct streaming (FASTEST!)
-            crate::utils::debug_log::debug_log("Using optimized ZIP streaming");
-            Ok(Box::new(zip::ZipArchiveFromStream::new(reader)?))
+            crate::debug_log!("Using optimized ZIP streaming");
+            Ok(Box::new(
+                zip::ZipArchiveFromStream::new(reader)?
+                    .with_password(password)
+                    .with_list_options(options),
+            ))
         }
         ArchiveType::Rar => {
-            crate::utils::debug_log::debug_log("Using optimized RAR streaming to temp file");
-            Ok(Box::new(rar::RarArchiveFromMemory::new_from_stream(
-                reader,
-            )?))
+            crate::debug_log!("Using optimized RAR streaming to temp file");
+            Ok(Box::new(
+                rar::RarArchiveFromMemory::new_from_stream(reader, password)?
+                    .with_list_options(options),
+            ))
         }
         ArchiveType::SevenZip => {
             // 7z: Streaming with RefCell (OPTIMIZED!)
-            crate::utils::debug_log::debug_log("Using optimized 7z streaming");
-            Ok(Box::new(sevenz::SevenZipArchiveFromStream::new(reader)?))
+            crate::debug_log!("Using optimized 7z streaming");
+            // Unsized streams were buffered above
+            let stream_len = stream_len.unwrap_or_default();
+            Ok(Box::new(
+                sevenz::SevenZipArchiveFromStream::from_start(reader, stream_len, password)?
+                    .with_list_options(options),
+            ))
+        }
+        ArchiveType::Directory => Err(CbxError::UnsupportedFormat(
+            "Directories cannot be opened from a stream".to_string(),
+        )),
+    }
+}
+
+/// Open an archive already held in memory
+///
+/// For callers with the archive bytes at hand (a download, a database blob)
+/// that would otherwise write a temp file. ZIP and 7z are read straight from
+/// the buffer; RAR still goes through a temp file internally.
+///
+/// # Returns
+/// * `Ok(Box<dyn Archive>)` - Opened archive handler
+/// * `Err(CbxError)` - If the format is unsupported or opening fails
+pub fn open_archive_from_bytes(data: Vec<u8>) -> Result<Box<dyn Archive>> {
+    open_archive_from_stream(std::io::Cursor::new(data))
+}
+
+/// Open an archive from a stream that cannot seek backwards
+///
+/// RAR is copied to a temp file front to back anyway, so the magic bytes are
+/// replayed ahead of the rest of the stream instead of rewinding. ZIP and 7z
+/// keep their directory at the end and need random access, so the stream is
+/// buffered to memory once.
+fn open_forward_only_stream<R: std::io::Read>(
+    archive_type: ArchiveType,
+    magic_bytes: [u8; 16],
+    reader: R,
+    password: Option<String>,
+    settings: &Settings,
+) -> Result<Box<dyn Archive>> {
+    use std::io::{Cursor, Read};
+
+    let options = settings.list_options();
+    let mut replayed = Cursor::new(magic_bytes).chain(reader);
+
+    match archive_type {
+        ArchiveType::Rar => Ok(Box::new(
+            rar::RarArchiveFromMemory::new_from_stream(replayed, password)?
+                .with_list_options(options),
+        )),
+        ArchiveType::Zip | ArchiveType::SevenZip => {
+            let limit = stream_buffer_limit(settings.max_archive_size_mb);
+            let data = buffer_stream(&mut replayed, limit)?;
+            crate::debug_log!("Buffered forward-only stream ({} bytes)", data.len());
+
+            let len = data.len() as u64;
+            let cursor = Cursor::new(data);
+            if archive_type == ArchiveType::Zip {
+                Ok(Box::new(
+                    zip::ZipArchiveFromStream::new(cursor)?
+                        .with_password(password)
+                        .with_list_options(options),
+                ))
+            } else {
+                Ok(Box::new(
+                    sevenz::SevenZipArchiveFromStream::from_start(cursor, len, password)?
+                        .with_list_options(options),
+                ))
+            }
+        }
+        ArchiveType::Directory => Err(CbxError::UnsupportedFormat(
+            "Directories cannot be opened from a stream".to_string(),
+        )),
+    }
+}
+
+/// Largest stream buffered to memory when it cannot be read in place
+const MAX_BUFFERED_STREAM_BYTES: u64 = 512 * 1024 * 1024;
+
+const MB: u64 = 1024 * 1024;
+
+/// Most bytes `buffer_stream` may hold
+///
+/// Buffered streams skip the `MaxArchiveSizeMB` check made on the reported
+/// size, so that limit (`max_mb`, capped at `MAX_BUFFERED_STREAM_BYTES`)
+/// applies here.
+fn stream_buffer_limit(max_mb: u32) -> u64 {
+    match max_mb {
+        0 => MAX_BUFFERED_STREAM_BYTES,
+        max_mb => MAX_BUFFERED_STREAM_BYTES.min(u64::from(max_mb) * MB),
+    }
+}
+
+/// Read the rest of `reader` into memory, failing past `limit` bytes
+fn buffer_stream<R: std::io::Read>(reader: R, limit: u64) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut data = Vec::new();
+    reader
+        .take(limit + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| CbxError::Archive(format!("Failed to buffer stream: {}", e)))?;
+    if data.len() as u64 > limit {
+        return Err(CbxError::ArchiveTooLarge {
+            size: data.len() as u64,
+            limit_mb: (limit / MB) as u32,
+        });
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+    /// Seekable stream that reports no size, like some shell-provided IStreams
+    struct SizelessStream {
+        inner: Cursor<Vec<u8>>,
+        /// Report a length of 0 instead of failing `Seek(End)`
+        zero_length: bool,
+    }
+
+    impl Read for SizelessStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for SizelessStream {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            match pos {
+                SeekFrom::End(_) if self.zero_length => Ok(0),
+                SeekFrom::End(_) => Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "size unknown",
+                )),
+                _ => self.inner.seek(pos),
+            }
+        }
+    }
+
+    #[test]
+    fn test_open_stream_without_size_is_buffered() {
+        let mut zip_data = Vec::new();
+        {
+            let mut zip = ::zip::ZipWriter::new(Cursor::new(&mut zip_data));
+            zip.start_file("01.jpg", ::zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(b"\xFF\xD8\xFF page").unwrap();
+            zip.finish().unwrap();
         }
+
+        let mut sevenz_data = Vec::new();
+        {
+            let mut sz = sevenz_rust::SevenZWriter::new(Cursor::new(&mut sevenz_data)).unwrap();
+            sz.push_archive_entry(
+                sevenz_rust::SevenZArchiveEntry::from_path(Path::new("01.jpg"), "01.jpg".into()),
+                Some(Cursor::new(b"\xFF\xD8\xFF page")),
+            )
+            .unwrap();
+            sz.finish().unwrap();
+        }
+
+        for data in [zip_data, sevenz_data] {
+            for zero_length in [false, true] {
+                let stream = SizelessStream {
+                    inner: Cursor::new(data.clone()),
+                    zero_length,
+                };
+                let archive = open_archive_from_stream(stream).unwrap();
+                let entry = archive.find_first_image(true).unwrap();
+                assert_eq!(archive.extract_entry(&entry).unwrap(), b"\xFF\xD8\xFF page");
+            }
+        }
+    }
+
+    #[test]
+    fn test_buffer_stream_caps_size() {
+        let data = vec![0u8; 1024];
+        assert_eq!(buffer_stream(&data[..], 1024).unwrap(), data);
+
+        assert!(matches!(
+            buffer_stream(&data[..], 1023),
+            Err(CbxError::ArchiveTooLarge { .. })
+        ));
+        assert!(matches!(
+            buffer_stream(std::io::repeat(0), 4 * MB),
+            Err(CbxError::ArchiveTooLarge { size, limit_mb: 4 }) if size == 4 * MB + 1
+        ));
+    }
+
+    #[test]
+    fn test_sniff_archive_type_trusts_header() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // RAR 5 signature under a CBZ name
+        let rar = dir.path().join("mislabeled.cbz");
+        std::fs::write(&rar, b"Rar!\x1A\x07\x01\x00rest of archive").unwrap();
+        assert_eq!(sniff_archive_type(&rar, ArchiveType::Zip), ArchiveType::Rar);
+
+        // ZIP under a RAR name
+        let zip = dir.path().join("mislabeled.rar");
+        std::fs::write(&zip, b"PK\x03\x04\x14\x00\x00\x00\x08\x00").unwrap();
+        assert_eq!(sniff_archive_type(&zip, ArchiveType::Rar), ArchiveType::Zip);
+
+        // Unknown headers and unreadable files keep the extension's guess
+        let sfx = dir.path().join("selfextract.cbz");
+        std::fs::write(&sfx, b"MZ\x90\x00 stub then PK").unwrap();
+        assert_eq!(sniff_archive_type(&sfx, ArchiveType::Zip), ArchiveType::Zip);
+        let missing = dir.path().join("missing.cbr");
+        assert_eq!(
+            sniff_archive_type(&missing, ArchiveType::Rar),
+            ArchiveType::Rar
+        );
     }
 }
@@ -6,26 +6,113 @@ use std::hash::BuildHasher;
 use std::io::{Read, Write as IoWrite};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use unrar::error::{Code, UnrarError};
 use unrar::Archive as UnrarArchive;
 
-use super::utils::{find_first_image, is_image_file, MAX_ENTRY_SIZE};
-use crate::archive::{Archive, ArchiveEntry, ArchiveMetadata, ArchiveType};
+use super::utils::{
+    comment_text, find_first_image, is_image_file, no_images_error, non_image_scan_exhausted,
+    MAX_ENTRY_SIZE,
+};
+use crate::archive::{
+    likely_photo_album, Archive, ArchiveEntry, ArchiveMetadata, ArchiveType, ListOptions,
+};
 use crate::utils::error::{CbxError, Result};
 
+/// unrar handle for `path`, using the configured password when there is one
+fn unrar_archive<'a>(path: &'a Path, password: Option<&'a str>) -> UnrarArchive<'a> {
+    match password {
+        Some(password) => UnrarArchive::with_password(path, password),
+        None => UnrarArchive::new(path),
+    }
+}
+
+/// Error for an unrar handle that failed to open
+///
+/// A file unrar cannot open (locked by another process, gone for a moment)
+/// is `CbxError::Io`, so it is retried instead of remembered as a broken
+/// archive. Every other code is a problem with the archive itself.
+fn open_error(context: &str, e: UnrarError) -> CbxError {
+    let message = format!("{}: {:?}", context, e);
+    if e.code == Code::EOpen {
+        CbxError::Io(std::io::Error::new(std::io::ErrorKind::Other, message))
+    } else {
+        CbxError::Archive(message)
+    }
+}
+
+/// Size of the buffer the archive comment is read into
+const COMMENT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// `CmtState` of a comment that was read in full, or cut at the buffer size
+/// (`ERAR_SMALL_BUF`)
+const COMMENT_READ: u32 = 1;
+const COMMENT_TRUNCATED: u32 = 20;
+
+/// Read the archive comment of the RAR file at `path`
+///
+/// The `unrar` crate only reports whether a comment exists, so the archive
+/// is opened once more through the UnRAR DLL interface with a comment
+/// buffer. Comments longer than `COMMENT_BUFFER_SIZE` are cut short.
+fn read_comment(path: &Path) -> Result<Option<String>> {
+    let name = widestring::WideCString::from_os_str(path.as_os_str())
+        .map_err(|e| CbxError::Archive(format!("Invalid RAR path: {}", e)))?;
+    let mut buffer = vec![0u8; COMMENT_BUFFER_SIZE];
+    let mut data =
+        unrar_sys::OpenArchiveDataEx::new(name.as_ptr() as *const _, unrar_sys::RAR_OM_LIST as _);
+    data.comment_buffer = buffer.as_mut_ptr() as *mut _;
+    data.comment_buffer_size = buffer.len() as _;
+
+    // SAFETY: `name` and `buffer` outlive the call, and the buffer size
+    // passed is its real length. The handle is closed right away.
+    let handle = unsafe { unrar_sys::RAROpenArchiveEx(&mut data as *mut _) };
+    if handle.is_null() {
+        return Err(CbxError::Archive(format!(
+            "Failed to open RAR for its comment (code {})",
+            data.open_result
+        )));
+    }
+    unsafe { unrar_sys::RARCloseArchive(handle) };
+
+    let len = match data.comment_state {
+        COMMENT_READ | COMMENT_TRUNCATED => (data.comment_size as usize).min(buffer.len()),
+        _ => 0,
+    };
+    Ok(comment_text(&buffer[..len]))
+}
+
+/// Entry name as listed and as re-matched during extraction
+///
+/// Listing and extraction must convert names identically: shares on
+/// case-sensitive filesystems hold archives whose names differ only by case,
+/// so names are kept verbatim (no case folding or separator rewriting) and
+/// compared exactly.
+fn stored_name(filename: &Path) -> String {
+    filename.to_string_lossy().into_owned()
+}
+
 /// RAR archive handler
 pub struct RarArchive {
     path: PathBuf,
+    /// Configured password for encrypted archives
+    password: Option<String>,
+    /// Sort order and scan limit from the settings
+    options: ListOptions,
 }
 
 impl RarArchive {
     /// Open a RAR archive from path
     pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with_password(path, None)
+    }
+
+    /// Open a RAR archive, decrypting with `password` (see `Settings::archive_password_for`)
+    pub fn open_with_password(path: &Path, password: Option<String>) -> Result<Self> {
         tracing::debug!("Opening RAR archive: {:?}", path);
 
         // Validate by attempting to list entries
-        let archive = UnrarArchive::new(path)
+        let archive = unrar_archive(path, password.as_deref())
             .open_for_listing()
-            .map_err(|e| CbxError::Archive(format!("Failed to open RAR archive: {:?}", e)))?;
+            .map_err(|e| open_error("Failed to open RAR archive", e))?;
 
         // Check if archive is accessible
         let mut has_entries = false;
@@ -47,32 +134,15 @@ impl RarArchive {
 
         Ok(Self {
             path: path.to_path_buf(),
+            password,
+            options: ListOptions::default(),
         })
     }
 
-    /// List all entries in archive
-    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
-        let archive = UnrarArchive::new(&self.path)
-            .open_for_listing()
-            .map_err(|e| CbxError::Archive(format!("Failed to open RAR for listing: {:?}", e)))?;
-
-        let mut entries = Vec::new();
-
-        for entry_result in archive {
-            let entry =
-                entry_result.map_err(|e| CbxError::Archive(format!("RAR entry error: {:?}", e)))?;
-
-            // Get filename from entry
-            let filename = entry.filename.to_string_lossy().to_string();
-
-            entries.push(ArchiveEntry {
-                name: filename,
-                size: entry.unpacked_size,
-                is_directory: entry.is_directory(),
-            });
-        }
-
-        Ok(entries)
+    /// List with `options` instead of the defaults (see `open_archive_with`)
+    pub fn with_list_options(mut self, options: ListOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
@@ -81,6 +151,10 @@ impl Archive for RarArchive {
         Ok(Box::new(Self::open(path)?))
     }
 
+    fn list_options(&self) -> ListOptions {
+        self.options
+    }
+
     fn find_first_image(&self, sort: bool) -> Result<ArchiveEntry> {
         tracing::debug!("Finding first image in RAR (sort={})", sort);
 
@@ -89,29 +163,35 @@ impl Archive for RarArchive {
             // without listing all entries (faster for large archives)
             tracing::debug!("Fast path: finding first image without full listing");
 
-            let archive = UnrarArchive::new(&self.path)
+            let archive = unrar_archive(&self.path, self.password.as_deref())
                 .open_for_listing()
-                .map_err(|e| {
-                    CbxError::Archive(format!("Failed to open RAR for listing: {:?}", e))
-                })?;
+                .map_err(|e| open_error("Failed to open RAR for listing", e))?;
 
+            let cap = self.options.max_non_image_scan;
+            let mut other_files = Vec::new();
             for entry_result in archive {
                 let entry = entry_result
                     .map_err(|e| CbxError::Archive(format!("RAR entry error: {:?}", e)))?;
 
-                let filename = entry.filename.to_string_lossy().to_string();
+                let filename = stored_name(&entry.filename);
 
-                if is_image_file(&filename) {
+                if !entry.is_directory() && is_image_file(&filename) {
                     tracing::info!("Found first image (unsorted): {}", filename);
                     return Ok(ArchiveEntry {
                         name: filename,
                         size: entry.unpacked_size,
-                        is_directory: entry.is_directory(),
+                        is_directory: false,
                     });
                 }
+                if !entry.is_directory() {
+                    other_files.push(filename);
+                    if non_image_scan_exhausted(other_files.len(), cap) {
+                        return Err(CbxError::NoImages);
+                    }
+                }
             }
 
-            return Err(CbxError::Archive("No images found in archive".to_string()));
+            return Err(no_images_error(other_files.iter().map(|s| s.as_str())));
         }
 
         // STANDARD PATH: List all entries and sort
@@ -121,10 +201,24 @@ impl Archive for RarArchive {
             return Err(CbxError::Archive("Archive is empty".to_string()));
         }
 
-        let names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
-
-        let image_name = find_first_image(names.iter().map(|s| s.as_str()), sort)
-            .ok_or_else(|| CbxError::Archive("No images found in archive".to_string()))?;
+        let names: Vec<String> = entries
+            .iter()
+            .filter(|e| !e.is_directory)
+            .map(|e| e.name.clone())
+            .collect();
+
+        let image_name = find_first_image(
+            names.iter().map(|s| s.as_str()),
+            sort.then_some(self.options.order),
+        )
+        .ok_or_else(|| {
+            no_images_error(
+                entries
+                    .iter()
+                    .filter(|e| !e.is_directory)
+                    .map(|e| e.name.as_str()),
+            )
+        })?;
 
         tracing::info!("Found first image (sorted): {}", image_name);
 
@@ -134,6 +228,38 @@ impl Archive for RarArchive {
             .ok_or_else(|| CbxError::Archive("Image entry not found".to_string()))
     }
 
+    fn find_images(&self) -> Result<Vec<ArchiveEntry>> {
+        Ok(self
+            .list_entries()?
+            .into_iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .collect())
+    }
+
+    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let archive = unrar_archive(&self.path, self.password.as_deref())
+            .open_for_listing()
+            .map_err(|e| open_error("Failed to open RAR for listing", e))?;
+
+        let mut entries = Vec::new();
+
+        for entry_result in archive {
+            let entry =
+                entry_result.map_err(|e| CbxError::Archive(format!("RAR entry error: {:?}", e)))?;
+
+            // Get filename from entry
+            let filename = stored_name(&entry.filename);
+
+            entries.push(ArchiveEntry {
+                name: filename,
+                size: entry.unpacked_size,
+                is_directory: entry.is_directory(),
+            });
+        }
+
+        Ok(entries)
+    }
+
     fn extract_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
         tracing::debug!("Extracting entry: {} ({} bytes)", entry.name, entry.size);
 
@@ -150,11 +276,9 @@ impl Archive for RarArchive {
             )));
         }
 
-        let mut archive = UnrarArchive::new(&self.path)
+        let mut archive = unrar_archive(&self.path, self.password.as_deref())
             .open_for_processing()
-            .map_err(|e| {
-                CbxError::Archive(format!("Failed to open RAR for processing: {:?}", e))
-            })?;
+            .map_err(|e| open_error("Failed to open RAR for processing", e))?;
 
         let mut extracted_data = None;
 
@@ -162,7 +286,7 @@ impl Archive for RarArchive {
         loop {
             match archive.read_header() {
                 Ok(Some(header)) => {
-                    let current_name = header.entry().filename.to_string_lossy().to_string();
+                    let current_name = stored_name(&header.entry().filename);
 
                     if current_name == entry.name {
                         // Extract to memory
@@ -200,7 +324,10 @@ impl Archive for RarArchive {
     fn get_metadata(&self) -> Result<ArchiveMetadata> {
         let entries = self.list_entries()?;
         let total_files = entries.len();
-        let image_count = entries.iter().filter(|e| is_image_file(&e.name)).count();
+        let image_count = entries
+            .iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .count();
 
         let compressed_size = std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
 
@@ -216,9 +343,14 @@ impl Archive for RarArchive {
             image_count,
             compressed_size,
             archive_type: ArchiveType::Rar,
+            likely_photo_album: likely_photo_album(self, image_count),
         })
     }
 
+    fn get_comment(&self) -> Result<Option<String>> {
+        read_comment(&self.path)
+    }
+
     fn archive_type(&self) -> ArchiveType {
         ArchiveType::Rar
     }
@@ -227,6 +359,10 @@ impl Archive for RarArchive {
 /// RAR archive handler for in-memory data (IStream support)
 pub struct RarArchiveFromMemory {
     temp_path: PathBuf,
+    /// Configured password for encrypted archives
+    password: Option<String>,
+    /// Sort order and scan limit from the settings
+    options: ListOptions,
 }
 
 const RAR_TEMP_PREFIX: &str = "cbxshell_rar_stream_";
@@ -279,11 +415,9 @@ impl RarArchiveFromMemory {
     /// # Returns
     /// * `Ok(Self)` - RAR archive ready for processing
     /// * `Err(CbxError)` - If writing or validation fails
-    pub fn new_from_stream<R: Read>(mut reader: R) -> Result<Self> {
+    pub fn new_from_stream<R: Read>(mut reader: R, password: Option<String>) -> Result<Self> {
         tracing::debug!("Creating RAR archive from stream (optimized)");
-        crate::utils::debug_log::debug_log(
-            ">>>>> RarArchiveFromMemory::new_from_stream STARTING <<<<<",
-        );
+        crate::debug_log!(">>>>> RarArchiveFromMemory::new_from_stream STARTING <<<<<");
 
         cleanup_stale_rar_temp_files(Duration::from_secs(300));
 
@@ -305,47 +439,39 @@ impl RarArchiveFromMemory {
         );
         let temp_path = temp_dir.join(temp_filename);
 
-        crate::utils::debug_log::debug_log(&format!("Temp file: {:?}", temp_path));
+        crate::debug_log!("Temp file: {:?}", temp_path);
 
         // Stream data to temp file in chunks (no full memory load!)
-        let mut file = File::create(&temp_path)
-            .map_err(|e| CbxError::Archive(format!("Failed to create temp RAR file: {}", e)))?;
+        let mut file = File::create(&temp_path)?;
 
         let mut total_written = 0u64;
         let mut buffer = vec![0u8; 1024 * 1024]; // 1MB chunks
 
         loop {
-            let bytes_read = reader
-                .read(&mut buffer)
-                .map_err(|e| CbxError::Archive(format!("Failed to read from stream: {}", e)))?;
+            let bytes_read = reader.read(&mut buffer)?;
 
             if bytes_read == 0 {
                 break; // EOF
             }
 
-            file.write_all(&buffer[..bytes_read])
-                .map_err(|e| CbxError::Archive(format!("Failed to write to temp file: {}", e)))?;
+            file.write_all(&buffer[..bytes_read])?;
 
             total_written += bytes_read as u64;
 
             if total_written % (10 * 1024 * 1024) == 0 {
                 // Log every 10MB
-                crate::utils::debug_log::debug_log(&format!(
-                    "Streamed {} MB to temp file",
-                    total_written / (1024 * 1024)
-                ));
+                crate::debug_log!("Streamed {} MB to temp file", total_written / (1024 * 1024));
             }
         }
 
-        file.sync_all()
-            .map_err(|e| CbxError::Archive(format!("Failed to sync temp RAR file: {}", e)))?;
+        file.sync_all()?;
 
         drop(file);
 
-        crate::utils::debug_log::debug_log(&format!("Total streamed: {} bytes", total_written));
+        crate::debug_log!("Total streamed: {} bytes", total_written);
 
         // Validate the temp file is a valid RAR
-        let _test = UnrarArchive::new(&temp_path)
+        let _test = unrar_archive(&temp_path, password.as_deref())
             .open_for_listing()
             .map_err(|e| {
                 // Clean up temp file on error
@@ -358,9 +484,7 @@ impl RarArchiveFromMemory {
                     || error_msg.contains("BadPassword")
                 {
                     tracing::info!("Skipping password-protected RAR archive");
-                    crate::utils::debug_log::debug_log(
-                        "RAR archive is password-protected - skipping",
-                    );
+                    crate::debug_log!("RAR archive is password-protected - skipping");
                     CbxError::Archive("Password-protected RAR archive (not supported)".to_string())
                 } else {
                     tracing::warn!("Invalid RAR data: {:?}", e);
@@ -369,35 +493,19 @@ impl RarArchiveFromMemory {
             })?;
 
         tracing::debug!("Temporary RAR file created from stream: {:?}", temp_path);
-        crate::utils::debug_log::debug_log(
-            ">>>>> RarArchiveFromMemory::new_from_stream COMPLETED <<<<<",
-        );
+        crate::debug_log!(">>>>> RarArchiveFromMemory::new_from_stream COMPLETED <<<<<");
 
-        Ok(Self { temp_path })
+        Ok(Self {
+            temp_path,
+            password,
+            options: ListOptions::default(),
+        })
     }
 
-    /// List all entries in archive
-    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
-        let archive = UnrarArchive::new(&self.temp_path)
-            .open_for_listing()
-            .map_err(|e| CbxError::Archive(format!("Failed to open RAR for listing: {:?}", e)))?;
-
-        let mut entries = Vec::new();
-
-        for entry_result in archive {
-            let entry =
-                entry_result.map_err(|e| CbxError::Archive(format!("RAR entry error: {:?}", e)))?;
-
-            let filename = entry.filename.to_string_lossy().to_string();
-
-            entries.push(ArchiveEntry {
-                name: filename,
-                size: entry.unpacked_size,
-                is_directory: entry.is_directory(),
-            });
-        }
-
-        Ok(entries)
+    /// List with `options` instead of the defaults (see `open_archive_with`)
+    pub fn with_list_options(mut self, options: ListOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
@@ -437,6 +545,10 @@ impl Archive for RarArchiveFromMemory {
         ))
     }
 
+    fn list_options(&self) -> ListOptions {
+        self.options
+    }
+
     fn find_first_image(&self, sort: bool) -> Result<ArchiveEntry> {
         tracing::debug!("Finding first image in RAR from memory (sort={})", sort);
 
@@ -444,29 +556,35 @@ impl Archive for RarArchiveFromMemory {
             // OPTIMIZATION: When not sorting, find first image immediately
             tracing::debug!("Fast path: finding first image without full listing");
 
-            let archive = UnrarArchive::new(&self.temp_path)
+            let archive = unrar_archive(&self.temp_path, self.password.as_deref())
                 .open_for_listing()
-                .map_err(|e| {
-                    CbxError::Archive(format!("Failed to open RAR for listing: {:?}", e))
-                })?;
+                .map_err(|e| open_error("Failed to open RAR for listing", e))?;
 
+            let cap = self.options.max_non_image_scan;
+            let mut other_files = Vec::new();
             for entry_result in archive {
                 let entry = entry_result
                     .map_err(|e| CbxError::Archive(format!("RAR entry error: {:?}", e)))?;
 
-                let filename = entry.filename.to_string_lossy().to_string();
+                let filename = stored_name(&entry.filename);
 
-                if is_image_file(&filename) {
+                if !entry.is_directory() && is_image_file(&filename) {
                     tracing::info!("Found first image (unsorted): {}", filename);
                     return Ok(ArchiveEntry {
                         name: filename,
                         size: entry.unpacked_size,
-                        is_directory: entry.is_directory(),
+                        is_directory: false,
                     });
                 }
+                if !entry.is_directory() {
+                    other_files.push(filename);
+                    if non_image_scan_exhausted(other_files.len(), cap) {
+                        return Err(CbxError::NoImages);
+                    }
+                }
             }
 
-            return Err(CbxError::Archive("No images found in archive".to_string()));
+            return Err(no_images_error(other_files.iter().map(|s| s.as_str())));
         }
 
         // STANDARD PATH: List all entries and sort
@@ -476,10 +594,24 @@ impl Archive for RarArchiveFromMemory {
             return Err(CbxError::Archive("Archive is empty".to_string()));
         }
 
-        let names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
-
-        let image_name = find_first_image(names.iter().map(|s| s.as_str()), sort)
-            .ok_or_else(|| CbxError::Archive("No images found in archive".to_string()))?;
+        let names: Vec<String> = entries
+            .iter()
+            .filter(|e| !e.is_directory)
+            .map(|e| e.name.clone())
+            .collect();
+
+        let image_name = find_first_image(
+            names.iter().map(|s| s.as_str()),
+            sort.then_some(self.options.order),
+        )
+        .ok_or_else(|| {
+            no_images_error(
+                entries
+                    .iter()
+                    .filter(|e| !e.is_directory)
+                    .map(|e| e.name.as_str()),
+            )
+        })?;
 
         tracing::info!("Found first image (sorted): {}", image_name);
 
@@ -489,6 +621,37 @@ impl Archive for RarArchiveFromMemory {
             .ok_or_else(|| CbxError::Archive("Image entry not found".to_string()))
     }
 
+    fn find_images(&self) -> Result<Vec<ArchiveEntry>> {
+        Ok(self
+            .list_entries()?
+            .into_iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .collect())
+    }
+
+    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let archive = unrar_archive(&self.temp_path, self.password.as_deref())
+            .open_for_listing()
+            .map_err(|e| open_error("Failed to open RAR for listing", e))?;
+
+        let mut entries = Vec::new();
+
+        for entry_result in archive {
+            let entry =
+                entry_result.map_err(|e| CbxError::Archive(format!("RAR entry error: {:?}", e)))?;
+
+            let filename = stored_name(&entry.filename);
+
+            entries.push(ArchiveEntry {
+                name: filename,
+                size: entry.unpacked_size,
+                is_directory: entry.is_directory(),
+            });
+        }
+
+        Ok(entries)
+    }
+
     fn extract_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
         tracing::debug!(
             "Extracting entry from memory: {} ({} bytes)",
@@ -509,11 +672,9 @@ impl Archive for RarArchiveFromMemory {
             )));
         }
 
-        let mut archive = UnrarArchive::new(&self.temp_path)
+        let mut archive = unrar_archive(&self.temp_path, self.password.as_deref())
             .open_for_processing()
-            .map_err(|e| {
-                CbxError::Archive(format!("Failed to open RAR for processing: {:?}", e))
-            })?;
+            .map_err(|e| open_error("Failed to open RAR for processing", e))?;
 
         let mut extracted_data = None;
 
@@ -521,7 +682,7 @@ impl Archive for RarArchiveFromMemory {
         loop {
             match archive.read_header() {
                 Ok(Some(header)) => {
-                    let current_name = header.entry().filename.to_string_lossy().to_string();
+                    let current_name = stored_name(&header.entry().filename);
 
                     if current_name == entry.name {
                         // Extract to memory
@@ -559,7 +720,10 @@ impl Archive for RarArchiveFromMemory {
     fn get_metadata(&self) -> Result<ArchiveMetadata> {
         let entries = self.list_entries()?;
         let total_files = entries.len();
-        let image_count = entries.iter().filter(|e| is_image_file(&e.name)).count();
+        let image_count = entries
+            .iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .count();
 
         let compressed_size = std::fs::metadata(&self.temp_path)
             .map(|m| m.len())
@@ -577,9 +741,14 @@ impl Archive for RarArchiveFromMemory {
             image_count,
             compressed_size: compressed_size,
             archive_type: ArchiveType::Rar,
+            likely_photo_album: likely_photo_album(self, image_count),
         })
     }
 
+    fn get_comment(&self) -> Result<Option<String>> {
+        read_comment(&self.temp_path)
+    }
+
     fn archive_type(&self) -> ArchiveType {
         ArchiveType::Rar
     }
@@ -613,6 +782,7 @@ mod tests {
 
         let result = RarArchive::open(temp_path);
         assert!(result.is_err());
+        assert!(read_comment(temp_path).is_err());
     }
 
     #[test]
@@ -620,10 +790,21 @@ mod tests {
         // This test doesn't need a real RAR file
         let rar = RarArchive {
             path: PathBuf::from("test.rar"),
+            password: None,
+            options: ListOptions::default(),
         };
         assert_eq!(rar.archive_type(), ArchiveType::Rar);
     }
 
+    #[test]
+    fn test_stored_name_is_verbatim() {
+        assert_eq!(stored_name(Path::new("Vol1/Page01.JPG")), "Vol1/Page01.JPG");
+        assert_ne!(
+            stored_name(Path::new("Vol1/Page01.JPG")),
+            stored_name(Path::new("vol1/page01.jpg"))
+        );
+    }
+
     // Note: More comprehensive tests require actual RAR files
     // These should be added as integration tests with test fixtures
 }
@@ -6,66 +6,83 @@ use std::fs::File;
 use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
 
-use super::utils::{find_first_image, is_image_file, MAX_ENTRY_SIZE};
-use crate::archive::{Archive, ArchiveEntry, ArchiveMetadata, ArchiveType};
+use super::utils::{
+    borrow_reader, find_first_image, is_image_file, no_images_error, non_image_scan_exhausted,
+    normalize_entry_name, read_entry_to_end, MAX_ENTRY_SIZE,
+};
+use crate::archive::{
+    likely_photo_album, Archive, ArchiveEntry, ArchiveMetadata, ArchiveType, ListOptions,
+};
 use crate::utils::error::{CbxError, Result};
+use crate::utils::file::open_file_with_retry;
+
+/// Open the 7z file at `path` along with its length
+///
+/// Failures are `CbxError::Io`, not `Archive`: a file that is locked or
+/// briefly unavailable may open on the next request, so it must not be
+/// remembered as a broken archive.
+fn open_with_len(path: &Path) -> Result<(File, u64)> {
+    let file = open_file_with_retry(path)?;
+    let len = file.metadata()?.len();
+    Ok((file, len))
+}
+
+/// `sevenz_rust` password for a configured one (empty when none)
+fn seven_zip_password(password: Option<&str>) -> Password {
+    password.map(Password::from).unwrap_or_else(Password::empty)
+}
+
+/// `CbxError` for a `sevenz_rust` failure, `context` prefixing other errors
+///
+/// A missing or wrong password is `PasswordProtected` rather than a damaged
+/// archive: with encrypted headers a wrong key fails while reading them,
+/// otherwise when the first folder's decoder is set up.
+fn seven_zip_error(context: &str, error: sevenz_rust::Error) -> CbxError {
+    match error {
+        sevenz_rust::Error::PasswordRequired | sevenz_rust::Error::MaybeBadPassword(_) => {
+            CbxError::PasswordProtected
+        }
+        e => CbxError::Archive(format!("{}: {}", context, e)),
+    }
+}
 
 /// 7-Zip archive handler
 pub struct SevenZipArchive {
     path: PathBuf,
+    /// Configured password for encrypted archives
+    password: Option<String>,
+    /// Sort order and scan limit from the settings
+    options: ListOptions,
 }
 
 impl SevenZipArchive {
     /// Open a 7z archive from path
     pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with_password(path, None)
+    }
+
+    /// Open a 7z archive, decrypting with `password` (see `Settings::archive_password_for`)
+    pub fn open_with_password(path: &Path, password: Option<String>) -> Result<Self> {
         tracing::debug!("Opening 7-Zip archive: {:?}", path);
 
         // Validate by attempting to open
-        let file = File::open(path)
-            .map_err(|e| CbxError::Archive(format!("Failed to open 7z file: {}", e)))?;
-
-        let file_len = file
-            .metadata()
-            .map_err(|e| CbxError::Archive(format!("Failed to get file metadata: {}", e)))?
-            .len();
+        let (file, file_len) = open_with_len(path)?;
 
-        let password = Password::empty();
-        let mut _reader = SevenZReader::new(file, file_len, password)
-            .map_err(|e| CbxError::Archive(format!("Invalid 7z archive: {}", e)))?;
+        let mut _reader =
+            SevenZReader::new(file, file_len, seven_zip_password(password.as_deref()))
+                .map_err(|e| seven_zip_error("Invalid 7z archive", e))?;
 
         Ok(Self {
             path: path.to_path_buf(),
+            password,
+            options: ListOptions::default(),
         })
     }
 
-    /// List all entries in archive
-    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
-        let file = File::open(&self.path)
-            .map_err(|e| CbxError::Archive(format!("Failed to open 7z: {}", e)))?;
-
-        let file_len = file
-            .metadata()
-            .map_err(|e| CbxError::Archive(format!("Failed to get file metadata: {}", e)))?
-            .len();
-
-        let password = Password::empty();
-        let mut archive = SevenZReader::new(file, file_len, password)
-            .map_err(|e| CbxError::Archive(format!("Failed to read 7z: {}", e)))?;
-
-        let mut entries = Vec::new();
-
-        archive
-            .for_each_entries(|entry, _reader| {
-                entries.push(ArchiveEntry {
-                    name: entry.name().to_string(),
-                    size: entry.size(),
-                    is_directory: entry.is_directory(),
-                });
-                Ok(true) // Continue iteration
-            })
-            .map_err(|e| CbxError::Archive(format!("7z iteration error: {}", e)))?;
-
-        Ok(entries)
+    /// List with `options` instead of the defaults (see `open_archive_with`)
+    pub fn with_list_options(mut self, options: ListOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
@@ -74,6 +91,10 @@ impl Archive for SevenZipArchive {
         Ok(Box::new(Self::open(path)?))
     }
 
+    fn list_options(&self) -> ListOptions {
+        self.options
+    }
+
     fn find_first_image(&self, sort: bool) -> Result<ArchiveEntry> {
         tracing::debug!("Finding first image in 7z (sort={})", sort);
 
@@ -81,39 +102,43 @@ impl Archive for SevenZipArchive {
             // OPTIMIZATION: When not sorting, find first image immediately
             tracing::debug!("Fast path: finding first image without full listing");
 
-            let file = File::open(&self.path)
-                .map_err(|e| CbxError::Archive(format!("Failed to open 7z: {}", e)))?;
-
-            let file_len = file
-                .metadata()
-                .map_err(|e| CbxError::Archive(format!("Failed to get file metadata: {}", e)))?
-                .len();
+            let (file, file_len) = open_with_len(&self.path)?;
 
-            let password = Password::empty();
+            let password = seven_zip_password(self.password.as_deref());
             let mut archive = SevenZReader::new(file, file_len, password)
-                .map_err(|e| CbxError::Archive(format!("Failed to read 7z: {}", e)))?;
+                .map_err(|e| seven_zip_error("Failed to read 7z", e))?;
 
+            let cap = self.options.max_non_image_scan;
             let mut first_image: Option<ArchiveEntry> = None;
+            let mut other_files = Vec::new();
+            let mut gave_up = false;
 
             archive
                 .for_each_entries(|entry, _reader| {
-                    let name = entry.name().to_string();
-                    if is_image_file(&name) {
+                    let name = normalize_entry_name(entry.name());
+                    if !entry.is_directory() && is_image_file(&name) {
                         tracing::info!("Found first image (unsorted): {}", name);
                         first_image = Some(ArchiveEntry {
                             name,
                             size: entry.size(),
-                            is_directory: entry.is_directory(),
+                            is_directory: false,
                         });
                         Ok(false) // Stop iteration
                     } else {
-                        Ok(true) // Continue
+                        if !entry.is_directory() {
+                            other_files.push(name);
+                            gave_up = non_image_scan_exhausted(other_files.len(), cap);
+                        }
+                        Ok(!gave_up) // Continue unless the scan gave up
                     }
                 })
-                .map_err(|e| CbxError::Archive(format!("7z iteration error: {}", e)))?;
+                .map_err(|e| seven_zip_error("7z iteration error", e))?;
 
+            if gave_up {
+                return Err(CbxError::NoImages);
+            }
             return first_image
-                .ok_or_else(|| CbxError::Archive("No images found in archive".to_string()));
+                .ok_or_else(|| no_images_error(other_files.iter().map(|s| s.as_str())));
         }
 
         // STANDARD PATH: List all entries and sort
@@ -123,10 +148,24 @@ impl Archive for SevenZipArchive {
             return Err(CbxError::Archive("Archive is empty".to_string()));
         }
 
-        let names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
+        let names: Vec<String> = entries
+            .iter()
+            .filter(|e| !e.is_directory)
+            .map(|e| e.name.clone())
+            .collect();
 
-        let image_name = find_first_image(names.iter().map(|s| s.as_str()), sort)
-            .ok_or_else(|| CbxError::Archive("No images found in archive".to_string()))?;
+        let image_name = find_first_image(
+            names.iter().map(|s| s.as_str()),
+            sort.then_some(self.options.order),
+        )
+        .ok_or_else(|| {
+            no_images_error(
+                entries
+                    .iter()
+                    .filter(|e| !e.is_directory)
+                    .map(|e| e.name.as_str()),
+            )
+        })?;
 
         tracing::info!("Found first image (sorted): {}", image_name);
 
@@ -136,6 +175,37 @@ impl Archive for SevenZipArchive {
             .ok_or_else(|| CbxError::Archive("Image entry not found".to_string()))
     }
 
+    fn find_images(&self) -> Result<Vec<ArchiveEntry>> {
+        Ok(self
+            .list_entries()?
+            .into_iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .collect())
+    }
+
+    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let (file, file_len) = open_with_len(&self.path)?;
+
+        let password = seven_zip_password(self.password.as_deref());
+        let mut archive = SevenZReader::new(file, file_len, password)
+            .map_err(|e| seven_zip_error("Failed to read 7z", e))?;
+
+        let mut entries = Vec::new();
+
+        archive
+            .for_each_entries(|entry, _reader| {
+                entries.push(ArchiveEntry {
+                    name: normalize_entry_name(entry.name()),
+                    size: entry.size(),
+                    is_directory: entry.is_directory(),
+                });
+                Ok(true) // Continue iteration
+            })
+            .map_err(|e| seven_zip_error("7z iteration error", e))?;
+
+        Ok(entries)
+    }
+
     fn extract_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
         tracing::debug!("Extracting entry: {} ({} bytes)", entry.name, entry.size);
 
@@ -152,41 +222,39 @@ impl Archive for SevenZipArchive {
             )));
         }
 
-        let file = File::open(&self.path)
-            .map_err(|e| CbxError::Archive(format!("Failed to open 7z: {}", e)))?;
+        let (file, file_len) = open_with_len(&self.path)?;
 
-        let file_len = file
-            .metadata()
-            .map_err(|e| CbxError::Archive(format!("Failed to get file metadata: {}", e)))?
-            .len();
-
-        let password = Password::empty();
+        let password = seven_zip_password(self.password.as_deref());
         let mut archive = SevenZReader::new(file, file_len, password)
-            .map_err(|e| CbxError::Archive(format!("Failed to read 7z: {}", e)))?;
+            .map_err(|e| seven_zip_error("Failed to read 7z", e))?;
 
+        let wanted = normalize_entry_name(&entry.name);
         let mut extracted_data = None;
 
         archive
             .for_each_entries(|sz_entry, reader| {
-                if sz_entry.name() == entry.name {
-                    let mut buffer = Vec::with_capacity(sz_entry.size() as usize);
-                    std::io::copy(reader, &mut buffer)
-                        .map_err(|e| sevenz_rust::Error::Io(e, "Extract failed".into()))?;
-                    extracted_data = Some(buffer);
+                // Compare normalized forms; the reader still extracts by the stored name
+                if normalize_entry_name(sz_entry.name()) == wanted {
+                    extracted_data =
+                        Some(read_entry_to_end(reader, sz_entry.size(), sz_entry.name()));
                     Ok(false) // Stop iteration
                 } else {
                     Ok(true) // Continue
                 }
             })
-            .map_err(|e| CbxError::Archive(format!("7z extraction error: {}", e)))?;
+            .map_err(|e| seven_zip_error("7z extraction error", e))?;
 
-        extracted_data.ok_or_else(|| CbxError::Archive(format!("Entry not found: {}", entry.name)))
+        extracted_data
+            .ok_or_else(|| CbxError::Archive(format!("Entry not found: {}", entry.name)))?
     }
 
     fn get_metadata(&self) -> Result<ArchiveMetadata> {
         let entries = self.list_entries()?;
         let total_files = entries.len();
-        let image_count = entries.iter().filter(|e| is_image_file(&e.name)).count();
+        let image_count = entries
+            .iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .count();
 
         let compressed_size = std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
 
@@ -202,6 +270,7 @@ impl Archive for SevenZipArchive {
             image_count,
             compressed_size,
             archive_type: ArchiveType::SevenZip,
+            likely_photo_album: likely_photo_album(self, image_count),
         })
     }
 
@@ -214,6 +283,7 @@ impl Archive for SevenZipArchive {
 mod tests {
     use super::*;
     use sevenz_rust::SevenZWriter;
+    use std::fs::File;
     use std::io::Write;
     use tempfile::Builder;
 
@@ -239,6 +309,38 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_directory_named_like_image_is_skipped() {
+        let temp_file = Builder::new()
+            .prefix("test_image_dir_")
+            .suffix(".cb7")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        {
+            let mut sz = SevenZWriter::new(File::create(temp_path).unwrap()).unwrap();
+            let mut directory = sevenz_rust::SevenZArchiveEntry::new();
+            directory.name = "cover.jpg".to_string();
+            directory.is_directory = true;
+            directory.has_stream = false;
+            sz.push_archive_entry::<&[u8]>(directory, None).unwrap();
+            sz.push_archive_entry(
+                sevenz_rust::SevenZArchiveEntry::from_path("page02.jpg", "page02.jpg".to_string()),
+                Some(&b"page data"[..]),
+            )
+            .unwrap();
+            sz.finish().unwrap();
+        }
+
+        let archive = SevenZipArchive::open(temp_path).unwrap();
+        for sort in [false, true] {
+            let entry = archive.find_first_image(sort).unwrap();
+            assert_eq!(entry.name, "page02.jpg", "sort={}", sort);
+            assert!(!entry.is_directory);
+        }
+        assert_eq!(archive.get_metadata().unwrap().image_count, 1);
+    }
+
     #[test]
     fn test_open_valid_7z() {
         let temp_file = Builder::new()
@@ -253,6 +355,72 @@ mod tests {
         assert_eq!(archive.archive_type(), ArchiveType::SevenZip);
     }
 
+    #[test]
+    fn test_decomposed_name_round_trips() {
+        let temp_file = Builder::new()
+            .prefix("test_nfd_")
+            .suffix(".cb7")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        // "Café" with a combining acute accent (NFD, as written on macOS)
+        create_test_7z_file(temp_path, &[("Cafe\u{301}/01.jpg", b"page data")]).unwrap();
+
+        let archive = SevenZipArchive::open(temp_path).unwrap();
+        let entry = archive.find_first_image(true).unwrap();
+        assert_eq!(archive.extract_entry(&entry).unwrap(), b"page data");
+
+        // Separators are normalized before comparing
+        let backslashed = ArchiveEntry {
+            name: entry.name.replace('/', "\\"),
+            ..entry.clone()
+        };
+        assert_eq!(archive.extract_entry(&backslashed).unwrap(), b"page data");
+
+        // Listed names are composed, and composed names extract the NFD entry
+        #[cfg(windows)]
+        {
+            assert_eq!(entry.name, "Caf\u{e9}/01.jpg");
+            let composed = ArchiveEntry {
+                name: "Caf\u{e9}/01.jpg".to_string(),
+                ..entry
+            };
+            assert_eq!(archive.extract_entry(&composed).unwrap(), b"page data");
+        }
+    }
+
+    #[test]
+    fn test_names_differing_only_by_case_extract_separately() {
+        let temp_file = Builder::new()
+            .prefix("test_case_")
+            .suffix(".cb7")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_7z_file(
+            temp_path,
+            &[("Page1.jpg", b"upper"), ("page1.jpg", b"lower")],
+        )
+        .unwrap();
+
+        let archive = SevenZipArchive::open(temp_path).unwrap();
+        let images = archive.find_images().unwrap();
+        let names: Vec<&str> = images.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["Page1.jpg", "page1.jpg"]);
+
+        // Stored names are carried verbatim from listing to extraction
+        assert_eq!(archive.extract_entry(&images[0]).unwrap(), b"upper");
+        assert_eq!(archive.extract_entry(&images[1]).unwrap(), b"lower");
+
+        let first = archive.find_first_image(true).unwrap();
+        let expected: &[u8] = if first.name == "Page1.jpg" {
+            b"upper"
+        } else {
+            b"lower"
+        };
+        assert_eq!(archive.extract_entry(&first).unwrap(), expected);
+    }
+
     #[test]
     fn test_open_invalid_7z() {
         let temp_file = Builder::new()
@@ -267,6 +435,25 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_wrong_password_is_password_protected() {
+        let bad_key = std::io::Error::new(std::io::ErrorKind::InvalidData, "bad lzma header");
+        let error = seven_zip_error(
+            "7z iteration error",
+            sevenz_rust::Error::MaybeBadPassword(bad_key),
+        );
+        assert!(matches!(error, CbxError::PasswordProtected), "{}", error);
+
+        let error = seven_zip_error("7z iteration error", sevenz_rust::Error::PasswordRequired);
+        assert!(matches!(error, CbxError::PasswordProtected), "{}", error);
+
+        let error = seven_zip_error(
+            "7z iteration error",
+            sevenz_rust::Error::BadSignature([0; 6]),
+        );
+        assert!(matches!(error, CbxError::Archive(_)), "{}", error);
+    }
+
     #[test]
     fn test_find_first_image_sorted() {
         let temp_file = Builder::new()
@@ -357,6 +544,55 @@ mod tests {
         assert_eq!(extracted, content);
     }
 
+    #[test]
+    fn test_open_from_stream() {
+        let temp_file = Builder::new()
+            .prefix("test_stream_")
+            .suffix(".7z")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_7z_file(
+            temp_path,
+            &[("readme.txt", b"text"), ("page1.jpg", b"image 1")],
+        )
+        .unwrap();
+
+        let archive =
+            crate::archive::open_archive_from_stream(File::open(temp_path).unwrap()).unwrap();
+        let entry = archive.find_first_image(true).unwrap();
+        assert_eq!(entry.name, "page1.jpg");
+        assert_eq!(archive.extract_entry(&entry).unwrap(), b"image 1");
+    }
+
+    #[test]
+    fn test_stream_reentrant_access_fails_cleanly() {
+        let temp_file = Builder::new()
+            .prefix("test_reentrant_")
+            .suffix(".7z")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_7z_file(temp_path, &[("page1.jpg", b"image 1")]).unwrap();
+
+        let archive = SevenZipArchiveFromStream::new(File::open(temp_path).unwrap()).unwrap();
+        let entry = archive.find_first_image(false).unwrap();
+
+        // Simulate a nested call made while an outer operation holds the reader
+        let held = archive.reader.borrow_mut();
+        assert!(matches!(
+            archive.find_first_image(false),
+            Err(CbxError::Archive(_))
+        ));
+        assert!(matches!(
+            archive.extract_entry(&entry),
+            Err(CbxError::Archive(_))
+        ));
+        drop(held);
+
+        assert_eq!(archive.extract_entry(&entry).unwrap(), b"image 1");
+    }
+
     #[test]
     fn test_get_metadata() {
         let temp_file = Builder::new()
@@ -407,10 +643,14 @@ mod tests {
 pub struct SevenZipArchiveFromStream<R: Read + Seek> {
     reader: std::cell::RefCell<R>,
     size: u64,
+    /// Configured password for encrypted archives
+    password: Option<String>,
+    /// Sort order and scan limit from the settings
+    options: ListOptions,
 }
 
 impl<R: Read + Seek> SevenZipArchiveFromStream<R> {
-    /// Create a 7z archive from a streaming reader
+    /// Create a 7z archive from a streaming reader at any position
     ///
     /// # Arguments
     /// * `reader` - Any Read + Seek implementer
@@ -418,72 +658,52 @@ impl<R: Read + Seek> SevenZipArchiveFromStream<R> {
     /// # Returns
     /// * `Ok(Self)` - Archive ready for processing
     /// * `Err(CbxError)` - If validation fails
+    #[cfg(test)]
     pub fn new(mut reader: R) -> Result<Self> {
         use std::io::SeekFrom;
 
-        // Get size
-        let size = reader
-            .seek(SeekFrom::End(0))
-            .map_err(|e| CbxError::Archive(format!("Failed to get stream size: {}", e)))?;
+        // Get size, then rewind for from_start
+        let size = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        Self::from_start(reader, size, None)
+    }
 
+    /// Create a 7z archive from a reader already positioned at 0
+    ///
+    /// Used by `open_archive_from_stream`, which positions the stream once
+    /// after magic detection. No seek is issued here: the header is read in
+    /// place, and every later operation seeks to the start itself.
+    ///
+    /// # Arguments
+    /// * `reader` - Any Read + Seek implementer, positioned at 0
+    /// * `size` - Total stream length in bytes
+    /// * `password` - Password for encrypted archives (see `Settings::archive_password_for`)
+    pub fn from_start(mut reader: R, size: u64, password: Option<String>) -> Result<Self> {
         tracing::debug!("Creating 7z archive from stream ({} bytes)", size);
-        crate::utils::debug_log::debug_log(&format!(
-            ">>>>> SevenZipArchiveFromStream::new ({} bytes) <<<<<",
+        crate::debug_log!(
+            ">>>>> SevenZipArchiveFromStream::from_start ({} bytes) <<<<<",
             size
-        ));
-
-        // Seek back to start
-        reader
-            .seek(SeekFrom::Start(0))
-            .map_err(|e| CbxError::Archive(format!("Failed to seek to start: {}", e)))?;
+        );
 
         // Validate by creating a test reader
-        let password = Password::empty();
-        let _test = SevenZReader::new(&mut reader, size, password)
-            .map_err(|e| CbxError::Archive(format!("Invalid 7z archive from stream: {}", e)))?;
-
-        // Seek back to start again
-        reader
-            .seek(SeekFrom::Start(0))
-            .map_err(|e| CbxError::Archive(format!("Failed to seek to start: {}", e)))?;
+        let _test = SevenZReader::new(&mut reader, size, seven_zip_password(password.as_deref()))
+            .map_err(|e| seven_zip_error("Invalid 7z archive from stream", e))?;
 
-        crate::utils::debug_log::debug_log("7z archive validated successfully");
+        crate::debug_log!("7z archive validated successfully");
 
         Ok(Self {
             reader: std::cell::RefCell::new(reader),
             size,
+            password,
+            options: ListOptions::default(),
         })
     }
 
-    /// List all entries in archive (helper method)
-    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
-        use std::io::SeekFrom;
-
-        let mut reader_ref = self.reader.borrow_mut();
-
-        // Seek to start
-        reader_ref
-            .seek(SeekFrom::Start(0))
-            .map_err(|e| CbxError::Archive(format!("Failed to seek to start: {}", e)))?;
-
-        let password = Password::empty();
-        let mut archive = SevenZReader::new(&mut *reader_ref, self.size, password)
-            .map_err(|e| CbxError::Archive(format!("Failed to create 7z reader: {}", e)))?;
-
-        let mut entries = Vec::new();
-
-        archive
-            .for_each_entries(|entry, _reader| {
-                entries.push(ArchiveEntry {
-                    name: entry.name().to_string(),
-                    size: entry.size(),
-                    is_directory: entry.is_directory(),
-                });
-                Ok(true) // Continue iteration
-            })
-            .map_err(|e| CbxError::Archive(format!("7z iteration error: {}", e)))?;
-
-        Ok(entries)
+    /// List with `options` instead of the defaults (see `open_archive_with`)
+    pub fn with_list_options(mut self, options: ListOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
@@ -495,49 +715,61 @@ impl<R: Read + Seek> Archive for SevenZipArchiveFromStream<R> {
         ))
     }
 
+    fn list_options(&self) -> ListOptions {
+        self.options
+    }
+
     fn find_first_image(&self, sort: bool) -> Result<ArchiveEntry> {
         tracing::debug!("Finding first image in 7z from stream (sort={})", sort);
-        crate::utils::debug_log::debug_log(&format!("7z stream: find_first_image (sort={})", sort));
+        crate::debug_log!("7z stream: find_first_image (sort={})", sort);
 
         if !sort {
             // OPTIMIZATION: Fast path - find first image without full listing
             use std::io::SeekFrom;
             tracing::debug!("7z stream: Fast path - finding first image");
 
-            let mut reader_ref = self.reader.borrow_mut();
+            let mut reader_ref = borrow_reader(&self.reader)?;
 
             // Seek to start
-            reader_ref
-                .seek(SeekFrom::Start(0))
-                .map_err(|e| CbxError::Archive(format!("Failed to seek to start: {}", e)))?;
+            reader_ref.seek(SeekFrom::Start(0))?;
 
-            let password = Password::empty();
+            let password = seven_zip_password(self.password.as_deref());
             let mut archive = SevenZReader::new(&mut *reader_ref, self.size, password)
-                .map_err(|e| CbxError::Archive(format!("Failed to create 7z reader: {}", e)))?;
+                .map_err(|e| seven_zip_error("Failed to create 7z reader", e))?;
 
+            let cap = self.options.max_non_image_scan;
             let mut first_image: Option<ArchiveEntry> = None;
+            let mut other_files = Vec::new();
+            let mut gave_up = false;
 
             archive
                 .for_each_entries(|entry, _reader| {
-                    let name = entry.name().to_string();
-                    if is_image_file(&name) {
+                    let name = normalize_entry_name(entry.name());
+                    if !entry.is_directory() && is_image_file(&name) {
                         tracing::info!("Found first image (unsorted, streaming): {}", name);
-                        crate::utils::debug_log::debug_log(&format!("Found first image: {}", name));
+                        crate::debug_log!("Found first image: {}", name);
 
                         first_image = Some(ArchiveEntry {
                             name,
                             size: entry.size(),
-                            is_directory: entry.is_directory(),
+                            is_directory: false,
                         });
                         Ok(false) // Stop iteration
                     } else {
-                        Ok(true) // Continue
+                        if !entry.is_directory() {
+                            other_files.push(name);
+                            gave_up = non_image_scan_exhausted(other_files.len(), cap);
+                        }
+                        Ok(!gave_up) // Continue unless the scan gave up
                     }
                 })
-                .map_err(|e| CbxError::Archive(format!("7z iteration error: {}", e)))?;
+                .map_err(|e| seven_zip_error("7z iteration error", e))?;
 
+            if gave_up {
+                return Err(CbxError::NoImages);
+            }
             return first_image
-                .ok_or_else(|| CbxError::Archive("No images found in archive".to_string()));
+                .ok_or_else(|| no_images_error(other_files.iter().map(|s| s.as_str())));
         }
 
         // STANDARD PATH: List all entries and sort
@@ -548,13 +780,27 @@ impl<R: Read + Seek> Archive for SevenZipArchiveFromStream<R> {
             return Err(CbxError::Archive("Archive is empty".to_string()));
         }
 
-        let names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
+        let names: Vec<String> = entries
+            .iter()
+            .filter(|e| !e.is_directory)
+            .map(|e| e.name.clone())
+            .collect();
 
-        let image_name = find_first_image(names.iter().map(|s| s.as_str()), sort)
-            .ok_or_else(|| CbxError::Archive("No images found in archive".to_string()))?;
+        let image_name = find_first_image(
+            names.iter().map(|s| s.as_str()),
+            sort.then_some(self.options.order),
+        )
+        .ok_or_else(|| {
+            no_images_error(
+                entries
+                    .iter()
+                    .filter(|e| !e.is_directory)
+                    .map(|e| e.name.as_str()),
+            )
+        })?;
 
         tracing::info!("Found first image (sorted, streaming): {}", image_name);
-        crate::utils::debug_log::debug_log(&format!("Found first image (sorted): {}", image_name));
+        crate::debug_log!("Found first image (sorted): {}", image_name);
 
         entries
             .into_iter()
@@ -562,16 +808,53 @@ impl<R: Read + Seek> Archive for SevenZipArchiveFromStream<R> {
             .ok_or_else(|| CbxError::Archive("Image entry not found".to_string()))
     }
 
+    fn find_images(&self) -> Result<Vec<ArchiveEntry>> {
+        Ok(self
+            .list_entries()?
+            .into_iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .collect())
+    }
+
+    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        use std::io::SeekFrom;
+
+        let mut reader_ref = borrow_reader(&self.reader)?;
+
+        // Seek to start
+        reader_ref.seek(SeekFrom::Start(0))?;
+
+        let password = seven_zip_password(self.password.as_deref());
+        let mut archive = SevenZReader::new(&mut *reader_ref, self.size, password)
+            .map_err(|e| seven_zip_error("Failed to create 7z reader", e))?;
+
+        let mut entries = Vec::new();
+
+        archive
+            .for_each_entries(|entry, _reader| {
+                entries.push(ArchiveEntry {
+                    name: normalize_entry_name(entry.name()),
+                    size: entry.size(),
+                    is_directory: entry.is_directory(),
+                });
+                Ok(true) // Continue iteration
+            })
+            .map_err(|e| seven_zip_error("7z iteration error", e))?;
+
+        Ok(entries)
+    }
+
     fn extract_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
         tracing::debug!(
             "Extracting entry from 7z stream: {} ({} bytes)",
             entry.name,
             entry.size
         );
-        crate::utils::debug_log::debug_log(&format!(
+        crate::debug_log!(
             "7z stream: extract_entry: {} ({} bytes)",
-            entry.name, entry.size
-        ));
+            entry.name,
+            entry.size
+        );
 
         // Safety check: prevent memory exhaustion
         if entry.size > MAX_ENTRY_SIZE {
@@ -589,49 +872,48 @@ impl<R: Read + Seek> Archive for SevenZipArchiveFromStream<R> {
         // Create a new reader for extraction
         use std::io::SeekFrom;
 
-        let mut reader_ref = self.reader.borrow_mut();
+        let mut reader_ref = borrow_reader(&self.reader)?;
 
         // Seek to start
-        reader_ref
-            .seek(SeekFrom::Start(0))
-            .map_err(|e| CbxError::Archive(format!("Failed to seek to start: {}", e)))?;
+        reader_ref.seek(SeekFrom::Start(0))?;
 
-        let password = Password::empty();
+        let password = seven_zip_password(self.password.as_deref());
         let mut archive = SevenZReader::new(&mut *reader_ref, self.size, password)
-            .map_err(|e| CbxError::Archive(format!("Failed to create 7z reader: {}", e)))?;
+            .map_err(|e| seven_zip_error("Failed to create 7z reader", e))?;
 
+        let wanted = normalize_entry_name(&entry.name);
         let mut extracted_data = None;
 
         archive
             .for_each_entries(|sz_entry, reader| {
-                if sz_entry.name() == entry.name {
-                    let mut buffer = Vec::with_capacity(sz_entry.size() as usize);
-                    std::io::copy(reader, &mut buffer)
-                        .map_err(|e| sevenz_rust::Error::Io(e, "Extract failed".into()))?;
-
-                    tracing::debug!("Extracted {} bytes from 7z stream", buffer.len());
-                    crate::utils::debug_log::debug_log(&format!(
-                        "Extracted {} bytes",
-                        buffer.len()
-                    ));
-
-                    extracted_data = Some(buffer);
+                // Compare normalized forms; the reader still extracts by the stored name
+                if normalize_entry_name(sz_entry.name()) == wanted {
+                    let data = read_entry_to_end(reader, sz_entry.size(), sz_entry.name());
+                    if let Ok(buffer) = &data {
+                        tracing::debug!("Extracted {} bytes from 7z stream", buffer.len());
+                        crate::debug_log!("Extracted {} bytes", buffer.len());
+                    }
+
+                    extracted_data = Some(data);
                     Ok(false) // Stop iteration
                 } else {
                     Ok(true) // Continue
                 }
             })
-            .map_err(|e| CbxError::Archive(format!("7z extraction error: {}", e)))?;
+            .map_err(|e| seven_zip_error("7z extraction error", e))?;
 
         extracted_data.ok_or_else(|| {
             CbxError::Archive(format!("Entry not found in 7z stream: {}", entry.name))
-        })
+        })?
     }
 
     fn get_metadata(&self) -> Result<ArchiveMetadata> {
         let entries = self.list_entries()?;
         let total_files = entries.len();
-        let image_count = entries.iter().filter(|e| is_image_file(&e.name)).count();
+        let image_count = entries
+            .iter()
+            .filter(|e| !e.is_directory && is_image_file(&e.name))
+            .count();
 
         tracing::debug!(
             "7z metadata (from stream): {} files, {} images",
@@ -644,6 +926,7 @@ impl<R: Read + Seek> Archive for SevenZipArchiveFromStream<R> {
             image_count,
             compressed_size: self.size,
             archive_type: ArchiveType::SevenZip,
+            likely_photo_album: likely_photo_album(self, image_count),
         })
     }
 
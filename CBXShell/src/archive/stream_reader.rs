@@ -129,10 +129,10 @@ impl Seek for IStreamReader {
 /// * `Ok(ArchiveType)` - The detected archive type
 /// * `Err(CbxError)` - If the format is not recognized
 pub fn detect_archive_type_from_bytes(data: &[u8]) -> Result<ArchiveType> {
-    crate::utils::debug_log::debug_log(">>>>> detect_archive_type_from_bytes STARTING <<<<<");
+    crate::debug_log!(">>>>> detect_archive_type_from_bytes STARTING <<<<<");
 
     if data.len() < 8 {
-        crate::utils::debug_log::debug_log(&format!("ERROR: Data too short: {} bytes", data.len()));
+        crate::debug_log!("ERROR: Data too short: {} bytes", data.len());
         return Err(CbxError::UnsupportedFormat("Data too short".to_string()));
     }
 
@@ -142,17 +142,13 @@ pub fn detect_archive_type_from_bytes(data: &[u8]) -> Result<ArchiveType> {
         .iter()
         .map(|b| format!("{:02X}", b))
         .collect();
-    crate::utils::debug_log::debug_log(&format!(
-        "First {} bytes: {}",
-        preview_len,
-        hex_preview.join(" ")
-    ));
+    crate::debug_log!("First {} bytes: {}", preview_len, hex_preview.join(" "));
 
     // Check ZIP magic bytes
     if data.len() >= 4 {
         let magic = &data[0..4];
         if magic == b"PK\x03\x04" || magic == b"PK\x05\x06" || magic == b"PK\x07\x08" {
-            crate::utils::debug_log::debug_log("Detected: ZIP format");
+            crate::debug_log!("Detected: ZIP format");
             return Ok(ArchiveType::Zip);
         }
     }
@@ -161,7 +157,7 @@ pub fn detect_archive_type_from_bytes(data: &[u8]) -> Result<ArchiveType> {
     if data.len() >= 6 {
         let magic = &data[0..6];
         if magic == b"7z\xBC\xAF\x27\x1C" {
-            crate::utils::debug_log::debug_log("Detected: 7-Zip format");
+            crate::debug_log!("Detected: 7-Zip format");
             return Ok(ArchiveType::SevenZip);
         }
     }
@@ -170,7 +166,7 @@ pub fn detect_archive_type_from_bytes(data: &[u8]) -> Result<ArchiveType> {
     if data.len() >= 7 {
         let magic = &data[0..7];
         if magic == b"Rar!\x1A\x07\x00" {
-            crate::utils::debug_log::debug_log("Detected: RAR 4.x format");
+            crate::debug_log!("Detected: RAR 4.x format");
             return Ok(ArchiveType::Rar);
         }
     }
@@ -178,12 +174,12 @@ pub fn detect_archive_type_from_bytes(data: &[u8]) -> Result<ArchiveType> {
     if data.len() >= 8 {
         let magic = &data[0..8];
         if magic == b"Rar!\x1A\x07\x01\x00" {
-            crate::utils::debug_log::debug_log("Detected: RAR 5.x format");
+            crate::debug_log!("Detected: RAR 5.x format");
             return Ok(ArchiveType::Rar);
         }
     }
 
-    crate::utils::debug_log::debug_log("ERROR: Unrecognized archive format");
+    crate::debug_log!("ERROR: Unrecognized archive format");
     Err(CbxError::UnsupportedFormat(
         "Unrecognized archive format".to_string(),
     ))
@@ -1,51 +1,509 @@
+use crate::archive::ArchiveEntry;
 use crate::utils::error::{CbxError, Result};
 ///! Shared utilities for archive processing
 ///!
 ///! Provides image detection, natural sorting, and common helpers
+use std::borrow::Cow;
+use std::cell::{RefCell, RefMut};
+use std::collections::HashSet;
+use std::io::Read;
 use std::path::Path;
+use std::sync::OnceLock;
 
 /// Maximum uncompressed size for a single entry (32MB)
 /// This matches the C++ implementation's CBXMEM_MAXBUFFER_SIZE
 pub const MAX_ENTRY_SIZE: u64 = 32 * 1024 * 1024;
 
+/// Largest buffer reserved up front for an entry, whatever size it declares
+///
+/// Declared sizes come from archive headers and can be far larger than the
+/// data actually present; the buffer grows past this as real bytes arrive.
+pub const MAX_CAPACITY_HINT: u64 = 4 * 1024 * 1024;
+
+/// Capacity to reserve for an entry declaring `declared_size` bytes
+pub fn capacity_hint(declared_size: u64) -> usize {
+    declared_size.min(MAX_CAPACITY_HINT) as usize
+}
+
+/// Read an entry to its end, enforcing `MAX_ENTRY_SIZE`
+///
+/// `declared_size` is only a capacity hint; the limit is checked against the
+/// bytes actually produced, so a wrong recorded size can't slip past it.
+///
+/// A read that fails partway (an entry declaring more data than the archive
+/// holds ends in an unexpected EOF or checksum error) keeps the bytes it got
+/// when they start with a known image signature; decoders stop at the image's
+/// own end marker, so the cover often still renders.
+pub fn read_entry_to_end(reader: impl Read, declared_size: u64, name: &str) -> Result<Vec<u8>> {
+    use crate::image_processor::magic::verify_image_format;
+
+    let mut buffer = Vec::with_capacity(capacity_hint(declared_size));
+    if let Err(e) = reader.take(MAX_ENTRY_SIZE + 1).read_to_end(&mut buffer) {
+        if buffer.is_empty() || verify_image_format(&buffer).is_err() {
+            return Err(CbxError::Archive(format!("Failed to extract entry: {}", e)));
+        }
+        tracing::warn!(
+            "Short read of {} ({} of {} bytes): {}; using partial data",
+            name,
+            buffer.len(),
+            declared_size,
+            e
+        );
+        crate::debug_log!(
+            "Partial read of {}: {} of {} declared bytes ({})",
+            name,
+            buffer.len(),
+            declared_size,
+            e
+        );
+    }
+
+    if buffer.len() as u64 > MAX_ENTRY_SIZE {
+        tracing::warn!("Entry exceeded {} bytes while reading", MAX_ENTRY_SIZE);
+        return Err(CbxError::Archive(
+            "Entry too large: more than 32MB of data".to_string(),
+        ));
+    }
+    Ok(buffer)
+}
+
 /// Supported image extensions
 /// Includes modern formats (WebP, AVIF) for Phase 3
 const IMAGE_EXTENSIONS: &[&str] = &[
-    "bmp", "ico", "gif", "jpg", "jpe", "jfif", "jpeg", "png", "tif", "tiff",
+    "bmp",
+    "ico",
+    "gif",
+    "jpg",
+    "jpe",
+    "jfif",
+    "jpeg",
+    "png",
+    "tif",
+    "tiff",
     "webp", // Phase 3
     "avif", // Phase 3
+    #[cfg(feature = "svg")]
+    "svg",
 ];
 
+/// Extensions longer than this are never images (lets lowercasing use a stack buffer)
+const MAX_IMAGE_EXTENSION_LEN: usize = 16;
+
+/// Image extensions as a set, built on first use
+fn image_extension_set() -> &'static HashSet<&'static str> {
+    static SET: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    SET.get_or_init(|| IMAGE_EXTENSIONS.iter().copied().collect())
+}
+
 /// Check if filename is an image based on extension
+///
+/// Called for every entry of an archive, so the extension is lowercased into
+/// a stack buffer instead of allocating. Directory names (trailing `/` or
+/// `\`, e.g. a ZIP folder called `cover.jpg/`) are never images.
 pub fn is_image_file(name: &str) -> bool {
+    if name.ends_with(['/', '\\']) {
+        return false;
+    }
+    let Some(ext) = Path::new(name).extension().and_then(|s| s.to_str()) else {
+        return false;
+    };
+    if ext.len() > MAX_IMAGE_EXTENSION_LEN {
+        return false;
+    }
+
+    let mut buffer = [0u8; MAX_IMAGE_EXTENSION_LEN];
+    let lower = &mut buffer[..ext.len()];
+    lower.copy_from_slice(ext.as_bytes());
+    lower.make_ascii_lowercase();
+
+    // ASCII lowercasing keeps the bytes valid UTF-8
+    std::str::from_utf8(lower).is_ok_and(|ext| image_extension_set().contains(ext))
+}
+
+/// Convert a raw archive comment to text, treating blank comments as absent
+///
+/// ZIP and RAR comments have no declared encoding; invalid UTF-8 is replaced.
+pub fn comment_text(raw: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(raw);
+    let trimmed = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+/// Check if the last path component of `name` is `file_name` (ASCII case-insensitive)
+pub fn has_file_name(name: &str, file_name: &str) -> bool {
+    name.rsplit(['/', '\\'])
+        .next()
+        .is_some_and(|last| last.eq_ignore_ascii_case(file_name))
+}
+
+/// Extensions of sidecar/metadata files that never hold page images
+const METADATA_EXTENSIONS: &[&str] = &[
+    "xml", "json", "txt", "nfo", "md", "opf", "ncx", "htm", "html", "sfv", "md5", "url", "ini",
+    "db",
+];
+
+/// Check if filename is a metadata/text file (ComicInfo.xml, readme, ...)
+pub fn is_metadata_file(name: &str) -> bool {
     if let Some(ext) = Path::new(name).extension().and_then(|s| s.to_str()) {
-        IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+        METADATA_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
     } else {
         false
     }
 }
 
+/// Build the error for an archive in which no image was found
+///
+/// `names` are the archive's file entries (directory entries ending in a
+/// separator are ignored). The error is always `CbxError::NoImages`; an
+/// archive holding only metadata or text is logged as a data-only layout
+/// whose pages live elsewhere, which otherwise looks like a listing bug.
+pub fn no_images_error<'a>(names: impl IntoIterator<Item = &'a str>) -> CbxError {
+    if is_data_only_layout(names) {
+        tracing::info!("Archive holds only metadata/text files, its pages live elsewhere");
+    }
+    CbxError::NoImages
+}
+
+/// Whether every file among `names` is metadata or text (and there is one)
+fn is_data_only_layout<'a>(names: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut files = names
+        .into_iter()
+        .filter(|name| !name.ends_with('/') && !name.ends_with('\\'))
+        .peekable();
+
+    files.peek().is_some() && files.all(is_metadata_file)
+}
+
+/// Check whether the unsorted first-image scan should give up
+///
+/// After `scanned` non-image files and no image the archive is taken not to
+/// be a comic, and the caller returns `CbxError::NoImages` instead of reading
+/// the remaining entries. A `cap` of 0 (see `ListOptions::max_non_image_scan`)
+/// never gives up.
+pub fn non_image_scan_exhausted(scanned: usize, cap: u32) -> bool {
+    let exhausted = cap != 0 && scanned >= cap as usize;
+    if exhausted {
+        tracing::info!("No image among the first {} files, giving up", scanned);
+    }
+    exhausted
+}
+
+/// Mutably borrow a shared archive reader without panicking on reentrancy
+///
+/// Stream-backed archives keep their reader in a `RefCell` because the trait
+/// takes `&self`. A nested call made while a borrow is held would otherwise
+/// panic with "already borrowed" inside Explorer; it fails as
+/// `CbxError::Archive` instead.
+pub fn borrow_reader<T>(reader: &RefCell<T>) -> Result<RefMut<'_, T>> {
+    reader
+        .try_borrow_mut()
+        .map_err(|_| CbxError::Archive("Archive reader is busy (reentrant access)".to_string()))
+}
+
+/// Normalize an entry name for listing and comparison: `/` separators, Unicode NFC
+///
+/// Archives made on macOS often store decomposed (NFD) names, while names from
+/// Windows tools, cover overrides and ComicInfo.xml are composed.
+pub fn normalize_entry_name(name: &str) -> String {
+    let name = name.replace('\\', "/");
+    to_nfc(&name).unwrap_or(name)
+}
+
+/// Compose `name` to NFC with the OS normalizer; `None` when already NFC or on failure
+#[cfg(windows)]
+fn to_nfc(name: &str) -> Option<String> {
+    use windows::Win32::Globalization::{NormalizationC, NormalizeString};
+
+    // ASCII is always in NFC
+    if name.is_ascii() {
+        return None;
+    }
+
+    let wide: Vec<u16> = name.encode_utf16().collect();
+
+    // UNAVOIDABLE UNSAFE: NormalizeString is a kernel32 FFI call
+    // Safety: both buffers are slices, so the API gets their exact lengths;
+    // the first call only asks for the required length
+    unsafe {
+        let estimate = NormalizeString(NormalizationC, &wide, None);
+        if estimate <= 0 {
+            return None;
+        }
+        let mut buffer = vec![0u16; estimate as usize];
+        let written = NormalizeString(NormalizationC, &wide, Some(&mut buffer));
+        if written <= 0 {
+            return None;
+        }
+        buffer.truncate(written as usize);
+        String::from_utf16(&buffer).ok()
+    }
+}
+
+#[cfg(not(windows))]
+fn to_nfc(_name: &str) -> Option<String> {
+    None
+}
+
 /// Natural sort comparison using natord (matches Windows StrCmpLogicalW)
+///
+/// Full-width digits (`０`-`９`, common in Japanese file names) compare as
+/// their ASCII equivalents, so `１０.jpg` sorts after `２.jpg`. Names natord
+/// treats as equal (e.g. differing only in whitespace) fall back to byte
+/// order, so case-only duplicates like `Page1.JPG` and `page1.jpg` always
+/// sort the same way.
 pub fn natural_sort_cmp(a: &str, b: &str) -> std::cmp::Ordering {
-    natord::compare(a, b)
+    natord::compare(&fold_full_width_digits(a), &fold_full_width_digits(b)).then_with(|| a.cmp(b))
+}
+
+/// Replace full-width digits with ASCII digits, borrowing when there are none
+fn fold_full_width_digits(name: &str) -> Cow<'_, str> {
+    const FULL_WIDTH_DIGITS: std::ops::RangeInclusive<char> = '\u{FF10}'..='\u{FF19}';
+
+    if !name.chars().any(|c| FULL_WIDTH_DIGITS.contains(&c)) {
+        return Cow::Borrowed(name);
+    }
+    name.chars()
+        .map(|c| {
+            if FULL_WIDTH_DIGITS.contains(&c) {
+                char::from(b'0' + (c as u32 - 0xFF10) as u8)
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Comparison used to order entry names when sorting is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NaturalSortMode {
+    /// `natural_sort_cmp`: locale-independent, same result on every machine
+    #[default]
+    Builtin,
+    /// Windows `StrCmpLogicalW`: the user's locale, exactly as Explorer sorts
+    Windows,
+}
+
+impl NaturalSortMode {
+    /// Map the `NaturalSortMode` registry DWORD to a mode
+    ///
+    /// Unknown values fall back to the default.
+    pub fn from_registry_value(value: u32) -> Self {
+        match value {
+            1 => NaturalSortMode::Windows,
+            _ => NaturalSortMode::Builtin,
+        }
+    }
+
+    /// The `NaturalSortMode` registry DWORD for this mode
+    pub fn registry_value(self) -> u32 {
+        match self {
+            NaturalSortMode::Builtin => 0,
+            NaturalSortMode::Windows => 1,
+        }
+    }
+
+    /// Compare two entry names
+    pub fn compare(self, a: &str, b: &str) -> std::cmp::Ordering {
+        match self {
+            NaturalSortMode::Builtin => natural_sort_cmp(a, b),
+            NaturalSortMode::Windows => logical_sort_cmp(a, b),
+        }
+    }
+}
+
+/// Part of an entry name that sorting compares
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// Whole name including folders, so `ch1/` pages come before `ch2/`
+    #[default]
+    FullPath,
+    /// File name only; entries with the same file name keep full path order
+    BaseName,
+}
+
+impl SortKey {
+    /// Map the `SortBy` registry DWORD to a key
+    ///
+    /// Unknown values fall back to the default.
+    pub fn from_registry_value(value: u32) -> Self {
+        match value {
+            1 => SortKey::BaseName,
+            _ => SortKey::FullPath,
+        }
+    }
+
+    /// The `SortBy` registry DWORD for this key
+    pub fn registry_value(self) -> u32 {
+        match self {
+            SortKey::FullPath => 0,
+            SortKey::BaseName => 1,
+        }
+    }
+
+    /// The part of `name` this key compares
+    pub fn of(self, name: &str) -> &str {
+        match self {
+            SortKey::FullPath => name,
+            SortKey::BaseName => name.rsplit(['/', '\\']).next().unwrap_or(name),
+        }
+    }
+}
+
+/// Order of entry names when sorting is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortOrder {
+    pub mode: NaturalSortMode,
+    pub sort_by: SortKey,
+}
+
+impl SortOrder {
+    /// Compare two entry names by `sort_by`, then by full name
+    pub fn compare(self, a: &str, b: &str) -> std::cmp::Ordering {
+        self.mode
+            .compare(self.sort_by.of(a), self.sort_by.of(b))
+            .then_with(|| self.mode.compare(a, b))
+    }
+}
+
+/// Settings that shape how a backend looks for images
+///
+/// Set on an archive when it is opened (see `open_archive_with`), so every
+/// listing of one request uses the same values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListOptions {
+    /// Order of entry names when sorting is enabled
+    pub order: SortOrder,
+    /// Non-image files the unsorted first-image scan passes over (0 = all)
+    pub max_non_image_scan: u32,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        Self {
+            order: SortOrder::default(),
+            max_non_image_scan: super::config::DEFAULT_MAX_NON_IMAGE_SCAN,
+        }
+    }
+}
+
+/// Compare with `StrCmpLogicalW`, breaking ties by byte order
+#[cfg(windows)]
+fn logical_sort_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use windows::core::HSTRING;
+    use windows::Win32::UI::Shell::StrCmpLogicalW;
+
+    // SAFETY: both strings are null-terminated HSTRINGs that outlive the call
+    let result = unsafe { StrCmpLogicalW(&HSTRING::from(a), &HSTRING::from(b)) };
+    result.cmp(&0).then_with(|| a.cmp(b))
+}
+
+#[cfg(not(windows))]
+fn logical_sort_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    natural_sort_cmp(a, b)
 }
 
 /// Find first image entry from a list, optionally sorted
 ///
-/// If `sort` is true, returns alphabetically first image (natural order).
-/// If `sort` is false, returns first image encountered (early exit optimization).
-pub fn find_first_image<'a>(names: impl Iterator<Item = &'a str>, sort: bool) -> Option<String> {
+/// With an `order`, returns the first image in that order.
+/// Without one, returns first image encountered (early exit optimization).
+pub fn find_first_image<'a>(
+    names: impl Iterator<Item = &'a str>,
+    order: Option<SortOrder>,
+) -> Option<String> {
+    find_first_image_skipping(names, order, &[])
+}
+
+/// Find first image entry, passing over intro/TOC pages
+///
+/// Images whose name matches `skip_stems` (see `is_skipped_page`) are only
+/// chosen when every image matches.
+pub fn find_first_image_skipping<'a>(
+    names: impl Iterator<Item = &'a str>,
+    order: Option<SortOrder>,
+    skip_stems: &[String],
+) -> Option<String> {
     let mut images: Vec<&str> = names.filter(|name| is_image_file(name)).collect();
 
     if images.is_empty() {
         return None;
     }
 
-    if sort {
-        images.sort_by(|a, b| natural_sort_cmp(a, b));
+    if let Some(order) = order {
+        images.sort_by(|a, b| order.compare(a, b));
+    }
+
+    images
+        .iter()
+        .find(|name| !is_skipped_page(name, skip_stems))
+        .or_else(|| images.first())
+        .map(|s| (*s).to_string())
+}
+
+/// Check whether an image is an intro/TOC page that should not be the cover
+///
+/// The file stem is split into alphabetic words (`00_intro` -> `intro`,
+/// `credits-2` -> `credits`); the page is skipped when any word equals one of
+/// `skip_stems`, ignoring case.
+pub fn is_skipped_page(name: &str, skip_stems: &[String]) -> bool {
+    if skip_stems.is_empty() {
+        return false;
     }
 
-    images.first().map(|s| (*s).to_string())
+    let Some(stem) = Path::new(&name.replace('\\', "/"))
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(str::to_string)
+    else {
+        return false;
+    };
+
+    stem.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .any(|word| {
+            skip_stems
+                .iter()
+                .any(|skip| skip.eq_ignore_ascii_case(word))
+        })
+}
+
+/// Whether an image is taller than it is wide (a page rather than a banner)
+pub fn is_portrait(width: u32, height: u32) -> bool {
+    height > width
+}
+
+/// Whether an entry name has a JPEG extension
+pub fn is_jpeg_name(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg"))
+}
+
+/// Whether sampled page dimensions look like a photo dump
+///
+/// Camera output is landscape and uniform; comic pages are portrait or vary
+/// (spreads, covers). An empty sample is never an album.
+pub fn looks_like_photo_album(sample: &[(u32, u32)]) -> bool {
+    !sample.is_empty()
+        && sample
+            .iter()
+            .all(|&(width, height)| !is_portrait(width, height) && width != height)
+        && sample.windows(2).all(|pair| pair[0] == pair[1])
+}
+
+/// Pick the image entry with the greatest uncompressed size
+///
+/// Ties are broken by natural name order so the result does not depend on
+/// archive order.
+pub fn select_largest_image(entries: Vec<ArchiveEntry>) -> Option<ArchiveEntry> {
+    entries
+        .into_iter()
+        .filter(|e| !e.is_directory && is_image_file(&e.name))
+        .max_by(|a, b| {
+            a.size
+                .cmp(&b.size)
+                .then_with(|| natural_sort_cmp(&b.name, &a.name))
+        })
 }
 
 /// Verify that extracted data is actually a valid image using magic headers
@@ -119,6 +577,49 @@ mod tests {
         assert!(!is_image_file("noextension"));
     }
 
+    #[test]
+    fn test_is_image_file_case_and_unknown() {
+        for name in ["a.Jpg", "a.jPEG", "dir/a.WebP", "a.AVIF", "a.TiF"] {
+            assert!(is_image_file(name), "{}", name);
+        }
+
+        for name in [
+            "a.jpgx",
+            "a.jp",
+            "a.",
+            ".png",
+            "a.png.bak",
+            "a.extension-much-longer-than-any-image",
+            "cover.jpg/",
+            "Chapter 1.png\\",
+            "a.pñg",
+        ] {
+            assert!(!is_image_file(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_is_image_file_large_listing() {
+        // Per-entry check across a 50k-entry listing
+        let names: Vec<String> = (0..50_000)
+            .map(|i| match i % 4 {
+                0 => format!("chapter{:03}/page{:05}.JPG", i / 1000, i),
+                1 => format!("chapter{:03}/page{:05}.png", i / 1000, i),
+                2 => format!("chapter{:03}/notes{:05}.txt", i / 1000, i),
+                _ => format!("chapter{:03}/page{:05}.webp", i / 1000, i),
+            })
+            .collect();
+
+        let images = names.iter().filter(|name| is_image_file(name)).count();
+        assert_eq!(images, 37_500);
+
+        // The set is built once and every extension fits the stack buffer
+        assert!(std::ptr::eq(image_extension_set(), image_extension_set()));
+        assert!(IMAGE_EXTENSIONS
+            .iter()
+            .all(|ext| ext.len() <= MAX_IMAGE_EXTENSION_LEN));
+    }
+
     #[test]
     fn test_natural_sort_cmp() {
         use std::cmp::Ordering;
@@ -132,21 +633,110 @@ mod tests {
         );
         assert_eq!(natural_sort_cmp("page1.jpg", "page1.jpg"), Ordering::Equal);
 
+        // Ties broken deterministically
+        assert_eq!(natural_sort_cmp("Page1.JPG", "page1.jpg"), Ordering::Less);
+        assert_eq!(natural_sort_cmp("page 1.jpg", "page1.jpg"), Ordering::Less);
+
         // Alphabetic fallback
         assert_eq!(natural_sort_cmp("apple.jpg", "banana.jpg"), Ordering::Less);
     }
 
+    #[test]
+    fn test_natural_sort_full_width_digits() {
+        use std::cmp::Ordering;
+
+        assert_eq!(
+            natural_sort_cmp("\u{FF12}.jpg", "\u{FF11}\u{FF10}.jpg"),
+            Ordering::Less
+        );
+        assert_eq!(
+            natural_sort_cmp(
+                "\u{7B2C}\u{FF19}\u{8A71}.png",
+                "\u{7B2C}\u{FF11}\u{FF12}\u{8A71}.png"
+            ),
+            Ordering::Less
+        );
+        // Mixed widths order by value; the tie still breaks deterministically
+        assert_eq!(
+            natural_sort_cmp("page\u{FF13}.jpg", "page10.jpg"),
+            Ordering::Less
+        );
+        assert_eq!(
+            natural_sort_cmp("page3.jpg", "page\u{FF13}.jpg"),
+            Ordering::Less
+        );
+
+        let mut names = vec!["\u{FF11}\u{FF10}.jpg", "\u{FF12}.jpg", "\u{FF11}.jpg"];
+        names.sort_by(|a, b| NaturalSortMode::Builtin.compare(a, b));
+        assert_eq!(
+            names,
+            ["\u{FF11}.jpg", "\u{FF12}.jpg", "\u{FF11}\u{FF10}.jpg"]
+        );
+        assert_eq!(
+            find_first_image(names.iter().rev().copied(), Some(SortOrder::default())),
+            Some("\u{FF11}.jpg".to_string())
+        );
+    }
+
+    #[test]
+    fn test_natural_sort_mode_from_registry_value() {
+        assert_eq!(
+            NaturalSortMode::from_registry_value(0),
+            NaturalSortMode::Builtin
+        );
+        assert_eq!(
+            NaturalSortMode::from_registry_value(1),
+            NaturalSortMode::Windows
+        );
+        assert_eq!(
+            NaturalSortMode::from_registry_value(7),
+            NaturalSortMode::Builtin
+        );
+        assert_eq!(NaturalSortMode::default(), NaturalSortMode::Builtin);
+    }
+
+    #[test]
+    fn test_sort_key_with_chapter_folders() {
+        let names = ["ch1/02.jpg", "ch2/01.jpg", "ch1/03.jpg", "ch10/01.jpg"];
+        let first = |sort_by| {
+            let order = SortOrder {
+                sort_by,
+                ..SortOrder::default()
+            };
+            let mut sorted = names.to_vec();
+            sorted.sort_by(|a, b| order.compare(a, b));
+            sorted
+        };
+
+        // Default: chapter order, then page order within the chapter
+        assert_eq!(
+            first(SortKey::FullPath),
+            ["ch1/02.jpg", "ch1/03.jpg", "ch2/01.jpg", "ch10/01.jpg"]
+        );
+        // File name only; equal names keep chapter order
+        assert_eq!(
+            first(SortKey::BaseName),
+            ["ch2/01.jpg", "ch10/01.jpg", "ch1/02.jpg", "ch1/03.jpg"]
+        );
+
+        assert_eq!(SortKey::BaseName.of("a\\b\\01.jpg"), "01.jpg");
+        assert_eq!(SortKey::BaseName.of("01.jpg"), "01.jpg");
+        assert_eq!(SortKey::from_registry_value(1), SortKey::BaseName);
+        assert_eq!(SortKey::from_registry_value(0), SortKey::FullPath);
+        assert_eq!(SortKey::default(), SortKey::FullPath);
+    }
+
     #[test]
     fn test_find_first_image_sorted() {
         let files = vec!["readme.txt", "page10.jpg", "page2.jpg", "page1.jpg"];
-        let result = find_first_image(files.iter().copied(), true);
+        let result = find_first_image(files.iter().copied(), Some(SortOrder::default()));
         assert_eq!(result, Some("page1.jpg".to_string()));
     }
 
     #[test]
     fn test_find_first_image_unsorted() {
         let files = vec!["readme.txt", "page10.jpg", "page2.jpg"];
-        let result = find_first_image(files.iter().copied(), false);
+        let result = find_first_image(files.iter().copied(), None);
         // Should return first encountered image
         assert_eq!(result, Some("page10.jpg".to_string()));
     }
@@ -154,17 +744,125 @@ mod tests {
     #[test]
     fn test_find_first_image_no_images() {
         let files = vec!["readme.txt", "license.md", "notes.doc"];
-        let result = find_first_image(files.iter().copied(), true);
+        let result = find_first_image(files.iter().copied(), Some(SortOrder::default()));
         assert_eq!(result, None);
     }
 
+    #[test]
+    fn test_non_image_scan_exhausted() {
+        assert!(!non_image_scan_exhausted(499, 500));
+        assert!(non_image_scan_exhausted(500, 500));
+        // A cap of 0 scans everything
+        assert!(!non_image_scan_exhausted(1_000_000, 0));
+    }
+
+    #[test]
+    fn test_no_images_error() {
+        assert!(is_data_only_layout([
+            "ComicInfo.xml",
+            "notes/readme.TXT",
+            "notes/"
+        ]));
+
+        // Unknown files or an empty listing are not a metadata-only layout
+        assert!(!is_data_only_layout(["ComicInfo.xml", "book.pdf"]));
+        assert!(!is_data_only_layout([]));
+        assert!(!is_data_only_layout(["pages/"]));
+
+        // Either way there is nothing to show
+        for names in [&["ComicInfo.xml"][..], &["book.pdf"], &[]] {
+            assert!(matches!(
+                no_images_error(names.iter().copied()),
+                CbxError::NoImages
+            ));
+        }
+    }
+
+    #[test]
+    fn test_find_first_image_skips_intro() {
+        let skip: Vec<String> = ["intro", "toc", "credits", "blank"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let files = ["00_intro.jpg", "01.jpg", "02.jpg"];
+        assert_eq!(
+            find_first_image(files.iter().copied(), Some(SortOrder::default())),
+            Some("00_intro.jpg".to_string())
+        );
+        for order in [Some(SortOrder::default()), None] {
+            assert_eq!(
+                find_first_image_skipping(files.iter().copied(), order, &skip),
+                Some("01.jpg".to_string())
+            );
+        }
+
+        // Only skipped pages: still pick one
+        let files = ["toc.png", "00_intro.jpg"];
+        assert_eq!(
+            find_first_image_skipping(files.iter().copied(), Some(SortOrder::default()), &skip),
+            Some("00_intro.jpg".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_skipped_page() {
+        let skip = vec!["intro".to_string(), "toc".to_string()];
+
+        assert!(is_skipped_page("00_intro.jpg", &skip));
+        assert!(is_skipped_page("Book/TOC-1.png", &skip));
+        assert!(is_skipped_page("Book\\00 Intro.jpg", &skip));
+        assert!(!is_skipped_page("introduction.jpg", &skip));
+        assert!(!is_skipped_page("01.jpg", &skip));
+        assert!(!is_skipped_page("00_intro.jpg", &[]));
+    }
+
     #[test]
     fn test_find_first_image_empty() {
         let files: Vec<&str> = vec![];
-        let result = find_first_image(files.iter().copied(), true);
+        let result = find_first_image(files.iter().copied(), Some(SortOrder::default()));
         assert_eq!(result, None);
     }
 
+    #[test]
+    fn test_select_largest_image() {
+        let entry = |name: &str, size: u64| ArchiveEntry {
+            name: name.to_string(),
+            size,
+            is_directory: false,
+        };
+
+        let entries = vec![
+            entry("page1.jpg", 100),
+            entry("huge.txt", 9000),
+            entry("cover.png", 500),
+            entry("page2.jpg", 300),
+        ];
+        let largest = select_largest_image(entries).unwrap();
+        assert_eq!(largest.name, "cover.png");
+
+        // Ties resolve to the naturally-first name
+        let entries = vec![entry("page10.jpg", 100), entry("page2.jpg", 100)];
+        assert_eq!(select_largest_image(entries).unwrap().name, "page2.jpg");
+
+        assert!(select_largest_image(vec![entry("readme.txt", 1)]).is_none());
+    }
+
+    #[test]
+    fn test_looks_like_photo_album() {
+        assert!(looks_like_photo_album(&[(4000, 3000); 4]));
+
+        // Portrait pages, square images and mixed sizes are not photo dumps
+        assert!(!looks_like_photo_album(&[(1000, 1500); 4]));
+        assert!(!looks_like_photo_album(&[(1000, 1000); 4]));
+        assert!(!looks_like_photo_album(&[(4000, 3000), (3000, 2000)]));
+        assert!(!looks_like_photo_album(&[]));
+
+        assert!(is_jpeg_name("DCIM/IMG_0001.JPG"));
+        assert!(is_jpeg_name("photo.jpeg"));
+        assert!(!is_jpeg_name("page.png"));
+    }
+
     #[test]
     fn test_max_entry_size() {
         assert_eq!(MAX_ENTRY_SIZE, 33_554_432);
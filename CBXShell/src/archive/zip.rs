@@ -3,19 +3,162 @@
 ///! Supports ZIP, CBZ, EPUB, and PHZ formats using the `zip` crate
 use std::cell::RefCell;
 use std::fs::File;
-use std::io::{BufReader, Read, Seek};
+use std::io::{BufReader, Read, Seek, Write};
 use std::path::{Path, PathBuf};
-use zip::ZipArchive as ZipReader;
-
-use super::utils::{find_first_image, is_image_file, MAX_ENTRY_SIZE};
-use crate::archive::{Archive, ArchiveEntry, ArchiveMetadata, ArchiveType};
+use zip::read::ZipFile;
+use zip::result::{ZipError, ZipResult};
+use zip::write::{FileOptions, ZipWriter};
+use zip::{CompressionMethod, ZipArchive as ZipReader};
+
+use super::utils::{
+    borrow_reader, comment_text, find_first_image, is_image_file, no_images_error,
+    non_image_scan_exhausted, read_entry_to_end, MAX_ENTRY_SIZE,
+};
+use crate::archive::{
+    likely_photo_album, Archive, ArchiveEntry, ArchiveMetadata, ArchiveType, ListOptions,
+};
 use crate::utils::error::{CbxError, Result};
+use crate::utils::file::open_file_with_retry;
+
+/// Whether a ZIP error means the entry is encrypted
+fn is_password_required(err: &ZipError) -> bool {
+    matches!(err, ZipError::UnsupportedArchive(msg) if *msg == ZipError::PASSWORD_REQUIRED)
+}
+
+/// Message of the zip crate when no end of central directory record exists
+const CENTRAL_DIRECTORY_END_MISSING: &str = "Could not find central directory end";
+
+/// Map a failure to read the archive's central directory
+///
+/// A missing end record means the file stops short, which is also how a ZIP
+/// still being written looks, so it is reported as `CbxError::Truncated`.
+fn directory_error(context: &str, err: ZipError) -> CbxError {
+    match err {
+        ZipError::InvalidArchive(msg) if msg == CENTRAL_DIRECTORY_END_MISSING => {
+            CbxError::Truncated(format!("{}: {}", context, msg))
+        }
+        err => CbxError::Archive(format!("{}: {}", context, err)),
+    }
+}
+
+/// Map a ZIP entry lookup error, keeping encrypted entries distinct
+fn entry_error(err: ZipError) -> CbxError {
+    if is_password_required(&err) {
+        CbxError::PasswordProtected
+    } else {
+        CbxError::Archive(format!("Entry not found: {}", err))
+    }
+}
+
+/// Extra field header ID of WinZip AES encryption
+const AES_EXTRA_FIELD_ID: u16 = 0x9901;
+
+/// Whether an entry's extra field carries the WinZip AES header
+fn has_aes_extra_field(extra: &[u8]) -> bool {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let len = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        if id == AES_EXTRA_FIELD_ID {
+            return true;
+        }
+        rest = rest.get(4 + len..).unwrap_or_default();
+    }
+    false
+}
+
+/// Open an entry for reading, reporting WinZip AES entries as encrypted
+///
+/// Without AES support, `ZipReader::by_index` panics on AES entries whose
+/// encrypted flag is clear (it unwraps an invalid-password result), which
+/// takes Explorer down under `panic = "abort"`. The AES extra field is
+/// checked on the raw entry first so every AES entry fails with
+/// `PASSWORD_REQUIRED` instead.
+///
+/// With a configured `password`, ZipCrypto entries are decrypted; a password
+/// that fails the header check is reported like a missing one. Unencrypted
+/// entries ignore it.
+fn open_entry<'a, R: Read + Seek>(
+    archive: &'a mut ZipReader<R>,
+    index: usize,
+    password: Option<&str>,
+) -> ZipResult<ZipFile<'a>> {
+    if has_aes_extra_field(archive.by_index_raw(index)?.extra_data()) {
+        return Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED));
+    }
+    match password {
+        Some(password) => archive
+            .by_index_decrypt(index, password.as_bytes())?
+            .map_err(|_| ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
+        None => archive.by_index(index),
+    }
+}
+
+/// Index and uncompressed size of the entry named `name`
+///
+/// Resolved on raw entries so AES entries can be looked up without
+/// `by_name`, which shares the `by_index` panic. The size is the central
+/// directory's: archives written in streaming mode leave it 0 in the local
+/// header and record it in a data descriptor after the data.
+fn locate_entry<R: Read + Seek>(archive: &mut ZipReader<R>, name: &str) -> Option<(usize, u64)> {
+    (0..archive.len()).find_map(|i| {
+        let file = archive.by_index_raw(i).ok()?;
+        (file.name() == name).then(|| (i, file.size()))
+    })
+}
+
+/// Whether an image entry is left out of listings because it is encrypted
+///
+/// Listings skip entries `open_entry` refuses, so an archive whose only pages
+/// are AES-encrypted (or locked with another password) would look like it
+/// holds no images. Only checked once a listing came up without images.
+fn has_locked_images<R: Read + Seek>(
+    archive: &RefCell<ZipReader<R>>,
+    password: Option<&str>,
+) -> bool {
+    let Ok(mut archive) = borrow_reader(archive) else {
+        return false;
+    };
+    (0..archive.len()).any(|i| {
+        let is_image = archive
+            .by_index_raw(i)
+            .map(|f| !f.is_dir() && is_image_file(f.name()))
+            .unwrap_or(false);
+        is_image
+            && matches!(open_entry(&mut archive, i, password), Err(ref e) if is_password_required(e))
+    })
+}
+
+/// Error for a listing of `entry_names` that found no image
+///
+/// `CbxError::PasswordProtected` when the images were skipped as encrypted.
+fn missing_image_error<R: Read + Seek>(
+    archive: &RefCell<ZipReader<R>>,
+    password: Option<&str>,
+    entry_names: &[String],
+) -> CbxError {
+    if has_locked_images(archive, password) {
+        CbxError::PasswordProtected
+    } else {
+        no_images_error(entry_names.iter().map(|s| s.as_str()))
+    }
+}
+
+/// Read an opened entry to its end (see `read_entry_to_end`)
+fn read_entry_data(zip_entry: ZipFile<'_>, size: u64) -> Result<Vec<u8>> {
+    let name = zip_entry.name().to_string();
+    read_entry_to_end(zip_entry, size, &name)
+}
 
 /// ZIP archive handler
 pub struct ZipArchive {
     archive: RefCell<ZipReader<BufReader<File>>>,
-    #[allow(dead_code)] // Stored for potential future use (metadata, error messages)
+    /// Used for metadata
     path: PathBuf,
+    /// Configured password for encrypted entries
+    password: Option<String>,
+    /// Sort order and scan limit from the settings
+    options: ListOptions,
 }
 
 impl ZipArchive {
@@ -23,34 +166,115 @@ impl ZipArchive {
     pub fn open(path: &Path) -> Result<Self> {
         tracing::debug!("Opening ZIP archive: {:?}", path);
 
-        let file = File::open(path)
-            .map_err(|e| CbxError::Archive(format!("Failed to open ZIP file: {}", e)))?;
+        // A locked or missing file is an I/O error, retried on the next request
+        let file = open_file_with_retry(path)?;
 
         let reader = BufReader::new(file);
-        let archive = ZipReader::new(reader)
-            .map_err(|e| CbxError::Archive(format!("Invalid ZIP archive: {}", e)))?;
+        let archive =
+            ZipReader::new(reader).map_err(|e| directory_error("Invalid ZIP archive", e))?;
 
         Ok(Self {
             archive: RefCell::new(archive),
             path: path.to_path_buf(),
+            password: None,
+            options: ListOptions::default(),
         })
     }
 
+    /// Decrypt ZipCrypto entries with `password` (see `Settings::archive_password_for`)
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// List with `options` instead of the defaults (see `open_archive_with`)
+    pub fn with_list_options(mut self, options: ListOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     /// Get all entry names (for internal use)
-    fn get_entry_names(&self) -> Vec<String> {
-        let mut archive = self.archive.borrow_mut();
-        (0..archive.len())
-            .filter_map(|i| archive.by_index(i).ok().map(|f| f.name().to_string()))
-            .collect()
+    fn get_entry_names(&self) -> Result<Vec<String>> {
+        let mut archive = borrow_reader(&self.archive)?;
+        Ok((0..archive.len())
+            .filter_map(|i| {
+                open_entry(&mut archive, i, self.password.as_deref())
+                    .ok()
+                    .map(|f| f.name().to_string())
+            })
+            .collect())
+    }
+
+    /// List all image entries with their sizes
+    fn list_image_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let mut archive = borrow_reader(&self.archive)?;
+        let mut entries = Vec::new();
+
+        for i in 0..archive.len() {
+            if let Ok(f) = open_entry(&mut archive, i, self.password.as_deref()) {
+                if !f.is_dir() && is_image_file(f.name()) {
+                    entries.push(ArchiveEntry {
+                        name: f.name().to_string(),
+                        size: f.size(),
+                        is_directory: false,
+                    });
+                }
+            }
+        }
+        drop(archive);
+
+        if entries.is_empty() && has_locked_images(&self.archive, self.password.as_deref()) {
+            return Err(CbxError::PasswordProtected);
+        }
+
+        Ok(entries)
+    }
+
+    /// Read an uncompressed, unencrypted entry in raw mode, skipping the
+    /// decompression machinery
+    ///
+    /// The bytes come from a seek and read on the archive's open file and are
+    /// checked against the entry's CRC-32 like the zip reader does. Returns
+    /// `None` for other entries, or when the read or the check fails, so the
+    /// regular read handles (and reports) them.
+    fn read_stored_entry(&self, index: usize) -> Option<Vec<u8>> {
+        // A busy reader just skips the fast path
+        let mut archive = borrow_reader(&self.archive).ok()?;
+        // Opening parses the local header, and fails for encrypted entries
+        let crc = {
+            let file = open_entry(&mut archive, index, self.password.as_deref()).ok()?;
+            if file.compression() != CompressionMethod::Stored
+                || file.compressed_size() != file.size()
+            {
+                return None;
+            }
+            file.crc32()
+        };
+
+        let raw = archive.by_index_raw(index).ok()?;
+        let size = raw.size();
+        let name = raw.name().to_string();
+        match read_entry_data(raw, size) {
+            Ok(buffer) if buffer.len() as u64 == size && crc32fast::hash(&buffer) == crc => {
+                Some(buffer)
+            }
+            Ok(_) => {
+                tracing::warn!("STORED fast path CRC mismatch for {}", name);
+                None
+            }
+            Err(e) => {
+                tracing::warn!("STORED fast path failed, falling back: {}", e);
+                None
+            }
+        }
     }
 
     /// Get entry details by name
     fn get_entry_by_name(&self, name: &str) -> Result<ArchiveEntry> {
-        let mut archive = self.archive.borrow_mut();
+        let mut archive = borrow_reader(&self.archive)?;
 
         for i in 0..archive.len() {
-            let zip_entry = archive
-                .by_index(i)
+            let zip_entry = open_entry(&mut archive, i, self.password.as_deref())
                 .map_err(|e| CbxError::Archive(format!("Failed to get entry {}: {}", i, e)))?;
 
             if zip_entry.name() == name {
@@ -71,6 +295,10 @@ impl Archive for ZipArchive {
         Ok(Box::new(Self::open(path)?))
     }
 
+    fn list_options(&self) -> ListOptions {
+        self.options
+    }
+
     fn find_first_image(&self, sort: bool) -> Result<ArchiveEntry> {
         tracing::debug!("Finding first image in ZIP (sort={})", sort);
 
@@ -79,34 +307,54 @@ impl Archive for ZipArchive {
             // without building full entry list (faster for large archives)
             tracing::debug!("Fast path: finding first image without full listing");
 
-            let mut archive = self.archive.borrow_mut();
+            let cap = self.options.max_non_image_scan;
+            let mut non_images = 0;
+            let mut archive = borrow_reader(&self.archive)?;
             for i in 0..archive.len() {
-                if let Ok(entry) = archive.by_index(i) {
+                if let Ok(entry) = open_entry(&mut archive, i, self.password.as_deref()) {
                     let name = entry.name().to_string();
-                    if is_image_file(&name) {
+                    if !entry.is_dir() && is_image_file(&name) {
                         tracing::info!("Found first image (unsorted): {}", name);
                         return Ok(ArchiveEntry {
                             name,
                             size: entry.size(),
-                            is_directory: entry.is_dir(),
+                            is_directory: false,
                         });
                     }
+                    if !entry.is_dir() {
+                        non_images += 1;
+                        if non_image_scan_exhausted(non_images, cap) {
+                            return Err(CbxError::NoImages);
+                        }
+                    }
                 }
             }
-
-            return Err(CbxError::Archive("No images found in archive".to_string()));
+            drop(archive);
+
+            let entry_names = self.get_entry_names()?;
+            return Err(missing_image_error(
+                &self.archive,
+                self.password.as_deref(),
+                &entry_names,
+            ));
         }
 
         // STANDARD PATH: List all entries and sort
-        let entry_names = self.get_entry_names();
+        let entry_names = self.get_entry_names()?;
 
-        if entry_names.is_empty() {
+        // Entries skipped as encrypted leave the listing empty too
+        if entry_names.is_empty() && !has_locked_images(&self.archive, self.password.as_deref()) {
             return Err(CbxError::Archive("Archive is empty".to_string()));
         }
 
         // Find first image using shared utility
-        let image_name = find_first_image(entry_names.iter().map(|s| s.as_str()), sort)
-            .ok_or_else(|| CbxError::Archive("No images found in archive".to_string()))?;
+        let image_name = find_first_image(
+            entry_names.iter().map(|s| s.as_str()),
+            sort.then_some(self.options.order),
+        )
+        .ok_or_else(|| {
+            missing_image_error(&self.archive, self.password.as_deref(), &entry_names)
+        })?;
 
         tracing::info!("Found first image (sorted): {}", image_name);
 
@@ -114,41 +362,66 @@ impl Archive for ZipArchive {
         self.get_entry_by_name(&image_name)
     }
 
+    fn find_images(&self) -> Result<Vec<ArchiveEntry>> {
+        self.list_image_entries()
+    }
+
+    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let mut archive = borrow_reader(&self.archive)?;
+        Ok((0..archive.len())
+            .filter_map(|i| {
+                let f = open_entry(&mut archive, i, self.password.as_deref()).ok()?;
+                Some(ArchiveEntry {
+                    name: f.name().to_string(),
+                    size: f.size(),
+                    is_directory: f.is_dir(),
+                })
+            })
+            .collect())
+    }
+
     fn extract_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
         tracing::debug!("Extracting entry: {} ({} bytes)", entry.name, entry.size);
 
+        // Sizes come from the central directory rather than `entry`
+        let (index, size) = locate_entry(&mut *borrow_reader(&self.archive)?, &entry.name)
+            .ok_or_else(|| CbxError::Archive(format!("Entry not found: {}", entry.name)))?;
+
         // Safety check: prevent memory exhaustion (32MB limit from C++ implementation)
-        if entry.size > MAX_ENTRY_SIZE {
-            tracing::warn!(
-                "Entry too large: {} bytes (max {})",
-                entry.size,
-                MAX_ENTRY_SIZE
-            );
+        if size > MAX_ENTRY_SIZE {
+            tracing::warn!("Entry too large: {} bytes (max {})", size, MAX_ENTRY_SIZE);
             return Err(CbxError::Archive(format!(
                 "Entry too large: {} bytes (max 32MB)",
-                entry.size
+                size
             )));
         }
 
-        let mut archive = self.archive.borrow_mut();
+        if let Some(buffer) = self.read_stored_entry(index) {
+            tracing::debug!("Read {} bytes via STORED fast path", buffer.len());
+            return Ok(buffer);
+        }
 
-        // Find and extract entry by name
-        let mut zip_entry = archive
-            .by_name(&entry.name)
-            .map_err(|e| CbxError::Archive(format!("Entry not found: {}", e)))?;
+        let mut archive = borrow_reader(&self.archive)?;
+        let zip_entry =
+            open_entry(&mut archive, index, self.password.as_deref()).map_err(entry_error)?;
 
         // Read to buffer (encrypted files will fail during read)
-        let mut buffer = Vec::with_capacity(entry.size as usize);
-        zip_entry
-            .read_to_end(&mut buffer)
-            .map_err(|e| CbxError::Archive(format!("Failed to extract entry: {}", e)))?;
+        let buffer = read_entry_data(zip_entry, size)?;
 
         tracing::debug!("Extracted {} bytes", buffer.len());
         Ok(buffer)
     }
 
+    fn is_password_protected(&self) -> bool {
+        let Ok(mut archive) = borrow_reader(&self.archive) else {
+            return false;
+        };
+        (0..archive.len())
+            .any(|i| matches!(open_entry(&mut archive, i, self.password.as_deref()), Err(ref e) if is_password_required(e)))
+    }
+
     fn get_metadata(&self) -> Result<ArchiveMetadata> {
-        let entry_names = self.get_entry_names();
+        let entry_names = self.get_entry_names()?;
         let total_files = entry_names.len();
         let image_count = entry_names
             .iter()
@@ -170,20 +443,80 @@ impl Archive for ZipArchive {
             image_count,
             compressed_size,
             archive_type: ArchiveType::Zip,
+            likely_photo_album: likely_photo_album(self, image_count),
         })
     }
 
+    fn get_comment(&self) -> Result<Option<String>> {
+        Ok(comment_text(borrow_reader(&self.archive)?.comment()))
+    }
+
     fn archive_type(&self) -> ArchiveType {
         ArchiveType::Zip
     }
 }
 
+/// Add `data` to the ZIP archive at `path` as entry `name`
+///
+/// The entry is stored uncompressed, replacing any entry already called
+/// `name`. The archive is copied (without recompressing) to a temporary file
+/// next to the original, the entry is added there, and the copy then replaces
+/// the original. A failed or interrupted write leaves the original untouched.
+pub fn write_entry(path: &Path, name: &str, data: &[u8]) -> Result<()> {
+    let temp_path = path.with_extension("cbxtmp");
+    let result = copy_without_entry(path, &temp_path, name)
+        .and_then(|writer| finish_with_entry(writer, name, data))
+        .and_then(|file| file.sync_all().map_err(CbxError::from))
+        .and_then(|()| std::fs::rename(&temp_path, path).map_err(CbxError::from));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Copy every entry except `name` to a new archive, without recompressing
+fn copy_without_entry(source: &Path, target: &Path, name: &str) -> Result<ZipWriter<File>> {
+    let mut reader = ZipReader::new(BufReader::new(File::open(source)?))
+        .map_err(|e| CbxError::Archive(format!("Invalid ZIP archive: {}", e)))?;
+    let mut writer = ZipWriter::new(File::create(target)?);
+    writer.set_raw_comment(reader.comment().to_vec());
+
+    for i in 0..reader.len() {
+        let entry = reader
+            .by_index_raw(i)
+            .map_err(|e| CbxError::Archive(format!("Failed to get entry {}: {}", i, e)))?;
+        if entry.name() == name {
+            continue;
+        }
+        writer
+            .raw_copy_file(entry)
+            .map_err(|e| CbxError::Archive(format!("Failed to copy entry {}: {}", i, e)))?;
+    }
+    Ok(writer)
+}
+
+/// Write `data` as a stored entry and close the archive, returning its writer
+fn finish_with_entry<W: Write + Seek>(
+    mut writer: ZipWriter<W>,
+    name: &str,
+    data: &[u8],
+) -> Result<W> {
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    writer
+        .start_file(name, options)
+        .map_err(|e| CbxError::Archive(format!("Failed to add {}: {}", name, e)))?;
+    writer.write_all(data)?;
+    writer
+        .finish()
+        .map_err(|e| CbxError::Archive(format!("Failed to finish ZIP: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write;
+    use crate::archive::{CoverStrategy, CoverVerification, SortOrder};
+    use crate::image_processor::magic::ImageFormat;
     use tempfile::Builder;
-    use zip::write::{FileOptions, ZipWriter};
 
     /// Create a test ZIP archive in memory for testing
     fn create_test_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
@@ -238,6 +571,63 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_open_zip_without_central_directory_is_truncated() {
+        // A ZIP cut off mid-write still has its local headers
+        let zip_data = create_test_zip(&[("01.jpg", &[0xAB; 4096])]);
+        let partial = &zip_data[..zip_data.len() / 2];
+        let temp_file = Builder::new().suffix(".zip").tempfile().unwrap();
+        std::fs::write(temp_file.path(), partial).unwrap();
+
+        let err = ZipArchive::open(temp_file.path()).err().unwrap();
+        assert!(matches!(err, CbxError::Truncated(_)), "{}", err);
+        let err = ZipArchiveFromStream::new(std::io::Cursor::new(partial.to_vec()))
+            .err()
+            .unwrap();
+        assert!(matches!(err, CbxError::Truncated(_)), "{}", err);
+
+        // A damaged directory is corrupt, not truncated
+        let mut damaged = zip_data.clone();
+        let end = damaged.len() - 22;
+        damaged[end + 16..end + 20].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = ZipArchiveFromStream::new(std::io::Cursor::new(damaged))
+            .err()
+            .unwrap();
+        assert!(matches!(err, CbxError::Archive(_)), "{}", err);
+    }
+
+    #[test]
+    fn test_names_differing_only_by_case_extract_separately() {
+        let temp_file = Builder::new()
+            .prefix("test_case_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(
+            temp_path,
+            &[("Page1.jpg", b"upper"), ("page1.jpg", b"lower")],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let images = archive.find_images().unwrap();
+        let names: Vec<&str> = images.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["Page1.jpg", "page1.jpg"]);
+
+        // Stored names are carried verbatim from listing to extraction
+        assert_eq!(archive.extract_entry(&images[0]).unwrap(), b"upper");
+        assert_eq!(archive.extract_entry(&images[1]).unwrap(), b"lower");
+
+        let first = archive.find_first_image(true).unwrap();
+        let expected: &[u8] = if first.name == "Page1.jpg" {
+            b"upper"
+        } else {
+            b"lower"
+        };
+        assert_eq!(archive.extract_entry(&first).unwrap(), expected);
+    }
+
     #[test]
     fn test_find_first_image_sorted() {
         let temp_file = Builder::new()
@@ -264,6 +654,32 @@ mod tests {
         assert_eq!(entry.name, "page1.jpg");
     }
 
+    #[test]
+    fn test_find_first_image_unsorted_gives_up_on_non_images() {
+        let temp_file = Builder::new()
+            .prefix("test_non_comic_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        let names: Vec<String> = (0..600).map(|i| format!("bin/lib{:03}.dll", i)).collect();
+        let mut files: Vec<(&str, &[u8])> = names
+            .iter()
+            .map(|name| (name.as_str(), &b"MZ"[..]))
+            .collect();
+        files.push(("zz_icon.png", b"image"));
+        create_test_zip_file(temp_path, &files).unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        // The fast path stops after the default 500 non-image files
+        assert!(matches!(
+            archive.find_first_image(false),
+            Err(CbxError::NoImages)
+        ));
+        // The sorted path lists everything anyway
+        assert_eq!(archive.find_first_image(true).unwrap().name, "zz_icon.png");
+    }
+
     #[test]
     fn test_find_first_image_unsorted() {
         let temp_file = Builder::new()
@@ -306,198 +722,1546 @@ mod tests {
     }
 
     #[test]
-    fn test_extract_entry() {
-        let content = b"fake jpeg data";
+    fn test_metadata_only_archive_reports_no_images() {
         let temp_file = Builder::new()
-            .prefix("test_extract_")
+            .prefix("test_metadata_only_")
             .suffix(".zip")
             .tempfile()
             .unwrap();
         let temp_path = temp_file.path();
-        create_test_zip_file(temp_path, &[("image.jpg", content)]).unwrap();
+        create_test_zip_file(
+            temp_path,
+            &[
+                ("ComicInfo.xml", b"<ComicInfo/>"),
+                ("info/readme.txt", b"pages are in the sidecar"),
+                ("index.json", b"{}"),
+            ],
+        )
+        .unwrap();
 
         let archive = ZipArchive::open(temp_path).unwrap();
-        let entry = archive.find_first_image(false).unwrap();
-        let extracted = archive.extract_entry(&entry).unwrap();
-
-        assert_eq!(extracted, content);
-    }
+        for sort in [true, false] {
+            assert!(matches!(
+                archive.find_first_image(sort),
+                Err(CbxError::NoImages)
+            ));
+        }
 
-    #[test]
-    fn test_get_metadata() {
+        // Any other archive without images fails the same way
         let temp_file = Builder::new()
-            .prefix("test_metadata_")
+            .prefix("test_no_images_other_")
             .suffix(".zip")
             .tempfile()
             .unwrap();
-        let temp_path = temp_file.path();
         create_test_zip_file(
-            temp_path,
-            &[
-                ("page1.jpg", b"image 1"),
-                ("page2.jpg", b"image 2"),
-                ("readme.txt", b"text"),
-            ],
+            temp_file.path(),
+            &[("readme.txt", b"text"), ("movie.mp4", b"video")],
         )
         .unwrap();
 
-        let archive = ZipArchive::open(temp_path).unwrap();
-        let metadata = archive.get_metadata().unwrap();
-
-        assert_eq!(metadata.total_files, 3);
-        assert_eq!(metadata.image_count, 2);
-        assert!(metadata.compressed_size > 0);
-        assert_eq!(metadata.archive_type, ArchiveType::Zip);
+        let archive = ZipArchive::open(temp_file.path()).unwrap();
+        assert!(matches!(
+            archive.find_first_image(true),
+            Err(CbxError::NoImages)
+        ));
     }
-}
-
-/// ZIP archive handler for IStream (direct streaming, no memory copy)
-///
-/// This is a performance-optimized version that streams directly from IStream
-/// without loading the entire archive into memory first.
-///
-/// # Performance
-/// - **Small archives (50MB)**: Similar to memory version
-/// - **Large archives (1GB+)**: 20-50x faster (no full memory load)
-///
-/// # Example
-/// For a 1GB archive:
-/// - Old approach: Load 1GB to memory (~3sec) + process
-/// - New approach: Stream directly (~50ms for metadata + image)
-pub struct ZipArchiveFromStream<R: Read + Seek> {
-    archive: RefCell<ZipReader<R>>,
-}
-
-impl<R: Read + Seek> ZipArchiveFromStream<R> {
-    /// Create a ZIP archive from a streaming reader
-    pub fn new(reader: R) -> Result<Self> {
-        let archive = ZipReader::new(reader)
-            .map_err(|e| CbxError::Archive(format!("Failed to open ZIP from stream: {}", e)))?;
 
-        Ok(Self {
-            archive: RefCell::new(archive),
-        })
-    }
+    #[test]
+    fn test_find_first_image_data_skips_decoy() {
+        // Minimal JPEG magic; verify_image_data only checks the header
+        let real_jpeg: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0x4A, 0x46];
+        let buffer = create_test_zip(&[
+            ("page1.jpg", b"this is not an image"),
+            ("page2.jpg", real_jpeg),
+        ]);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+
+        // Default fast path returns the decoy by name
+        let (entry, _) = archive
+            .find_first_image_data(false, CoverVerification::Off, &[])
+            .unwrap();
+        assert_eq!(entry.name, "page1.jpg");
 
-    /// Get all entry names (for internal use)
-    fn get_entry_names(&self) -> Vec<String> {
-        let mut archive = self.archive.borrow_mut();
-        (0..archive.len())
-            .filter_map(|i| archive.by_index(i).ok().map(|f| f.name().to_string()))
-            .collect()
+        // Verified fast path skips it
+        for sort in [false, true] {
+            let (entry, data) = archive
+                .find_first_image_data(sort, CoverVerification::Header, &[])
+                .unwrap();
+            assert_eq!(entry.name, "page2.jpg");
+            assert_eq!(data, real_jpeg);
+        }
     }
 
-    /// Get entry details by name
-    fn get_entry_by_name(&self, name: &str) -> Result<ArchiveEntry> {
-        let mut archive = self.archive.borrow_mut();
-
-        for i in 0..archive.len() {
-            let zip_entry = archive
-                .by_index(i)
-                .map_err(|e| CbxError::Archive(format!("Failed to get entry {}: {}", i, e)))?;
+    /// Reader over an in-memory buffer that refuses to seek, like a
+    /// pipe-backed IStream
+    struct ForwardOnly(std::io::Cursor<Vec<u8>>);
 
-            if zip_entry.name() == name {
-                return Ok(ArchiveEntry {
-                    name: name.to_string(),
-                    size: zip_entry.size(),
-                    is_directory: zip_entry.is_dir(),
-                });
-            }
+    impl Read for ForwardOnly {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
         }
-
-        Err(CbxError::Archive(format!("Entry not found: {}", name)))
     }
-}
 
-impl<R: Read + Seek> Archive for ZipArchiveFromStream<R> {
-    fn open(_path: &Path) -> Result<Box<dyn Archive>> {
-        // Not used for stream-based archives
-        Err(CbxError::Archive(
-            "Use open_archive_from_stream instead".to_string(),
-        ))
+    impl std::io::Seek for ForwardOnly {
+        fn seek(&mut self, _pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "stream is forward-only",
+            ))
+        }
     }
 
-    fn find_first_image(&self, sort: bool) -> Result<ArchiveEntry> {
-        tracing::debug!("Finding first image in ZIP from stream (sort={})", sort);
-
-        if !sort {
-            // OPTIMIZATION: When not sorting, find first image immediately
-            tracing::debug!("Fast path: finding first image without full listing");
+    #[test]
+    fn test_open_forward_only_stream() {
+        let png = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+        let buffer = create_test_zip(&[("readme.txt", b"text"), ("page1.png", png)]);
 
-            let mut archive = self.archive.borrow_mut();
-            for i in 0..archive.len() {
-                if let Ok(entry) = archive.by_index(i) {
-                    let name = entry.name().to_string();
-                    if is_image_file(&name) {
-                        tracing::info!("Found first image (unsorted): {}", name);
-                        return Ok(ArchiveEntry {
-                            name,
-                            size: entry.size(),
-                            is_directory: entry.is_dir(),
-                        });
-                    }
-                }
-            }
+        let archive =
+            crate::archive::open_archive_from_stream(ForwardOnly(std::io::Cursor::new(buffer)))
+                .unwrap();
 
-            return Err(CbxError::Archive("No images found in archive".to_string()));
-        }
+        let entry = archive.find_first_image(true).unwrap();
+        assert_eq!(entry.name, "page1.png");
+        assert_eq!(archive.extract_entry(&entry).unwrap(), png);
+    }
 
-        // STANDARD PATH: List all entries and sort
-        let entry_names = self.get_entry_names();
+    /// Reader that counts seeks, standing in for an IStream where each seek
+    /// is a COM round-trip
+    struct CountingSeeks {
+        inner: std::io::Cursor<Vec<u8>>,
+        seeks: std::rc::Rc<std::cell::Cell<usize>>,
+    }
 
-        if entry_names.is_empty() {
-            return Err(CbxError::Archive("Archive is empty".to_string()));
+    impl Read for CountingSeeks {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.inner.read(buf)
         }
+    }
 
-        // Find first image using shared utility
-        let image_name = find_first_image(entry_names.iter().map(|s| s.as_str()), sort)
-            .ok_or_else(|| CbxError::Archive("No images found in archive".to_string()))?;
+    impl std::io::Seek for CountingSeeks {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.seeks.set(self.seeks.get() + 1);
+            self.inner.seek(pos)
+        }
+    }
 
-        tracing::info!("Found first image (sorted): {}", image_name);
+    fn counting_seeks(buffer: Vec<u8>) -> (CountingSeeks, std::rc::Rc<std::cell::Cell<usize>>) {
+        let seeks = std::rc::Rc::new(std::cell::Cell::new(0));
+        let reader = CountingSeeks {
+            inner: std::io::Cursor::new(buffer),
+            seeks: seeks.clone(),
+        };
+        (reader, seeks)
+    }
 
-        // Get entry details
-        self.get_entry_by_name(&image_name)
+    #[test]
+    fn test_open_from_stream_rewinds_once() {
+        let buffer = create_test_zip(&[("page1.jpg", b"image"), ("readme.txt", b"text")]);
+
+        // Seeks the zip crate needs on its own to read the central directory
+        let (reader, seeks) = counting_seeks(buffer.clone());
+        ZipArchiveFromStream::new(reader).unwrap();
+        let zip_seeks = seeks.get();
+
+        // Magic detection adds exactly one rewind on top of that
+        let (reader, seeks) = counting_seeks(buffer);
+        crate::archive::open_archive_from_stream(reader).unwrap();
+        assert_eq!(seeks.get(), zip_seeks + 1);
     }
 
-    fn extract_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
-        tracing::debug!(
-            "Extracting entry from stream: {} ({} bytes)",
-            entry.name,
-            entry.size
-        );
+    #[test]
+    fn test_stream_reentrant_access_fails_cleanly() {
+        let buffer = create_test_zip(&[("page1.jpg", b"image"), ("readme.txt", b"text")]);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+        let entry = archive.find_first_image(true).unwrap();
 
-        // Safety check: prevent memory exhaustion
-        if entry.size > MAX_ENTRY_SIZE {
-            tracing::warn!(
-                "Entry too large: {} bytes (max {})",
-                entry.size,
-                MAX_ENTRY_SIZE
-            );
-            return Err(CbxError::Archive(format!(
-                "Entry too large: {} bytes (max 32MB)",
-                entry.size
-            )));
+        // Simulate a nested call made while an outer operation holds the reader
+        let held = archive.archive.borrow_mut();
+        for result in [
+            archive.find_first_image(true),
+            archive.find_first_image(false),
+        ] {
+            assert!(matches!(result, Err(CbxError::Archive(_))));
         }
+        assert!(matches!(
+            archive.extract_entry(&entry),
+            Err(CbxError::Archive(_))
+        ));
+        assert!(archive.find_images().is_err());
+        assert!(archive.get_comment().is_err());
+        assert!(!archive.is_password_protected());
+        drop(held);
+
+        assert_eq!(archive.extract_entry(&entry).unwrap(), b"image");
+    }
 
-        let mut archive = self.archive.borrow_mut();
+    #[test]
+    fn test_find_first_image_data_decode_skips_corrupt() {
+        // Valid JPEG magic followed by garbage: passes the header check only
+        let corrupt_jpeg: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0x4A, 0x46, 0x00, 0x01];
+        let mut valid_jpeg = Vec::new();
+        image::RgbImage::from_pixel(4, 6, image::Rgb([10, 20, 30]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut valid_jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let buffer = create_test_zip(&[("page1.jpg", corrupt_jpeg), ("page2.jpg", &valid_jpeg)]);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
 
-        // Find and extract entry by name
-        let mut zip_entry = archive
-            .by_name(&entry.name)
-            .map_err(|e| CbxError::Archive(format!("Entry not found: {}", e)))?;
+        for verify in [CoverVerification::Off, CoverVerification::Header] {
+            let (entry, _) = archive.find_first_image_data(true, verify, &[]).unwrap();
+            assert_eq!(entry.name, "page1.jpg");
+        }
 
-        // Read to buffer
-        let mut buffer = Vec::with_capacity(entry.size as usize);
-        zip_entry
-            .read_to_end(&mut buffer)
-            .map_err(|e| CbxError::Archive(format!("Failed to extract entry: {}", e)))?;
+        let (entry, data) = archive
+            .find_first_image_data(true, CoverVerification::Decode, &[])
+            .unwrap();
+        assert_eq!(entry.name, "page2.jpg");
+        assert_eq!(data, valid_jpeg);
+    }
 
-        tracing::debug!("Extracted {} bytes", buffer.len());
-        Ok(buffer)
+    #[test]
+    fn test_find_first_image_data_no_valid_images() {
+        let buffer = create_test_zip(&[("page1.jpg", b"text"), ("page2.png", b"more text")]);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+        assert!(archive
+            .find_first_image_data(true, CoverVerification::Header, &[])
+            .is_err());
     }
 
-    fn get_metadata(&self) -> Result<ArchiveMetadata> {
-        let entry_names = self.get_entry_names();
+    #[test]
+    fn test_find_first_image_data_skips_intro() {
+        let real_jpeg: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0x4A, 0x46];
+        let buffer = create_test_zip(&[
+            ("00_intro.jpg", real_jpeg),
+            ("10.jpg", real_jpeg),
+            ("01.jpg", real_jpeg),
+        ]);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+        let skip = vec!["intro".to_string(), "toc".to_string()];
+
+        let (entry, _) = archive
+            .find_first_image_data(true, CoverVerification::Off, &[])
+            .unwrap();
+        assert_eq!(entry.name, "00_intro.jpg");
+
+        for verify in [CoverVerification::Off, CoverVerification::Header] {
+            let (entry, _) = archive.find_first_image_data(true, verify, &skip).unwrap();
+            assert_eq!(entry.name, "01.jpg");
+        }
+    }
+
+    #[test]
+    fn test_find_cover_largest() {
+        let temp_file = Builder::new()
+            .prefix("test_largest_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(
+            temp_path,
+            &[
+                ("page1.jpg", &[0u8; 100]),
+                ("page2.jpg", &[0u8; 3000]),
+                ("page3.jpg", &[0u8; 500]),
+                ("notes.txt", &[0u8; 9000]),
+            ],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let entry = archive.find_cover(CoverStrategy::Largest, false).unwrap();
+        assert_eq!(entry.name, "page2.jpg");
+        assert_eq!(entry.size, 3000);
+
+        // Default strategy still returns the first image
+        let entry = archive
+            .find_cover(CoverStrategy::FirstSorted, true)
+            .unwrap();
+        assert_eq!(entry.name, "page1.jpg");
+    }
+
+    #[test]
+    fn test_find_cover_opf() {
+        let temp_file = Builder::new().suffix(".epub").tempfile().unwrap();
+        let temp_path = temp_file.path();
+        let container = br#"<container><rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles></container>"#;
+        let opf = br#"<package><metadata><meta name="cover" content="cov"/></metadata>
+<manifest><item id="cov" href="Images/Front.jpg" media-type="image/jpeg"/></manifest></package>"#;
+        create_test_zip_file(
+            temp_path,
+            &[
+                ("META-INF/container.xml", container),
+                ("OEBPS/content.opf", opf),
+                ("OEBPS/Images/back.jpg", &[0u8; 10]),
+                ("OEBPS/Images/front.jpg", &[0u8; 10]),
+            ],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let entry = archive.find_cover(CoverStrategy::Opf, true).unwrap();
+        assert_eq!(entry.name, "OEBPS/Images/front.jpg");
+
+        // Without a package document the first image is used
+        create_test_zip_file(temp_path, &[("b.jpg", &[0u8; 10]), ("a.jpg", &[0u8; 10])]).unwrap();
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let entry = archive.find_cover(CoverStrategy::Opf, true).unwrap();
+        assert_eq!(entry.name, "a.jpg");
+    }
+
+    /// Encode a gray JPEG of the given size
+    fn create_test_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(width, height, image::Rgb([128, 128, 128]));
+        let mut data = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut data),
+            image::ImageFormat::Jpeg,
+        )
+        .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_likely_photo_album() {
+        let photo = create_test_jpeg(8, 6);
+        let names: Vec<String> = (0..100).map(|i| format!("IMG_{:04}.jpg", i)).collect();
+        let files: Vec<(&str, &[u8])> = names
+            .iter()
+            .map(|name| (name.as_str(), photo.as_slice()))
+            .collect();
+
+        let buffer = create_test_zip(&files);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+        assert!(archive.get_metadata().unwrap().likely_photo_album);
+
+        // Too few images: nothing is probed
+        let buffer = create_test_zip(&files[..10]);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+        assert!(!archive.get_metadata().unwrap().likely_photo_album);
+
+        // A portrait page among the first few means comic pages
+        let page = create_test_jpeg(6, 8);
+        let mut comic = files.clone();
+        comic[1] = ("IMG_0001.jpg", page.as_slice());
+        let buffer = create_test_zip(&comic);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+        assert!(!archive.get_metadata().unwrap().likely_photo_album);
+    }
+
+    #[test]
+    fn test_find_cover_portrait() {
+        let temp_file = Builder::new()
+            .prefix("test_portrait_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        let banner = create_test_jpeg(64, 16);
+        let page = create_test_jpeg(16, 24);
+        create_test_zip_file(temp_path, &[("banner.jpg", &banner), ("page1.jpg", &page)]).unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let entry = archive.find_cover(CoverStrategy::Portrait, true).unwrap();
+        assert_eq!(entry.name, "page1.jpg");
+
+        // Disabled: the banner is the first image
+        let entry = archive
+            .find_cover(CoverStrategy::FirstSorted, true)
+            .unwrap();
+        assert_eq!(entry.name, "banner.jpg");
+    }
+
+    #[test]
+    fn test_find_cover_portrait_falls_back_to_first_image() {
+        let temp_file = Builder::new()
+            .prefix("test_portrait_fallback_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        let wide = create_test_jpeg(64, 16);
+        create_test_zip_file(
+            temp_path,
+            &[("b.jpg", &wide), ("a.jpg", &wide), ("c.jpg", b"not a jpeg")],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let entry = archive.find_cover(CoverStrategy::Portrait, true).unwrap();
+        assert_eq!(entry.name, "a.jpg");
+    }
+
+    #[test]
+    fn test_find_cover_with_override() {
+        let temp_file = Builder::new()
+            .prefix("test_override_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(
+            temp_path,
+            &[
+                ("pages/page1.jpg", b"image 1"),
+                ("pages/page2.jpg", b"image 2"),
+                ("notes.txt", b"text"),
+            ],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+
+        // Separator and case differences still match
+        let entry = archive
+            .find_cover_with_override(Some("Pages\\PAGE2.jpg"), CoverStrategy::FirstSorted, true)
+            .unwrap();
+        assert_eq!(entry.name, "pages/page2.jpg");
+
+        // Stale or non-image overrides fall back to the strategy
+        for name in [Some("missing.jpg"), Some("notes.txt"), None] {
+            let entry = archive
+                .find_cover_with_override(name, CoverStrategy::FirstSorted, true)
+                .unwrap();
+            assert_eq!(entry.name, "pages/page1.jpg");
+        }
+    }
+
+    /// Single uncompressed entry
+    fn create_stored_zip(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        {
+            let mut zip = ZipWriter::new(std::io::Cursor::new(&mut buffer));
+            let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+            zip.start_file(name, options).unwrap();
+            zip.write_all(data).unwrap();
+            zip.finish().unwrap();
+        }
+        buffer
+    }
+
+    /// Rewrite a single-entry STORED zip the way streaming writers lay it out:
+    /// flag bit 3 set, zero CRC and sizes in the local header, and the real
+    /// values in a data descriptor after the data
+    fn create_streamed_zip(name: &str, data: &[u8]) -> Vec<u8> {
+        let buffer = create_stored_zip(name, data);
+
+        let u16_at = |at: usize| u16::from_le_bytes([buffer[at], buffer[at + 1]]) as usize;
+        let data_end = 30 + u16_at(26) + u16_at(28) + data.len();
+        let crc = buffer[14..18].to_vec();
+
+        let mut out = buffer[..data_end].to_vec();
+        out[6] |= 0x08;
+        out[14..26].fill(0);
+        out.extend_from_slice(b"PK\x07\x08");
+        out.extend_from_slice(&crc);
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+
+        let mut central = buffer[data_end..].to_vec();
+        central[8] |= 0x08;
+        let eocd = central.len() - 22;
+        let cd_offset = u32::from_le_bytes(central[eocd + 16..eocd + 20].try_into().unwrap());
+        central[eocd + 16..eocd + 20].copy_from_slice(&(cd_offset + 16).to_le_bytes());
+        out.extend_from_slice(&central);
+        out
+    }
+
+    #[test]
+    fn test_streamed_zip_with_data_descriptor() {
+        let data = b"streamed image data".repeat(10);
+        let bytes = create_streamed_zip("page1.jpg", &data);
+        // Local header really carries no sizes
+        assert_eq!(&bytes[18..26], &[0u8; 8]);
+
+        let temp_file = Builder::new()
+            .prefix("test_streamed_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        std::fs::write(temp_file.path(), &bytes).unwrap();
+        let from_file = ZipArchive::open(temp_file.path()).unwrap();
+        let from_stream = ZipArchiveFromStream::new(std::io::Cursor::new(bytes.clone())).unwrap();
+
+        // A caller-built entry without a size still extracts in full
+        let sizeless = ArchiveEntry {
+            name: "page1.jpg".to_string(),
+            size: 0,
+            is_directory: false,
+        };
+        for archive in [&from_file as &dyn Archive, &from_stream] {
+            let entry = archive.find_first_image(true).unwrap();
+            assert_eq!(entry.size, data.len() as u64);
+            assert_eq!(archive.extract_entry(&entry).unwrap(), data);
+            assert_eq!(archive.extract_entry(&sizeless).unwrap(), data);
+        }
+    }
+
+    /// Traditional PKWARE (ZipCrypto) cipher state
+    struct ZipCrypto {
+        keys: [u32; 3],
+    }
+
+    impl ZipCrypto {
+        fn new(password: &[u8]) -> Self {
+            let mut cipher = Self {
+                keys: [0x1234_5678, 0x2345_6789, 0x3456_7890],
+            };
+            for &byte in password {
+                cipher.update(byte);
+            }
+            cipher
+        }
+
+        fn crc32_byte(crc: u32, byte: u8) -> u32 {
+            let mut crc = crc ^ byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+            crc
+        }
+
+        fn update(&mut self, byte: u8) {
+            self.keys[0] = Self::crc32_byte(self.keys[0], byte);
+            self.keys[1] = self.keys[1]
+                .wrapping_add(self.keys[0] & 0xff)
+                .wrapping_mul(134_775_813)
+                .wrapping_add(1);
+            self.keys[2] = Self::crc32_byte(self.keys[2], (self.keys[1] >> 24) as u8);
+        }
+
+        fn encrypt(&mut self, byte: u8) -> u8 {
+            let temp = (self.keys[2] | 2) as u16;
+            let cipher = byte ^ (temp.wrapping_mul(temp ^ 1) >> 8) as u8;
+            self.update(byte);
+            cipher
+        }
+    }
+
+    /// Single stored entry encrypted with ZipCrypto under `password`
+    fn create_encrypted_zip(name: &str, data: &[u8], password: &str) -> Vec<u8> {
+        let plain = create_stored_zip(name, data);
+
+        let u16_at = |at: usize| u16::from_le_bytes([plain[at], plain[at + 1]]) as usize;
+        let header_len = 30 + u16_at(26) + u16_at(28);
+        let data_end = header_len + data.len();
+        let crc = u32::from_le_bytes(plain[14..18].try_into().unwrap());
+
+        // Encryption header: 11 filler bytes, then the CRC's high byte as check
+        let mut header = [0x5Au8; 12];
+        header[11] = (crc >> 24) as u8;
+        let mut cipher = ZipCrypto::new(password.as_bytes());
+        let encrypted: Vec<u8> = header
+            .iter()
+            .chain(data)
+            .map(|&byte| cipher.encrypt(byte))
+            .collect();
+        let compressed_size = (encrypted.len() as u32).to_le_bytes();
+
+        let mut out = plain[..header_len].to_vec();
+        out[6] |= 0x01;
+        out[18..22].copy_from_slice(&compressed_size);
+        out.extend_from_slice(&encrypted);
+
+        let mut central = plain[data_end..].to_vec();
+        central[8] |= 0x01;
+        central[20..24].copy_from_slice(&compressed_size);
+        let eocd = central.len() - 22;
+        let cd_offset = u32::from_le_bytes(central[eocd + 16..eocd + 20].try_into().unwrap());
+        central[eocd + 16..eocd + 20].copy_from_slice(&(cd_offset + 12).to_le_bytes());
+        out.extend_from_slice(&central);
+        out
+    }
+
+    #[test]
+    fn test_encrypted_zip_with_password() {
+        let data = b"encrypted cover data".repeat(8);
+        let bytes = create_encrypted_zip("page1.jpg", &data, "secret");
+        let entry = ArchiveEntry {
+            name: "page1.jpg".to_string(),
+            size: data.len() as u64,
+            is_directory: false,
+        };
+
+        let locked = ZipArchiveFromStream::new(std::io::Cursor::new(bytes.clone())).unwrap();
+        assert!(locked.is_password_protected());
+        assert!(matches!(
+            locked.extract_entry(&entry),
+            Err(CbxError::PasswordProtected)
+        ));
+
+        let wrong = ZipArchiveFromStream::new(std::io::Cursor::new(bytes.clone()))
+            .unwrap()
+            .with_password(Some("wrong".to_string()));
+        assert!(matches!(
+            wrong.extract_entry(&entry),
+            Err(CbxError::PasswordProtected)
+        ));
+
+        let temp_file = Builder::new()
+            .prefix("test_encrypted_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        std::fs::write(temp_file.path(), &bytes).unwrap();
+        let from_file = ZipArchive::open(temp_file.path())
+            .unwrap()
+            .with_password(Some("secret".to_string()));
+        let from_stream = ZipArchiveFromStream::new(std::io::Cursor::new(bytes))
+            .unwrap()
+            .with_password(Some("secret".to_string()));
+
+        for archive in [&from_file as &dyn Archive, &from_stream] {
+            assert!(!archive.is_password_protected());
+            let cover = archive.find_first_image(true).unwrap();
+            assert_eq!(cover.name, "page1.jpg");
+            assert_eq!(archive.extract_entry(&cover).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_size_limit_uses_central_directory() {
+        let mut bytes = create_test_zip(&[("page1.jpg", b"small")]);
+        // Central directory claims more than the limit; the entry doesn't
+        let central = bytes.len() - 22 - (46 + "page1.jpg".len());
+        assert_eq!(&bytes[central..central + 4], b"PK\x01\x02");
+        let oversized = (MAX_ENTRY_SIZE as u32 + 1).to_le_bytes();
+        bytes[central + 24..central + 28].copy_from_slice(&oversized);
+
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(bytes)).unwrap();
+        let sizeless = ArchiveEntry {
+            name: "page1.jpg".to_string(),
+            size: 0,
+            is_directory: false,
+        };
+        let err = archive.extract_entry(&sizeless).unwrap_err();
+        assert!(err.to_string().contains("Entry too large"), "{}", err);
+    }
+
+    /// Stored entry whose headers declare `declared` bytes it doesn't have
+    fn create_inflated_zip(name: &str, data: &[u8], declared: u32) -> Vec<u8> {
+        let mut bytes = create_stored_zip(name, data);
+        let declared = declared.to_le_bytes();
+        bytes[18..22].copy_from_slice(&declared);
+        bytes[22..26].copy_from_slice(&declared);
+        let central = bytes.len() - 22 - (46 + name.len());
+        assert_eq!(&bytes[central..central + 4], b"PK\x01\x02");
+        bytes[central + 20..central + 24].copy_from_slice(&declared);
+        bytes[central + 24..central + 28].copy_from_slice(&declared);
+        bytes
+    }
+
+    #[test]
+    fn test_inflated_entry_size_keeps_partial_image() {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend_from_slice(&[0x42; 200]);
+        let declared = 20 * 1024 * 1024;
+        let bytes = create_inflated_zip("page1.png", &png, declared);
+
+        let temp_file = Builder::new()
+            .prefix("test_inflated_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        std::fs::write(temp_file.path(), &bytes).unwrap();
+        let from_file = ZipArchive::open(temp_file.path()).unwrap();
+        let from_stream = ZipArchiveFromStream::new(std::io::Cursor::new(bytes)).unwrap();
+
+        for archive in [&from_file as &dyn Archive, &from_stream] {
+            let entry = archive.find_first_image(true).unwrap();
+            assert_eq!(entry.size, declared as u64);
+            // Whatever the archive really holds, starting with the image
+            let data = archive.extract_entry(&entry).unwrap();
+            assert!(data.starts_with(&png));
+            assert!((data.len() as u64) < declared as u64);
+        }
+    }
+
+    #[test]
+    fn test_inflated_entry_size_rejects_partial_non_image() {
+        let bytes = create_inflated_zip("page1.jpg", &[0x42; 200], 20 * 1024 * 1024);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(bytes)).unwrap();
+
+        let entry = archive.find_first_image(true).unwrap();
+        let err = archive.extract_entry(&entry).unwrap_err();
+        assert!(
+            err.to_string().contains("Failed to extract entry"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_case_only_duplicate_names() {
+        let temp_file = Builder::new()
+            .prefix("test_case_dupes_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(
+            temp_path,
+            &[
+                ("page1.jpg", b"lower"),
+                ("Page1.JPG", b"upper"),
+                ("page2.jpg", b"image 2"),
+            ],
+        )
+        .unwrap();
+        let archive = ZipArchive::open(temp_path).unwrap();
+
+        // Sorted: the stored name comes back verbatim and extracts its own data
+        let entry = archive.find_first_image(true).unwrap();
+        assert_eq!(entry.name, "Page1.JPG");
+        assert_eq!(archive.extract_entry(&entry).unwrap(), b"upper");
+
+        // Unsorted: archive order
+        let entry = archive.find_first_image(false).unwrap();
+        assert_eq!(entry.name, "page1.jpg");
+        assert_eq!(archive.extract_entry(&entry).unwrap(), b"lower");
+
+        // Each casing stays addressable, including through an override
+        for (name, data) in [("page1.jpg", &b"lower"[..]), ("Page1.JPG", &b"upper"[..])] {
+            let entry = archive
+                .find_cover_with_override(Some(name), CoverStrategy::FirstSorted, true)
+                .unwrap();
+            assert_eq!(entry.name, name);
+            assert_eq!(archive.extract_entry(&entry).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_extract_stored_entry_fast_path() {
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        let page1: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let page2: Vec<u8> = (0..5000u32).map(|i| (i % 7) as u8).collect();
+
+        let temp_file = Builder::new()
+            .prefix("test_stored_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        {
+            let mut zip = ZipWriter::new(std::fs::File::create(temp_file.path()).unwrap());
+            zip.start_file("page1.jpg", stored).unwrap();
+            zip.write_all(&page1).unwrap();
+            zip.start_file("page2.jpg", deflated).unwrap();
+            zip.write_all(&page2).unwrap();
+            zip.finish().unwrap();
+        }
+
+        let archive = ZipArchive::open(temp_file.path()).unwrap();
+
+        // STORED entry takes the fast path, DEFLATED does not
+        assert_eq!(archive.read_stored_entry(0).unwrap(), page1);
+        assert!(archive.read_stored_entry(1).is_none());
+
+        for (name, expected) in [("page1.jpg", &page1), ("page2.jpg", &page2)] {
+            let entry = archive.get_entry_by_name(name).unwrap();
+            assert_eq!(&archive.extract_entry(&entry).unwrap(), expected);
+        }
+
+        // A damaged STORED entry fails its CRC instead of being returned
+        let mut bytes = std::fs::read(temp_file.path()).unwrap();
+        let offset = bytes.windows(page1.len()).position(|w| w == page1).unwrap();
+        bytes[offset + 100] ^= 0xFF;
+        std::fs::write(temp_file.path(), &bytes).unwrap();
+        let archive = ZipArchive::open(temp_file.path()).unwrap();
+        assert!(archive.read_stored_entry(0).is_none());
+        let entry = archive.get_entry_by_name("page1.jpg").unwrap();
+        assert!(archive.extract_entry(&entry).is_err());
+    }
+
+    /// Build a ZIP holding one WinZip AES (AE-2, AES-256) entry
+    ///
+    /// The zip crate cannot write AES entries, so the headers are assembled by
+    /// hand. `flag_encrypted` controls general purpose bit 0, which some tools
+    /// leave clear on AES entries.
+    fn create_aes_zip(name: &str, flag_encrypted: bool) -> Vec<u8> {
+        // Salt, password verifier, ciphertext and MAC; never decrypted
+        let data = [0x5Au8; 40];
+        let extra: &[u8] = &[
+            0x01, 0x99, // AES extra field ID (0x9901)
+            0x07, 0x00, // data size
+            0x02, 0x00, // vendor version AE-2
+            b'A', b'E', // vendor ID
+            0x03, // AES-256
+            0x08, 0x00, // actual compression method: deflate
+        ];
+        let flags: u16 = if flag_encrypted { 1 } else { 0 };
+
+        // Fields shared by the local and central headers, from "flags" on
+        let mut common = Vec::new();
+        common.extend_from_slice(&flags.to_le_bytes());
+        common.extend_from_slice(&99u16.to_le_bytes()); // method: AES
+        common.extend_from_slice(&[0; 4]); // time, date
+        common.extend_from_slice(&0u32.to_le_bytes()); // CRC (unused by AE-2)
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&32u32.to_le_bytes()); // uncompressed size
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+
+        let mut zip = Vec::new();
+        zip.extend_from_slice(b"PK\x03\x04");
+        zip.extend_from_slice(&51u16.to_le_bytes()); // version needed
+        zip.extend_from_slice(&common);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(extra);
+        zip.extend_from_slice(&data);
+
+        let central_start = zip.len();
+        zip.extend_from_slice(b"PK\x01\x02");
+        zip.extend_from_slice(&63u16.to_le_bytes()); // version made by
+        zip.extend_from_slice(&51u16.to_le_bytes()); // version needed
+        zip.extend_from_slice(&common);
+        zip.extend_from_slice(&[0; 6]); // comment length, disk, internal attributes
+        zip.extend_from_slice(&[0; 4]); // external attributes
+        zip.extend_from_slice(&0u32.to_le_bytes()); // local header offset
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(extra);
+        let central_size = zip.len() - central_start;
+
+        zip.extend_from_slice(b"PK\x05\x06");
+        zip.extend_from_slice(&[0; 4]); // disk numbers
+        zip.extend_from_slice(&1u16.to_le_bytes());
+        zip.extend_from_slice(&1u16.to_le_bytes());
+        zip.extend_from_slice(&(central_size as u32).to_le_bytes());
+        zip.extend_from_slice(&(central_start as u32).to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        zip
+    }
+
+    #[test]
+    fn test_aes_entry_reports_password_protected() {
+        for flag_encrypted in [true, false] {
+            let buffer = create_aes_zip("page1.jpg", flag_encrypted);
+            let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+
+            assert!(archive.is_password_protected());
+            assert!(matches!(
+                archive.find_images(),
+                Err(CbxError::PasswordProtected)
+            ));
+            for sort in [false, true] {
+                assert!(matches!(
+                    archive.find_first_image(sort),
+                    Err(CbxError::PasswordProtected)
+                ));
+            }
+
+            let entry = ArchiveEntry {
+                name: "page1.jpg".to_string(),
+                size: 32,
+                is_directory: false,
+            };
+            assert!(matches!(
+                archive.extract_entry(&entry),
+                Err(CbxError::PasswordProtected)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_aes_entry_reports_password_protected_from_file() {
+        let temp_file = Builder::new()
+            .prefix("test_aes_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        std::fs::write(temp_file.path(), create_aes_zip("page1.jpg", false)).unwrap();
+
+        let archive = ZipArchive::open(temp_file.path()).unwrap();
+        let entry = ArchiveEntry {
+            name: "page1.jpg".to_string(),
+            size: 32,
+            is_directory: false,
+        };
+        assert!(matches!(
+            archive.extract_entry(&entry),
+            Err(CbxError::PasswordProtected)
+        ));
+    }
+
+    #[test]
+    fn test_get_comment() {
+        let mut buffer = Vec::new();
+        {
+            let mut zip = ZipWriter::new(std::io::Cursor::new(&mut buffer));
+            zip.set_comment("Vol. 1 - Story & Art by Someone");
+            zip.start_file("page1.jpg", FileOptions::default()).unwrap();
+            zip.write_all(b"image").unwrap();
+            zip.finish().unwrap();
+        }
+
+        let temp_file = Builder::new()
+            .prefix("test_comment_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        std::fs::write(temp_file.path(), &buffer).unwrap();
+
+        let expected = Some("Vol. 1 - Story & Art by Someone".to_string());
+        let archive = ZipArchive::open(temp_file.path()).unwrap();
+        assert_eq!(archive.get_comment().unwrap(), expected);
+
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+        assert_eq!(archive.get_comment().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_get_comment_absent() {
+        let buffer = create_test_zip(&[("page1.jpg", b"image")]);
+        let archive = ZipArchiveFromStream::new(std::io::Cursor::new(buffer)).unwrap();
+        assert_eq!(archive.get_comment().unwrap(), None);
+
+        assert_eq!(comment_text(b"  \0\r\n"), None);
+    }
+
+    #[test]
+    fn test_comic_info_front_cover_selects_page() {
+        let comic_info = br#"<ComicInfo><Pages>
+            <Page Image="0"/><Page Image="1"/><Page Image="2" Type="FrontCover"/>
+        </Pages></ComicInfo>"#;
+        let temp_file = Builder::new()
+            .prefix("test_comic_info_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(
+            temp_path,
+            &[
+                ("page002.jpg", b"two"),
+                ("page001.jpg", b"one"),
+                ("ComicInfo.xml", comic_info),
+                ("page003.jpg", b"three"),
+                ("page004.jpg", b"four"),
+            ],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let name = archive
+            .manifest_cover_name(&archive.list_entries().unwrap(), Some(SortOrder::default()));
+        assert_eq!(name.as_deref(), Some("page003.jpg"));
+
+        let entry = archive
+            .find_cover_with_override(name.as_deref(), CoverStrategy::FirstSorted, true)
+            .unwrap();
+        assert_eq!(entry.name, "page003.jpg");
+    }
+
+    #[test]
+    fn test_cover_manifest_wins_and_absent_falls_back() {
+        let temp_file = Builder::new()
+            .prefix("test_cbxcover_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(
+            temp_path,
+            &[
+                ("page001.jpg", b"one"),
+                ("art/back.jpg", b"back"),
+                ("CBXCOVER.txt", b"art/back.jpg\n"),
+                (
+                    "ComicInfo.xml",
+                    br#"<Pages><Page Image="0" Type="FrontCover"/></Pages>"#,
+                ),
+            ],
+        )
+        .unwrap();
+        let archive = ZipArchive::open(temp_path).unwrap();
+        assert_eq!(
+            archive
+                .manifest_cover_name(&archive.list_entries().unwrap(), Some(SortOrder::default()))
+                .as_deref(),
+            Some("art/back.jpg")
+        );
+
+        let temp_file = Builder::new()
+            .prefix("test_no_manifest_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        create_test_zip_file(temp_file.path(), &[("page001.jpg", b"one")]).unwrap();
+        let archive = ZipArchive::open(temp_file.path()).unwrap();
+        assert_eq!(
+            archive
+                .manifest_cover_name(&archive.list_entries().unwrap(), Some(SortOrder::default())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_open_archive_through_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("real.cbz");
+        let link = dir.path().join("linked.cbz");
+        create_test_zip_file(&target, &[("page001.jpg", b"cover")]).unwrap();
+
+        #[cfg(windows)]
+        let created = std::os::windows::fs::symlink_file(&target, &link);
+        #[cfg(unix)]
+        let created = std::os::unix::fs::symlink(&target, &link);
+        if let Err(e) = created {
+            // Windows needs Developer Mode or admin rights for symlinks
+            eprintln!("Skipping symlink test: {}", e);
+            return;
+        }
+
+        let archive = crate::archive::open_archive(&link).unwrap();
+        let entry = archive.find_first_image(true).unwrap();
+        assert_eq!(archive.extract_entry(&entry).unwrap(), b"cover");
+    }
+
+    #[test]
+    fn test_read_cover() {
+        const PNG: &[u8] = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00];
+        let temp_file = Builder::new()
+            .prefix("test_read_cover_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(
+            temp_path,
+            &[("notes.txt", b"not an image"), ("page01.png", PNG)],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let (data, format) = archive.read_cover(true).unwrap();
+
+        assert_eq!(data, PNG);
+        assert_eq!(format, ImageFormat::Png);
+    }
+
+    #[test]
+    fn test_list_pages_and_page_dimensions() {
+        let temp_file = Builder::new()
+            .prefix("test_pages_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        let page2 = create_test_jpeg(40, 60);
+        let page1 = create_test_jpeg(30, 20);
+        create_test_zip_file(
+            temp_path,
+            &[
+                ("page2.jpg", &page2),
+                ("notes.txt", b"not an image"),
+                ("page1.jpg", &page1),
+            ],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let pages = archive.list_pages().unwrap();
+        let images = archive.find_images().unwrap();
+
+        assert_eq!(pages.len(), images.len());
+        for (index, (page, image)) in pages.iter().zip(&images).enumerate() {
+            assert_eq!(page.index, index);
+            assert_eq!(page.name, image.name);
+            assert_eq!(page.size, image.size);
+        }
+
+        assert_eq!(archive.page_dimensions(0).unwrap(), (40, 60));
+        assert_eq!(archive.page_dimensions(1).unwrap(), (30, 20));
+        assert!(archive.page_dimensions(2).is_err());
+    }
+
+    #[test]
+    fn test_find_embedded_preview() {
+        let temp_file = Builder::new()
+            .prefix("test_preview_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        let preview = create_test_jpeg(8, 12);
+        let page = create_test_jpeg(64, 96);
+        create_test_zip_file(
+            temp_path,
+            &[("page01.jpg", &page), ("__cover.jpg", &preview)],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let entries = archive.list_entries().unwrap();
+        let names = vec!["__cover.jpg".to_string(), "preview.jpg".to_string()];
+        let (entry, data) = archive.find_embedded_preview(&entries, &names).unwrap();
+        assert_eq!(entry.name, "__cover.jpg");
+        assert_eq!(data, preview);
+
+        // Disabled (no names) or no match: normal selection runs
+        assert!(archive.find_embedded_preview(&entries, &[]).is_none());
+        assert!(archive
+            .find_embedded_preview(
+                &archive.list_entries().unwrap(),
+                &["preview.jpg".to_string()]
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_embedded_preview_skips_invalid_preview() {
+        let temp_file = Builder::new()
+            .prefix("test_preview_bad_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        let preview = create_test_jpeg(8, 12);
+        create_test_zip_file(
+            temp_path,
+            &[("__cover.jpg", b"truncated"), ("preview.jpg", &preview)],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let names = vec!["__cover.jpg".to_string(), "preview.jpg".to_string()];
+        let (entry, _) = archive
+            .find_embedded_preview(&archive.list_entries().unwrap(), &names)
+            .unwrap();
+        assert_eq!(entry.name, "preview.jpg");
+    }
+
+    #[test]
+    fn test_directory_named_like_image_is_skipped() {
+        let temp_file = Builder::new()
+            .prefix("test_image_dir_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        let page = create_test_jpeg(8, 12);
+        {
+            let mut zip = ZipWriter::new(File::create(temp_path).unwrap());
+            zip.add_directory("cover.jpg", FileOptions::default())
+                .unwrap();
+            zip.start_file("page02.jpg", FileOptions::default())
+                .unwrap();
+            zip.write_all(&page).unwrap();
+            zip.finish().unwrap();
+        }
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        for sort in [false, true] {
+            let entry = archive.find_first_image(sort).unwrap();
+            assert_eq!(entry.name, "page02.jpg", "sort={}", sort);
+            assert!(!entry.is_directory);
+        }
+        assert_eq!(archive.find_images().unwrap().len(), 1);
+        assert_eq!(archive.get_metadata().unwrap().image_count, 1);
+    }
+
+    #[test]
+    fn test_write_entry_appends_preview() {
+        let temp_file = Builder::new()
+            .prefix("test_write_entry_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        let page = create_test_jpeg(64, 96);
+        create_test_zip_file(temp_path, &[("page01.jpg", &page)]).unwrap();
+
+        let preview = create_test_jpeg(8, 12);
+        write_entry(temp_path, "__cover.jpg", &preview).unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let (entry, data) = archive
+            .find_embedded_preview(
+                &archive.list_entries().unwrap(),
+                &["__cover.jpg".to_string()],
+            )
+            .unwrap();
+        assert_eq!(entry.name, "__cover.jpg");
+        assert_eq!(data, preview);
+        // Pages are still readable after the rewrite
+        let page_entry = archive.find_entries(&["page01.jpg"]).unwrap().remove(0);
+        assert_eq!(archive.extract_entry(&page_entry).unwrap(), page);
+    }
+
+    #[test]
+    fn test_write_entry_replaces_existing_preview() {
+        let temp_file = Builder::new()
+            .prefix("test_write_entry_replace_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        let page = create_test_jpeg(64, 96);
+        let old_preview = create_test_jpeg(4, 6);
+        create_test_zip_file(
+            temp_path,
+            &[("page01.jpg", &page), ("__cover.jpg", &old_preview)],
+        )
+        .unwrap();
+
+        let preview = create_test_jpeg(8, 12);
+        write_entry(temp_path, "__cover.jpg", &preview).unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        assert_eq!(
+            archive.get_entry_names().unwrap(),
+            vec!["page01.jpg".to_string(), "__cover.jpg".to_string()]
+        );
+        let (_, data) = archive
+            .find_embedded_preview(
+                &archive.list_entries().unwrap(),
+                &["__cover.jpg".to_string()],
+            )
+            .unwrap();
+        assert_eq!(data, preview);
+        assert!(!temp_path.with_extension("cbxtmp").exists());
+    }
+
+    #[test]
+    fn test_write_entry_never_writes_the_original() {
+        let temp_file = Builder::new()
+            .prefix("test_write_entry_atomic_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(temp_path, &[("page01.jpg", &create_test_jpeg(8, 8))]).unwrap();
+        let original = std::fs::read(temp_path).unwrap();
+
+        // The temporary copy can't be created, so nothing is written anywhere
+        let blocker = temp_path.with_extension("cbxtmp");
+        std::fs::create_dir(&blocker).unwrap();
+        let result = write_entry(temp_path, "__cover.jpg", b"data");
+        std::fs::remove_dir(&blocker).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(temp_path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_write_entry_rejects_non_zip() {
+        let temp_file = Builder::new()
+            .prefix("test_write_entry_bad_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        std::fs::write(temp_file.path(), b"not a zip").unwrap();
+
+        let result = write_entry(temp_file.path(), "__cover.jpg", b"data");
+        assert!(matches!(result, Err(CbxError::Archive(_))));
+        assert_eq!(std::fs::read(temp_file.path()).unwrap(), b"not a zip");
+    }
+
+    #[test]
+    fn test_read_cover_rejects_misnamed_image() {
+        let temp_file = Builder::new()
+            .prefix("test_read_cover_bad_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(temp_path, &[("page01.jpg", b"not really a jpeg")]).unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        assert!(archive.read_cover(true).is_err());
+    }
+
+    #[test]
+    fn test_extract_entry() {
+        let content = b"fake jpeg data";
+        let temp_file = Builder::new()
+            .prefix("test_extract_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(temp_path, &[("image.jpg", content)]).unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let entry = archive.find_first_image(false).unwrap();
+        let extracted = archive.extract_entry(&entry).unwrap();
+
+        assert_eq!(extracted, content);
+    }
+
+    #[test]
+    fn test_get_metadata() {
+        let temp_file = Builder::new()
+            .prefix("test_metadata_")
+            .suffix(".zip")
+            .tempfile()
+            .unwrap();
+        let temp_path = temp_file.path();
+        create_test_zip_file(
+            temp_path,
+            &[
+                ("page1.jpg", b"image 1"),
+                ("page2.jpg", b"image 2"),
+                ("readme.txt", b"text"),
+            ],
+        )
+        .unwrap();
+
+        let archive = ZipArchive::open(temp_path).unwrap();
+        let metadata = archive.get_metadata().unwrap();
+
+        assert_eq!(metadata.total_files, 3);
+        assert_eq!(metadata.image_count, 2);
+        assert!(metadata.compressed_size > 0);
+        assert_eq!(metadata.archive_type, ArchiveType::Zip);
+    }
+}
+
+/// ZIP archive handler for IStream (direct streaming, no memory copy)
+///
+/// This is a performance-optimized version that streams directly from IStream
+/// without loading the entire archive into memory first.
+///
+/// # Performance
+/// - **Small archives (50MB)**: Similar to memory version
+/// - **Large archives (1GB+)**: 20-50x faster (no full memory load)
+///
+/// # Example
+/// For a 1GB archive:
+/// - Old approach: Load 1GB to memory (~3sec) + process
+/// - New approach: Stream directly (~50ms for metadata + image)
+pub struct ZipArchiveFromStream<R: Read + Seek> {
+    archive: RefCell<ZipReader<R>>,
+    /// Configured password for encrypted entries
+    password: Option<String>,
+    /// Sort order and scan limit from the settings
+    options: ListOptions,
+}
+
+impl<R: Read + Seek> ZipArchiveFromStream<R> {
+    /// Create a ZIP archive from a streaming reader
+    pub fn new(reader: R) -> Result<Self> {
+        let archive = ZipReader::new(reader)
+            .map_err(|e| directory_error("Failed to open ZIP from stream", e))?;
+
+        Ok(Self {
+            archive: RefCell::new(archive),
+            password: None,
+            options: ListOptions::default(),
+        })
+    }
+
+    /// Decrypt ZipCrypto entries with `password` (see `Settings::archive_password_for`)
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// List with `options` instead of the defaults (see `open_archive_with`)
+    pub fn with_list_options(mut self, options: ListOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Get all entry names (for internal use)
+    fn get_entry_names(&self) -> Result<Vec<String>> {
+        let mut archive = borrow_reader(&self.archive)?;
+        Ok((0..archive.len())
+            .filter_map(|i| {
+                open_entry(&mut archive, i, self.password.as_deref())
+                    .ok()
+                    .map(|f| f.name().to_string())
+            })
+            .collect())
+    }
+
+    /// List all image entries with their sizes
+    fn list_image_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let mut archive = borrow_reader(&self.archive)?;
+        let mut entries = Vec::new();
+
+        for i in 0..archive.len() {
+            if let Ok(f) = open_entry(&mut archive, i, self.password.as_deref()) {
+                if !f.is_dir() && is_image_file(f.name()) {
+                    entries.push(ArchiveEntry {
+                        name: f.name().to_string(),
+                        size: f.size(),
+                        is_directory: false,
+                    });
+                }
+            }
+        }
+        drop(archive);
+
+        if entries.is_empty() && has_locked_images(&self.archive, self.password.as_deref()) {
+            return Err(CbxError::PasswordProtected);
+        }
+
+        Ok(entries)
+    }
+
+    /// Get entry details by name
+    fn get_entry_by_name(&self, name: &str) -> Result<ArchiveEntry> {
+        let mut archive = borrow_reader(&self.archive)?;
+
+        for i in 0..archive.len() {
+            let zip_entry = open_entry(&mut archive, i, self.password.as_deref())
+                .map_err(|e| CbxError::Archive(format!("Failed to get entry {}: {}", i, e)))?;
+
+            if zip_entry.name() == name {
+                return Ok(ArchiveEntry {
+                    name: name.to_string(),
+                    size: zip_entry.size(),
+                    is_directory: zip_entry.is_dir(),
+                });
+            }
+        }
+
+        Err(CbxError::Archive(format!("Entry not found: {}", name)))
+    }
+}
+
+impl<R: Read + Seek> Archive for ZipArchiveFromStream<R> {
+    fn open(_path: &Path) -> Result<Box<dyn Archive>> {
+        // Not used for stream-based archives
+        Err(CbxError::Archive(
+            "Use open_archive_from_stream instead".to_string(),
+        ))
+    }
+
+    fn list_options(&self) -> ListOptions {
+        self.options
+    }
+
+    fn find_first_image(&self, sort: bool) -> Result<ArchiveEntry> {
+        tracing::debug!("Finding first image in ZIP from stream (sort={})", sort);
+
+        if !sort {
+            // OPTIMIZATION: When not sorting, find first image immediately
+            tracing::debug!("Fast path: finding first image without full listing");
+
+            let cap = self.options.max_non_image_scan;
+            let mut non_images = 0;
+            let mut archive = borrow_reader(&self.archive)?;
+            for i in 0..archive.len() {
+                if let Ok(entry) = open_entry(&mut archive, i, self.password.as_deref()) {
+                    let name = entry.name().to_string();
+                    if !entry.is_dir() && is_image_file(&name) {
+                        tracing::info!("Found first image (unsorted): {}", name);
+                        return Ok(ArchiveEntry {
+                            name,
+                            size: entry.size(),
+                            is_directory: false,
+                        });
+                    }
+                    if !entry.is_dir() {
+                        non_images += 1;
+                        if non_image_scan_exhausted(non_images, cap) {
+                            return Err(CbxError::NoImages);
+                        }
+                    }
+                }
+            }
+            drop(archive);
+
+            let entry_names = self.get_entry_names()?;
+            return Err(missing_image_error(
+                &self.archive,
+                self.password.as_deref(),
+                &entry_names,
+            ));
+        }
+
+        // STANDARD PATH: List all entries and sort
+        let entry_names = self.get_entry_names()?;
+
+        // Entries skipped as encrypted leave the listing empty too
+        if entry_names.is_empty() && !has_locked_images(&self.archive, self.password.as_deref()) {
+            return Err(CbxError::Archive("Archive is empty".to_string()));
+        }
+
+        // Find first image using shared utility
+        let image_name = find_first_image(
+            entry_names.iter().map(|s| s.as_str()),
+            sort.then_some(self.options.order),
+        )
+        .ok_or_else(|| {
+            missing_image_error(&self.archive, self.password.as_deref(), &entry_names)
+        })?;
+
+        tracing::info!("Found first image (sorted): {}", image_name);
+
+        // Get entry details
+        self.get_entry_by_name(&image_name)
+    }
+
+    fn find_images(&self) -> Result<Vec<ArchiveEntry>> {
+        self.list_image_entries()
+    }
+
+    fn list_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let mut archive = borrow_reader(&self.archive)?;
+        Ok((0..archive.len())
+            .filter_map(|i| {
+                let f = open_entry(&mut archive, i, self.password.as_deref()).ok()?;
+                Some(ArchiveEntry {
+                    name: f.name().to_string(),
+                    size: f.size(),
+                    is_directory: f.is_dir(),
+                })
+            })
+            .collect())
+    }
+
+    fn extract_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
+        tracing::debug!(
+            "Extracting entry from stream: {} ({} bytes)",
+            entry.name,
+            entry.size
+        );
+
+        let mut archive = borrow_reader(&self.archive)?;
+
+        // Find entry by name; sizes come from the central directory
+        let (index, size) = locate_entry(&mut archive, &entry.name)
+            .ok_or_else(|| CbxError::Archive(format!("Entry not found: {}", entry.name)))?;
+
+        // Safety check: prevent memory exhaustion
+        if size > MAX_ENTRY_SIZE {
+            tracing::warn!("Entry too large: {} bytes (max {})", size, MAX_ENTRY_SIZE);
+            return Err(CbxError::Archive(format!(
+                "Entry too large: {} bytes (max 32MB)",
+                size
+            )));
+        }
+
+        // Read to buffer
+        let zip_entry =
+            open_entry(&mut archive, index, self.password.as_deref()).map_err(entry_error)?;
+        let buffer = read_entry_data(zip_entry, size)?;
+
+        tracing::debug!("Extracted {} bytes", buffer.len());
+        Ok(buffer)
+    }
+
+    fn is_password_protected(&self) -> bool {
+        let Ok(mut archive) = borrow_reader(&self.archive) else {
+            return false;
+        };
+        (0..archive.len())
+            .any(|i| matches!(open_entry(&mut archive, i, self.password.as_deref()), Err(ref e) if is_password_required(e)))
+    }
+
+    fn get_metadata(&self) -> Result<ArchiveMetadata> {
+        let entry_names = self.get_entry_names()?;
         let total_files = entry_names.len();
         let image_count = entry_names
             .iter()
@@ -515,9 +2279,14 @@ impl<R: Read + Seek> Archive for ZipArchiveFromStream<R> {
             image_count,
             compressed_size: 0, // Not available from stream without full scan
             archive_type: ArchiveType::Zip,
+            likely_photo_album: likely_photo_album(self, image_count),
         })
     }
 
+    fn get_comment(&self) -> Result<Option<String>> {
+        Ok(comment_text(borrow_reader(&self.archive)?.comment()))
+    }
+
     fn archive_type(&self) -> ArchiveType {
         ArchiveType::Zip
     }
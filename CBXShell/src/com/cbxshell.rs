@@ -1,11 +1,20 @@
-use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicU32;
-use std::sync::Mutex;
 ///! CBXShell main COM object implementation
 ///!
+use crate::image_processor::cover_cache::{self, CoverKey};
+use crate::image_processor::thumbnail::ThumbnailConfig;
+use crate::settings::Settings;
+use image::{DynamicImage, RgbaImage};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU32;
+use std::sync::{Arc, Mutex};
 use windows::{
-    core::*, Win32::Foundation::*, Win32::Graphics::Gdi::HBITMAP, Win32::System::Com::*,
-    Win32::UI::Shell::PropertiesSystem::*, Win32::UI::Shell::*,
+    core::*,
+    Win32::Foundation::*,
+    Win32::Graphics::Gdi::HBITMAP,
+    Win32::System::Com::*,
+    Win32::System::Ole::{IObjectWithSite, IObjectWithSite_Impl},
+    Win32::UI::Shell::PropertiesSystem::*,
+    Win32::UI::Shell::*,
 };
 
 /// CBXShell COM object
@@ -14,46 +23,86 @@ use windows::{
     IInitializeWithStream,
     IInitializeWithFile,
     IInitializeWithItem,
-    IQueryInfo
+    IQueryInfo,
+    IObjectWithSite
 )]
 pub struct CBXShell {
     #[allow(dead_code)] // Used by COM infrastructure through #[implement] macro
     ref_count: AtomicU32,
     stream: Mutex<Option<IStream>>,
     file_path: Mutex<Option<PathBuf>>,
+    /// Host site from `IObjectWithSite::SetSite`, the hook for host-side
+    /// diagnostics and progress
+    site: Mutex<Option<IUnknown>>,
+    /// Dimensions of the current source's cover, from the decode of
+    /// `GetThumbnail` or probed by the first query that needs them. Like any
+    /// per-source cache it must be cleared in `set_source`.
+    cover_dimensions: DimensionCache,
+    /// Why the last `GetThumbnail` of this source failed, for the tooltip
+    thumbnail_hint: Mutex<Option<String>>,
 }
 
 impl CBXShell {
     /// Create a new CBXShell instance
     pub fn new() -> Result<IThumbnailProvider> {
         tracing::debug!("Creating CBXShell instance (IThumbnailProvider)");
-        crate::utils::debug_log::debug_log("===== CBXShell::new() CALLED =====");
+        crate::debug_log!("===== CBXShell::new() CALLED =====");
 
         let cbxshell = CBXShell {
             ref_count: AtomicU32::new(1),
             stream: Mutex::new(None),
             file_path: Mutex::new(None),
+            site: Mutex::new(None),
+            cover_dimensions: DimensionCache::default(),
+            thumbnail_hint: Mutex::new(None),
         };
 
         crate::add_dll_ref();
-        crate::utils::debug_log::debug_log("CBXShell instance created successfully");
+        crate::debug_log!("CBXShell instance created successfully");
         Ok(cbxshell.into())
     }
 
-    /// Get the stored IStream
-    fn get_stream(&self) -> Option<IStream> {
-        self.stream.lock().unwrap().clone()
-    }
-
     fn get_file_path(&self) -> Option<PathBuf> {
         self.file_path.lock().unwrap().clone()
     }
 
-    fn is_rar_archive_path(path: &Path) -> bool {
-        path.extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| ext.eq_ignore_ascii_case("rar") || ext.eq_ignore_ascii_case("cbr"))
-            .unwrap_or(false)
+    /// Report a non-fatal problem, noting whether a host site is attached
+    ///
+    /// Hosts don't share a diagnostics interface yet, so this only logs; it
+    /// is where forwarding to the site belongs once one is supported.
+    fn report_diagnostic(&self, message: &str) {
+        let hosted = self.site.lock().unwrap().is_some();
+        tracing::warn!(hosted, "{}", message);
+        crate::debug_log!("Diagnostic (host site: {}): {}", hosted, message);
+    }
+
+    /// Replace the thumbnail source
+    ///
+    /// Every `Initialize` variant goes through here so a re-Initialize never
+    /// leaves the previous source behind. Both locks are held together so a
+    /// concurrent reader can't observe a half-updated pair.
+    fn set_source(&self, stream: Option<IStream>, file_path: Option<PathBuf>) {
+        let mut stream_guard = self.stream.lock().unwrap();
+        let mut path_guard = self.file_path.lock().unwrap();
+        *stream_guard = stream;
+        *path_guard = file_path;
+        self.cover_dimensions.clear();
+        *self.thumbnail_hint.lock().unwrap() = None;
+    }
+
+    /// Forget the current source and everything cached about it
+    ///
+    /// Every `Initialize` variant starts here, so one that fails leaves the
+    /// instance uninitialized instead of still describing the previous file.
+    fn clear_source(&self) {
+        self.set_source(None, None);
+    }
+
+    /// Get the current thumbnail source with the precedence rule applied
+    fn get_source(&self) -> (Option<PathBuf>, Option<IStream>) {
+        let stream_guard = self.stream.lock().unwrap();
+        let path_guard = self.file_path.lock().unwrap();
+        resolve_source(path_guard.clone(), stream_guard.clone())
     }
 
     fn normalize_stream_name_to_path(stream_name: &str) -> Option<PathBuf> {
@@ -103,189 +152,668 @@ impl CBXShell {
         }
     }
 
+    /// The file behind `stream` if it may be opened by path instead
+    ///
+    /// Cloud placeholders are left to the stream (see `opens_by_path`).
+    fn stream_backing_file(stream: &IStream) -> Option<PathBuf> {
+        Self::recover_file_path_from_stream(stream)
+            .filter(|path| opens_by_path(path, crate::utils::file::is_cloud_placeholder))
+    }
+
+    /// Open the current source for the tooltip, with the file behind it
+    ///
+    /// Explorer often initializes tooltips with a stream only. Such a stream
+    /// is opened by its backing file when it has one (see
+    /// `stream_backing_file`), otherwise read through the stream itself.
+    fn open_for_info_tip(
+        &self,
+        settings: &Settings,
+    ) -> (Option<PathBuf>, Option<crate::archive::OpenedArchive>) {
+        use crate::archive::{classify_archive, open_archive_from_stream_with, IStreamReader};
+
+        let (file_path, stream) = self.get_source();
+        let path = file_path.or_else(|| stream.as_ref().and_then(Self::stream_backing_file));
+        let opened = match (&path, stream) {
+            (Some(path), _) => crate::archive::open_classified(path).ok(),
+            (None, Some(stream)) => {
+                open_archive_from_stream_with(IStreamReader::new(stream), settings)
+                    .and_then(|archive| classify_archive(archive, None))
+                    .ok()
+            }
+            (None, None) => None,
+        };
+        (path, opened)
+    }
+
+    /// Determine the archive size without opening it
+    ///
+    /// Prefers `IStream::Stat` when a stream is available, otherwise falls back
+    /// to file system metadata for the path.
+    fn source_size(path: Option<&Path>, stream: Option<&IStream>) -> Option<u64> {
+        if let Some(stream) = stream {
+            let mut stat: STATSTG = unsafe { std::mem::zeroed() };
+            if unsafe { stream.Stat(&mut stat, STATFLAG_NONAME) }.is_ok() {
+                return Some(stat.cbSize);
+            }
+        }
+
+        path.and_then(|p| std::fs::metadata(p).ok())
+            .map(|m| m.len())
+    }
+
     /// Extract thumbnail from archive (internal implementation)
     ///
-    /// This is the core thumbnail extraction logic for IThumbnailProvider that:
-    /// 1. Gets the IStream from IInitializeWithStream
-    /// 2. Reads archive data from stream into memory
-    /// 3. Detects archive type from magic bytes
-    /// 4. Opens the archive from memory
-    /// 5. Reads sort preference from registry
-    /// 6. Finds the first image (alphabetically if sorted)
-    /// 7. Extracts the image data
-    /// 8. Creates thumbnail HBITMAP with requested size
+    /// Applies the checks that need the COM source (enabled switch, size
+    /// limit, recent failures of the same file), then hands the request to
+    /// the shared `ThumbnailService`, which runs it within the worker cap,
+    /// request timeout and crash guard.
+    /// Archives on disk go to a worker; a stream whose file can't be opened
+    /// directly is read on this thread.
     ///
     /// # Arguments
     /// * `cx` - Maximum thumbnail width/height in pixels
+    /// * `settings` - Configuration loaded for this request
     ///
     /// # Returns
-    /// * `Ok(HBITMAP)` - Successfully created thumbnail
+    /// * `Ok(RenderedThumbnail)` - Thumbnail pixels, not yet wrapped for the shell
     /// * `Err(CbxError)` - Failed to extract or create thumbnail
-    fn extract_thumbnail_internal(&self, cx: u32) -> crate::utils::error::Result<HBITMAP> {
-        use crate::archive::{
-            open_archive, open_archive_from_stream, should_sort_images, IStreamReader,
-        };
-        use crate::image_processor::thumbnail::create_thumbnail_with_size;
+    fn extract_thumbnail_internal(
+        &self,
+        cx: u32,
+        settings: &Settings,
+    ) -> crate::utils::error::Result<RenderedThumbnail> {
+        use crate::archive::exceeds_max_archive_size;
         use crate::utils::error::CbxError;
+        use crate::utils::failure_cache;
+        use std::time::SystemTime;
 
-        crate::utils::debug_log::debug_log(
-            ">>>>> extract_thumbnail_internal STARTING (SOURCE-AWARE) <<<<<",
-        );
-        crate::utils::debug_log::debug_log(&format!("Requested thumbnail size: {}x{}", cx, cx));
+        // Master switch: bail out before touching the archive
+        if !settings.enabled {
+            crate::debug_log!("Thumbnails disabled (Enabled=0), skipping");
+            return Err(CbxError::Disabled);
+        }
 
-        let file_path = self.get_file_path();
-        let stream = self.get_stream();
+        crate::debug_log!(">>>>> extract_thumbnail_internal STARTING (SOURCE-AWARE) <<<<<");
+        crate::debug_log!("Requested thumbnail size: {}x{}", cx, cx);
+
+        let (file_path, stream) = self.get_source();
+
+        // Step 0: Skip archives above the configured size limit (0 = unlimited)
+        let max_mb = settings.max_archive_size_mb;
+        if max_mb != 0 {
+            if let Some(size) = Self::source_size(file_path.as_deref(), stream.as_ref()) {
+                if exceeds_max_archive_size(size, max_mb) {
+                    tracing::info!("Archive size {} exceeds limit of {} MB", size, max_mb);
+                    crate::debug_log!(
+                        "Step 0: Archive size {} bytes exceeds MaxArchiveSizeMB={}, skipping",
+                        size,
+                        max_mb
+                    );
+                    return Err(CbxError::ArchiveTooLarge {
+                        size,
+                        limit_mb: max_mb,
+                    });
+                }
+            }
+        }
 
-        let archive = match (file_path.as_deref(), stream) {
-            (Some(path), stream_opt) if Self::is_rar_archive_path(path) => {
-                crate::utils::debug_log::debug_log(
-                    "Step 1: Using direct path-based RAR open (temp file bypass)",
-                );
-                tracing::info!("Opening RAR directly from file path: {:?}", path);
+        // Step 0b: Skip a file that failed moments ago, until its cooldown
+        // ends or it changes
+        let recovered = match (&file_path, &stream) {
+            (None, Some(stream)) => Self::stream_backing_file(stream),
+            _ => None,
+        };
+        let failure_key = file_path
+            .as_deref()
+            .or(recovered.as_deref())
+            .and_then(CoverKey::for_file);
+        if let Some(error) = failure_key.as_ref().and_then(failure_cache::lookup) {
+            crate::debug_log!("Step 0b: Failed recently, skipping: {:?}", error.kind());
+            return Err(error);
+        }
 
-                match open_archive(path) {
-                    Ok(archive) => archive,
-                    Err(path_err) => {
+        // Step 1: Queue the request; streams backed by a RAR file are opened by path
+        // A truncated file that was just modified is retried, not recorded
+        let rendered = Self::queue_extraction(file_path, recovered, stream, cx, settings);
+        let rendered = match (rendered, failure_key) {
+            (Err(e), Some(key)) => {
+                let e = failure_cache::settle_partial_write(e, key.modified(), SystemTime::now());
+                failure_cache::record(key, &e);
+                Err(e)
+            }
+            (rendered, _) => rendered,
+        }?;
+
+        crate::debug_log!(">>>>> extract_thumbnail_internal COMPLETED SUCCESSFULLY <<<<<");
+        Ok(rendered)
+    }
+
+    /// Hand the source to the thumbnail service (step 1)
+    ///
+    /// `recovered` is the file behind `stream`, if any. A RAR file is opened by
+    /// path and read through the stream only when that fails (see
+    /// `is_rar_archive_path`); other formats read the stream and use the path
+    /// for the cover cache and crash guard.
+    fn queue_extraction(
+        file_path: Option<PathBuf>,
+        recovered: Option<PathBuf>,
+        stream: Option<IStream>,
+        cx: u32,
+        settings: &Settings,
+    ) -> crate::utils::error::Result<RenderedThumbnail> {
+        use crate::service::{ThumbnailService, ThumbnailSource};
+        use crate::utils::error::CbxError;
+
+        let service = ThumbnailService::global();
+        match (file_path, recovered, stream) {
+            (Some(path), _, _) => {
+                crate::debug_log!("Step 1: Queueing path-based extraction: {:?}", path);
+                service.request_thumbnail(ThumbnailSource::File(path), cx, settings)
+            }
+            (None, Some(path), Some(stream)) if is_rar_archive_path(&path) => {
+                crate::debug_log!(
+                    "Step 1: Recovered file path from stream, queueing: {:?}",
+                    path
+                );
+                let file = ThumbnailSource::File(path.clone());
+                match service.request_thumbnail(file, cx, settings) {
+                    Err(e @ (CbxError::Io(_) | CbxError::Archive(_))) => {
                         tracing::warn!(
-                            "Direct RAR open failed for {:?}: {}. Trying IStream fallback.",
+                            "Path open failed for {:?}: {}. Trying IStream fallback.",
                             path,
-                            path_err
+                            e
                         );
-                        crate::utils::debug_log::debug_log(&format!(
-                            "WARN: Direct RAR open failed: {}. Falling back to IStream path",
-                            path_err
-                        ));
-
-                        if let Some(stream) = stream_opt {
-                            let reader = IStreamReader::new(stream);
-                            open_archive_from_stream(reader).map_err(|stream_err| {
-                                CbxError::Archive(format!(
-                                    "RAR open failed from file path ({}) and stream fallback ({})",
-                                    path_err, stream_err
-                                ))
-                            })?
-                        } else {
-                            return Err(path_err);
-                        }
-                    }
-                }
-            }
-            (_, Some(stream)) => {
-                if let Some(recovered_path) = Self::recover_file_path_from_stream(&stream) {
-                    if Self::is_rar_archive_path(&recovered_path) {
-                        crate::utils::debug_log::debug_log(&format!(
-                            "Step 1: Recovered RAR file path from stream and opening directly: {:?}",
-                            recovered_path
-                        ));
-                        open_archive(&recovered_path)?
-                    } else {
-                        crate::utils::debug_log::debug_log(
-                            "Step 1: Using IStream-based archive open (optimized streaming)",
+                        crate::debug_log!(
+                            "WARN: Path open failed: {}. Falling back to IStream path",
+                            e
                         );
-                        let reader = IStreamReader::new(stream);
-                        tracing::debug!("IStreamReader created for direct streaming");
-                        open_archive_from_stream(reader)?
+                        let source = ThumbnailSource::Stream {
+                            stream,
+                            path: Some(path),
+                        };
+                        service.request_thumbnail(source, cx, settings)
                     }
-                } else {
-                    crate::utils::debug_log::debug_log(
-                        "Step 1: Using IStream-based archive open (optimized streaming)",
-                    );
-                    let reader = IStreamReader::new(stream);
-                    tracing::debug!("IStreamReader created for direct streaming");
-                    open_archive_from_stream(reader)?
+                    result => result,
                 }
             }
-            (Some(path), None) => {
-                crate::utils::debug_log::debug_log(
-                    "Step 1: Stream unavailable, falling back to direct path open",
-                );
-                tracing::info!("Opening archive from file path fallback: {:?}", path);
-                open_archive(path)?
+            (None, recovered, Some(stream)) => {
+                crate::debug_log!("Step 1: Using IStream-based archive open (optimized streaming)");
+                let source = ThumbnailSource::Stream {
+                    stream,
+                    path: recovered,
+                };
+                service.request_thumbnail(source, cx, settings)
             }
-            (None, None) => {
-                crate::utils::debug_log::debug_log(
-                    "ERROR: No stream or file path set in extract_thumbnail_internal",
+            (None, _, None) => {
+                crate::debug_log!(
+                    "ERROR: No stream or file path set in extract_thumbnail_internal"
                 );
-                return Err(CbxError::Archive(
+                Err(CbxError::Archive(
                     "No stream or file path initialized".to_string(),
-                ));
+                ))
             }
-        };
-        crate::utils::debug_log::debug_log("Step 3: Archive opened successfully");
-
-        // Step 4: Read sort preference from registry
-        let sort = should_sort_images();
-        tracing::debug!("Sort preference: {}", sort);
-        crate::utils::debug_log::debug_log(&format!("Step 4: Sort preference: {}", sort));
-
-        // Step 5: Find first image in archive
-        crate::utils::debug_log::debug_log("Step 5: Finding first image...");
-        let entry = archive.find_first_image(sort)?;
-        tracing::info!("Found image: {} ({} bytes)", entry.name, entry.size);
-        crate::utils::debug_log::debug_log(&format!(
-            "Step 5: Found image: {} ({} bytes)",
-            entry.name, entry.size
-        ));
+        }
+    }
+}
 
-        // Step 6: Extract image data
-        crate::utils::debug_log::debug_log("Step 6: Extracting image data...");
-        let image_data = archive.extract_entry(&entry)?;
-        tracing::debug!("Extracted {} bytes of image data", image_data.len());
-        crate::utils::debug_log::debug_log(&format!(
-            "Step 6: Extracted {} bytes of image data",
-            image_data.len()
-        ));
+/// Extract the thumbnail of the archive at `path`
+///
+/// Runs on a `ThumbnailService` worker. Reuses a cover decoded for an earlier
+/// request at another size; the key captures the modification time and the
+/// cover selection settings before the archive is opened.
+pub(crate) fn extract_from_path(
+    path: &Path,
+    cx: u32,
+    settings: &Settings,
+) -> crate::utils::error::Result<RenderedThumbnail> {
+    let user_override = crate::archive::cover_override(path);
+    let selection = settings.cover_selection(Some(path), user_override.as_deref());
+    let cover_key = CoverKey::for_file(path).map(|key| key.with_selection(selection));
+    if let Some(decoded) = cover_key.as_ref().and_then(cover_cache::lookup) {
+        crate::debug_log!("Step 2: Decoded cover cache hit");
+        return render_cover(decoded, cx, settings);
+    }
 
-        // Step 6b: Verify image format using magic headers
-        crate::utils::debug_log::debug_log("Step 6b: Verifying image format with magic headers...");
-        crate::archive::verify_image_data(&image_data, &entry.name)?;
-        crate::utils::debug_log::debug_log("Step 6b: Image format verification passed");
+    tracing::info!("Opening archive from file path: {:?}", path);
+    let archive = crate::archive::open_archive_with(path, settings)?;
+    crate::debug_log!("Step 3: Archive opened successfully");
+
+    let strategy = settings.cover_strategy_for(Some(path));
+    render_archive_cover(
+        archive.as_ref(),
+        user_override,
+        cover_key,
+        strategy,
+        cx,
+        settings,
+    )
+}
 
-        // Step 7: Use requested size from IThumbnailProvider::GetThumbnail
-        // IThumbnailProvider provides cx (max dimension), we create square thumbnails
-        let thumbnail_size = if cx == 0 { 256 } else { cx };
-        tracing::debug!(
-            "Creating thumbnail with size: {}x{}",
-            thumbnail_size,
-            thumbnail_size
-        );
-        crate::utils::debug_log::debug_log(&format!(
-            "Step 7: Creating thumbnail with size: {}x{}",
-            thumbnail_size, thumbnail_size
-        ));
+/// Extract the thumbnail of an archive read through `stream`
+///
+/// `path` is the file behind the stream when known, used only for the cover
+/// cache.
+pub(crate) fn extract_from_stream(
+    stream: IStream,
+    path: Option<&Path>,
+    cx: u32,
+    settings: &Settings,
+) -> crate::utils::error::Result<RenderedThumbnail> {
+    use crate::archive::{open_archive_from_stream_with, IStreamReader};
+
+    let selection = settings.cover_selection(path, None);
+    let cover_key = path
+        .and_then(CoverKey::for_file)
+        .map(|key| key.with_selection(selection));
+    if let Some(decoded) = cover_key.as_ref().and_then(cover_cache::lookup) {
+        crate::debug_log!("Step 2: Decoded cover cache hit");
+        return render_cover(decoded, cx, settings);
+    }
 
-        // Step 8: Create thumbnail HBITMAP
-        crate::utils::debug_log::debug_log("Step 8: Creating thumbnail HBITMAP...");
-        let hbitmap = match create_thumbnail_with_size(&image_data, thumbnail_size, thumbnail_size)
-        {
-            Ok(bmp) => {
-                tracing::info!("Thumbnail created successfully: {:?}", bmp);
-                crate::utils::debug_log::debug_log(&format!(
-                    "Step 8: Thumbnail created successfully - HBITMAP: {:?} (handle: 0x{:x})",
-                    bmp, bmp.0 as usize
-                ));
-                bmp
+    let reader = IStreamReader::new(stream);
+    tracing::debug!("IStreamReader created for direct streaming");
+    let archive = open_archive_from_stream_with(reader, settings)?;
+    crate::debug_log!("Step 3: Archive opened successfully");
+
+    let strategy = settings.cover_strategy_for(path);
+    render_archive_cover(archive.as_ref(), None, cover_key, strategy, cx, settings)
+}
+
+/// Whether the file behind a stream should be opened by its path
+///
+/// Opening a cloud placeholder's path downloads the whole file, which
+/// `IInitializeWithItem` avoided by handing over the item's stream; that
+/// stream fetches only the ranges the archive reader touches.
+fn opens_by_path(path: &Path, is_placeholder: impl FnOnce(&Path) -> bool) -> bool {
+    path.is_file() && !is_placeholder(path)
+}
+
+/// Whether a stream's recovered file is opened by path instead
+///
+/// Only RAR: unrar reads files, so its stream would first be copied to a temp
+/// file. The other backends read the stream directly. Like opening by path,
+/// the file's header decides over its extension (see `sniff_archive_type`).
+fn is_rar_archive_path(path: &Path) -> bool {
+    use crate::archive::{sniff_archive_type, ArchiveType};
+
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(ArchiveType::from_extension)
+        .map(|guess| sniff_archive_type(path, guess))
+        == Some(ArchiveType::Rar)
+}
+
+/// Convert a UTF-16 path from the shell, keeping unpaired surrogates
+///
+/// Windows paths are arbitrary UTF-16, so the lossless `OsString` conversion
+/// is used there; elsewhere (tests) unpaired surrogates become U+FFFD.
+fn path_from_wide(wide: &[u16]) -> PathBuf {
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStringExt;
+        PathBuf::from(std::ffi::OsString::from_wide(wide))
+    }
+    #[cfg(not(windows))]
+    {
+        PathBuf::from(String::from_utf16_lossy(wide))
+    }
+}
+
+/// Cover dimensions of one `CBXShell` instance's current source
+///
+/// Filled from the decode `GetThumbnail` already does, or by the first query
+/// that needs them before any thumbnail was rendered; either way the cover is
+/// extracted once per source.
+#[derive(Default)]
+struct DimensionCache(Mutex<Option<(u32, u32)>>);
+
+impl DimensionCache {
+    #[cfg(test)]
+    fn get(&self) -> Option<(u32, u32)> {
+        *self.0.lock().unwrap()
+    }
+
+    /// Cached dimensions, else those `probe` finds, which are kept
+    fn get_or_probe(&self, probe: impl FnOnce() -> Option<(u32, u32)>) -> Option<(u32, u32)> {
+        let mut cached = self.0.lock().unwrap();
+        if cached.is_none() {
+            *cached = probe();
+        }
+        *cached
+    }
+
+    fn set(&self, dimensions: (u32, u32)) {
+        *self.0.lock().unwrap() = Some(dimensions);
+    }
+
+    fn clear(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+/// Thumbnail pixels rendered for one request
+#[derive(Debug)]
+pub(crate) struct RenderedThumbnail {
+    pub pixels: RgbaImage,
+    pub alpha: AlphaKind,
+    /// Width and height of the decoded cover the pixels were scaled from
+    pub cover_size: (u32, u32),
+}
+
+/// Alpha semantics of rendered thumbnail pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AlphaKind {
+    /// Composited onto the background; reported as `WTSAT_RGB`
+    Opaque,
+    /// Transparency kept; reported as `WTSAT_ARGB`
+    Transparent,
+}
+
+impl AlphaKind {
+    fn for_config(config: &ThumbnailConfig) -> Self {
+        if config.preserve_alpha || config.book_effect {
+            AlphaKind::Transparent
+        } else {
+            AlphaKind::Opaque
+        }
+    }
+}
+
+/// Render the cover of an opened archive as thumbnail pixels
+///
+/// The shell-independent half of `GetThumbnail`: cover selection, decode and
+/// resize. Wrapping the pixels in whatever the shell asked for is done by the
+/// caller (`rgba_to_hbitmap_for_shell` today).
+///
+/// # Arguments
+/// * `archive` - Opened archive
+/// * `user_override` - Cover the user picked for this archive, if any
+/// * `cover_key` - Key to store the decoded cover under for other sizes
+/// * `strategy` - Cover selection strategy for the archive's extension
+/// * `cx` - Maximum thumbnail width/height requested by Explorer
+/// * `settings` - Configuration loaded for this request
+fn render_archive_cover(
+    archive: &dyn crate::archive::Archive,
+    user_override: Option<String>,
+    cover_key: Option<CoverKey>,
+    strategy: crate::archive::CoverStrategy,
+    cx: u32,
+    settings: &Settings,
+) -> crate::utils::error::Result<RenderedThumbnail> {
+    use crate::image_processor::thumbnail::decode_cover;
+    use crate::utils::error::CbxError;
+
+    // Step 4: Sort preference
+    let sort = settings.sort;
+    tracing::debug!("Sort preference: {}", sort);
+    crate::debug_log!("Step 4: Sort preference: {}", sort);
+
+    // Steps 5-6: Find and extract the cover image
+    let (entry, image_data) = select_cover(archive, user_override, strategy, settings)?;
+    tracing::info!("Found image: {} ({} bytes)", entry.name, entry.size);
+    crate::debug_log!("Step 5: Found image: {} ({} bytes)", entry.name, entry.size);
+    tracing::debug!("Extracted {} bytes of image data", image_data.len());
+    crate::debug_log!("Step 6: Extracted {} bytes of image data", image_data.len());
+
+    // Step 6b: Verify image format using magic headers
+    crate::debug_log!("Step 6b: Verifying image format with magic headers...");
+    crate::archive::verify_image_data(&image_data, &entry.name)?;
+    crate::debug_log!("Step 6b: Image format verification passed");
+
+    // Step 7: Decode the cover, keeping it for requests at other sizes
+    crate::debug_log!("Step 7: Decoding cover image...");
+    let decode = settings.decode_options();
+    let decoded = match decode_cover(&image_data, &decode) {
+        Ok(img) => Arc::new(img),
+        Err(e @ CbxError::CodecMissing(_)) if settings.next_image_on_missing_codec => {
+            tracing::warn!(
+                "Cover {} not decodable ({}), trying next image",
+                entry.name,
+                e
+            );
+            crate::debug_log!("Step 7: {} for {}, trying next image", e, entry.name);
+            let order = sort.then(|| settings.sort_order());
+            let (next, img) = decode_next_image(archive, &entry.name, order, &decode, e)?;
+            tracing::info!("Using {} instead of {}", next.name, entry.name);
+            Arc::new(img)
+        }
+        Err(e) => {
+            tracing::error!("Failed to decode cover: {}", e);
+            crate::debug_log!("ERROR: Image data size: {} bytes", image_data.len());
+            return Err(e);
+        }
+    };
+    if let Some(key) = cover_key {
+        cover_cache::store(key, Arc::clone(&decoded));
+    }
+
+    render_cover(decoded, cx, settings)
+}
+
+/// Find the cover of `archive` and extract its bytes (steps 5-6)
+///
+/// The user-selected override wins, then an embedded preview, then a cover
+/// named inside the archive, then `strategy`.
+fn select_cover(
+    archive: &dyn crate::archive::Archive,
+    user_override: Option<String>,
+    strategy: crate::archive::CoverStrategy,
+    settings: &Settings,
+) -> crate::utils::error::Result<(crate::archive::ArchiveEntry, Vec<u8>)> {
+    use crate::archive::CoverStrategy;
+
+    let sort = settings.sort;
+    // The preview and manifest lookups share one listing, skipped when the
+    // user picked the cover
+    let entries = match user_override {
+        None => archive.list_entries().unwrap_or_else(|e| {
+            tracing::warn!("Failed to list entries for cover lookups: {}", e);
+            Vec::new()
+        }),
+        Some(_) => Vec::new(),
+    };
+    let preview = archive.find_embedded_preview(&entries, &settings.embedded_preview_names);
+
+    crate::debug_log!("Step 5: Finding cover image (strategy: {:?})...", strategy);
+    let selected = match preview {
+        Some(preview) => preview,
+        None => {
+            let order = sort.then(|| settings.sort_order());
+            let override_name =
+                user_override.or_else(|| archive.manifest_cover_name(&entries, order));
+            if override_name.is_none() && strategy == CoverStrategy::FirstSorted {
+                // Fast path; VerifyFirstImage skips misnamed or corrupt images
+                archive.find_first_image_data(
+                    sort,
+                    settings.verify_first_image,
+                    &settings.cover_skip_stems,
+                )?
+            } else {
+                let entry =
+                    archive.find_cover_with_override(override_name.as_deref(), strategy, sort)?;
+                let image_data = archive.extract_entry(&entry)?;
+                (entry, image_data)
             }
+        }
+    };
+    Ok(selected)
+}
+
+/// Width and height of the cover of `archive`, read from the image header
+/// without decoding
+///
+/// `path` is the archive's file, if known. A cover decoded by an earlier
+/// thumbnail of the same file is answered from the cover cache without
+/// touching `archive`.
+fn probe_cover_dimensions(
+    path: Option<&Path>,
+    archive: &dyn crate::archive::Archive,
+    settings: &Settings,
+) -> Option<(u32, u32)> {
+    use crate::image_processor::decoder::probe_dimensions;
+
+    let user_override = path.and_then(crate::archive::cover_override);
+    let selection = settings.cover_selection(path, user_override.as_deref());
+    let cover_key = path
+        .and_then(CoverKey::for_file)
+        .map(|key| key.with_selection(selection));
+    if let Some(decoded) = cover_key.as_ref().and_then(cover_cache::lookup) {
+        return Some((decoded.width(), decoded.height()));
+    }
+
+    let strategy = settings.cover_strategy_for(path);
+    let (_, image_data) = select_cover(archive, user_override, strategy, settings).ok()?;
+    probe_dimensions(&image_data).ok()
+}
+
+/// Decode the first image other than `skipped` that succeeds
+///
+/// Fallback for covers needing a missing OS codec. Up to
+/// `MISSING_CODEC_FALLBACK_LIMIT` images are tried in reading order; when all
+/// fail, the last error (initially `error`, the cover's) is returned.
+/// Images are sorted with `order` when sorting is enabled.
+fn decode_next_image(
+    archive: &dyn crate::archive::Archive,
+    skipped: &str,
+    order: Option<crate::archive::SortOrder>,
+    decode: &crate::image_processor::decoder::DecodeOptions,
+    error: crate::utils::error::CbxError,
+) -> crate::utils::error::Result<(crate::archive::ArchiveEntry, DynamicImage)> {
+    use crate::archive::{verify_image_data, MISSING_CODEC_FALLBACK_LIMIT};
+    use crate::image_processor::thumbnail::decode_cover;
+
+    let mut images = archive.find_images()?;
+    if let Some(order) = order {
+        images.sort_by(|a, b| order.compare(&a.name, &b.name));
+    }
+
+    let mut last_error = error;
+    let candidates = images.into_iter().filter(|e| e.name != skipped);
+    for entry in candidates.take(MISSING_CODEC_FALLBACK_LIMIT) {
+        let decoded = archive.extract_entry(&entry).and_then(|data| {
+            verify_image_data(&data, &entry.name)?;
+            decode_cover(&data, decode)
+        });
+        match decoded {
+            Ok(img) => return Ok((entry, img)),
             Err(e) => {
-                tracing::error!("Failed to create thumbnail: {}", e);
-                crate::utils::debug_log::debug_log(&format!(
-                    "ERROR Step 8: Thumbnail creation failed: {}",
-                    e
-                ));
-                crate::utils::debug_log::debug_log(&format!(
-                    "ERROR: Image data size: {} bytes, requested size: {}x{}",
-                    image_data.len(),
-                    thumbnail_size,
-                    thumbnail_size
-                ));
-                return Err(e);
+                tracing::warn!("Fallback image {} failed: {}", entry.name, e);
+                last_error = e;
             }
-        };
+        }
+    }
 
-        crate::utils::debug_log::debug_log(
-            ">>>>> extract_thumbnail_internal COMPLETED SUCCESSFULLY <<<<<",
-        );
-        Ok(hbitmap)
+    Err(last_error)
+}
+
+/// Thumbnail size for Explorer's `cx`
+///
+/// IThumbnailProvider provides cx (max dimension), we create square thumbnails.
+/// ForceThumbnailSize overrides cx when set; MaxThumbnailDim only lowers it.
+fn requested_thumbnail_size(cx: u32, settings: &Settings) -> u32 {
+    use crate::archive::{cap_thumbnail_size, select_thumbnail_size};
+
+    cap_thumbnail_size(
+        select_thumbnail_size(cx, settings.force_thumbnail_size),
+        settings.max_thumbnail_dim,
+    )
+}
+
+/// Resize a decoded cover to the requested thumbnail size (steps 7b-8)
+fn render_cover(
+    decoded: Arc<DynamicImage>,
+    cx: u32,
+    settings: &Settings,
+) -> crate::utils::error::Result<RenderedThumbnail> {
+    use crate::image_processor::thumbnail::render_decoded;
+
+    // Step 7b: Use requested size from IThumbnailProvider::GetThumbnail
+    let thumbnail_size = requested_thumbnail_size(cx, settings);
+    tracing::debug!(
+        "Creating thumbnail with size: {}x{}",
+        thumbnail_size,
+        thumbnail_size
+    );
+    crate::debug_log!(
+        "Step 7b: Creating thumbnail with size: {}x{}",
+        thumbnail_size,
+        thumbnail_size
+    );
+
+    // Step 8: Render thumbnail pixels (the cached decode is copied, not moved)
+    crate::debug_log!("Step 8: Rendering thumbnail pixels...");
+    let config = ThumbnailConfig {
+        max_width: thumbnail_size,
+        max_height: thumbnail_size,
+        resize_filter: settings.resize_filter,
+        grayscale: settings.grayscale,
+        book_effect: settings.book_effect,
+        allow_upscale_to: settings.allow_upscale_to,
+        ..Default::default()
+    };
+    let cover_size = (decoded.width(), decoded.height());
+    let img = Arc::try_unwrap(decoded).unwrap_or_else(|shared| (*shared).clone());
+    match render_decoded(img, &config) {
+        Ok(rgba) => {
+            crate::debug_log!(
+                "Step 8: Thumbnail rendered: {}x{}",
+                rgba.width(),
+                rgba.height()
+            );
+            Ok(RenderedThumbnail {
+                pixels: rgba,
+                alpha: AlphaKind::for_config(&config),
+                cover_size,
+            })
+        }
+        Err(e) => {
+            tracing::error!("Failed to create thumbnail: {}", e);
+            crate::debug_log!("ERROR Step 8: Thumbnail creation failed: {}", e);
+            crate::debug_log!(
+                "ERROR: Requested size: {}x{}",
+                thumbnail_size,
+                thumbnail_size
+            );
+            Err(e)
+        }
+    }
+}
+
+/// Wrap rendered pixels as the HBITMAP and alpha type `GetThumbnail` returns
+///
+/// A WIC-based return for newer shells would be a sibling of this function.
+fn rgba_to_hbitmap_for_shell(
+    rgba: &RgbaImage,
+    alpha: AlphaKind,
+) -> crate::utils::error::Result<(HBITMAP, WTS_ALPHATYPE)> {
+    let hbitmap = crate::image_processor::thumbnail::rgba_to_hbitmap(rgba)?;
+    let alpha_type = match alpha {
+        AlphaKind::Opaque => WTSAT_RGB,
+        AlphaKind::Transparent => WTSAT_ARGB,
+    };
+    Ok((hbitmap, alpha_type))
+}
+
+/// Pick the source to extract from when both a path and a stream are set
+///
+/// `Initialize` always clears the other field, so both being present means
+/// stale state. The stream wins: it is what Explorer handed us most recently
+/// through the preferred interface, and a RAR path can still be recovered from
+/// it via `IStream::Stat`. The path is dropped so every branch sees one source.
+fn resolve_source<S>(
+    file_path: Option<PathBuf>,
+    stream: Option<S>,
+) -> (Option<PathBuf>, Option<S>) {
+    match (file_path, stream) {
+        (Some(path), Some(stream)) => {
+            tracing::warn!("Both stream and file path set; ignoring path {:?}", path);
+            crate::debug_log!(
+                "WARN: Both stream and file path are set, using stream and ignoring path {:?}",
+                path
+            );
+            (None, Some(stream))
+        }
+        source => source,
     }
 }
 
@@ -299,32 +827,33 @@ impl Drop for CBXShell {
 // IInitializeWithStream implementation (replaces IPersistFile)
 impl IInitializeWithStream_Impl for CBXShell {
     fn Initialize(&self, pstream: Option<&IStream>, _grfmode: u32) -> Result<()> {
-        crate::utils::debug_log::debug_log("===== IInitializeWithStream::Initialize CALLED =====");
+        crate::debug_log!("===== IInitializeWithStream::Initialize CALLED =====");
         tracing::info!("IInitializeWithStream::Initialize called");
+        self.clear_source();
 
         let stream = pstream
             .ok_or_else(|| {
-                crate::utils::debug_log::debug_log("ERROR: IStream pointer is null");
+                crate::debug_log!("ERROR: IStream pointer is null");
                 Error::from(E_POINTER)
             })?
             .clone();
 
-        crate::utils::debug_log::debug_log("IStream received and cloned successfully");
+        crate::debug_log!("IStream received and cloned successfully");
 
-        *self.stream.lock().unwrap() = Some(stream);
-        *self.file_path.lock().unwrap() = None;
+        self.set_source(Some(stream), None);
 
-        crate::utils::debug_log::debug_log("SUCCESS: IInitializeWithStream::Initialize completed");
+        crate::debug_log!("SUCCESS: IInitializeWithStream::Initialize completed");
         Ok(())
     }
 }
 
 impl IInitializeWithItem_Impl for CBXShell {
     fn Initialize(&self, psi: Option<&IShellItem>, _grfmode: u32) -> Result<()> {
-        crate::utils::debug_log::debug_log("===== IInitializeWithItem::Initialize CALLED =====");
+        crate::debug_log!("===== IInitializeWithItem::Initialize CALLED =====");
+        self.clear_source();
 
         let item = psi.ok_or_else(|| {
-            crate::utils::debug_log::debug_log("ERROR: IShellItem pointer is null");
+            crate::debug_log!("ERROR: IShellItem pointer is null");
             Error::from(E_POINTER)
         })?;
 
@@ -335,44 +864,92 @@ impl IInitializeWithItem_Impl for CBXShell {
         }
 
         if path_string.is_empty() {
-            crate::utils::debug_log::debug_log("ERROR: IInitializeWithItem path is empty");
+            crate::debug_log!("ERROR: IInitializeWithItem path is empty");
             return Err(Error::from(E_INVALIDARG));
         }
 
-        *self.file_path.lock().unwrap() = Some(PathBuf::from(path_string));
-        *self.stream.lock().unwrap() = None;
+        let path = PathBuf::from(path_string);
+
+        // Opening a cloud placeholder's path downloads the whole file; the
+        // item's stream fetches only the ranges the archive reader touches
+        if crate::utils::file::is_cloud_placeholder(&path) {
+            match unsafe { item.BindToHandler::<_, IStream>(None, &BHID_Stream) } {
+                Ok(stream) => {
+                    tracing::info!("{:?} is a cloud placeholder, reading it as a stream", path);
+                    crate::debug_log!("Cloud placeholder, using the item's stream");
+                    self.set_source(Some(stream), None);
+                    return Ok(());
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "{:?} is a cloud placeholder but has no stream ({}), opening the path",
+                        path,
+                        e
+                    );
+                }
+            }
+        }
+
+        self.set_source(None, Some(path));
 
-        crate::utils::debug_log::debug_log("SUCCESS: IInitializeWithItem::Initialize completed");
+        crate::debug_log!("SUCCESS: IInitializeWithItem::Initialize completed");
         Ok(())
     }
 }
 
 impl IInitializeWithFile_Impl for CBXShell {
     fn Initialize(&self, pszfilepath: &PCWSTR, _grfmode: u32) -> Result<()> {
-        crate::utils::debug_log::debug_log("===== IInitializeWithFile::Initialize CALLED =====");
+        crate::debug_log!("===== IInitializeWithFile::Initialize CALLED =====");
         tracing::info!("IInitializeWithFile::Initialize called");
+        self.clear_source();
 
         if pszfilepath.is_null() {
-            crate::utils::debug_log::debug_log("ERROR: File path pointer is null");
+            crate::debug_log!("ERROR: File path pointer is null");
             return Err(Error::from(E_INVALIDARG));
         }
 
-        let path_string = unsafe { pszfilepath.to_string()? };
-        if path_string.is_empty() {
-            crate::utils::debug_log::debug_log("ERROR: File path is empty");
+        // Paths may hold lone surrogates, which `to_string` rejects
+        let wide = unsafe { pszfilepath.as_wide() };
+        if wide.is_empty() {
+            crate::debug_log!("ERROR: File path is empty");
             return Err(Error::from(E_INVALIDARG));
         }
 
-        let path = PathBuf::from(path_string);
-        crate::utils::debug_log::debug_log(&format!(
-            "IInitializeWithFile received path: {:?}",
-            path
-        ));
+        let path = path_from_wide(wide);
+        crate::debug_log!("IInitializeWithFile received path: {:?}", path);
+
+        if !path.exists() {
+            // Let the shell resolve the raw UTF-16 itself
+            match unsafe {
+                SHCreateStreamOnFileEx(
+                    *pszfilepath,
+                    (STGM_READ | STGM_SHARE_DENY_NONE).0,
+                    0,
+                    FALSE,
+                    None,
+                )
+            } {
+                Ok(stream) => {
+                    tracing::info!("Path {:?} not found, opened as a stream", path);
+                    crate::debug_log!("Path not found, using stream opened by the shell");
+                    self.set_source(Some(stream), None);
+                    return Ok(());
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "File path {:?} not found and not openable ({}), raw UTF-16: {:04X?}",
+                        path,
+                        e,
+                        wide
+                    );
+                    crate::debug_log!("WARN: Path not openable, raw UTF-16: {:04X?}", wide);
+                }
+            }
+        }
 
-        *self.file_path.lock().unwrap() = Some(path);
-        *self.stream.lock().unwrap() = None;
+        self.set_source(None, Some(path));
 
-        crate::utils::debug_log::debug_log("SUCCESS: IInitializeWithFile::Initialize completed");
+        crate::debug_log!("SUCCESS: IInitializeWithFile::Initialize completed");
         Ok(())
     }
 }
@@ -386,30 +963,42 @@ impl IThumbnailProvider_Impl for CBXShell {
         pdwalpha: *mut WTS_ALPHATYPE,
     ) -> Result<()> {
         tracing::info!("IThumbnailProvider::GetThumbnail called (cx={})", cx);
-        crate::utils::debug_log::debug_log(&format!(
+        crate::debug_log!(
             "===== IThumbnailProvider::GetThumbnail CALLED (cx={}) =====",
             cx
-        ));
+        );
 
         if phbmp.is_null() {
             return Err(Error::from(E_POINTER));
         }
 
-        match self.extract_thumbnail_internal(cx) {
-            Ok(hbitmap) => {
+        // Read the configuration once for the whole request
+        let settings = Settings::load();
+        crate::image_processor::codecs::start_codec_warmup(settings.warm_codecs);
+        let result = self
+            .extract_thumbnail_internal(cx, &settings)
+            .and_then(|rendered| {
+                // Later per-file queries reuse the size of the decoded cover
+                self.cover_dimensions.set(rendered.cover_size);
+                rgba_to_hbitmap_for_shell(&rendered.pixels, rendered.alpha)
+            });
+
+        *self.thumbnail_hint.lock().unwrap() =
+            result.as_ref().err().and_then(|e| e.thumbnail_hint());
+        match result {
+            Ok((hbitmap, alpha_type)) => {
                 tracing::info!("GetThumbnail succeeded, returning HBITMAP: {:?}", hbitmap);
-                crate::utils::debug_log::debug_log(&format!(
+                crate::debug_log!(
                     "SUCCESS: GetThumbnail completed - HBITMAP: {:?} (handle: 0x{:x})",
-                    hbitmap, hbitmap.0 as usize
-                ));
+                    hbitmap,
+                    hbitmap.0 as usize
+                );
 
                 unsafe {
                     *phbmp = hbitmap;
                     if !pdwalpha.is_null() {
-                        *pdwalpha = WTSAT_RGB;
-                        crate::utils::debug_log::debug_log(
-                            "Alpha type set to WTSAT_RGB (no alpha channel)",
-                        );
+                        *pdwalpha = alpha_type;
+                        crate::debug_log!("Alpha type set to {:?}", alpha_type);
                     }
                 }
 
@@ -417,24 +1006,95 @@ impl IThumbnailProvider_Impl for CBXShell {
             }
             Err(e) => {
                 tracing::error!("GetThumbnail failed: {}", e);
-                crate::utils::debug_log::debug_log(&format!("ERROR: GetThumbnail failed - {}", e));
+                crate::debug_log!("ERROR: GetThumbnail failed - {}", e);
+                self.report_diagnostic(&format!("Thumbnail extraction failed: {}", e));
+                // Disabled thumbnails are the user's choice, not a failure;
+                // a recent failure was reported when it happened
+                if settings.event_log
+                    && !matches!(
+                        e,
+                        crate::utils::error::CbxError::Disabled
+                            | crate::utils::error::CbxError::RecentlyFailed { .. }
+                    )
+                {
+                    let path = self
+                        .get_file_path()
+                        .unwrap_or_else(|| PathBuf::from("<stream>"));
+                    crate::utils::event_log::report_failure(&path, &e);
+                }
                 let hresult: HRESULT = e.into();
-                crate::utils::debug_log::debug_log(&format!("Returning HRESULT: {:?}", hresult));
+                crate::debug_log!("Returning HRESULT: {:?}", hresult);
                 Err(Error::from(hresult))
             }
         }
     }
 }
 
+/// Longest archive comment shown in a tooltip
+const MAX_INFO_TIP_CHARS: usize = 1024;
+
+/// Build the tooltip from the archive comment, content summary ("comic, 24
+/// pages"), cover dimensions and the reason no thumbnail can be shown
+fn info_tip_text(
+    comment: Option<String>,
+    summary: Option<String>,
+    cover_dimensions: Option<(u32, u32)>,
+    thumbnail_hint: Option<String>,
+) -> Option<String> {
+    let mut lines = Vec::new();
+    if let Some(comment) = comment {
+        lines.push(comment.chars().take(MAX_INFO_TIP_CHARS).collect::<String>());
+    }
+    lines.extend(summary);
+    lines.extend(cover_dimensions.map(|(width, height)| format!("Cover: {} x {}", width, height)));
+    lines.extend(thumbnail_hint);
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// Hint for the failure the failure cache holds for the file at `path`
+fn recorded_thumbnail_hint(path: &Path) -> Option<String> {
+    let key = CoverKey::for_file(path)?;
+    crate::utils::failure_cache::lookup(&key)?.thumbnail_hint()
+}
+
 // IQueryInfo implementation
 impl IQueryInfo_Impl for CBXShell {
     fn GetInfoTip(&self, _dwflags: &QITIPF_FLAGS) -> Result<PWSTR> {
         tracing::info!("IQueryInfo::GetInfoTip called");
 
-        // TODO: Implement info tip generation
-        // For now, return E_FAIL as stub
-        tracing::warn!("GetInfoTip not yet implemented - returning E_FAIL");
-        Err(Error::from(E_FAIL))
+        // The archive comment (title/credits), what the archive holds and the
+        // cover's size make the tip
+        let settings = Settings::load();
+        let (path, opened) = self.open_for_info_tip(&settings);
+        let comment = opened
+            .as_ref()
+            .and_then(|opened| opened.archive.get_comment().ok().flatten());
+        let summary = opened.as_ref().map(|opened| opened.summary());
+        // Known once GetThumbnail decoded the cover; otherwise probed here
+        // and kept for later queries
+        let cover_dimensions = self.cover_dimensions.get_or_probe(|| {
+            let opened = opened.as_ref()?;
+            probe_cover_dimensions(path.as_deref(), opened.archive.as_ref(), &settings)
+        });
+        drop(opened);
+
+        // Tell the user why the thumbnail is missing when they can fix it.
+        // Nothing is extracted for this: the reason comes from this source's
+        // last GetThumbnail, else from a recent failure of the same file.
+        let thumbnail_hint = self
+            .thumbnail_hint
+            .lock()
+            .unwrap()
+            .clone()
+            .or_else(|| path.as_deref().and_then(recorded_thumbnail_hint));
+
+        match info_tip_text(comment, summary, cover_dimensions, thumbnail_hint) {
+            Some(tip) => unsafe { SHStrDupW(&HSTRING::from(tip)) },
+            None => {
+                tracing::debug!("No archive comment or cover available - returning E_FAIL");
+                Err(Error::from(E_FAIL))
+            }
+        }
     }
 
     fn GetInfoFlags(&self) -> Result<u32> {
@@ -443,16 +1103,48 @@ impl IQueryInfo_Impl for CBXShell {
     }
 }
 
+// IObjectWithSite implementation (host site for diagnostics)
+impl IObjectWithSite_Impl for CBXShell {
+    fn SetSite(&self, punksite: Option<&IUnknown>) -> Result<()> {
+        tracing::debug!(
+            "IObjectWithSite::SetSite called (site: {})",
+            punksite.is_some()
+        );
+        crate::debug_log!(
+            "IObjectWithSite::SetSite - site set: {}",
+            punksite.is_some()
+        );
+
+        // Passing None releases the site, breaking any reference cycle
+        *self.site.lock().unwrap() = punksite.cloned();
+        Ok(())
+    }
+
+    fn GetSite(&self, riid: *const GUID, ppvsite: *mut *mut core::ffi::c_void) -> Result<()> {
+        if riid.is_null() || ppvsite.is_null() {
+            return Err(Error::from(E_POINTER));
+        }
+        unsafe { *ppvsite = std::ptr::null_mut() };
+
+        match self.site.lock().unwrap().as_ref() {
+            Some(site) => unsafe { site.query(riid, ppvsite) }.ok(),
+            None => Err(Error::from(E_FAIL)),
+        }
+    }
+}
+
 #[cfg(all(test, windows, feature = "e2e-windows"))]
 mod tests {
     use super::*;
+    use crate::utils::com::ScopedCom;
     use std::io::Write as _;
     use windows::Win32::Foundation::BOOL;
-    use windows::Win32::Graphics::Gdi::DeleteObject;
-    use windows::Win32::System::Com::StructuredStorage::CreateStreamOnHGlobal;
-    use windows::Win32::System::Com::{
-        CoInitializeEx, CoUninitialize, IStream, COINIT_APARTMENTTHREADED, STREAM_SEEK_SET,
+    use windows::Win32::Graphics::Gdi::{
+        DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO,
+        BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
     };
+    use windows::Win32::System::Com::StructuredStorage::CreateStreamOnHGlobal;
+    use windows::Win32::System::Com::{IStream, STREAM_SEEK_SET};
     use zip::write::{FileOptions, ZipWriter};
 
     /// Minimal valid JPEG (1x1 red pixel)
@@ -470,15 +1162,94 @@ mod tests {
         0x01, 0x01, 0x00, 0x00, 0x3F, 0x00, 0x54, 0xDF, 0xFF, 0xD9,
     ];
 
+    /// What the COM pipeline tests need from the machine
+    ///
+    /// These tests run the real shell path (COM activation, GDI bitmaps), so
+    /// they need a desktop session: an interactive window station whose
+    /// screen DC can be opened. Building with `e2e-windows` asserts that one
+    /// is there, so a runner without it (service-hosted, SSH) fails instead
+    /// of passing without running anything.
+    struct Desktop {
+        _com: ScopedCom,
+    }
+
+    impl Desktop {
+        fn acquire() -> Self {
+            let com = ScopedCom::new();
+            let screen = unsafe { GetDC(HWND(0)) };
+            assert!(
+                !screen.is_invalid(),
+                "COM pipeline tests need a desktop session (e2e-windows is enabled)"
+            );
+            unsafe { ReleaseDC(HWND(0), screen) };
+            Self { _com: com }
+        }
+    }
+
+    /// Width, height and top-down BGRA pixels of a thumbnail bitmap
+    unsafe fn bitmap_pixels(hbitmap: HBITMAP) -> (i32, i32, Vec<u8>) {
+        let mut bitmap = BITMAP::default();
+        let written = GetObjectW(
+            hbitmap,
+            std::mem::size_of::<BITMAP>() as i32,
+            Some(&mut bitmap as *mut BITMAP as *mut _),
+        );
+        assert_ne!(written, 0, "GetObjectW failed");
+        let (width, height) = (bitmap.bmWidth, bitmap.bmHeight.abs());
+
+        let mut info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                biHeight: -height, // Top-down rows
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0 as u32,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let screen = GetDC(HWND(0));
+        let lines = GetDIBits(
+            screen,
+            hbitmap,
+            0,
+            height as u32,
+            Some(pixels.as_mut_ptr() as *mut _),
+            &mut info,
+            DIB_RGB_COLORS,
+        );
+        ReleaseDC(HWND(0), screen);
+        assert_eq!(lines, height, "GetDIBits failed");
+
+        (width, height, pixels)
+    }
+
+    /// PNG of a single colour
+    fn solid_png(width: u32, height: u32, rgb: [u8; 3]) -> Vec<u8> {
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(width, height, image::Rgba([rgb[0], rgb[1], rgb[2], 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
     /// Create a test CBZ archive in memory and return as IStream
     fn create_test_cbz_stream() -> Result<IStream> {
+        create_cbz_stream(&[("page001.jpg", MINIMAL_JPEG)])
+    }
+
+    /// Create a CBZ archive holding `files` in memory and return as IStream
+    fn create_cbz_stream(files: &[(&str, &[u8])]) -> Result<IStream> {
         // Create ZIP in memory
         let mut buffer = Vec::new();
         {
             let mut zip = ZipWriter::new(std::io::Cursor::new(&mut buffer));
-            zip.start_file("page001.jpg", FileOptions::default())
-                .unwrap();
-            zip.write_all(MINIMAL_JPEG).unwrap();
+            for (name, content) in files {
+                zip.start_file(*name, FileOptions::default()).unwrap();
+                zip.write_all(content).unwrap();
+            }
             zip.finish().unwrap();
         }
 
@@ -509,13 +1280,14 @@ mod tests {
     }
 
     #[test]
-    #[ignore = "requires Windows COM/GDI runtime"]
     fn test_extract_thumbnail_pipeline() {
         unsafe {
-            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+            let _desktop = Desktop::acquire();
 
-            // Create test CBZ stream
-            let stream = create_test_cbz_stream().expect("Failed to create test stream");
+            // Create test CBZ stream with a 64x32 red cover
+            let cover = solid_png(64, 32, [200, 30, 30]);
+            let stream = create_cbz_stream(&[("page001.png", &cover)])
+                .expect("Failed to create test stream");
 
             // Create CBXShell instance
             let thumbnail_provider = CBXShell::new().expect("Failed to create CBXShell");
@@ -544,16 +1316,21 @@ mod tests {
 
             assert_ne!(hbitmap.0, 0, "HBITMAP should not be null");
 
+            // Small covers keep their size; pixels are BGRA
+            let (width, height, pixels) = bitmap_pixels(hbitmap);
+            assert_eq!((width, height), (64, 32));
+            let center = ((16 * width + 32) * 4) as usize;
+            let (b, g, r) = (pixels[center], pixels[center + 1], pixels[center + 2]);
+            assert!(r > 180 && g < 60 && b < 60, "BGR {:?}", (b, g, r));
+
             DeleteObject(hbitmap).expect("Failed to delete HBITMAP");
-            CoUninitialize();
         }
     }
 
     #[test]
-    #[ignore = "requires Windows COM runtime"]
     fn test_extract_without_initialize_fails() {
         unsafe {
-            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+            let _desktop = Desktop::acquire();
 
             // Create CBXShell without initializing stream
             let thumbnail_provider = CBXShell::new().expect("Failed to create CBXShell");
@@ -570,16 +1347,13 @@ mod tests {
                 result.is_err(),
                 "GetThumbnail should fail without Initialize"
             );
-
-            CoUninitialize();
         }
     }
 
     #[test]
-    #[ignore = "requires Windows COM/GDI runtime"]
     fn test_thumbnail_size_parameter() {
         unsafe {
-            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+            let _desktop = Desktop::acquire();
 
             let stream = create_test_cbz_stream().expect("Failed to create test stream");
             let thumbnail_provider = CBXShell::new().expect("Failed to create CBXShell");
@@ -600,7 +1374,518 @@ mod tests {
             assert_ne!(hbitmap.0, 0, "HBITMAP should not be null");
 
             DeleteObject(hbitmap).ok();
-            CoUninitialize();
         }
     }
+
+    #[test]
+    fn test_reinitialize_uses_latest_source() {
+        unsafe {
+            let _desktop = Desktop::acquire();
+
+            let stream = create_test_cbz_stream().expect("Failed to create test stream");
+            let missing = std::env::temp_dir().join("cbxshell-missing-source-test.cbz");
+            let missing_path = HSTRING::from(missing.as_os_str());
+
+            let thumbnail_provider = CBXShell::new().expect("Failed to create CBXShell");
+            let init_stream: IInitializeWithStream = thumbnail_provider.cast().unwrap();
+            let init_file: IInitializeWithFile = thumbnail_provider.cast().unwrap();
+
+            let mut hbitmap = HBITMAP::default();
+            let mut alpha_type = WTS_ALPHATYPE::default();
+
+            // Stream then missing path: the valid stream must not be used
+            init_stream.Initialize(Some(&stream), STGM_READ.0).unwrap();
+            init_file
+                .Initialize(PCWSTR(missing_path.as_ptr()), STGM_READ.0)
+                .unwrap();
+            assert!(thumbnail_provider
+                .GetThumbnail(128, &mut hbitmap, &mut alpha_type)
+                .is_err());
+
+            // Missing path then stream: the stale path must not be used
+            stream.Seek(0, STREAM_SEEK_SET, None).unwrap();
+            init_stream.Initialize(Some(&stream), STGM_READ.0).unwrap();
+            thumbnail_provider
+                .GetThumbnail(128, &mut hbitmap, &mut alpha_type)
+                .expect("GetThumbnail should use the latest stream");
+            assert_ne!(hbitmap.0, 0, "HBITMAP should not be null");
+
+            DeleteObject(hbitmap).ok();
+        }
+    }
+
+    /// CBZ file on disk whose only page is a `width` x `height` PNG
+    fn temp_cbz_with_cover(width: u32, height: u32) -> tempfile::NamedTempFile {
+        let png = solid_png(width, height, [200, 40, 40]);
+        let file = tempfile::Builder::new()
+            .prefix("cbxshell_reinit_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let mut zip = ZipWriter::new(std::fs::File::create(file.path()).unwrap());
+        zip.start_file("page001.png", FileOptions::default())
+            .unwrap();
+        zip.write_all(&png).unwrap();
+        zip.finish().unwrap();
+        file
+    }
+
+    #[test]
+    fn test_reinitialize_with_another_file_resets_caches() {
+        unsafe {
+            let _desktop = Desktop::acquire();
+
+            let wide = temp_cbz_with_cover(40, 20);
+            let tall = temp_cbz_with_cover(20, 40);
+
+            let thumbnail_provider = CBXShell::new().expect("Failed to create CBXShell");
+            let init_file: IInitializeWithFile = thumbnail_provider.cast().unwrap();
+            let shell: &CBXShell = windows::core::AsImpl::as_impl(&thumbnail_provider);
+
+            for (file, landscape, cover_size) in [(&wide, true, (40, 20)), (&tall, false, (20, 40))]
+            {
+                let path = HSTRING::from(file.path().as_os_str());
+                init_file
+                    .Initialize(PCWSTR(path.as_ptr()), STGM_READ.0)
+                    .unwrap();
+                assert_eq!(shell.cover_dimensions.get(), None);
+
+                let mut hbitmap = HBITMAP::default();
+                let mut alpha_type = WTS_ALPHATYPE::default();
+                thumbnail_provider
+                    .GetThumbnail(256, &mut hbitmap, &mut alpha_type)
+                    .unwrap();
+                let (width, height, _) = bitmap_pixels(hbitmap);
+                assert_eq!(width > height, landscape, "{} x {}", width, height);
+                DeleteObject(hbitmap).ok();
+
+                // The thumbnail's decode fills the dimension cache for this file
+                assert_eq!(shell.cover_dimensions.get(), Some(cover_size));
+            }
+
+            // A failed re-Initialize leaves nothing of the previous file
+            let missing = HSTRING::new();
+            assert!(init_file
+                .Initialize(PCWSTR(missing.as_ptr()), STGM_READ.0)
+                .is_err());
+            let mut hbitmap = HBITMAP::default();
+            let mut alpha_type = WTS_ALPHATYPE::default();
+            assert!(thumbnail_provider
+                .GetThumbnail(256, &mut hbitmap, &mut alpha_type)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_initialize_with_lone_surrogate_path() {
+        unsafe {
+            let _desktop = Desktop::acquire();
+
+            // A missing file whose name holds an unpaired surrogate
+            let mut wide: Vec<u16> = std::env::temp_dir()
+                .join("cbxshell-surrogate-")
+                .as_os_str()
+                .to_string_lossy()
+                .encode_utf16()
+                .collect();
+            wide.push(0xDC00);
+            wide.extend(".cbz".encode_utf16().chain(Some(0)));
+
+            let thumbnail_provider = CBXShell::new().expect("Failed to create CBXShell");
+            let init_file: IInitializeWithFile = thumbnail_provider.cast().unwrap();
+
+            // Accepted rather than rejected as invalid UTF-16
+            init_file
+                .Initialize(PCWSTR(wide.as_ptr()), STGM_READ.0)
+                .expect("Initialize should accept a lone surrogate");
+
+            let mut hbitmap = HBITMAP::default();
+            let mut alpha_type = WTS_ALPHATYPE::default();
+            assert!(thumbnail_provider
+                .GetThumbnail(128, &mut hbitmap, &mut alpha_type)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_set_and_get_site() {
+        unsafe {
+            let _desktop = Desktop::acquire();
+
+            let thumbnail_provider = CBXShell::new().expect("Failed to create CBXShell");
+            let with_site: IObjectWithSite = thumbnail_provider
+                .cast()
+                .expect("Failed to cast to IObjectWithSite");
+
+            // No site until the host sets one
+            assert!(with_site.GetSite::<IUnknown>().is_err());
+
+            // Any COM object can act as the site
+            let site = create_test_cbz_stream().expect("Failed to create test stream");
+            with_site.SetSite(&site).expect("SetSite failed");
+            let returned: IStream = with_site.GetSite().expect("GetSite failed");
+            assert_eq!(returned, site);
+
+            with_site.SetSite(None).expect("Clearing the site failed");
+            assert!(with_site.GetSite::<IStream>().is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+    use crate::archive::config::MemoryConfigSource;
+    use crate::archive::{open_archive, CoverStrategy, SortOrder};
+    use crate::image_processor::decoder::DecodeOptions;
+    use crate::utils::error::CbxError;
+    use std::io::Write;
+    use zip::write::{FileOptions, ZipWriter};
+
+    /// Settings with every value missing, whatever the registry holds
+    fn default_settings() -> Settings {
+        Settings::load_from(&MemoryConfigSource::default())
+    }
+
+    /// Encode a solid-color PNG of the given size
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_pixel(width, height, image::Rgba([200, 40, 40, 255]));
+        let mut data = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut data),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+        data
+    }
+
+    /// Write a CBZ holding the given entries to a temp file
+    fn temp_cbz(files: &[(&str, &[u8])]) -> tempfile::NamedTempFile {
+        let file = tempfile::Builder::new()
+            .prefix("cbxshell_render_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+        let mut zip = ZipWriter::new(std::fs::File::create(file.path()).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+        file
+    }
+
+    #[test]
+    fn test_resolve_source_prefers_stream() {
+        let path = PathBuf::from("book.cbr");
+
+        assert_eq!(resolve_source(Some(path.clone()), Some(1)), (None, Some(1)));
+        assert_eq!(
+            resolve_source(Some(path.clone()), None::<u8>),
+            (Some(path), None)
+        );
+        assert_eq!(resolve_source(None, Some(1)), (None, Some(1)));
+        assert_eq!(resolve_source(None, None::<u8>), (None, None));
+    }
+
+    #[test]
+    fn test_path_from_wide_handles_lone_surrogate() {
+        // "C:\\a\u{D800}b.cbz" with an unpaired high surrogate
+        let mut wide: Vec<u16> = "C:\\a".encode_utf16().collect();
+        wide.push(0xD800);
+        wide.extend("b.cbz".encode_utf16());
+
+        let path = path_from_wide(&wide);
+        assert_eq!(path.extension().and_then(|e| e.to_str()), Some("cbz"));
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::ffi::OsStrExt;
+            let round_trip: Vec<u16> = path.as_os_str().encode_wide().collect();
+            assert_eq!(round_trip, wide);
+        }
+
+        let valid: Vec<u16> = "C:\\comics\\book.cbz".encode_utf16().collect();
+        assert_eq!(
+            path_from_wide(&valid),
+            PathBuf::from("C:\\comics\\book.cbz")
+        );
+    }
+
+    #[test]
+    fn test_opens_by_path_skips_cloud_placeholders() {
+        let file = temp_cbz(&[("01.png", &png(4, 4))]);
+        assert!(opens_by_path(file.path(), |_| false));
+        // Online-only: read through the stream instead of hydrating it
+        assert!(!opens_by_path(file.path(), |_| true));
+        assert!(!opens_by_path(
+            &file.path().with_extension("missing"),
+            |_| false
+        ));
+    }
+
+    #[test]
+    fn test_is_rar_archive_path() {
+        assert!(is_rar_archive_path(Path::new("C:\\comics\\book.cbr")));
+        assert!(is_rar_archive_path(Path::new("C:\\comics\\book.RAR")));
+        assert!(!is_rar_archive_path(Path::new("C:\\comics\\book.cbz")));
+        assert!(!is_rar_archive_path(Path::new("C:\\comics\\book.cb7")));
+        assert!(!is_rar_archive_path(Path::new("C:\\comics\\book")));
+
+        // The header wins over the extension
+        let dir = tempfile::tempdir().unwrap();
+        let rar = dir.path().join("mislabeled.cbz");
+        std::fs::write(&rar, b"Rar!\x1A\x07\x01\x00rest of archive").unwrap();
+        assert!(is_rar_archive_path(&rar));
+
+        let zip = dir.path().join("mislabeled.cbr");
+        std::fs::write(&zip, b"PK\x03\x04\x14\x00\x00\x00\x08\x00").unwrap();
+        assert!(!is_rar_archive_path(&zip));
+    }
+
+    #[test]
+    fn test_dimension_cache_is_filled_by_the_thumbnail_decode() {
+        let file = temp_cbz(&[("01.png", &png(300, 600)), ("02.png", &png(10, 10))]);
+        let archive = open_archive(file.path()).unwrap();
+        let cache = DimensionCache::default();
+        assert_eq!(cache.get(), None);
+
+        // One decode for the thumbnail answers every later query
+        let rendered = render_archive_cover(
+            archive.as_ref(),
+            None,
+            None,
+            CoverStrategy::default(),
+            32,
+            &default_settings(),
+        )
+        .unwrap();
+        cache.set(rendered.cover_size);
+        for _ in 0..3 {
+            assert_eq!(
+                cache.get_or_probe(|| panic!("probed despite the decode")),
+                Some((300, 600))
+            );
+        }
+
+        // A re-Initialize clears the cache
+        cache.clear();
+        assert_eq!(cache.get(), None);
+    }
+
+    #[test]
+    fn test_dimension_probe_extracts_once() {
+        let file = temp_cbz(&[("01.png", &png(300, 600)), ("02.png", &png(10, 10))]);
+        let archive = open_archive(file.path()).unwrap();
+        let cache = DimensionCache::default();
+        let mut probes = 0;
+
+        // The tooltip probes the cover; later queries reuse the result
+        for _ in 0..3 {
+            let dimensions = cache.get_or_probe(|| {
+                probes += 1;
+                probe_cover_dimensions(Some(file.path()), archive.as_ref(), &default_settings())
+            });
+            assert_eq!(dimensions, Some((300, 600)));
+        }
+        assert_eq!(probes, 1);
+    }
+
+    #[test]
+    fn test_info_tip_text() {
+        assert_eq!(info_tip_text(None, None, None, None), None);
+        assert_eq!(
+            info_tip_text(
+                Some("Vol. 1".to_string()),
+                Some("comic, 24 pages".to_string()),
+                None,
+                None
+            )
+            .as_deref(),
+            Some("Vol. 1\ncomic, 24 pages")
+        );
+        assert_eq!(
+            info_tip_text(
+                None,
+                Some("comic, 1 page".to_string()),
+                Some((300, 600)),
+                None
+            )
+            .as_deref(),
+            Some("comic, 1 page\nCover: 300 x 600")
+        );
+        assert_eq!(
+            info_tip_text(Some("x".repeat(MAX_INFO_TIP_CHARS + 10)), None, None, None)
+                .map(|tip| tip.len()),
+            Some(MAX_INFO_TIP_CHARS)
+        );
+    }
+
+    #[test]
+    fn test_render_archive_cover_fits_requested_size() {
+        let cover = png(300, 600);
+        let file = temp_cbz(&[("01.png", &cover), ("notes.txt", b"not an image")]);
+        let archive = open_archive(file.path()).unwrap();
+
+        let rendered = render_archive_cover(
+            archive.as_ref(),
+            None,
+            None,
+            CoverStrategy::default(),
+            96,
+            &default_settings(),
+        )
+        .unwrap();
+
+        assert_eq!(rendered.pixels.dimensions(), (48, 96));
+        assert_eq!(rendered.alpha, AlphaKind::Opaque);
+        assert_eq!(rendered.pixels.get_pixel(24, 48)[3], 255);
+        assert_eq!(rendered.cover_size, (300, 600));
+    }
+
+    #[test]
+    fn test_render_archive_cover_caches_decoded_cover() {
+        let cover = png(300, 600);
+        let file = temp_cbz(&[("01.png", &cover)]);
+        let archive = open_archive(file.path()).unwrap();
+        let key = CoverKey::for_file(file.path()).unwrap();
+        assert!(cover_cache::lookup(&key).is_none());
+
+        render_archive_cover(
+            archive.as_ref(),
+            None,
+            Some(key.clone()),
+            CoverStrategy::default(),
+            96,
+            &default_settings(),
+        )
+        .unwrap();
+
+        // A later request at another size renders from the cached decode
+        let decoded = cover_cache::lookup(&key).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (300, 600));
+        let rendered = render_cover(decoded, 200, &default_settings()).unwrap();
+        assert_eq!(rendered.pixels.dimensions(), (100, 200));
+    }
+
+    #[test]
+    fn test_missing_codec_falls_back_to_next_image() {
+        // ftyp box with the avif brand and no decodable payload
+        let mut avif = b"\x00\x00\x00\x18ftypavif\x00\x00\x00\x00avifmif1".to_vec();
+        avif.resize(512, 0);
+        let mut jpeg = Vec::new();
+        image::RgbImage::from_pixel(40, 60, image::Rgb([10, 200, 10]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let file = temp_cbz(&[("01.avif", &avif), ("02.jpg", &jpeg)]);
+        let archive = open_archive(file.path()).unwrap();
+
+        // Option off (default): the cover's missing codec fails the thumbnail
+        let err = render_archive_cover(
+            archive.as_ref(),
+            None,
+            None,
+            CoverStrategy::default(),
+            96,
+            &default_settings(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, CbxError::CodecMissing(_)));
+
+        // Option on: the next page becomes the thumbnail
+        let settings = Settings {
+            next_image_on_missing_codec: true,
+            ..default_settings()
+        };
+        let rendered = render_archive_cover(
+            archive.as_ref(),
+            None,
+            None,
+            CoverStrategy::default(),
+            96,
+            &settings,
+        )
+        .unwrap();
+        assert_eq!(rendered.pixels.dimensions(), (40, 60));
+        assert_eq!(rendered.cover_size, (40, 60));
+
+        let (entry, img) = decode_next_image(
+            archive.as_ref(),
+            "01.avif",
+            Some(SortOrder::default()),
+            &DecodeOptions::default(),
+            err,
+        )
+        .unwrap();
+        assert_eq!(entry.name, "02.jpg");
+        assert_eq!((img.width(), img.height()), (40, 60));
+
+        // Nothing else decodable: the original error comes back
+        let err = CbxError::CodecMissing("AVIF".to_string());
+        let file = temp_cbz(&[("01.avif", &avif), ("notes.txt", b"text")]);
+        let archive = open_archive(file.path()).unwrap();
+        let err = decode_next_image(
+            archive.as_ref(),
+            "01.avif",
+            Some(SortOrder::default()),
+            &DecodeOptions::default(),
+            err,
+        )
+        .unwrap_err();
+        assert!(matches!(err, CbxError::CodecMissing(_)));
+    }
+
+    #[test]
+    fn test_info_tip_explains_missing_codec() {
+        let mut avif = b"\x00\x00\x00\x18ftypavif\x00\x00\x00\x00avifmif1".to_vec();
+        avif.resize(512, 0);
+        let file = temp_cbz(&[("01.avif", &avif)]);
+        assert_eq!(recorded_thumbnail_hint(file.path()), None);
+
+        // The failed thumbnail is remembered; the tooltip reads it back
+        let err = extract_from_path(file.path(), 96, &default_settings()).unwrap_err();
+        crate::utils::failure_cache::record(CoverKey::for_file(file.path()).unwrap(), &err);
+        let hint = recorded_thumbnail_hint(file.path());
+        let tip = info_tip_text(Some("Vol. 1".to_string()), None, None, hint).unwrap();
+        assert!(tip.starts_with("Vol. 1\n"));
+        assert!(
+            tip.contains("Thumbnail unavailable: AVIF codec not installed"),
+            "{}",
+            tip
+        );
+    }
+
+    #[test]
+    fn test_render_archive_cover_without_images_fails() {
+        let file = temp_cbz(&[("notes.txt", b"not an image")]);
+        let archive = open_archive(file.path()).unwrap();
+
+        assert!(render_archive_cover(
+            archive.as_ref(),
+            None,
+            None,
+            CoverStrategy::default(),
+            96,
+            &default_settings(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_alpha_kind_follows_config() {
+        let mut config = ThumbnailConfig::default();
+        assert_eq!(AlphaKind::for_config(&config), AlphaKind::Opaque);
+
+        config.preserve_alpha = true;
+        assert_eq!(AlphaKind::for_config(&config), AlphaKind::Transparent);
+        // The book effect's shadow margin is transparent
+        let config = ThumbnailConfig {
+            book_effect: true,
+            ..Default::default()
+        };
+        assert_eq!(AlphaKind::for_config(&config), AlphaKind::Transparent);
+    }
 }
@@ -2,22 +2,30 @@ use std::sync::atomic::AtomicU32;
 ///! COM Class Factory implementation
 use windows::{core::*, Win32::Foundation::*, Win32::System::Com::*};
 
-use super::CBXShell;
+use super::{CBXShell, SetCoverCommand, CLSID_SET_COVER_COMMAND};
 
 /// ClassFactory for creating CBXShell instances
 #[implement(IClassFactory)]
 pub struct ClassFactory {
     #[allow(dead_code)] // Used by COM infrastructure through #[implement] macro
     ref_count: AtomicU32,
+    /// CLSID of the object this factory creates
+    clsid: GUID,
 }
 
 impl ClassFactory {
     /// Create a new class factory
     pub fn new() -> Result<IClassFactory> {
-        tracing::debug!("Creating ClassFactory");
+        Self::for_clsid(super::CLSID_CBXSHELL)
+    }
+
+    /// Create a class factory for one of the CLSIDs served by this DLL
+    pub fn for_clsid(clsid: GUID) -> Result<IClassFactory> {
+        tracing::debug!("Creating ClassFactory for {:?}", clsid);
 
         let factory = ClassFactory {
             ref_count: AtomicU32::new(1),
+            clsid,
         };
 
         crate::add_dll_ref();
@@ -34,49 +42,50 @@ impl IClassFactory_Impl for ClassFactory {
         ppv: *mut *mut std::ffi::c_void,
     ) -> Result<()> {
         tracing::debug!("ClassFactory::CreateInstance called");
-        crate::utils::debug_log::debug_log("===== ClassFactory::CreateInstance CALLED =====");
-        crate::utils::debug_log::debug_log(&format!("IID requested: {:?}", unsafe { *riid }));
+        crate::debug_log!("===== ClassFactory::CreateInstance CALLED =====");
+        crate::debug_log!("IID requested: {:?}", unsafe { *riid });
 
         // Aggregation not supported
         if punkouter.is_some() {
             tracing::warn!("Aggregation not supported");
-            crate::utils::debug_log::debug_log("ERROR: Aggregation not supported");
+            crate::debug_log!("ERROR: Aggregation not supported");
             return Err(CLASS_E_NOAGGREGATION.into());
         }
 
         unsafe {
-            crate::utils::debug_log::debug_log("Creating CBXShell instance...");
-            let cbxshell = CBXShell::new()?;
-            crate::utils::debug_log::debug_log("COM handler instance created");
+            crate::debug_log!("Creating COM handler instance...");
+            let instance: IUnknown = if self.clsid == CLSID_SET_COVER_COMMAND {
+                SetCoverCommand::new()?.cast()?
+            } else {
+                CBXShell::new()?.cast()?
+            };
+            crate::debug_log!("COM handler instance created");
 
             // Cast to IUnknown and query for requested interface
-            match cbxshell.cast::<IUnknown>() {
+            match instance.cast::<IUnknown>() {
                 Ok(iunknown) => {
-                    crate::utils::debug_log::debug_log("CBXShell cast to IUnknown succeeded");
+                    crate::debug_log!("CBXShell cast to IUnknown succeeded");
 
                     match iunknown.query(riid, ppv as *mut _) {
                         S_OK => {
                             tracing::debug!("CBXShell instance created successfully");
-                            crate::utils::debug_log::debug_log(
-                                "SUCCESS: QueryInterface succeeded - CBXShell instance returned",
+                            crate::debug_log!(
+                                "SUCCESS: QueryInterface succeeded - CBXShell instance returned"
                             );
                             Ok(())
                         }
                         hr => {
-                            crate::utils::debug_log::debug_log(&format!(
+                            crate::debug_log!(
                                 "ERROR: QueryInterface failed with HRESULT: {:?}",
                                 hr
-                            ));
+                            );
                             Err(Error::from(hr))
                         }
                     }
                 }
                 Err(e) => {
                     tracing::error!("Failed to cast CBXShell to IUnknown: {:?}", e);
-                    crate::utils::debug_log::debug_log(&format!(
-                        "ERROR: Cast to IUnknown failed: {:?}",
-                        e
-                    ));
+                    crate::debug_log!("ERROR: Cast to IUnknown failed: {:?}", e);
                     Err(Error::from(E_NOINTERFACE))
                 }
             }
@@ -0,0 +1,205 @@
+///! "Set as cover" context menu command (IExplorerCommand)
+///!
+///! Shown for pages inside an archive that Explorer has expanded (e.g. a CBZ
+///! opened as a compressed folder). Invoking it stores the page as the
+///! archive's cover override, which the thumbnail provider then prefers.
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU32;
+use windows::{core::*, Win32::Foundation::*, Win32::System::Com::*, Win32::UI::Shell::*};
+
+use super::CLSID_SET_COVER_COMMAND;
+use crate::archive::{set_cover_override, ArchiveType};
+
+const COMMAND_TITLE: &str = "Set as cover";
+
+/// IExplorerCommand handler for "Set as cover"
+#[implement(IExplorerCommand)]
+pub struct SetCoverCommand {
+    #[allow(dead_code)] // Used by COM infrastructure through #[implement] macro
+    ref_count: AtomicU32,
+}
+
+impl SetCoverCommand {
+    /// Create a new command instance
+    pub fn new() -> Result<IExplorerCommand> {
+        tracing::debug!("Creating SetCoverCommand instance");
+
+        let command = SetCoverCommand {
+            ref_count: AtomicU32::new(1),
+        };
+
+        crate::add_dll_ref();
+        Ok(command.into())
+    }
+
+    /// Resolve the selected page to (archive path, entry name)
+    ///
+    /// Only single selections are supported; multi-select hides the command.
+    fn selected_page(items: Option<&IShellItemArray>) -> Option<(PathBuf, String)> {
+        let items = items?;
+
+        // UNAVOIDABLE UNSAFE: IShellItemArray/IShellItem are COM interfaces
+        // Safety guarantees:
+        // - items is a valid interface reference provided by Explorer
+        // - The display name is freed exactly once with CoTaskMemFree
+        unsafe {
+            if items.GetCount().ok()? != 1 {
+                return None;
+            }
+
+            let item = items.GetItemAt(0).ok()?;
+            let display_name = item.GetDisplayName(SIGDN_DESKTOPABSOLUTEPARSING).ok()?;
+            let path_string = display_name.to_string();
+            CoTaskMemFree(Some(display_name.0 as _));
+
+            split_archive_entry_path(Path::new(&path_string.ok()?))
+        }
+    }
+}
+
+/// Split a parsing path that points inside an archive into its parts
+///
+/// Expanded archives expose pages as `C:\Comics\book.cbz\pages\001.jpg`. The
+/// nearest ancestor that is an existing file with an archive extension is the
+/// archive; the remainder (joined with `/`) is the entry name.
+pub fn split_archive_entry_path(path: &Path) -> Option<(PathBuf, String)> {
+    let archive_path = path.ancestors().skip(1).find(|ancestor| {
+        ancestor
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(ArchiveType::from_extension)
+            .is_some()
+            && ancestor.is_file()
+    })?;
+
+    let entry_name = path
+        .strip_prefix(archive_path)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if entry_name.is_empty() {
+        return None;
+    }
+
+    Some((archive_path.to_path_buf(), entry_name))
+}
+
+impl IExplorerCommand_Impl for SetCoverCommand {
+    fn GetTitle(&self, _psiitemarray: Option<&IShellItemArray>) -> Result<PWSTR> {
+        let title = HSTRING::from(COMMAND_TITLE);
+        // UNAVOIDABLE UNSAFE: SHStrDupW allocates the CoTaskMem string Explorer frees
+        unsafe { SHStrDupW(&title) }
+    }
+
+    fn GetIcon(&self, _psiitemarray: Option<&IShellItemArray>) -> Result<PWSTR> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn GetToolTip(&self, _psiitemarray: Option<&IShellItemArray>) -> Result<PWSTR> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn GetCanonicalName(&self) -> Result<GUID> {
+        Ok(CLSID_SET_COVER_COMMAND)
+    }
+
+    fn GetState(&self, psiitemarray: Option<&IShellItemArray>, _foktobeslow: BOOL) -> Result<u32> {
+        // Only offer the command for a single page inside an archive
+        let state = match Self::selected_page(psiitemarray) {
+            Some(_) => ECS_ENABLED,
+            None => ECS_HIDDEN,
+        };
+        Ok(state.0 as u32)
+    }
+
+    fn Invoke(
+        &self,
+        psiitemarray: Option<&IShellItemArray>,
+        _pbc: Option<&IBindCtx>,
+    ) -> Result<()> {
+        crate::debug_log!("===== SetCoverCommand::Invoke CALLED =====");
+
+        let (archive_path, entry_name) =
+            Self::selected_page(psiitemarray).ok_or_else(|| Error::from(E_INVALIDARG))?;
+
+        tracing::info!("Setting cover of {:?} to {}", archive_path, entry_name);
+        set_cover_override(&archive_path, &entry_name).map_err(|e| {
+            tracing::error!("Failed to store cover override: {}", e);
+            Error::from(E_FAIL)
+        })?;
+        crate::image_processor::cover_cache::forget(&archive_path);
+
+        // Ask Explorer to refresh the archive's thumbnail
+        let path_wide: Vec<u16> = archive_path
+            .to_string_lossy()
+            .encode_utf16()
+            .chain(Some(0))
+            .collect();
+        // UNAVOIDABLE UNSAFE: SHChangeNotify is a Windows FFI call
+        // Safety guarantees:
+        // - path_wide is null-terminated and outlives the call
+        unsafe {
+            SHChangeNotify(
+                SHCNE_UPDATEITEM,
+                SHCNF_PATHW,
+                Some(path_wide.as_ptr() as _),
+                None,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn GetFlags(&self) -> Result<u32> {
+        Ok(ECF_DEFAULT.0 as u32)
+    }
+
+    fn EnumSubCommands(&self) -> Result<IEnumExplorerCommand> {
+        Err(E_NOTIMPL.into())
+    }
+}
+
+impl Drop for SetCoverCommand {
+    fn drop(&mut self) {
+        crate::release_dll_ref();
+        tracing::debug!("SetCoverCommand dropped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_archive_entry_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("book.cbz");
+        std::fs::write(&archive, b"PK").unwrap();
+
+        let (archive_path, entry) =
+            split_archive_entry_path(&archive.join("pages").join("002.jpg")).unwrap();
+        assert_eq!(archive_path, archive);
+        assert_eq!(entry, "pages/002.jpg");
+    }
+
+    #[test]
+    fn test_split_archive_entry_path_rejects_plain_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("book.cbz");
+        std::fs::write(&archive, b"PK").unwrap();
+
+        // The archive itself has no page to select
+        assert!(split_archive_entry_path(&archive).is_none());
+
+        // Regular image outside any archive
+        assert!(split_archive_entry_path(&dir.path().join("cover.jpg")).is_none());
+
+        // A folder merely named like an archive is not an archive
+        let folder = dir.path().join("scans.zip");
+        std::fs::create_dir(&folder).unwrap();
+        assert!(split_archive_entry_path(&folder.join("001.jpg")).is_none());
+    }
+}
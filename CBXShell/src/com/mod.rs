@@ -1,13 +1,19 @@
 mod cbxshell;
 ///! COM implementation for CBXShell Windows Shell Extension
 mod class_factory;
+mod explorer_command;
 mod extract_image;
 mod persist_file;
 mod query_info;
 
 pub use cbxshell::CBXShell;
+pub(crate) use cbxshell::{extract_from_path, extract_from_stream, RenderedThumbnail};
 pub use class_factory::ClassFactory;
+pub use explorer_command::SetCoverCommand;
 
 use windows::core::GUID;
 
 pub const CLSID_CBXSHELL: GUID = GUID::from_u128(0x9E6ECB90_5A61_42BD_B851_D3297D9C7F39);
+
+/// CLSID of the "Set as cover" IExplorerCommand handler
+pub const CLSID_SET_COVER_COMMAND: GUID = GUID::from_u128(0x4F0C2A7E_8D3B_4C61_9A52_6E1B7D03C5A4);
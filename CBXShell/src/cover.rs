@@ -0,0 +1,228 @@
+//! Cover extraction without the COM layer
+//!
+//! Runs the same archive → image → thumbnail pipeline as the shell extension,
+//! but returns RGBA pixels (or PNG bytes) instead of an HBITMAP. Used by
+//! CBXManager and other hosts that need a cover outside Explorer.
+
+use std::path::Path;
+
+use image::RgbaImage;
+
+use crate::archive::{
+    cover_override, open_archive_with, verify_image_data, write_zip_entry, ArchiveType,
+    CoverStrategy, EMBEDDED_PREVIEW_FILE_NAME,
+};
+use crate::image_processor::thumbnail::{render_thumbnail, ThumbnailConfig};
+use crate::settings::Settings;
+use crate::utils::error::{CbxError, Result};
+
+/// Extract the cover of an archive as an RGBA thumbnail
+///
+/// Honours the same registry settings as the shell extension (sort order,
+/// cover strategy and per-archive cover overrides), and covers named inside
+/// the archive by ComicInfo.xml or a `cbxcover` manifest. Embedded previews
+/// (`EmbeddedPreview`) are used ahead of the pages themselves. The result fits
+/// within `max_dim` x `max_dim`, preserves the aspect ratio and is never
+/// upscaled.
+///
+/// # Arguments
+/// * `path` - Path to a supported archive (.cbz, .cbr, .cb7, ...)
+/// * `max_dim` - Maximum thumbnail width/height in pixels
+///
+/// # Returns
+/// * `Ok(RgbaImage)` - Opaque cover thumbnail
+/// * `Err(CbxError)` - Failed to open the archive or decode the cover
+pub fn extract_cover(path: &Path, max_dim: u32) -> Result<RgbaImage> {
+    tracing::debug!("Extracting cover from {:?} (max {}px)", path, max_dim);
+
+    let settings = Settings::load();
+    let archive = open_archive_with(path, &settings)?;
+    let sort = settings.sort;
+    let user_override = cover_override(path);
+    let entries = match user_override {
+        None => archive.list_entries()?,
+        Some(_) => Vec::new(),
+    };
+    let strategy = settings.cover_strategy_for(Some(path));
+
+    let preview = archive.find_embedded_preview(&entries, &settings.embedded_preview_names);
+    let (entry, image_data) = match preview {
+        Some(preview) => preview,
+        None => {
+            let override_name = user_override.or_else(|| {
+                archive.manifest_cover_name(&entries, sort.then(|| settings.sort_order()))
+            });
+            if override_name.is_none() && strategy == CoverStrategy::FirstSorted {
+                archive.find_first_image_data(
+                    sort,
+                    settings.verify_first_image,
+                    &settings.cover_skip_stems,
+                )?
+            } else {
+                let entry =
+                    archive.find_cover_with_override(override_name.as_deref(), strategy, sort)?;
+                let image_data = archive.extract_entry(&entry)?;
+                (entry, image_data)
+            }
+        }
+    };
+    verify_image_data(&image_data, &entry.name)?;
+
+    let config = ThumbnailConfig {
+        max_width: max_dim,
+        max_height: max_dim,
+        resize_filter: settings.resize_filter,
+        grayscale: settings.grayscale,
+        decode: settings.decode_options(),
+        ..Default::default()
+    };
+    render_thumbnail(&image_data, &config)
+}
+
+/// Extract the cover of an archive as a PNG-encoded thumbnail
+///
+/// `extract_cover` followed by PNG encoding, for hosts that serve or store
+/// image files (e.g. a web library backend) rather than handle pixels.
+///
+/// # Arguments
+/// * `path` - Path to a supported archive (.cbz, .cbr, .cb7, ...)
+/// * `max_dim` - Maximum thumbnail width/height in pixels
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - PNG file bytes of the cover thumbnail
+/// * `Err(CbxError)` - Failed to open the archive, decode or encode the cover
+pub fn cover_png(path: &Path, max_dim: u32) -> Result<Vec<u8>> {
+    let cover = extract_cover(path, max_dim)?;
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    cover
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|e| CbxError::Image(format!("Failed to encode PNG: {}", e)))?;
+    Ok(png.into_inner())
+}
+
+/// JPEG quality of embedded previews
+const EMBEDDED_PREVIEW_QUALITY: u8 = 90;
+
+/// Store the cover of a ZIP/CBZ archive inside it as an embedded preview
+///
+/// The cover is rendered like `extract_cover`, encoded as JPEG and written to
+/// the archive as `__cover.jpg` (replacing an earlier one), where the
+/// `EmbeddedPreview` lookup finds it without running cover selection. RAR and
+/// 7z archives can't be written and are refused.
+///
+/// # Arguments
+/// * `path` - Path to a writable .zip or .cbz archive
+/// * `max_dim` - Maximum preview width/height in pixels
+///
+/// # Returns
+/// * `Ok(())` - Preview written
+/// * `Err(CbxError::UnsupportedFormat)` - Not a ZIP/CBZ archive
+/// * `Err(CbxError)` - Failed to render the cover or write the archive
+pub fn embed_preview(path: &Path, max_dim: u32) -> Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .ok_or(CbxError::InvalidPath)?;
+    // EPUB/PHZ are ZIPs too, but their layout belongs to other readers
+    let writable = matches!(extension.to_lowercase().as_str(), "zip" | "cbz");
+    if !writable || path.is_dir() {
+        return Err(CbxError::UnsupportedFormat(format!(
+            "cannot embed a preview in {} files",
+            ArchiveType::from_extension(extension)
+                .map(|t| t.as_str())
+                .unwrap_or(extension)
+        )));
+    }
+
+    let cover = extract_cover(path, max_dim)?;
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, EMBEDDED_PREVIEW_QUALITY)
+        .encode_image(&image::DynamicImage::ImageRgba8(cover).to_rgb8())
+        .map_err(|e| CbxError::Image(format!("Failed to encode JPEG: {}", e)))?;
+
+    tracing::info!("Embedding {} byte preview into {:?}", jpeg.len(), path);
+    write_zip_entry(path, EMBEDDED_PREVIEW_FILE_NAME, &jpeg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::open_archive;
+    use std::io::Write;
+    use tempfile::Builder;
+    use zip::write::{FileOptions, ZipWriter};
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_pixel(width, height, image::Rgba([255, 0, 0, 255]));
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut buffer, image::ImageFormat::Png).unwrap();
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn test_extract_cover_from_cbz() {
+        let temp_file = Builder::new()
+            .prefix("test_cover_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+
+        {
+            let mut zip = ZipWriter::new(std::fs::File::create(temp_file.path()).unwrap());
+            zip.start_file("page1.png", FileOptions::default()).unwrap();
+            zip.write_all(&png_bytes(4, 2)).unwrap();
+            zip.finish().unwrap();
+        }
+
+        let cover = extract_cover(temp_file.path(), 256).unwrap();
+        assert_eq!(cover.dimensions(), (4, 2));
+    }
+
+    #[test]
+    fn test_embed_preview_round_trip() {
+        let temp_file = Builder::new()
+            .prefix("test_embed_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+
+        {
+            let mut zip = ZipWriter::new(std::fs::File::create(temp_file.path()).unwrap());
+            zip.start_file("page1.png", FileOptions::default()).unwrap();
+            zip.write_all(&png_bytes(40, 60)).unwrap();
+            zip.finish().unwrap();
+        }
+
+        embed_preview(temp_file.path(), 20).unwrap();
+
+        let archive = open_archive(temp_file.path()).unwrap();
+        let (entry, data) = archive
+            .find_embedded_preview(
+                &archive.list_entries().unwrap(),
+                &[EMBEDDED_PREVIEW_FILE_NAME.to_string()],
+            )
+            .unwrap();
+        assert_eq!(entry.name, EMBEDDED_PREVIEW_FILE_NAME);
+        let preview = image::load_from_memory(&data).unwrap();
+        assert_eq!((preview.width(), preview.height()), (13, 20));
+    }
+
+    #[test]
+    fn test_embed_preview_refuses_rar_and_7z() {
+        for name in ["book.cbr", "book.7z", "book.epub"] {
+            let result = embed_preview(Path::new(name), 256);
+            assert!(
+                matches!(result, Err(CbxError::UnsupportedFormat(_))),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_cover_missing_file() {
+        let result = extract_cover(Path::new("does_not_exist.cbz"), 256);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! WIC codec warm-up and availability diagnostics
+//!
+//! OS codecs (HEIF/AVIF, WebP) load lazily, so the first thumbnail that needs
+//! one pays for loading the codec DLLs. With the `WarmCodecs` registry flag
+//! set, the first thumbnail request spawns a short-lived thread that creates
+//! each decoder once and logs which ones are installed.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use windows::core::GUID;
+use windows::Win32::Graphics::Imaging::{GUID_ContainerFormatHeif, GUID_ContainerFormatWebp};
+
+/// Modern codecs worth warming: (display name, WIC container format)
+///
+/// AVIF files are read by the HEIF container decoder (with the AV1 extension).
+const MODERN_CODECS: &[(&str, GUID)] = &[
+    ("HEIF/AVIF", GUID_ContainerFormatHeif),
+    ("WebP", GUID_ContainerFormatWebp),
+];
+
+/// Set once the warm-up thread was spawned; it runs at most once per load
+static WARMUP_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Set on DLL unload so a warm-up that hasn't finished stops early
+static WARMUP_STOP: AtomicBool = AtomicBool::new(false);
+
+/// Availability of one OS codec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodecAvailability {
+    pub name: &'static str,
+    pub installed: bool,
+}
+
+/// Probe each modern codec with `has_decoder`
+///
+/// `has_decoder` creates (and thereby loads) the decoder for a container
+/// format; split out so the probe can be tested without WIC.
+pub fn probe_codecs<F>(mut has_decoder: F) -> Vec<CodecAvailability>
+where
+    F: FnMut(&GUID) -> bool,
+{
+    MODERN_CODECS
+        .iter()
+        .map(|(name, container)| CodecAvailability {
+            name,
+            installed: has_decoder(container),
+        })
+        .collect()
+}
+
+/// One-line summary for the log, e.g. `HEIF/AVIF: missing, WebP: installed`
+pub fn describe_codecs(codecs: &[CodecAvailability]) -> String {
+    codecs
+        .iter()
+        .map(|codec| {
+            let state = if codec.installed {
+                "installed"
+            } else {
+                "missing"
+            };
+            format!("{}: {}", codec.name, state)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Spawn the codec warm-up thread on the first request with `WarmCodecs` set
+///
+/// Called per thumbnail request with the flag from its `Settings`, so the
+/// check costs no registry read and no thread is created when the flag is
+/// off; after the first spawn it returns at once.
+///
+/// The thread holds a DLL reference so `DllCanUnloadNow` keeps the module
+/// loaded until it finishes.
+pub fn start_codec_warmup(warm_codecs: bool) {
+    if !warm_codecs || WARMUP_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    crate::add_dll_ref();
+    let spawned = std::thread::Builder::new()
+        .name("cbxshell-codec-warmup".to_string())
+        .spawn(|| {
+            if !warmup_stopped() {
+                let codecs = warm_wic_codecs();
+                let summary = describe_codecs(&codecs);
+                tracing::info!("WIC codecs: {}", summary);
+                crate::debug_log!("WIC codec warm-up: {}", summary);
+            }
+            crate::release_dll_ref();
+        });
+
+    if let Err(e) = spawned {
+        crate::release_dll_ref();
+        tracing::warn!("Failed to spawn codec warm-up thread: {}", e);
+    }
+}
+
+/// Ask a running warm-up to stop before loading further decoders
+///
+/// Safe to call from `DllMain`; only sets a flag.
+pub fn stop_codec_warmup() {
+    WARMUP_STOP.store(true, Ordering::SeqCst);
+}
+
+fn warmup_stopped() -> bool {
+    WARMUP_STOP.load(Ordering::SeqCst)
+}
+
+/// Probe which modern OS codecs are installed
+///
+/// Loads each decoder once, like the warm-up; always "missing" off Windows.
+pub fn installed_codecs() -> Vec<CodecAvailability> {
+    warm_wic_codecs()
+}
+
+/// Create the WIC factory and each modern decoder once
+#[cfg(target_os = "windows")]
+fn warm_wic_codecs() -> Vec<CodecAvailability> {
+    use windows::Win32::Graphics::Imaging::{CLSID_WICImagingFactory, IWICImagingFactory};
+    use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+
+    let _com = crate::utils::com::ScopedCom::new();
+
+    // UNAVOIDABLE UNSAFE: WIC COM activation and decoder creation (windowscodecs.dll)
+    // Safety: COM is initialized for this thread by `_com`, which outlives the
+    // factory and decoders; GUID pointers reference 'static constants
+    let factory: IWICImagingFactory =
+        match unsafe { CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER) } {
+            Ok(factory) => factory,
+            Err(e) => {
+                tracing::warn!("WIC factory unavailable: {}", e);
+                return probe_codecs(|_| false);
+            }
+        };
+
+    // Codecs left unprobed after a stop are reported missing
+    probe_codecs(|container| {
+        !warmup_stopped() && unsafe { factory.CreateDecoder(container, std::ptr::null()) }.is_ok()
+    })
+}
+
+#[cfg(not(target_os = "windows"))]
+fn warm_wic_codecs() -> Vec<CodecAvailability> {
+    probe_codecs(|_| false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_codecs_reports_each_codec() {
+        let mut probed = Vec::new();
+        let codecs = probe_codecs(|container| {
+            probed.push(*container);
+            *container == GUID_ContainerFormatWebp
+        });
+
+        assert_eq!(
+            probed,
+            vec![GUID_ContainerFormatHeif, GUID_ContainerFormatWebp]
+        );
+        assert_eq!(
+            describe_codecs(&codecs),
+            "HEIF/AVIF: missing, WebP: installed"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Process-wide cache of decoded covers
+//!
+//! Explorer asks for the same archive at several sizes (details pane, large
+//! icons, extra large icons) in quick succession. Keeping the decoded cover
+//! lets those requests skip opening the archive and decoding the image again;
+//! only the resize runs per size.
+//!
+//! Entries are keyed by file path and last-modified time, so a rewritten
+//! archive misses and replaces its stale entry. The key also carries a
+//! fingerprint of the settings that pick the cover (`Settings::cover_selection`),
+//! so changing the strategy, sort order, skipped pages or cover override
+//! does the same. The cache is bounded by the total size of the decoded
+//! pixels and evicts least recently used covers.
+
+use image::DynamicImage;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Upper bound on decoded pixel bytes held by the process-wide cache
+pub const COVER_CACHE_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Identity of an archive file at a point in time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverKey {
+    path: PathBuf,
+    modified: SystemTime,
+    /// Settings the cover was selected with (0 when not given)
+    selection: u64,
+}
+
+impl CoverKey {
+    pub fn new(path: PathBuf, modified: SystemTime) -> Self {
+        Self {
+            path,
+            modified,
+            selection: 0,
+        }
+    }
+
+    /// The same file, for a cover selected with the `selection` fingerprint
+    pub fn with_selection(self, selection: u64) -> Self {
+        Self { selection, ..self }
+    }
+
+    /// Key for a file on disk, or `None` when its metadata can't be read
+    pub fn for_file(path: &Path) -> Option<Self> {
+        let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+        Some(Self::new(path.to_path_buf(), modified))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn modified(&self) -> SystemTime {
+        self.modified
+    }
+}
+
+struct CachedCover {
+    modified: SystemTime,
+    selection: u64,
+    image: Arc<DynamicImage>,
+}
+
+/// Byte-bounded LRU cache of decoded covers
+pub struct CoverCache {
+    max_bytes: usize,
+    used_bytes: usize,
+    entries: HashMap<PathBuf, CachedCover>,
+    /// Paths, least recently used first
+    order: VecDeque<PathBuf>,
+}
+
+impl CoverCache {
+    /// Create a cache holding at most `max_bytes` of decoded pixels
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            used_bytes: 0,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Look up a cover and mark it as most recently used
+    ///
+    /// An entry for the same path with a different modification time or
+    /// selection is stale; it is dropped and the lookup misses.
+    pub fn get(&mut self, key: &CoverKey) -> Option<Arc<DynamicImage>> {
+        let cached = self.entries.get(&key.path)?;
+        if cached.modified != key.modified || cached.selection != key.selection {
+            self.remove(&key.path);
+            return None;
+        }
+        let image = Arc::clone(&cached.image);
+        self.touch(&key.path);
+        Some(image)
+    }
+
+    /// Insert a cover, evicting least recently used ones until it fits
+    ///
+    /// Covers larger than the whole budget are not cached.
+    pub fn insert(&mut self, key: CoverKey, image: Arc<DynamicImage>) {
+        let size = image_bytes(&image);
+        self.remove(&key.path);
+        if size > self.max_bytes {
+            return;
+        }
+
+        while self.used_bytes + size > self.max_bytes {
+            match self.order.pop_front() {
+                Some(evicted) => {
+                    if let Some(cached) = self.entries.remove(&evicted) {
+                        self.used_bytes -= image_bytes(&cached.image);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        self.used_bytes += size;
+        self.order.push_back(key.path.clone());
+        self.entries.insert(
+            key.path,
+            CachedCover {
+                modified: key.modified,
+                selection: key.selection,
+                image,
+            },
+        );
+    }
+
+    /// Number of cached covers
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Decoded bytes currently held
+    #[cfg(test)]
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(cached) = self.entries.remove(path) {
+            self.used_bytes -= image_bytes(&cached.image);
+            if let Some(position) = self.order.iter().position(|p| p == path) {
+                self.order.remove(position);
+            }
+        }
+    }
+
+    fn touch(&mut self, path: &Path) {
+        if let Some(position) = self.order.iter().position(|p| p == path) {
+            if let Some(entry) = self.order.remove(position) {
+                self.order.push_back(entry);
+            }
+        }
+    }
+}
+
+fn image_bytes(image: &DynamicImage) -> usize {
+    image.as_bytes().len()
+}
+
+fn global() -> &'static Mutex<CoverCache> {
+    static CACHE: OnceLock<Mutex<CoverCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(CoverCache::new(COVER_CACHE_MAX_BYTES)))
+}
+
+/// Look up a decoded cover in the process-wide cache
+pub fn lookup(key: &CoverKey) -> Option<Arc<DynamicImage>> {
+    global().lock().ok()?.get(key)
+}
+
+/// Store a decoded cover in the process-wide cache
+pub fn store(key: CoverKey, image: Arc<DynamicImage>) {
+    if let Ok(mut cache) = global().lock() {
+        cache.insert(key, image);
+    }
+}
+
+/// Drop a file's cover from the process-wide cache
+///
+/// For changes the modification time doesn't capture, such as a new cover
+/// override.
+pub fn forget(path: &Path) {
+    if let Ok(mut cache) = global().lock() {
+        cache.remove(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+    use std::time::Duration;
+
+    fn key(path: &str, secs: u64) -> CoverKey {
+        CoverKey::new(
+            PathBuf::from(path),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+        )
+    }
+
+    fn cover(width: u32, height: u32) -> Arc<DynamicImage> {
+        Arc::new(DynamicImage::ImageRgba8(RgbaImage::new(width, height)))
+    }
+
+    #[test]
+    fn test_hit_and_miss() {
+        let mut cache = CoverCache::new(1024 * 1024);
+        assert!(cache.get(&key("a.cbz", 1)).is_none());
+
+        cache.insert(key("a.cbz", 1), cover(10, 10));
+        let hit = cache.get(&key("a.cbz", 1)).unwrap();
+        assert_eq!(hit.width(), 10);
+        assert!(cache.get(&key("b.cbz", 1)).is_none());
+    }
+
+    #[test]
+    fn test_selection_invalidates() {
+        let mut cache = CoverCache::new(1024 * 1024);
+        cache.insert(key("a.cbz", 1).with_selection(7), cover(10, 10));
+
+        assert!(cache.get(&key("a.cbz", 1).with_selection(7)).is_some());
+        // Picked under other settings: decoded again
+        assert!(cache.get(&key("a.cbz", 1).with_selection(8)).is_none());
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_modified_time_invalidates() {
+        let mut cache = CoverCache::new(1024 * 1024);
+        cache.insert(key("a.cbz", 1), cover(10, 10));
+
+        assert!(cache.get(&key("a.cbz", 2)).is_none());
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.used_bytes(), 0);
+        // The stale entry is gone even for the old time
+        assert!(cache.get(&key("a.cbz", 1)).is_none());
+    }
+
+    #[test]
+    fn test_reinsert_replaces_entry() {
+        let mut cache = CoverCache::new(1024 * 1024);
+        cache.insert(key("a.cbz", 1), cover(10, 10));
+        cache.insert(key("a.cbz", 2), cover(20, 20));
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.used_bytes(), 20 * 20 * 4);
+        assert_eq!(cache.get(&key("a.cbz", 2)).unwrap().width(), 20);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_by_bytes() {
+        // Room for two 10x10 RGBA covers
+        let mut cache = CoverCache::new(2 * 10 * 10 * 4);
+        cache.insert(key("a.cbz", 1), cover(10, 10));
+        cache.insert(key("b.cbz", 1), cover(10, 10));
+        cache.get(&key("a.cbz", 1)).unwrap();
+
+        cache.insert(key("c.cbz", 1), cover(10, 10));
+        assert!(cache.get(&key("b.cbz", 1)).is_none());
+        assert!(cache.get(&key("a.cbz", 1)).is_some());
+        assert!(cache.get(&key("c.cbz", 1)).is_some());
+        assert!(cache.used_bytes() <= 2 * 10 * 10 * 4);
+    }
+
+    #[test]
+    fn test_oversized_cover_not_cached() {
+        let mut cache = CoverCache::new(100);
+        cache.insert(key("a.cbz", 1), cover(10, 10));
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.used_bytes(), 0);
+    }
+}
@@ -6,14 +6,63 @@
 //!
 //! This keeps compatibility while enabling newer Windows codec capabilities
 //! (e.g., AVIF via installed system codec) without bundling large codec libraries.
+//!
+//! Multi-page TIFFs are decoded from their largest page, since the first page
+//! is often a low-resolution preview.
+//!
+//! Formats that only an OS codec can decode (AVIF) skip the fallback: if WIC
+//! can't decode them the codec is missing, and the `image` crate would only
+//! fail again with a less useful error.
+//!
+//! The `image` crate path runs under a watchdog (`DecodeTimeoutMs`) so a
+//! malformed file cannot freeze Explorer's thumbnail thread.
+//!
+//! SVG covers are rasterized with resvg when built with the `svg` feature
+//! (also under the watchdog); without it they fail with `UnsupportedFormat`.
+//!
+//! With `LenientDecode` on, a JPEG or PNG missing its end marker (an
+//! interrupted download) renders whatever rows could be decoded instead of
+//! failing: WIC errors fall back to the image crate, which fills the missing
+//! JPEG scan in gray, and PNGs are decoded row by row until the data runs out.
 
-use crate::utils::debug_log::debug_log;
 use crate::utils::error::CbxError;
 use image::{DynamicImage, ImageBuffer, ImageReader, RgbaImage};
 use std::io::Cursor;
+use std::sync::mpsc;
+use std::time::Duration;
 
 type Result<T> = std::result::Result<T, CbxError>;
 
+/// Bytes at the end of a file searched for its end marker
+///
+/// Some encoders append padding or metadata after the JPEG EOI / PNG IEND.
+const END_MARKER_SLACK: usize = 1024;
+
+/// Longest side SVG covers are rasterized at
+///
+/// The decoded cover is cached and resized for every requested size, so the
+/// vector is drawn once at the largest size Explorer commonly asks for.
+#[cfg(feature = "svg")]
+const SVG_RASTER_SIZE: u32 = 1024;
+
+/// Settings that change how covers are decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// `LenientDecode`: render truncated JPEG/PNG data partially
+    pub lenient: bool,
+    /// `DecodeTimeoutMs`: abandon image crate decodes after this long (0 = never)
+    pub timeout_ms: u32,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            lenient: false,
+            timeout_ms: crate::archive::config::DEFAULT_DECODE_TIMEOUT_MS,
+        }
+    }
+}
+
 /// Decode image from raw bytes
 ///
 /// This function attempts to automatically detect the image format and decode it.
@@ -33,33 +82,273 @@ type Result<T> = std::result::Result<T, CbxError>;
 /// println!("Image dimensions: {}x{}", img.width(), img.height());
 /// ```
 pub fn decode_image(data: &[u8]) -> Result<DynamicImage> {
+    decode_image_with(data, &DecodeOptions::default())
+}
+
+/// Decode image from raw bytes with the given watchdog and leniency
+///
+/// `decode_image` uses the defaults; the shell extension passes the options
+/// from its `Settings` (see `Settings::decode_options`).
+pub fn decode_image_with(data: &[u8], options: &DecodeOptions) -> Result<DynamicImage> {
     if data.is_empty() {
         return Err(CbxError::Image("Empty image data".to_string()));
     }
 
-    debug_log(&format!(
-        "WIC decode attempt started for {} bytes",
-        data.len()
-    ));
+    crate::debug_log!("WIC decode attempt started for {} bytes", data.len());
+
+    let format = super::magic::detect_image_format(data).ok();
+
+    // GIF transparency is a palette index, not an alpha channel. WIC's RGBA
+    // conversion of the first frame can surface the transparent slot as its
+    // palette color, while the image crate maps it to alpha 0. Decode GIFs with
+    // the image crate so apply_background sees real transparency.
+    if format == Some(super::magic::ImageFormat::Gif) {
+        crate::debug_log!("GIF detected, decoding with image crate to honor transparency index");
+        return decode_with_image_crate_watchdog(data, options.timeout_ms);
+    }
+
+    // WIC has no SVG decoder
+    if format == Some(super::magic::ImageFormat::Svg) {
+        crate::debug_log!("SVG detected, rasterizing");
+        return decode_svg(data, options.timeout_ms);
+    }
 
     // Fast path: try Windows WIC decoder first.
     // WIC can use OS-installed codecs and may leverage platform-specific optimizations.
-    if let Some(img) = try_decode_with_wic(data)? {
-        debug_log(&format!(
-            "WIC decode path used successfully: {}x{}",
-            img.width(),
-            img.height()
-        ));
-        return Ok(img);
+    let lenient = options.lenient && is_truncated(data);
+    match try_decode_with_wic(data) {
+        Ok(Some(img)) => {
+            crate::debug_log!(
+                "WIC decode path used successfully: {}x{}",
+                img.width(),
+                img.height()
+            );
+            return Ok(img);
+        }
+        Ok(None) => {}
+        Err(e) if lenient => {
+            crate::debug_log!("WIC failed on truncated image, trying image crate: {}", e);
+        }
+        Err(e) => return Err(e),
+    }
+
+    if let Some(codec) = format.and_then(os_codec_name) {
+        crate::debug_log!(
+            "WIC could not decode {} and no fallback exists, codec missing",
+            codec
+        );
+        return Err(CbxError::CodecMissing(codec.to_string()));
     }
 
-    debug_log("WIC decode path unavailable, falling back to image crate");
+    crate::debug_log!("WIC decode path unavailable, falling back to image crate");
 
     // Fallback path: decode via Rust image crate for broad compatibility.
-    decode_with_image_crate(data)
+    match decode_with_image_crate_watchdog(data, options.timeout_ms) {
+        Err(e) if lenient => decode_partial_png(data).ok_or(e),
+        result => result,
+    }
+}
+
+/// Whether `data` is a JPEG or PNG whose end marker is missing
+///
+/// Only the last `END_MARKER_SLACK` bytes are searched. Other formats are
+/// never reported as truncated.
+pub fn is_truncated(data: &[u8]) -> bool {
+    let tail = &data[data.len().saturating_sub(END_MARKER_SLACK)..];
+    match super::magic::detect_image_format(data) {
+        Ok(super::magic::ImageFormat::Jpeg) => !tail.windows(2).any(|w| w == [0xFF, 0xD9]),
+        Ok(super::magic::ImageFormat::Png) => !tail.windows(4).any(|w| w == b"IEND"),
+        _ => false,
+    }
+}
+
+/// Decode the rows of a truncated PNG that are present
+///
+/// Returns `None` for interlaced PNGs (their early passes cover the whole
+/// image at low resolution, not a top portion) or when no row decodes.
+fn decode_partial_png(data: &[u8]) -> Option<DynamicImage> {
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    if reader.info().interlaced {
+        return None;
+    }
+
+    let width = reader.info().width;
+    let mut pixels = Vec::new();
+    let mut rows = 0u32;
+    while let Ok(Some(row)) = reader.next_row() {
+        pixels.extend_from_slice(row.data());
+        rows += 1;
+    }
+    if rows == 0 {
+        return None;
+    }
+    crate::debug_log!(
+        "Truncated PNG: decoded {} of {} rows",
+        rows,
+        reader.info().height
+    );
+
+    match reader.output_color_type().0 {
+        png::ColorType::Grayscale => {
+            ImageBuffer::from_raw(width, rows, pixels).map(DynamicImage::ImageLuma8)
+        }
+        png::ColorType::GrayscaleAlpha => {
+            ImageBuffer::from_raw(width, rows, pixels).map(DynamicImage::ImageLumaA8)
+        }
+        png::ColorType::Rgb => {
+            ImageBuffer::from_raw(width, rows, pixels).map(DynamicImage::ImageRgb8)
+        }
+        png::ColorType::Rgba => {
+            ImageBuffer::from_raw(width, rows, pixels).map(DynamicImage::ImageRgba8)
+        }
+        png::ColorType::Indexed => None,
+    }
+}
+
+/// Rasterize an SVG cover under the decode watchdog
+#[cfg(feature = "svg")]
+fn decode_svg(data: &[u8], timeout_ms: u32) -> Result<DynamicImage> {
+    decode_with_watchdog(data, rasterize_svg, timeout_ms)
+}
+
+/// SVG covers need the `svg` feature
+#[cfg(not(feature = "svg"))]
+fn decode_svg(_data: &[u8], _timeout_ms: u32) -> Result<DynamicImage> {
+    Err(CbxError::UnsupportedFormat(
+        "SVG (built without the svg feature)".to_string(),
+    ))
+}
+
+/// Render an SVG so its longest side is `SVG_RASTER_SIZE` pixels
+#[cfg(feature = "svg")]
+fn rasterize_svg(data: &[u8]) -> Result<DynamicImage> {
+    use resvg::{tiny_skia, usvg};
+
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default())
+        .map_err(|e| CbxError::Image(format!("Failed to parse SVG: {}", e)))?;
+    let size = tree.size();
+    let scale = SVG_RASTER_SIZE as f32 / size.width().max(size.height());
+    let width = ((size.width() * scale).round() as u32).max(1);
+    let height = ((size.height() * scale).round() as u32).max(1);
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| CbxError::Image(format!("Invalid SVG size: {}x{}", width, height)))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia stores premultiplied alpha
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, pixels)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| CbxError::Image("SVG pixel buffer size mismatch".to_string()))
+}
+
+/// Name of the OS codec a format needs, for formats only WIC can decode
+///
+/// The image crate is built without decoders for these, so falling back to it
+/// after WIC fails is pointless.
+fn os_codec_name(format: super::magic::ImageFormat) -> Option<&'static str> {
+    match format {
+        super::magic::ImageFormat::Avif => Some("AVIF"),
+        _ => None,
+    }
+}
+
+/// Decode with the image crate, abandoning the decode after `timeout_ms`
+///
+/// WIC is not covered: its COM objects belong to the caller's apartment.
+fn decode_with_image_crate_watchdog(data: &[u8], timeout_ms: u32) -> Result<DynamicImage> {
+    decode_with_watchdog(data, decode_with_image_crate, timeout_ms)
+}
+
+/// Run `decode` on `data`, abandoning it after `timeout_ms` (0 = never)
+fn decode_with_watchdog(
+    data: &[u8],
+    decode: fn(&[u8]) -> Result<DynamicImage>,
+    timeout_ms: u32,
+) -> Result<DynamicImage> {
+    match timeout_ms {
+        0 => decode(data),
+        timeout_ms => {
+            let owned = data.to_vec();
+            run_with_timeout(
+                move || decode(&owned),
+                Duration::from_millis(u64::from(timeout_ms)),
+            )
+        }
+    }
+}
+
+/// Run `decode` on a worker thread and wait at most `timeout` for the result
+///
+/// Decoders are not cancellable, so on timeout the worker is detached and
+/// finishes in the background; its result is dropped. The worker holds a DLL
+/// reference until it exits so DllCanUnloadNow keeps the module mapped while
+/// it is still running.
+fn run_with_timeout<T, F>(decode: F, timeout: Duration) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+
+    crate::add_dll_ref();
+    std::thread::Builder::new()
+        .name("cbx-decode".to_string())
+        .spawn(move || {
+            /// Releases the DLL reference even if the decoder panics
+            struct DllRefGuard;
+            impl Drop for DllRefGuard {
+                fn drop(&mut self) {
+                    crate::release_dll_ref();
+                }
+            }
+            let _dll_ref = DllRefGuard;
+
+            // Receiver is gone if the watchdog already gave up
+            let _ = sender.send(decode());
+        })
+        .map_err(|e| {
+            crate::release_dll_ref();
+            CbxError::Image(format!("Failed to spawn decode thread: {}", e))
+        })?;
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            tracing::warn!("Image decode timed out after {:?}", timeout);
+            crate::debug_log!("Image decode timed out after {:?}", timeout);
+            Err(CbxError::Image("decode timed out".to_string()))
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            // The worker panicked before sending a result
+            Err(CbxError::Image("Decode thread panicked".to_string()))
+        }
+    }
 }
 
 fn decode_with_image_crate(data: &[u8]) -> Result<DynamicImage> {
+    if matches!(
+        super::magic::detect_image_format(data),
+        Ok(super::magic::ImageFormat::Tiff)
+    ) {
+        if let Some(img) = decode_tiff_cover_page(data) {
+            return Ok(img);
+        }
+    }
+
     // Create a reader from the byte slice
     let reader = ImageReader::new(Cursor::new(data))
         .with_guessed_format()
@@ -71,26 +360,169 @@ fn decode_with_image_crate(data: &[u8]) -> Result<DynamicImage> {
         .map_err(|e| CbxError::Image(format!("Failed to decode image: {}", e)))
 }
 
+/// Read image dimensions from the header without decoding pixels
+///
+/// Cheap enough to run on several candidates during cover selection.
+///
+/// # Returns
+/// * `Ok((width, height))` - Dimensions declared by the image header
+/// * `Err(CbxError::Image)` - Unknown format or unreadable header
+pub fn probe_dimensions(data: &[u8]) -> Result<(u32, u32)> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| CbxError::Image(format!("Format detection failed: {}", e)))?
+        .into_dimensions()
+        .map_err(|e| CbxError::Image(format!("Failed to read image dimensions: {}", e)))
+}
+
+/// Pick the cover frame of a multi-page image
+///
+/// Returns the index of the largest frame by pixel area, the earliest one on
+/// ties (0 for an empty list).
+fn select_cover_frame(sizes: &[(u32, u32)]) -> usize {
+    let area = |&(width, height): &(u32, u32)| u64::from(width) * u64::from(height);
+    sizes
+        .iter()
+        .enumerate()
+        .max_by(|(ia, a), (ib, b)| area(a).cmp(&area(b)).then(ib.cmp(ia)))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+/// Decode the cover page of a multi-page TIFF with the `tiff` crate
+///
+/// The image crate only decodes the first page. Returns `None` when the first
+/// page is already the cover or the chosen page's color type is not handled
+/// here, so the caller falls back to the regular decode.
+fn decode_tiff_cover_page(data: &[u8]) -> Option<DynamicImage> {
+    use tiff::decoder::{Decoder, DecodingResult};
+    use tiff::ColorType;
+
+    let mut decoder = Decoder::new(Cursor::new(data)).ok()?;
+    let mut sizes = vec![decoder.dimensions().ok()?];
+    while decoder.more_images() {
+        decoder.next_image().ok()?;
+        sizes.push(decoder.dimensions().ok()?);
+    }
+
+    let index = select_cover_frame(&sizes);
+    if index == 0 {
+        return None;
+    }
+    let (width, height) = sizes[index];
+    crate::debug_log!(
+        "Multi-page TIFF: using page {} of {} ({}x{})",
+        index + 1,
+        sizes.len(),
+        width,
+        height
+    );
+
+    decoder.seek_to_image(index).ok()?;
+    let color_type = decoder.colortype().ok()?;
+    match (color_type, decoder.read_image().ok()?) {
+        (ColorType::Gray(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgba16)
+        }
+        (color_type, _) => {
+            crate::debug_log!(
+                "Multi-page TIFF: unsupported color type {:?}, using first page",
+                color_type
+            );
+            None
+        }
+    }
+}
+
+/// Index of the WIC frame to use as the cover
+///
+/// Only multi-page TIFFs pick a frame; everything else uses frame 0.
+#[cfg(target_os = "windows")]
+fn wic_cover_frame_index(
+    data: &[u8],
+    decoder: &windows::Win32::Graphics::Imaging::IWICBitmapDecoder,
+) -> u32 {
+    if !matches!(
+        super::magic::detect_image_format(data),
+        Ok(super::magic::ImageFormat::Tiff)
+    ) {
+        return 0;
+    }
+
+    let count = unsafe { decoder.GetFrameCount() }.unwrap_or(1);
+    if count <= 1 {
+        return 0;
+    }
+
+    let sizes: Vec<(u32, u32)> = (0..count)
+        .map(|index| {
+            let mut width = 0u32;
+            let mut height = 0u32;
+            let read = unsafe {
+                decoder
+                    .GetFrame(index)
+                    .and_then(|frame| frame.GetSize(&mut width, &mut height))
+            };
+            if read.is_ok() {
+                (width, height)
+            } else {
+                (0, 0)
+            }
+        })
+        .collect();
+
+    let index = select_cover_frame(&sizes) as u32;
+    crate::debug_log!(
+        "Multi-page TIFF: WIC frame {} of {} selected",
+        index + 1,
+        count
+    );
+    index
+}
+
 #[cfg(target_os = "windows")]
 fn try_decode_with_wic(data: &[u8]) -> Result<Option<DynamicImage>> {
     use windows::Win32::Graphics::Imaging::{
-        CLSID_WICImagingFactory, GUID_WICPixelFormat32bppRGBA, IWICBitmapDecoder,
-        IWICFormatConverter, IWICImagingFactory, WICBitmapDitherTypeNone,
-        WICBitmapPaletteTypeCustom, WICDecodeMetadataCacheOnDemand,
+        CLSID_WICImagingFactory, GUID_WICPixelFormat128bppPRGBAFloat,
+        GUID_WICPixelFormat32bppPBGRA, GUID_WICPixelFormat32bppPRGBA, GUID_WICPixelFormat32bppRGBA,
+        GUID_WICPixelFormat64bppPBGRA, GUID_WICPixelFormat64bppPRGBA,
+        GUID_WICPixelFormat64bppPRGBAHalf, IWICBitmapDecoder, IWICFormatConverter,
+        IWICImagingFactory, WICBitmapDitherTypeNone, WICBitmapPaletteTypeCustom,
+        WICDecodeMetadataCacheOnDemand,
     };
     use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
 
+    // Declared first so every WIC object is released before COM is uninitialized
+    let _com = crate::utils::com::ScopedCom::new();
     let factory: IWICImagingFactory =
         match unsafe { CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER) } {
             Ok(factory) => factory,
             Err(e) => {
-                // Some callers (including tests) may run on threads without COM initialization.
+                // COM may be unusable on this thread (or WIC missing entirely).
                 // Treat WIC setup failures as non-fatal so decode_image can still use image-crate fallback.
                 tracing::debug!("WIC factory creation failed, fallback to image crate: {e}");
-                debug_log(&format!(
+                crate::debug_log!(
                     "WIC factory creation failed, fallback to image crate: {}",
                     e
-                ));
+                );
                 return Ok(None);
             }
         };
@@ -115,22 +547,20 @@ fn try_decode_with_wic(data: &[u8]) -> Result<Option<DynamicImage>> {
         Ok(decoder) => decoder,
         Err(e) => {
             tracing::debug!("WIC decoder unavailable for image, fallback to image crate: {e}");
-            debug_log(&format!(
+            crate::debug_log!(
                 "WIC decoder unavailable for image, fallback to image crate: {}",
                 e
-            ));
+            );
             return Ok(None);
         }
     };
 
-    let frame = match unsafe { decoder.GetFrame(0) } {
+    let frame_index = wic_cover_frame_index(data, &decoder);
+    let frame = match unsafe { decoder.GetFrame(frame_index) } {
         Ok(frame) => frame,
         Err(e) => {
             tracing::debug!("WIC frame decode failed, fallback to image crate: {e}");
-            debug_log(&format!(
-                "WIC frame decode failed, fallback to image crate: {}",
-                e
-            ));
+            crate::debug_log!("WIC frame decode failed, fallback to image crate: {}", e);
             return Ok(None);
         }
     };
@@ -149,6 +579,25 @@ fn try_decode_with_wic(data: &[u8]) -> Result<Option<DynamicImage>> {
         ));
     }
 
+    // Premultiplied frames are copied as premultiplied RGBA and converted
+    // below, so everything after decode sees straight alpha
+    let premultiplied = unsafe { frame.GetPixelFormat() }.is_ok_and(|format| {
+        [
+            GUID_WICPixelFormat32bppPBGRA,
+            GUID_WICPixelFormat32bppPRGBA,
+            GUID_WICPixelFormat64bppPBGRA,
+            GUID_WICPixelFormat64bppPRGBA,
+            GUID_WICPixelFormat64bppPRGBAHalf,
+            GUID_WICPixelFormat128bppPRGBAFloat,
+        ]
+        .contains(&format)
+    });
+    let target_format = if premultiplied {
+        &GUID_WICPixelFormat32bppPRGBA
+    } else {
+        &GUID_WICPixelFormat32bppRGBA
+    };
+
     let converter: IWICFormatConverter = unsafe {
         factory
             .CreateFormatConverter()
@@ -159,7 +608,7 @@ fn try_decode_with_wic(data: &[u8]) -> Result<Option<DynamicImage>> {
         converter
             .Initialize(
                 &frame,
-                &GUID_WICPixelFormat32bppRGBA,
+                target_format,
                 WICBitmapDitherTypeNone,
                 None,
                 0.0,
@@ -182,11 +631,14 @@ fn try_decode_with_wic(data: &[u8]) -> Result<Option<DynamicImage>> {
             .map_err(|e| CbxError::Image(format!("WIC pixel copy failed: {}", e)))?;
     }
 
-    let rgba: RgbaImage = ImageBuffer::from_raw(width, height, pixels).ok_or_else(|| {
+    let mut rgba: RgbaImage = ImageBuffer::from_raw(width, height, pixels).ok_or_else(|| {
         CbxError::Image("WIC decoded data had unexpected pixel buffer size".to_string())
     })?;
+    if premultiplied {
+        super::thumbnail::unpremultiply_alpha(&mut rgba);
+    }
 
-    debug_log(&format!("WIC decode succeeded: {}x{}", width, height));
+    crate::debug_log!("WIC decode succeeded: {}x{}", width, height);
     tracing::debug!("Decoded image with WIC: {}x{}", width, height);
     Ok(Some(DynamicImage::ImageRgba8(rgba)))
 }
@@ -280,4 +732,191 @@ mod tests {
         let result = decode_image(not_image);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_decode_avif_without_codec_fails_fast() {
+        // ftyp box with the avif brand and no decodable payload
+        let mut avif = b"\x00\x00\x00\x18ftypavif\x00\x00\x00\x00avifmif1".to_vec();
+        avif.resize(512, 0);
+
+        let start = std::time::Instant::now();
+        let result = decode_image(&avif);
+
+        match result {
+            Err(CbxError::CodecMissing(codec)) => assert_eq!(codec, "AVIF"),
+            other => panic!("expected missing codec error, got {:?}", other.map(|_| ())),
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            os_codec_name(crate::image_processor::magic::ImageFormat::Png),
+            None
+        );
+    }
+
+    /// 200x100 SVG: transparent, with a red rect over its left half
+    const SVG_RECT: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+  <rect x="0" y="0" width="100" height="100" fill="red"/>
+</svg>"#;
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_decode_svg_rect() {
+        let img = decode_image_with(SVG_RECT, &DecodeOptions::default())
+            .unwrap()
+            .to_rgba8();
+
+        // Scaled so the longest side is SVG_RASTER_SIZE, aspect kept
+        assert_eq!(img.dimensions(), (SVG_RASTER_SIZE, SVG_RASTER_SIZE / 2));
+        assert_eq!(img.get_pixel(100, 256).0, [255, 0, 0, 255]);
+        assert_eq!(img.get_pixel(900, 256).0[3], 0);
+    }
+
+    #[cfg(not(feature = "svg"))]
+    #[test]
+    fn test_decode_svg_needs_feature() {
+        match decode_image_with(SVG_RECT, &DecodeOptions::default()) {
+            Err(CbxError::UnsupportedFormat(format)) => assert!(format.contains("svg feature")),
+            other => panic!("expected unsupported format, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_select_cover_frame() {
+        assert_eq!(select_cover_frame(&[]), 0);
+        assert_eq!(select_cover_frame(&[(100, 100)]), 0);
+        assert_eq!(select_cover_frame(&[(100, 150), (800, 1200)]), 1);
+        // Earliest frame wins a tie
+        assert_eq!(select_cover_frame(&[(10, 10), (800, 1200), (1200, 800)]), 1);
+    }
+
+    #[test]
+    fn test_decode_multi_page_tiff_uses_largest_page() {
+        use tiff::encoder::{colortype, TiffEncoder};
+
+        let mut tiff_data = Cursor::new(Vec::new());
+        {
+            let mut encoder = TiffEncoder::new(&mut tiff_data).unwrap();
+            // Page 1: low-resolution preview
+            encoder
+                .write_image::<colortype::RGB8>(4, 6, &[0u8; 4 * 6 * 3])
+                .unwrap();
+            // Page 2: full-resolution page
+            encoder
+                .write_image::<colortype::RGB8>(16, 24, &[255u8; 16 * 24 * 3])
+                .unwrap();
+        }
+
+        let img = decode_image(tiff_data.get_ref()).unwrap();
+        assert_eq!((img.width(), img.height()), (16, 24));
+        assert_eq!(img.to_rgba8().get_pixel(0, 0).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_run_with_timeout_returns_result() {
+        let result = run_with_timeout(|| Ok(42u32), Duration::from_secs(5));
+        assert_eq!(result.unwrap(), 42);
+
+        let result: Result<u32> = run_with_timeout(
+            || Err(CbxError::Image("bad data".to_string())),
+            Duration::from_secs(5),
+        );
+        assert!(matches!(result, Err(CbxError::Image(msg)) if msg == "bad data"));
+    }
+
+    #[test]
+    fn test_run_with_timeout_fires_on_slow_decode() {
+        let start = std::time::Instant::now();
+        let result: Result<u32> = run_with_timeout(
+            || {
+                // Stand-in for a decoder stuck on a malformed image
+                std::thread::sleep(Duration::from_secs(2));
+                Ok(0)
+            },
+            Duration::from_millis(50),
+        );
+
+        assert!(matches!(result, Err(CbxError::Image(msg)) if msg == "decode timed out"));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_run_with_timeout_reports_panic() {
+        let result: Result<u32> =
+            run_with_timeout(|| panic!("decoder bug"), Duration::from_secs(5));
+        assert!(result.is_err());
+    }
+
+    /// 64x64 gradient, distinct per row so partial renders can be compared
+    fn gradient() -> RgbaImage {
+        ImageBuffer::from_fn(64, 64, |x, y| {
+            image::Rgba([x as u8 * 4, y as u8 * 4, 128, 255])
+        })
+    }
+
+    fn encode(img: &RgbaImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        // Opaque, and JPEG has no alpha channel
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(img.clone()).to_rgb8())
+            .write_to(&mut data, format)
+            .unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn test_is_truncated() {
+        assert!(!is_truncated(MINIMAL_JPEG));
+        assert!(!is_truncated(MINIMAL_PNG));
+        assert!(is_truncated(&MINIMAL_JPEG[..MINIMAL_JPEG.len() - 2]));
+        assert!(is_truncated(&MINIMAL_PNG[..MINIMAL_PNG.len() - 12]));
+        assert!(!is_truncated(b"GIF89a"));
+    }
+
+    #[test]
+    fn test_lenient_decode_truncated_jpeg() {
+        let original = gradient();
+        let jpeg = encode(&original, image::ImageFormat::Jpeg);
+        // Cut mid-scan
+        let truncated = &jpeg[..jpeg.len() * 6 / 10];
+        assert!(is_truncated(truncated));
+
+        let lenient = DecodeOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        let img = decode_image_with(truncated, &lenient).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (64, 64));
+        // The top rows survive, up to JPEG loss
+        for x in 0..64 {
+            let (decoded, expected) = (img.get_pixel(x, 2).0, original.get_pixel(x, 2).0);
+            for channel in 0..3 {
+                assert!(decoded[channel].abs_diff(expected[channel]) <= 16);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lenient_decode_truncated_png() {
+        let png = encode(&gradient(), image::ImageFormat::Png);
+        let truncated = &png[..png.len() * 6 / 10];
+        assert!(is_truncated(truncated));
+
+        assert!(decode_image_with(truncated, &DecodeOptions::default()).is_err());
+        let lenient = DecodeOptions {
+            lenient: true,
+            ..Default::default()
+        };
+
+        let img = decode_image_with(truncated, &lenient).unwrap().to_rgba8();
+        assert_eq!(img.width(), 64);
+        assert!(img.height() > 0 && img.height() < 64);
+        assert_eq!(img.get_pixel(5, 3).0, gradient().get_pixel(5, 3).0);
+    }
+
+    #[test]
+    fn test_probe_dimensions() {
+        assert_eq!(probe_dimensions(MINIMAL_PNG).unwrap(), (1, 1));
+        assert_eq!(probe_dimensions(MINIMAL_JPEG).unwrap(), (1, 1));
+        assert!(probe_dimensions(b"not an image").is_err());
+    }
 }
@@ -11,6 +11,10 @@
 //! - **resizer**: Calculates thumbnail dimensions and performs high-quality resizing
 //! - **hbitmap**: Converts pixel data to Windows HBITMAP format
 //! - **thumbnail**: Orchestrates the complete pipeline
+//! - **phash**: Perceptual hashes of covers for duplicate detection
+//! - **codecs**: Optional warm-up of OS codecs on the first thumbnail request
+//! - **cover_cache**: Decoded covers reused across thumbnail sizes
+//! - **placeholder**: Blurred low-res placeholders made from a decoded cover
 //!
 //! # Pipeline
 //!
@@ -33,6 +37,7 @@
 //! - AVIF (.avif) - NEW in Rust version!
 //! - TIFF (.tif, .tiff)
 //! - ICO (.ico)
+//! - SVG (.svg) - with the `svg` feature
 //!
 //! # Examples
 //!
@@ -67,27 +72,78 @@
 //! - Same white background for transparent images
 //! - Same HALFTONE-equivalent resize quality (Triangle/Bilinear)
 
-mod decoder;
+pub mod codecs;
+pub mod cover_cache;
+pub mod decoder;
 mod hbitmap;
 pub mod magic;
+pub mod phash;
+pub mod placeholder;
 mod resizer;
 pub mod thumbnail;
 
+use magic::ImageFormat;
+
 /// Supported image file extensions
 ///
 /// This matches the C++ implementation in cbxArchive.h:553-567 plus new formats.
 #[allow(dead_code)] // Used by is_image_file function
 const SUPPORTED_EXTENSIONS: &[&str] = &[
-    "jpg", "jpeg", "jpe", "jfif", // JPEG
+    "jpg",
+    "jpeg",
+    "jpe",
+    "jfif", // JPEG
     "png",  // PNG
     "gif",  // GIF
     "bmp",  // BMP
     "webp", // WebP (NEW!)
     "avif", // AVIF (NEW!)
-    "tif", "tiff", // TIFF
+    "tif",
+    "tiff", // TIFF
     "ico",  // Icon
+    #[cfg(feature = "svg")]
+    "svg", // SVG (rasterized with resvg)
+];
+
+/// Formats the bundled `image` crate decodes without any OS codec
+const BUILTIN_IMAGE_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Jpeg,
+    ImageFormat::Png,
+    ImageFormat::Gif,
+    ImageFormat::Bmp,
+    ImageFormat::Tiff,
+    ImageFormat::Ico,
+    ImageFormat::WebP,
+    #[cfg(feature = "svg")]
+    ImageFormat::Svg,
 ];
 
+/// Formats decoded only through WIC, with the codec (as named by
+/// `codecs::installed_codecs`) each one needs
+const OS_CODEC_IMAGE_FORMATS: &[(ImageFormat, &str)] = &[(ImageFormat::Avif, "HEIF/AVIF")];
+
+/// Image formats this build can decode on this machine
+///
+/// Built-in formats are always listed; WIC-only formats (AVIF) only when
+/// their OS codec is installed. Probing loads the codecs, so call this from
+/// diagnostics rather than the thumbnail path.
+pub fn supported_image_formats() -> Vec<ImageFormat> {
+    supported_image_formats_with(&codecs::installed_codecs())
+}
+
+fn supported_image_formats_with(codecs: &[codecs::CodecAvailability]) -> Vec<ImageFormat> {
+    let installed = |name: &str| codecs.iter().any(|c| c.name == name && c.installed);
+
+    let mut formats = BUILTIN_IMAGE_FORMATS.to_vec();
+    formats.extend(
+        OS_CODEC_IMAGE_FORMATS
+            .iter()
+            .filter(|(_, codec)| installed(codec))
+            .map(|(format, _)| *format),
+    );
+    formats
+}
+
 /// Check if a file is a supported image format
 ///
 /// This function checks the file extension against the list of supported formats.
@@ -188,6 +244,33 @@ mod tests {
         assert!(is_image_file("image.WEBP"));
     }
 
+    #[test]
+    fn test_supported_image_formats_follow_codecs() {
+        let codecs = |installed: bool| {
+            vec![
+                codecs::CodecAvailability {
+                    name: "HEIF/AVIF",
+                    installed,
+                },
+                codecs::CodecAvailability {
+                    name: "WebP",
+                    installed,
+                },
+            ]
+        };
+
+        // Built-in decoders are listed whether or not OS codecs exist
+        let without = supported_image_formats_with(&codecs(false));
+        assert_eq!(without, BUILTIN_IMAGE_FORMATS);
+        assert!(without.contains(&ImageFormat::WebP));
+        assert!(!without.contains(&ImageFormat::Avif));
+
+        let with = supported_image_formats_with(&codecs(true));
+        assert!(BUILTIN_IMAGE_FORMATS.iter().all(|f| with.contains(f)));
+        assert!(with.contains(&ImageFormat::Avif));
+        assert_eq!(with.len(), BUILTIN_IMAGE_FORMATS.len() + 1);
+    }
+
     #[test]
     fn test_is_image_file_edge_cases() {
         assert!(!is_image_file(""));
@@ -0,0 +1,195 @@
+//! Perceptual hashing of cover images
+//!
+//! Produces a 64-bit DCT hash that is stable across re-encodes and rescales,
+//! so duplicate covers can be found by Hamming distance.
+//!
+//! Two entry points:
+//! - `compute_cover_phash` hashes the rendered 256px thumbnail (same pixels
+//!   Explorer shows)
+//! - `compute_cover_phash_fast` resizes the decoded image straight to the
+//!   16x16 hash input, skipping the thumbnail step; use it when hashing a
+//!   whole library
+
+use super::decoder;
+use super::resizer::{resize_image, ResizeFilter};
+use super::thumbnail::{apply_background, render_thumbnail, ThumbnailConfig};
+use crate::utils::error::CbxError;
+use image::RgbaImage;
+
+type Result<T> = std::result::Result<T, CbxError>;
+
+/// Side length of the grayscale image the DCT runs on
+const HASH_INPUT_SIZE: u32 = 16;
+
+/// Low-frequency DCT coefficients kept per axis (8x8 = 64 bits)
+const HASH_COEFFS: usize = 8;
+
+/// Compute the perceptual hash of a cover from its rendered thumbnail
+///
+/// # Arguments
+/// * `data` - Raw image file bytes
+///
+/// # Returns
+/// * `Ok(u64)` - 64-bit perceptual hash
+/// * `Err(CbxError)` - Failed to decode or resize the image
+pub fn compute_cover_phash(data: &[u8]) -> Result<u64> {
+    let thumbnail = render_thumbnail(data, &ThumbnailConfig::default())?;
+    let small = resize_image(
+        &thumbnail,
+        HASH_INPUT_SIZE,
+        HASH_INPUT_SIZE,
+        ResizeFilter::Triangle,
+    )?;
+    Ok(hash_pixels(&small))
+}
+
+/// Compute the perceptual hash of a cover in a single decode-and-downscale
+///
+/// Equivalent to `compute_cover_phash` for duplicate detection, but resizes
+/// the decoded image directly to 16x16 instead of rendering a 256px
+/// thumbnail first.
+pub fn compute_cover_phash_fast(data: &[u8]) -> Result<u64> {
+    let rgba = decoder::decode_image(data)?.to_rgba8();
+    let mut small = resize_image(
+        &rgba,
+        HASH_INPUT_SIZE,
+        HASH_INPUT_SIZE,
+        ResizeFilter::Triangle,
+    )?;
+    let config = ThumbnailConfig::default();
+    apply_background(&mut small, config.background_color, config.opaque_threshold);
+    Ok(hash_pixels(&small))
+}
+
+/// Number of differing bits between two hashes
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Hash a `HASH_INPUT_SIZE` square image
+///
+/// Runs a 2D DCT-II over the luma, keeps the top-left 8x8 coefficients and
+/// sets a bit for each coefficient above their median (DC excluded from the
+/// median so overall brightness does not dominate).
+fn hash_pixels(img: &RgbaImage) -> u64 {
+    let n = HASH_INPUT_SIZE as usize;
+    let luma: Vec<f64> = img
+        .pixels()
+        .map(|p| 0.299 * f64::from(p[0]) + 0.587 * f64::from(p[1]) + 0.114 * f64::from(p[2]))
+        .collect();
+
+    // cos((2x + 1) * u * pi / 2n) for the kept frequencies
+    let cosines: Vec<f64> = (0..HASH_COEFFS)
+        .flat_map(|u| {
+            (0..n).map(move |x| {
+                ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * n) as f64).cos()
+            })
+        })
+        .collect();
+
+    let mut coeffs = [0f64; HASH_COEFFS * HASH_COEFFS];
+    for v in 0..HASH_COEFFS {
+        for u in 0..HASH_COEFFS {
+            let mut sum = 0.0;
+            for y in 0..n {
+                for x in 0..n {
+                    sum += luma[y * n + x] * cosines[u * n + x] * cosines[v * n + y];
+                }
+            }
+            coeffs[v * HASH_COEFFS + u] = sum;
+        }
+    }
+
+    let mut sorted: Vec<f64> = coeffs[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+
+    coeffs
+        .iter()
+        .enumerate()
+        .filter(|(_, &c)| c > median)
+        .fold(0u64, |hash, (i, _)| hash | (1u64 << i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// Distance above which two covers are considered different
+    const DIFFERENT_THRESHOLD: u32 = 10;
+
+    fn png_bytes(img: &RgbaImage) -> Vec<u8> {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut buffer, image::ImageFormat::Png).unwrap();
+        buffer.into_inner()
+    }
+
+    /// 300x400 "page" of random gray 50px blocks, deterministic per seed
+    fn blocks(seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        let levels: Vec<u8> = (0..48)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                (state >> 56) as u8
+            })
+            .collect();
+
+        png_bytes(&RgbaImage::from_fn(300, 400, |x, y| {
+            let v = levels[(y / 50 * 6 + x / 50) as usize];
+            Rgba([v, v, v, 255])
+        }))
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(0, u64::MAX), 64);
+    }
+
+    #[test]
+    fn test_same_image_same_hash() {
+        let data = blocks(1);
+        for hash in [compute_cover_phash, compute_cover_phash_fast] {
+            assert_eq!(hash(&data).unwrap(), hash(&data).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_different_images_far_apart() {
+        let images = [blocks(1), blocks(2), blocks(3)];
+
+        for hash in [compute_cover_phash, compute_cover_phash_fast] {
+            let hashes: Vec<u64> = images.iter().map(|d| hash(d).unwrap()).collect();
+            for i in 0..hashes.len() {
+                for j in i + 1..hashes.len() {
+                    assert!(
+                        hamming_distance(hashes[i], hashes[j]) > DIFFERENT_THRESHOLD,
+                        "images {} and {} too close",
+                        i,
+                        j
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_and_full_agree() {
+        // Both paths must reach the same same/different verdicts
+        for data in [blocks(1), blocks(2), blocks(3)] {
+            let full = compute_cover_phash(&data).unwrap();
+            let fast = compute_cover_phash_fast(&data).unwrap();
+            assert!(hamming_distance(full, fast) <= DIFFERENT_THRESHOLD);
+        }
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        assert!(compute_cover_phash(b"not an image").is_err());
+        assert!(compute_cover_phash_fast(b"not an image").is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Blurred low-resolution cover placeholders
+//!
+//! A host can show a placeholder straight away and swap in the sharp page
+//! once it is ready. The placeholder is built from a cover that is already
+//! decoded (a cached thumbnail or an `extract_cover` result): it is shrunk to
+//! a few dozen pixels, blurred there (where blurring is nearly free) and
+//! stretched back up. Nothing is decoded here, so the caller decides what
+//! that cover costs.
+
+use super::resizer::{resize_image, ResizeFilter};
+use crate::utils::error::{CbxError, Result};
+use image::RgbaImage;
+
+/// Longer side of the image that gets blurred
+const PLACEHOLDER_TINY_SIZE: u32 = 24;
+
+/// Gaussian blur sigma, in tiny-image pixels
+const PLACEHOLDER_BLUR_SIGMA: f32 = 0.8;
+
+/// Render a blurred placeholder for an already decoded `cover`
+///
+/// Unlike thumbnails, the placeholder always fills `size` on its longer side
+/// (upscaling small covers) so it occupies the same area as the final page.
+///
+/// # Arguments
+/// * `cover` - Decoded cover, typically a small thumbnail
+/// * `size` - Longer side of the placeholder in pixels
+///
+/// # Returns
+/// * `Ok(RgbaImage)` - Placeholder with the cover's aspect ratio
+/// * `Err(CbxError)` - Zero size or an empty cover
+pub fn cover_placeholder(cover: &RgbaImage, size: u32) -> Result<RgbaImage> {
+    if size == 0 {
+        return Err(CbxError::Image(
+            "Placeholder size must be greater than zero".to_string(),
+        ));
+    }
+
+    let (width, height) = cover.dimensions();
+    if width == 0 || height == 0 {
+        return Err(CbxError::Image("Placeholder cover is empty".to_string()));
+    }
+
+    let (tiny_width, tiny_height) = fit_longer_side(width, height, PLACEHOLDER_TINY_SIZE);
+    let tiny = resize_image(cover, tiny_width, tiny_height, ResizeFilter::Triangle)?;
+    let blurred = image::imageops::blur(&tiny, PLACEHOLDER_BLUR_SIGMA);
+
+    let (target_width, target_height) = fit_longer_side(width, height, size);
+    resize_image(
+        &blurred,
+        target_width,
+        target_height,
+        ResizeFilter::Triangle,
+    )
+}
+
+/// Scale `width` x `height` so the longer side is `side`, keeping the aspect
+/// ratio (at least 1x1)
+fn fit_longer_side(width: u32, height: u32, side: u32) -> (u32, u32) {
+    let longer = width.max(height).max(1) as f32;
+    let scale = side as f32 / longer;
+    let scaled = |value: u32| ((value as f32 * scale).round() as u32).clamp(1, side);
+    (scaled(width), scaled(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Black and white checkerboard with `cell`-pixel squares
+    fn checkerboard(width: u32, height: u32, cell: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let value = if (x / cell + y / cell) % 2 == 0 {
+                0
+            } else {
+                255
+            };
+            image::Rgba([value, value, value, 255])
+        })
+    }
+
+    #[test]
+    fn test_cover_placeholder_dimensions() {
+        let placeholder = cover_placeholder(&checkerboard(600, 900, 10), 512).unwrap();
+        assert_eq!(placeholder.dimensions(), (341, 512));
+
+        // Small covers are stretched to the requested size as well
+        let small = cover_placeholder(&checkerboard(20, 10, 2), 200).unwrap();
+        assert_eq!(small.dimensions(), (200, 100));
+    }
+
+    #[test]
+    fn test_cover_placeholder_is_blurred() {
+        // The sharp black/white edges are gone: every pixel is a mid grey
+        let placeholder = cover_placeholder(&checkerboard(240, 240, 10), 240).unwrap();
+        assert!(placeholder
+            .pixels()
+            .all(|pixel| (32..=224).contains(&pixel[0])));
+    }
+
+    #[test]
+    fn test_cover_placeholder_rejects_bad_input() {
+        assert!(cover_placeholder(&checkerboard(10, 10, 2), 0).is_err());
+        assert!(cover_placeholder(&RgbaImage::new(0, 0), 64).is_err());
+    }
+
+    #[test]
+    fn test_fit_longer_side() {
+        assert_eq!(fit_longer_side(600, 900, 24), (16, 24));
+        assert_eq!(fit_longer_side(900, 600, 24), (24, 16));
+        assert_eq!(fit_longer_side(1000, 1, 24), (24, 1));
+    }
+}
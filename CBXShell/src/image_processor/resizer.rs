@@ -5,8 +5,8 @@
 
 use crate::utils::error::CbxError;
 use fast_image_resize as fr;
-use fast_image_resize::images::Image;
-use image::RgbaImage;
+use fast_image_resize::images::{Image, ImageRef};
+use image::{GrayImage, RgbaImage};
 
 type Result<T> = std::result::Result<T, CbxError>;
 
@@ -22,12 +22,16 @@ pub enum ResizeFilter {
     /// Lanczos3 filtering (slower, highest quality)
     /// Best for photographic content where quality is critical
     Lanczos3,
+
+    /// GDI `StretchBlt` in HALFTONE mode, for exact parity with the C++ version
+    /// Windows only; elsewhere (and for grayscale resizes) this is `Triangle`
+    GdiHalftone,
 }
 
 impl From<ResizeFilter> for fr::FilterType {
     fn from(filter: ResizeFilter) -> Self {
         match filter {
-            ResizeFilter::Triangle => fr::FilterType::Bilinear,
+            ResizeFilter::Triangle | ResizeFilter::GdiHalftone => fr::FilterType::Bilinear,
             ResizeFilter::Lanczos3 => fr::FilterType::Lanczos3,
         }
     }
@@ -96,6 +100,52 @@ pub fn calculate_thumbnail_size(
     (new_width.max(1), new_height.max(1))
 }
 
+/// Largest factor a cover is enlarged by when upscaling is allowed
+///
+/// A gently enlarged cover beats a small one centered in a large tile, but
+/// icon-sized images blown up further only look blurry.
+pub const MAX_UPSCALE_FACTOR: f32 = 2.0;
+
+/// Calculate thumbnail dimensions, enlarging small sources up to `upscale_to`
+///
+/// Sources larger than the bounds are scaled down exactly like
+/// `calculate_thumbnail_size`. Smaller ones grow to fit the bounds, but the
+/// longer side never exceeds `upscale_to` and the size at most doubles
+/// (`MAX_UPSCALE_FACTOR`).
+///
+/// # Examples
+/// ```ignore
+/// // 600x900 page for a 1024px request, capped at 1024 -> 683x1024
+/// let (w, h) = calculate_upscaled_size(600, 900, 1024, 1024, 1024);
+/// assert_eq!((w, h), (683, 1024));
+/// ```
+pub fn calculate_upscaled_size(
+    src_width: u32,
+    src_height: u32,
+    max_width: u32,
+    max_height: u32,
+    upscale_to: u32,
+) -> (u32, u32) {
+    let (width, height) = calculate_thumbnail_size(src_width, src_height, max_width, max_height);
+    if (width, height) != (src_width, src_height) || src_width == 0 || src_height == 0 {
+        return (width, height);
+    }
+
+    let longer = src_width.max(src_height) as f32;
+    let scale = (max_width as f32 / src_width as f32)
+        .min(max_height as f32 / src_height as f32)
+        .min(upscale_to as f32 / longer)
+        .min(MAX_UPSCALE_FACTOR);
+    if scale <= 1.0 {
+        return (src_width, src_height);
+    }
+
+    // src * scale is at most each bound, so rounding can't overshoot them
+    let new_width = (src_width as f32 * scale).round() as u32;
+    let new_height = (src_height as f32 * scale).round() as u32;
+    (new_width, new_height)
+}
+
 /// Resize image to target dimensions using high-quality algorithm
 ///
 /// Uses fast_image_resize for efficient SIMD-optimized resizing.
@@ -135,17 +185,75 @@ pub fn resize_image(
         return Ok(source.clone());
     }
 
-    // Create source image view for fast_image_resize
-    let src_view = Image::from_vec_u8(
-        src_width,
-        src_height,
-        source.as_raw().to_vec(),
+    #[cfg(target_os = "windows")]
+    if filter == ResizeFilter::GdiHalftone {
+        return resize_with_gdi_halftone(source, target_width, target_height);
+    }
+
+    let pixels = resize_pixels(
+        source.as_raw(),
+        (src_width, src_height),
+        (target_width, target_height),
         fr::PixelType::U8x4,
-    )
-    .map_err(|e| CbxError::Image(format!("Failed to create source view: {}", e)))?;
+        filter,
+    )?;
+
+    // Convert back to RgbaImage
+    RgbaImage::from_raw(target_width, target_height, pixels)
+        .ok_or_else(|| CbxError::Image("Failed to create output image".to_string()))
+}
+
+/// Resize a single-channel grayscale image
+///
+/// Same as `resize_image`, but filtering a single luma channel cannot
+/// introduce chroma noise, so gray scans stay perfectly neutral.
+pub fn resize_gray(
+    source: &GrayImage,
+    target_width: u32,
+    target_height: u32,
+    filter: ResizeFilter,
+) -> Result<GrayImage> {
+    let (src_width, src_height) = source.dimensions();
+
+    if target_width == 0 || target_height == 0 {
+        return Err(CbxError::Image(
+            "Target dimensions must be greater than zero".to_string(),
+        ));
+    }
+
+    if src_width == target_width && src_height == target_height {
+        return Ok(source.clone());
+    }
+
+    let pixels = resize_pixels(
+        source.as_raw(),
+        (src_width, src_height),
+        (target_width, target_height),
+        fr::PixelType::U8,
+        filter,
+    )?;
+
+    GrayImage::from_raw(target_width, target_height, pixels)
+        .ok_or_else(|| CbxError::Image("Failed to create output image".to_string()))
+}
+
+/// Run fast_image_resize over a raw pixel buffer of the given pixel type
+///
+/// The source is borrowed, not copied: thumbnails are often made from
+/// multi-megapixel pages, and a second full-size buffer is pure overhead.
+fn resize_pixels(
+    pixels: &[u8],
+    (src_width, src_height): (u32, u32),
+    (target_width, target_height): (u32, u32),
+    pixel_type: fr::PixelType,
+    filter: ResizeFilter,
+) -> Result<Vec<u8>> {
+    // Create source image view for fast_image_resize
+    let src_view = ImageRef::new(src_width, src_height, pixels, pixel_type)
+        .map_err(|e| CbxError::Image(format!("Failed to create source view: {}", e)))?;
 
     // Create destination image buffer
-    let mut dst_image = Image::new(target_width, target_height, fr::PixelType::U8x4);
+    let mut dst_image = Image::new(target_width, target_height, pixel_type);
 
     // Create resizer with selected algorithm
     let mut resizer = fr::Resizer::new();
@@ -159,9 +267,153 @@ pub fn resize_image(
         )
         .map_err(|e| CbxError::Image(format!("Resize operation failed: {}", e)))?;
 
-    // Convert back to RgbaImage
-    RgbaImage::from_raw(target_width, target_height, dst_image.into_vec())
-        .ok_or_else(|| CbxError::Image("Failed to create output image".to_string()))
+    Ok(dst_image.into_vec())
+}
+
+/// Resize with GDI `StretchBlt` in HALFTONE mode (the C++ resize path)
+///
+/// HALFTONE leaves the alpha channel undefined, so color comes from GDI and
+/// alpha from a Bilinear resize of the source.
+#[cfg(target_os = "windows")]
+fn resize_with_gdi_halftone(
+    source: &RgbaImage,
+    target_width: u32,
+    target_height: u32,
+) -> Result<RgbaImage> {
+    use windows::Win32::Graphics::Gdi::DeleteObject;
+
+    let (src_width, src_height) = source.dimensions();
+    let mut resized = resize_image(source, target_width, target_height, ResizeFilter::Triangle)?;
+
+    let src_bitmap =
+        super::hbitmap::create_hbitmap_from_rgba(source.as_raw(), src_width, src_height)?;
+    let blank = vec![0u8; resized.as_raw().len()];
+    let dst_bitmap =
+        match super::hbitmap::create_hbitmap_from_bgra(&blank, target_width, target_height) {
+            Ok(bitmap) => bitmap,
+            Err(e) => {
+                // UNAVOIDABLE UNSAFE: GDI handle cleanup (gdi32.dll)
+                unsafe {
+                    let _ = DeleteObject(src_bitmap);
+                }
+                return Err(e);
+            }
+        };
+
+    // UNAVOIDABLE UNSAFE: GDI device contexts and blits (gdi32.dll)
+    // Safety: both bitmaps are valid top-down 32bpp DIB sections of the given
+    // sizes, and are deleted exactly once below
+    let bgra = unsafe {
+        let result = stretch_blt_halftone(
+            src_bitmap,
+            (src_width, src_height),
+            dst_bitmap,
+            (target_width, target_height),
+        );
+        let _ = DeleteObject(src_bitmap);
+        let _ = DeleteObject(dst_bitmap);
+        result?
+    };
+
+    for (pixel, bgra) in resized.pixels_mut().zip(bgra.chunks_exact(4)) {
+        pixel[0] = bgra[2];
+        pixel[1] = bgra[1];
+        pixel[2] = bgra[0];
+    }
+
+    Ok(resized)
+}
+
+/// StretchBlt `src` onto `dst` in HALFTONE mode and read back `dst` as BGRA
+///
+/// # Safety
+/// `src` and `dst` must be valid DIB sections of the given sizes that are not
+/// selected into any device context.
+#[cfg(target_os = "windows")]
+unsafe fn stretch_blt_halftone(
+    src: windows::Win32::Graphics::Gdi::HBITMAP,
+    (src_width, src_height): (u32, u32),
+    dst: windows::Win32::Graphics::Gdi::HBITMAP,
+    (target_width, target_height): (u32, u32),
+) -> Result<Vec<u8>> {
+    use windows::Win32::Graphics::Gdi::*;
+
+    let src_dc = CreateCompatibleDC(None);
+    let dst_dc = CreateCompatibleDC(None);
+    if src_dc.is_invalid() || dst_dc.is_invalid() {
+        let _ = DeleteDC(src_dc);
+        let _ = DeleteDC(dst_dc);
+        return Err(CbxError::Image(
+            "CreateCompatibleDC failed for HALFTONE resize".to_string(),
+        ));
+    }
+
+    let old_src = SelectObject(src_dc, src);
+    let old_dst = SelectObject(dst_dc, dst);
+
+    // Same sequence as the C++ version: the brush origin must be reset
+    // after switching to HALFTONE
+    SetStretchBltMode(dst_dc, HALFTONE);
+    let _ = SetBrushOrgEx(dst_dc, 0, 0, None);
+    let blitted = StretchBlt(
+        dst_dc,
+        0,
+        0,
+        target_width as i32,
+        target_height as i32,
+        src_dc,
+        0,
+        0,
+        src_width as i32,
+        src_height as i32,
+        SRCCOPY,
+    )
+    .as_bool();
+
+    SelectObject(src_dc, old_src);
+    SelectObject(dst_dc, old_dst);
+
+    let mut bgra = vec![0u8; (target_width * target_height * 4) as usize];
+    let mut bmi = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: target_width as i32,
+            biHeight: -(target_height as i32), // Top-down, like our DIB sections
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let lines = if blitted {
+        GetDIBits(
+            dst_dc,
+            dst,
+            0,
+            target_height,
+            Some(bgra.as_mut_ptr().cast()),
+            &mut bmi,
+            DIB_RGB_COLORS,
+        )
+    } else {
+        0
+    };
+
+    let _ = DeleteDC(src_dc);
+    let _ = DeleteDC(dst_dc);
+
+    if !blitted {
+        return Err(CbxError::Image("StretchBlt (HALFTONE) failed".to_string()));
+    }
+    if lines != target_height as i32 {
+        return Err(CbxError::Image(format!(
+            "GetDIBits returned {} of {} lines",
+            lines, target_height
+        )));
+    }
+
+    Ok(bgra)
 }
 
 #[cfg(test)]
@@ -216,6 +468,36 @@ mod tests {
         assert_eq!(h, 75);
     }
 
+    #[test]
+    fn test_upscaled_size() {
+        // Large request, slightly smaller source: grows to the bounds
+        assert_eq!(
+            calculate_upscaled_size(600, 900, 1024, 1024, 1024),
+            (683, 1024)
+        );
+        // Capped below the request
+        assert_eq!(
+            calculate_upscaled_size(600, 900, 1024, 1024, 960),
+            (640, 960)
+        );
+        // Never more than MAX_UPSCALE_FACTOR
+        assert_eq!(
+            calculate_upscaled_size(100, 150, 1024, 1024, 1024),
+            (200, 300)
+        );
+        // A cap below the source means no upscaling
+        assert_eq!(
+            calculate_upscaled_size(600, 900, 1024, 1024, 512),
+            (600, 900)
+        );
+        // Sources larger than the bounds still scale down as usual
+        assert_eq!(
+            calculate_upscaled_size(2000, 3000, 256, 256, 1024),
+            calculate_thumbnail_size(2000, 3000, 256, 256)
+        );
+        assert_eq!(calculate_upscaled_size(0, 0, 256, 256, 1024), (0, 0));
+    }
+
     #[test]
     fn test_exact_fit() {
         // Image exactly matches max size
@@ -243,6 +525,13 @@ mod tests {
         assert_eq!(h, 6); // 256 * (100/4000) = 6.4 rounded to 6
     }
 
+    #[test]
+    fn test_very_tall_image_keeps_one_pixel() {
+        // 256 * (1/5000) rounds to 0; both sides are clamped to at least 1px
+        assert_eq!(calculate_thumbnail_size(1, 5000, 256, 256), (1, 256));
+        assert_eq!(calculate_thumbnail_size(5000, 1, 256, 256), (256, 1));
+    }
+
     #[test]
     fn test_resize_image_downscale() {
         // Create a simple 4x4 red image
@@ -305,6 +594,32 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_resize_borrowed_source_matches_expected() {
+        // Left half black, right half white: averages stay exact away from the edge
+        let source = RgbaImage::from_fn(400, 200, |x, _| {
+            if x < 200 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let before = source.clone();
+
+        let resized = resize_image(&source, 40, 20, ResizeFilter::Triangle).unwrap();
+
+        assert_eq!(source, before, "source must not be modified");
+        assert_eq!(*resized.get_pixel(2, 10), Rgba([0, 0, 0, 255]));
+        assert_eq!(*resized.get_pixel(37, 10), Rgba([255, 255, 255, 255]));
+
+        let gray = GrayImage::from_fn(400, 200, |x, _| {
+            image::Luma([if x < 200 { 0 } else { 255 }])
+        });
+        let resized = resize_gray(&gray, 40, 20, ResizeFilter::Triangle).unwrap();
+        assert_eq!(resized.get_pixel(2, 10).0, [0]);
+        assert_eq!(resized.get_pixel(37, 10).0, [255]);
+    }
+
     #[test]
     fn test_resize_large_to_small() {
         // Create a large gradient image
@@ -323,4 +638,19 @@ mod tests {
         assert_eq!(resized.width(), 200);
         assert_eq!(resized.height(), 160);
     }
+
+    #[test]
+    fn test_resize_gdi_halftone_dimensions() {
+        let mut source = RgbaImage::from_pixel(64, 48, Rgba([30, 120, 220, 255]));
+        source.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+
+        let resized = resize_image(&source, 16, 12, ResizeFilter::GdiHalftone).unwrap();
+
+        assert_eq!(resized.dimensions(), (16, 12));
+        // Solid interior keeps its color and stays opaque
+        let pixel = resized.get_pixel(8, 6);
+        for (channel, expected) in pixel.0.iter().zip([30u8, 120, 220, 255]) {
+            assert!(channel.abs_diff(expected) <= 2, "{:?}", pixel);
+        }
+    }
 }
@@ -1,29 +1,64 @@
 //! Thumbnail generation pipeline
 //!
 //! This module orchestrates the complete thumbnail generation process:
-//! 1. Decode image from raw bytes
+//! 1. Decode image from raw bytes, optionally keeping one half of a
+//!    double-page spread
 //! 2. Calculate target thumbnail size (aspect ratio preserved)
 //! 3. Resize image using high-quality algorithm
-//! 4. Apply white background for transparent images (C++ behavior)
+//! 4. Apply white background for transparent images (C++ behavior),
+//!    optionally rounding the corners and framing the cover like a book
 //! 5. Convert RGBA to BGRA format
 //! 6. Create Windows HBITMAP
 //!
 //! This matches the C++ implementation in cbxArchive.h:628-666 (OnExtract).
 
 use crate::utils::error::CbxError;
-use image::{GenericImageView, RgbaImage};
+use image::{DynamicImage, GenericImageView, GrayImage, RgbaImage};
 use windows::Win32::Graphics::Gdi::HBITMAP;
 
-use super::decoder;
+use super::decoder::{self, DecodeOptions};
 use super::hbitmap;
-use super::resizer::{self, ResizeFilter};
+use super::resizer;
+pub use super::resizer::ResizeFilter;
 
 type Result<T> = std::result::Result<T, CbxError>;
 
+/// Thumbnail size used when a caller passes 0 (Windows default thumbnail size)
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+
+/// Width/height ratio above which a cover is treated as a double-page spread
+pub const SPREAD_ASPECT_RATIO: f32 = 1.8;
+
+/// Long side / short side ratio above which an image is rejected as a cover
+///
+/// Strips like 1x5000 would otherwise shrink to a 1-pixel-wide sliver.
+pub const MAX_ASPECT_RATIO: u32 = 100;
+
+/// Offset of the book effect's drop shadow, and the canvas margin reserved for it
+pub const BOOK_SHADOW_OFFSET: u32 = 4;
+
+/// Peak opacity of the book effect's drop shadow
+const BOOK_SHADOW_ALPHA: u8 = 96;
+
+/// Book effect border, blended over the cover's outermost pixels
+const BOOK_BORDER_COLOR: (u8, u8, u8, u8) = (0, 0, 0, 112);
+
+/// Half of a double-page spread to keep as the cover
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadHalf {
+    /// Left page (front cover of right-to-left books such as manga)
+    Left,
+    /// Right page (front cover of left-to-right books)
+    Right,
+}
+
 /// Thumbnail generation configuration
 ///
 /// Controls all aspects of thumbnail creation including size limits,
 /// background color, and resize algorithm quality.
+///
+/// A `max_width` or `max_height` of 0 means `DEFAULT_THUMBNAIL_SIZE`, matching
+/// how Explorer's `cx == 0` is handled.
 #[derive(Debug, Clone)]
 pub struct ThumbnailConfig {
     /// Maximum thumbnail width in pixels
@@ -39,6 +74,54 @@ pub struct ThumbnailConfig {
     /// Resize algorithm to use
     /// Default: Triangle (matches C++ HALFTONE mode)
     pub resize_filter: ResizeFilter,
+
+    /// Resize grayscale sources (e.g. black-and-white scans) in a single
+    /// luma channel so the thumbnail keeps a clean, chroma-free gray ramp
+    /// Default: false
+    pub grayscale: bool,
+
+    /// Keep transparency instead of compositing onto `background_color`
+    ///
+    /// The bitmap must then be reported to Explorer as `WTSAT_ARGB`.
+    /// Default: false
+    pub preserve_alpha: bool,
+
+    /// Radius of anti-aliased rounded corners in pixels (0 = square corners)
+    ///
+    /// Corners are cut transparent with `preserve_alpha`, otherwise filled
+    /// with `background_color`. Default: 0
+    pub corner_radius: u32,
+
+    /// Alpha at or above which a pixel is treated as fully opaque
+    ///
+    /// Images with slight alpha noise (e.g. 254 everywhere) would otherwise
+    /// be blended with `background_color`, shifting their colors.
+    /// Default: 255 (only alpha 255 is opaque)
+    pub opaque_threshold: u8,
+
+    /// Keep only one half of covers wider than `SPREAD_ASPECT_RATIO`
+    ///
+    /// Double-page spreads look tiny when fit to a square; cropping to one
+    /// page lets the portrait cover fill the frame. Default: None
+    pub spread_crop: Option<SpreadHalf>,
+
+    /// Frame the cover like a physical book: a 1px border plus a soft drop
+    /// shadow in a `BOOK_SHADOW_OFFSET` margin on the right and bottom
+    ///
+    /// The margin is transparent, so the bitmap must be reported to Explorer
+    /// as `WTSAT_ARGB`. Default: false
+    pub book_effect: bool,
+
+    /// Enlarge covers smaller than the requested size, up to this many
+    /// pixels on the longer side
+    ///
+    /// Upscaling always uses Lanczos3 and at most doubles the size (see
+    /// `resizer::calculate_upscaled_size`). Default: None (never upscale)
+    pub allow_upscale_to: Option<u32>,
+
+    /// Decode watchdog and leniency for step 1
+    /// Default: `DecodeOptions::default()` (5 s watchdog, strict)
+    pub decode: DecodeOptions,
 }
 
 impl Default for ThumbnailConfig {
@@ -49,14 +132,36 @@ impl Default for ThumbnailConfig {
     /// - Filter: Triangle/Bilinear (matches HALFTONE)
     fn default() -> Self {
         Self {
-            max_width: 256,
-            max_height: 256,
+            max_width: DEFAULT_THUMBNAIL_SIZE,
+            max_height: DEFAULT_THUMBNAIL_SIZE,
             background_color: (255, 255, 255, 255), // White background
             resize_filter: ResizeFilter::Triangle,  // Match C++ HALFTONE
+            grayscale: false,
+            preserve_alpha: false,
+            corner_radius: 0,
+            opaque_threshold: 255,
+            spread_crop: None,
+            book_effect: false,
+            allow_upscale_to: None,
+            decode: DecodeOptions::default(),
         }
     }
 }
 
+impl ThumbnailConfig {
+    /// Size bounds with 0 replaced by `DEFAULT_THUMBNAIL_SIZE`
+    pub fn effective_bounds(&self) -> (u32, u32) {
+        let or_default = |size: u32| {
+            if size == 0 {
+                DEFAULT_THUMBNAIL_SIZE
+            } else {
+                size
+            }
+        };
+        (or_default(self.max_width), or_default(self.max_height))
+    }
+}
+
 /// Create thumbnail HBITMAP from image data
 ///
 /// This is the main entry point for thumbnail generation. It orchestrates
@@ -103,22 +208,59 @@ impl Default for ThumbnailConfig {
 /// // Remember to DeleteObject(hbitmap) when done
 /// ```
 pub fn create_thumbnail(image_data: &[u8], config: ThumbnailConfig) -> Result<HBITMAP> {
+    let rgba = render_thumbnail(image_data, &config)?;
+    rgba_to_hbitmap(&rgba)
+}
+
+/// Wrap rendered thumbnail pixels in a Windows HBITMAP (steps 6-7)
+///
+/// For callers that render with `render_thumbnail` first and only then decide
+/// how to hand the pixels to the shell.
+pub fn rgba_to_hbitmap(rgba: &RgbaImage) -> Result<HBITMAP> {
+    let (width, height) = rgba.dimensions();
+
+    // Step 6: Convert RGBA to BGRA (Windows format)
+    let bgra = hbitmap::rgba_to_bgra(rgba.as_raw());
+
+    // Step 7: Create Windows HBITMAP
+    hbitmap::create_hbitmap_from_bgra(&bgra, width, height)
+}
+
+/// Render a thumbnail to an opaque RGBA image
+///
+/// Runs steps 1-5 of the pipeline (decode, size, resize, background) without
+/// touching GDI, so it can be used off the shell thread or on non-Windows hosts.
+///
+/// # Arguments
+/// * `image_data` - Raw image file bytes (any supported format)
+/// * `config` - Thumbnail generation configuration
+///
+/// # Returns
+/// * `Ok(RgbaImage)` - Thumbnail pixels with the background applied
+/// * `Err(CbxError)` - Failed to decode or resize the image
+pub fn render_thumbnail(image_data: &[u8], config: &ThumbnailConfig) -> Result<RgbaImage> {
+    let img = decode_cover(image_data, &config.decode)?;
+    render_decoded(img, config)
+}
+
+/// Decode raw image bytes (step 1)
+///
+/// Split from `render_thumbnail` so a decoded cover can be kept and rendered
+/// again at another size with `render_decoded`.
+pub fn decode_cover(image_data: &[u8], options: &DecodeOptions) -> Result<DynamicImage> {
     // Step 1: Decode image from bytes
-    crate::utils::debug_log::debug_log(&format!(
-        "Decoding image from {} bytes...",
-        image_data.len()
-    ));
-    let img = match decoder::decode_image(image_data) {
+    crate::debug_log!("Decoding image from {} bytes...", image_data.len());
+    let img = match decoder::decode_image_with(image_data, options) {
         Ok(img) => {
-            crate::utils::debug_log::debug_log(&format!(
+            crate::debug_log!(
                 "Image decoded successfully: {}x{}",
                 img.width(),
                 img.height()
-            ));
+            );
             img
         }
         Err(e) => {
-            crate::utils::debug_log::debug_log(&format!("ERROR: Image decoding failed: {}", e));
+            crate::debug_log!("ERROR: Image decoding failed: {}", e);
             // Try to detect format from magic bytes for better error message
             let format_hint = if image_data.len() >= 4 {
                 match &image_data[0..4] {
@@ -136,19 +278,53 @@ pub fn create_thumbnail(image_data: &[u8], config: ThumbnailConfig) -> Result<HB
             } else {
                 "Too small"
             };
-            crate::utils::debug_log::debug_log(&format!("Detected format: {}", format_hint));
+            crate::debug_log!("Detected format: {}", format_hint);
             return Err(e);
         }
     };
+    Ok(img)
+}
+
+/// Render an already decoded image (steps 1b-5b)
+///
+/// # Arguments
+/// * `img` - Decoded source image
+/// * `config` - Thumbnail generation configuration
+///
+/// # Returns
+/// * `Ok(RgbaImage)` - Thumbnail pixels with the background applied
+/// * `Err(CbxError)` - Unsupported dimensions or resize failure
+pub fn render_decoded(img: DynamicImage, config: &ThumbnailConfig) -> Result<RgbaImage> {
+    // Step 1b: Keep one page of a double-page spread
+    let img = match config
+        .spread_crop
+        .and_then(|half| spread_crop_rect(img.width(), img.height(), half))
+    {
+        Some((x, width)) => img.crop_imm(x, 0, width, img.height()),
+        None => img,
+    };
 
     // Step 2: Calculate target thumbnail size
     let (src_width, src_height) = img.dimensions();
-    let (target_width, target_height) = resizer::calculate_thumbnail_size(
-        src_width,
-        src_height,
-        config.max_width,
-        config.max_height,
-    );
+    check_aspect_ratio(src_width, src_height)?;
+    let (mut max_width, mut max_height) = config.effective_bounds();
+    if config.book_effect {
+        // Leave room for the shadow so the framed cover still fits
+        max_width = max_width.saturating_sub(BOOK_SHADOW_OFFSET).max(1);
+        max_height = max_height.saturating_sub(BOOK_SHADOW_OFFSET).max(1);
+    }
+    let (target_width, target_height) = match config.allow_upscale_to {
+        Some(upscale_to) => resizer::calculate_upscaled_size(
+            src_width, src_height, max_width, max_height, upscale_to,
+        ),
+        None => resizer::calculate_thumbnail_size(src_width, src_height, max_width, max_height),
+    };
+    // Bilinear (and HALFTONE) enlargements look soft; Lanczos3 keeps edges
+    let resize_filter = if target_width > src_width {
+        ResizeFilter::Lanczos3
+    } else {
+        config.resize_filter
+    };
 
     // Handle edge case: zero dimensions
     if target_width == 0 || target_height == 0 {
@@ -157,24 +333,221 @@ pub fn create_thumbnail(image_data: &[u8], config: ThumbnailConfig) -> Result<HB
         ));
     }
 
-    // Step 3: Convert to RGBA format
-    let mut rgba = img.to_rgba8();
+    // Grayscale sources: resize the luma channel alone, then expand
+    let gray = if config.grayscale {
+        opaque_grayscale(&img)
+    } else {
+        None
+    };
+
+    let mut rgba = match gray {
+        Some(luma) => {
+            let luma = if (target_width, target_height) != (src_width, src_height) {
+                resizer::resize_gray(&luma, target_width, target_height, resize_filter)?
+            } else {
+                luma
+            };
+            gray_to_rgba(&luma)
+        }
+        None => {
+            // Step 3: Convert to RGBA format (moves the buffer if already RGBA8)
+            let mut rgba = img.into_rgba8();
+
+            // Step 4: Resize if dimensions changed
+            if (target_width, target_height) != (src_width, src_height) {
+                rgba = resizer::resize_image(&rgba, target_width, target_height, resize_filter)?;
+            }
 
-    // Step 4: Resize if dimensions changed
-    if (target_width, target_height) != (src_width, src_height) {
-        rgba = resizer::resize_image(&rgba, target_width, target_height, config.resize_filter)?;
+            // Step 5: Apply white background for transparency (C++ behavior)
+            // This matches the C++ code which fills the background with white (RGB 255,255,255)
+            // before drawing the image
+            if !config.preserve_alpha {
+                apply_background(&mut rgba, config.background_color, config.opaque_threshold);
+            }
+            rgba
+        }
+    };
+
+    // Step 5b: Optional rounded corners
+    if config.corner_radius > 0 {
+        let fill = (!config.preserve_alpha).then_some(config.background_color);
+        round_corners(&mut rgba, config.corner_radius, fill);
     }
 
-    // Step 5: Apply white background for transparency (C++ behavior)
-    // This matches the C++ code which fills the background with white (RGB 255,255,255)
-    // before drawing the image
-    apply_background(&mut rgba, config.background_color);
+    // Step 5c: Optional book frame
+    if config.book_effect {
+        rgba = book_frame(&rgba);
+    }
 
-    // Step 6: Convert RGBA to BGRA (Windows format)
-    let bgra = hbitmap::rgba_to_bgra(rgba.as_raw());
+    Ok(rgba)
+}
 
-    // Step 7: Create Windows HBITMAP
-    hbitmap::create_hbitmap_from_bgra(&bgra, target_width, target_height)
+/// Draw a cover onto a transparent canvas with a border and drop shadow
+///
+/// The canvas is `BOOK_SHADOW_OFFSET` larger in both directions. The shadow
+/// is the cover's rectangle shifted by the offset, fading out toward its
+/// edges; the cover is composited over it and outlined with
+/// `BOOK_BORDER_COLOR`.
+fn book_frame(cover: &RgbaImage) -> RgbaImage {
+    let (width, height) = cover.dimensions();
+    let offset = BOOK_SHADOW_OFFSET;
+    let mut canvas = RgbaImage::new(width + offset, height + offset);
+
+    // Shadow: opacity ramps up over `offset` pixels from each edge
+    for y in offset..height + offset {
+        for x in offset..width + offset {
+            let edge = (x - offset)
+                .min(y - offset)
+                .min(width + offset - 1 - x)
+                .min(height + offset - 1 - y)
+                + 1;
+            let alpha = BOOK_SHADOW_ALPHA as u32 * edge.min(offset) / offset;
+            canvas.put_pixel(x, y, image::Rgba([0, 0, 0, alpha as u8]));
+        }
+    }
+
+    for (x, y, pixel) in cover.enumerate_pixels() {
+        let mut pixel = *pixel;
+        if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+            let (r, g, b, a) = BOOK_BORDER_COLOR;
+            pixel = blend_over(image::Rgba([r, g, b, a]), pixel);
+        }
+        let below = *canvas.get_pixel(x, y);
+        canvas.put_pixel(x, y, blend_over(pixel, below));
+    }
+
+    canvas
+}
+
+/// Porter-Duff "over": `top` composited onto `bottom` (straight alpha)
+fn blend_over(top: image::Rgba<u8>, bottom: image::Rgba<u8>) -> image::Rgba<u8> {
+    let top_alpha = top[3] as f32 / 255.0;
+    let bottom_alpha = bottom[3] as f32 / 255.0 * (1.0 - top_alpha);
+    let alpha = top_alpha + bottom_alpha;
+    if alpha <= 0.0 {
+        return image::Rgba([0, 0, 0, 0]);
+    }
+
+    let mut out = [0u8; 4];
+    for channel in 0..3 {
+        let value =
+            (top[channel] as f32 * top_alpha + bottom[channel] as f32 * bottom_alpha) / alpha;
+        out[channel] = value.round() as u8;
+    }
+    out[3] = (alpha * 255.0).round() as u8;
+    image::Rgba(out)
+}
+
+/// Mask the corners of an image with anti-aliased quarter circles
+///
+/// Each corner pixel is weighted by how much of it lies inside the circle.
+/// With `fill`, the outside part is blended toward that color and the pixel
+/// stays opaque; without, the pixel's alpha is scaled down instead.
+///
+/// The radius is clamped to half the shorter side.
+fn round_corners(rgba: &mut RgbaImage, radius: u32, fill: Option<(u8, u8, u8, u8)>) {
+    let (width, height) = rgba.dimensions();
+    let radius = radius.min(width / 2).min(height / 2);
+    if radius == 0 {
+        return;
+    }
+    let r = radius as f32;
+
+    for y in (0..radius).chain(height - radius..height) {
+        for x in (0..radius).chain(width - radius..width) {
+            // Distance from the pixel center to the corner circle's center
+            let dx = if x < radius {
+                r - (x as f32 + 0.5)
+            } else {
+                (x as f32 + 0.5) - (width - radius) as f32
+            };
+            let dy = if y < radius {
+                r - (y as f32 + 0.5)
+            } else {
+                (y as f32 + 0.5) - (height - radius) as f32
+            };
+            let coverage = (r - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+            if coverage >= 1.0 {
+                continue;
+            }
+
+            let pixel = rgba.get_pixel_mut(x, y);
+            match fill {
+                Some(bg) => {
+                    for (channel, bg) in pixel.0.iter_mut().zip([bg.0, bg.1, bg.2]) {
+                        *channel =
+                            (*channel as f32 * coverage + bg as f32 * (1.0 - coverage)) as u8;
+                    }
+                }
+                None => pixel[3] = (pixel[3] as f32 * coverage) as u8,
+            }
+        }
+    }
+}
+
+/// Reject images with a zero dimension or beyond `MAX_ASPECT_RATIO`
+fn check_aspect_ratio(width: u32, height: u32) -> Result<()> {
+    let (long, short) = (width.max(height), width.min(height));
+    if short == 0 {
+        return Err(CbxError::Image(format!(
+            "Invalid image dimensions ({}x{})",
+            width, height
+        )));
+    }
+    if u64::from(long) > u64::from(short) * u64::from(MAX_ASPECT_RATIO) {
+        return Err(CbxError::Image(format!(
+            "Image aspect ratio too extreme for a thumbnail ({}x{}, limit {}:1)",
+            width, height, MAX_ASPECT_RATIO
+        )));
+    }
+    Ok(())
+}
+
+/// Return the luma of an opaque grayscale image, `None` for anything else
+///
+/// Gray color types qualify directly. RGB images qualify when every pixel has
+/// R == G == B, which is how many scanners save black-and-white pages.
+/// Images with alpha keep the RGBA path so the background is composited.
+fn opaque_grayscale(img: &DynamicImage) -> Option<GrayImage> {
+    if img.color().has_alpha() {
+        return None;
+    }
+    if !img.color().has_color() {
+        return Some(img.to_luma8());
+    }
+
+    let rgb = img.to_rgb8();
+    if !rgb.pixels().all(|p| p[0] == p[1] && p[1] == p[2]) {
+        return None;
+    }
+    GrayImage::from_raw(
+        rgb.width(),
+        rgb.height(),
+        rgb.pixels().map(|p| p[0]).collect(),
+    )
+}
+
+/// Expand a luma image to opaque RGBA with R == G == B
+fn gray_to_rgba(luma: &GrayImage) -> RgbaImage {
+    RgbaImage::from_fn(luma.width(), luma.height(), |x, y| {
+        let v = luma.get_pixel(x, y)[0];
+        image::Rgba([v, v, v, 255])
+    })
+}
+
+/// Horizontal crop `(x, width)` keeping one half of a spread
+///
+/// Returns `None` when the image is not wider than `SPREAD_ASPECT_RATIO`.
+fn spread_crop_rect(width: u32, height: u32, half: SpreadHalf) -> Option<(u32, u32)> {
+    if height == 0 || (width as f32 / height as f32) <= SPREAD_ASPECT_RATIO {
+        return None;
+    }
+
+    let left_width = width / 2;
+    match half {
+        SpreadHalf::Left => Some((0, left_width)),
+        SpreadHalf::Right => Some((left_width, width - left_width)),
+    }
 }
 
 /// Apply background color to transparent areas
@@ -193,6 +566,7 @@ pub fn create_thumbnail(image_data: &[u8], config: ThumbnailConfig) -> Result<HB
 /// # Arguments
 /// * `rgba` - Image to modify (in-place)
 /// * `bg` - Background color (R, G, B, A)
+/// * `opaque_threshold` - Pixels with alpha at or above this are not blended
 ///
 /// # C++ Equivalent (cbxArchive.h:658-662)
 /// ```cpp
@@ -200,11 +574,11 @@ pub fn create_thumbnail(image_data: &[u8], config: ThumbnailConfig) -> Result<HB
 /// FillRect(hdcDest, &rcDest, hBrush);
 /// DeleteObject(hBrush);
 /// ```
-fn apply_background(rgba: &mut RgbaImage, bg: (u8, u8, u8, u8)) {
+pub(super) fn apply_background(rgba: &mut RgbaImage, bg: (u8, u8, u8, u8), opaque_threshold: u8) {
     for pixel in rgba.pixels_mut() {
         let alpha = pixel[3] as f32 / 255.0;
 
-        if alpha < 1.0 {
+        if pixel[3] < opaque_threshold {
             // Blend with background using alpha compositing
             pixel[0] = ((pixel[0] as f32 * alpha) + (bg.0 as f32 * (1.0 - alpha))) as u8;
             pixel[1] = ((pixel[1] as f32 * alpha) + (bg.1 as f32 * (1.0 - alpha))) as u8;
@@ -216,14 +590,32 @@ fn apply_background(rgba: &mut RgbaImage, bg: (u8, u8, u8, u8)) {
     }
 }
 
+/// Convert premultiplied RGBA pixels to straight alpha in place
+///
+/// `apply_background` expects straight alpha; premultiplied color would be
+/// scaled by alpha twice, darkening anti-aliased edges into halos. Fully
+/// transparent pixels carry no color and are left as they are.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))] // Only WIC yields premultiplied pixels
+pub(super) fn unpremultiply_alpha(rgba: &mut RgbaImage) {
+    for pixel in rgba.pixels_mut() {
+        let alpha = pixel[3] as u32;
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+        for channel in &mut pixel.0[..3] {
+            *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+}
+
 /// Create thumbnail with custom dimensions
 ///
 /// Convenience function for quick thumbnail creation with custom size.
 ///
 /// # Arguments
 /// * `image_data` - Raw image file bytes
-/// * `max_width` - Maximum thumbnail width
-/// * `max_height` - Maximum thumbnail height
+/// * `max_width` - Maximum thumbnail width (0 = `DEFAULT_THUMBNAIL_SIZE`)
+/// * `max_height` - Maximum thumbnail height (0 = `DEFAULT_THUMBNAIL_SIZE`)
 ///
 /// # Returns
 /// * `Ok(HBITMAP)` - Successfully created thumbnail
@@ -310,6 +702,178 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_create_thumbnail_with_size_zero_uses_default() {
+        let result = create_thumbnail_with_size(MINIMAL_JPEG, 0, 0);
+        assert!(
+            result.is_ok(),
+            "0x0 should mean default size: {:?}",
+            result.err()
+        );
+
+        if let Ok(hbitmap) = result {
+            unsafe {
+                DeleteObject(hbitmap);
+            }
+        }
+    }
+
+    /// Encode a solid PNG of the given size
+    fn solid_png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_pixel(width, height, Rgba([10, 20, 30, 255]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        png.into_inner()
+    }
+
+    #[test]
+    fn test_render_thumbnail_rejects_extreme_aspect_ratio() {
+        for (width, height) in [(1, 5000), (5000, 1)] {
+            let err = render_thumbnail(&solid_png(width, height), &ThumbnailConfig::default())
+                .unwrap_err();
+            assert!(
+                matches!(&err, CbxError::Image(msg) if msg.contains("aspect ratio")),
+                "{}x{}: {}",
+                width,
+                height,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_thumbnail_thin_strip_is_not_degenerate() {
+        // 3x300 is exactly at the limit; the short side rounds up to 3px
+        let thumb = render_thumbnail(&solid_png(3, 300), &ThumbnailConfig::default()).unwrap();
+        assert_eq!(thumb.dimensions(), (3, 256));
+
+        let thumb = render_thumbnail(&solid_png(300, 3), &ThumbnailConfig::default()).unwrap();
+        assert_eq!(thumb.dimensions(), (256, 3));
+    }
+
+    #[test]
+    fn test_spread_crop_keeps_requested_half() {
+        let img = RgbaImage::from_fn(2000, 1000, |x, _| {
+            if x < 1000 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+        let mut png = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        let png = png.into_inner();
+
+        for (half, expected) in [
+            (SpreadHalf::Left, Rgba([255, 0, 0, 255])),
+            (SpreadHalf::Right, Rgba([0, 0, 255, 255])),
+        ] {
+            let config = ThumbnailConfig {
+                spread_crop: Some(half),
+                ..Default::default()
+            };
+            let thumb = render_thumbnail(&png, &config).unwrap();
+
+            assert_eq!(thumb.dimensions(), (256, 256));
+            assert!(thumb.pixels().all(|p| *p == expected), "{:?}", half);
+        }
+
+        // Without the option the whole spread is kept
+        let thumb = render_thumbnail(&png, &ThumbnailConfig::default()).unwrap();
+        assert_eq!(thumb.dimensions(), (256, 128));
+    }
+
+    #[test]
+    fn test_spread_crop_rect() {
+        assert_eq!(
+            spread_crop_rect(2000, 1000, SpreadHalf::Left),
+            Some((0, 1000))
+        );
+        assert_eq!(
+            spread_crop_rect(2001, 1000, SpreadHalf::Right),
+            Some((1000, 1001))
+        );
+        // Regular pages and mildly wide images are left alone
+        assert_eq!(spread_crop_rect(1000, 1500, SpreadHalf::Right), None);
+        assert_eq!(spread_crop_rect(1600, 1000, SpreadHalf::Right), None);
+    }
+
+    #[test]
+    fn test_render_thumbnail_zero_size_bounded_by_default() {
+        let img = RgbaImage::from_pixel(600, 300, Rgba([0, 0, 255, 255]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+
+        let config = ThumbnailConfig {
+            max_width: 0,
+            max_height: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.effective_bounds(),
+            (DEFAULT_THUMBNAIL_SIZE, DEFAULT_THUMBNAIL_SIZE)
+        );
+
+        let thumbnail = render_thumbnail(png.get_ref(), &config).unwrap();
+        assert_eq!(thumbnail.dimensions(), (256, 128));
+    }
+
+    #[test]
+    fn test_render_thumbnail_is_opaque() {
+        let config = ThumbnailConfig {
+            max_width: 64,
+            max_height: 64,
+            ..Default::default()
+        };
+        let rgba = render_thumbnail(MINIMAL_JPEG, &config).unwrap();
+
+        // 1x1 source is never upscaled
+        assert_eq!(rgba.dimensions(), (1, 1));
+        assert!(rgba.pixels().all(|p| p[3] == 255));
+    }
+
+    /// Encode a 4x4 GIF whose left half is transparent and right half opaque red
+    fn transparent_gif() -> Vec<u8> {
+        use image::codecs::gif::GifEncoder;
+
+        let img = RgbaImage::from_fn(4, 4, |x, _| {
+            if x < 2 {
+                Rgba([0, 255, 0, 0]) // Transparent slot with a visible "odd hue"
+            } else {
+                Rgba([255, 0, 0, 255])
+            }
+        });
+
+        let mut buffer = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut buffer);
+            encoder
+                .encode(img.as_raw(), 4, 4, image::ExtendedColorType::Rgba8)
+                .unwrap();
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_gif_transparency_uses_background() {
+        let config = ThumbnailConfig {
+            max_width: 64,
+            max_height: 64,
+            background_color: (0, 0, 255, 255),
+            ..Default::default()
+        };
+        let rgba = render_thumbnail(&transparent_gif(), &config).unwrap();
+        assert_eq!(rgba.dimensions(), (4, 4));
+
+        // Transparent pixels take the background color, not the palette hue
+        assert_eq!(*rgba.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+        assert_eq!(*rgba.get_pixel(1, 3), Rgba([0, 0, 255, 255]));
+
+        // Opaque pixels are unchanged
+        assert_eq!(*rgba.get_pixel(2, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*rgba.get_pixel(3, 3), Rgba([255, 0, 0, 255]));
+    }
+
     #[test]
     fn test_create_thumbnail_invalid_data() {
         let invalid_data = b"This is not an image";
@@ -329,7 +893,7 @@ mod tests {
         let mut img = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 255]));
 
         // Apply white background
-        apply_background(&mut img, (255, 255, 255, 255));
+        apply_background(&mut img, (255, 255, 255, 255), 255);
 
         // Should remain red since it's fully opaque
         let pixel = img.get_pixel(0, 0);
@@ -345,7 +909,7 @@ mod tests {
         let mut img = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 0]));
 
         // Apply white background
-        apply_background(&mut img, (255, 255, 255, 255));
+        apply_background(&mut img, (255, 255, 255, 255), 255);
 
         // Should become white since original is fully transparent
         let pixel = img.get_pixel(0, 0);
@@ -361,7 +925,7 @@ mod tests {
         let mut img = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 128]));
 
         // Apply white background
-        apply_background(&mut img, (255, 255, 255, 255));
+        apply_background(&mut img, (255, 255, 255, 255), 255);
 
         // Should be blend of red and white
         let pixel = img.get_pixel(0, 0);
@@ -372,6 +936,113 @@ mod tests {
         assert_eq!(pixel[3], 255); // Alpha (opaque)
     }
 
+    #[test]
+    fn test_unpremultiply_before_background() {
+        let straight = Rgba([200, 100, 0, 128]);
+        // Same pixel as a premultiplied source delivers it
+        let premultiplied = Rgba([100, 50, 0, 128]);
+
+        let mut expected = RgbaImage::from_pixel(1, 1, straight);
+        apply_background(&mut expected, (255, 255, 255, 255), 255);
+
+        let mut img = RgbaImage::from_pixel(1, 1, premultiplied);
+        unpremultiply_alpha(&mut img);
+        apply_background(&mut img, (255, 255, 255, 255), 255);
+
+        let (got, want) = (img.get_pixel(0, 0), expected.get_pixel(0, 0));
+        for channel in 0..3 {
+            assert!(
+                got[channel].abs_diff(want[channel]) <= 1,
+                "channel {}: {} vs {}",
+                channel,
+                got[channel],
+                want[channel]
+            );
+        }
+
+        // Blending the premultiplied pixel directly gives the dark halo
+        let mut halo = RgbaImage::from_pixel(1, 1, premultiplied);
+        apply_background(&mut halo, (255, 255, 255, 255), 255);
+        assert!(halo.get_pixel(0, 0)[0] < want[0] - 40);
+    }
+
+    #[test]
+    fn test_unpremultiply_leaves_opaque_and_transparent() {
+        let mut img = RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgba([10, 20, 30, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        let original = img.clone();
+        unpremultiply_alpha(&mut img);
+        assert_eq!(img, original);
+    }
+
+    #[test]
+    fn test_apply_background_opaque_threshold() {
+        // Alpha noise: 254 everywhere, composited onto black so blending shows
+        let noisy = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 254]));
+
+        let mut img = noisy.clone();
+        apply_background(&mut img, (0, 0, 0, 255), 250);
+        assert!(img.pixels().all(|p| *p == Rgba([255, 0, 0, 255])));
+
+        let mut img = noisy;
+        apply_background(&mut img, (0, 0, 0, 255), 255);
+        let pixel = img.get_pixel(0, 0);
+        assert!(pixel[0] < 255, "254 alpha should blend at threshold 255");
+        assert_eq!(pixel[3], 255);
+    }
+
+    #[test]
+    fn test_render_thumbnail_grayscale_stays_neutral() {
+        // Black-and-white "scan": stripes of varying gray in an L8 PNG
+        let img = GrayImage::from_fn(300, 400, |x, y| {
+            image::Luma([((x * 7 + y * 3) % 256) as u8])
+        });
+        let mut png = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+
+        let config = ThumbnailConfig {
+            max_width: 64,
+            max_height: 64,
+            grayscale: true,
+            ..Default::default()
+        };
+        let thumbnail = render_thumbnail(png.get_ref(), &config).unwrap();
+        assert_eq!(thumbnail.dimensions(), (48, 64));
+
+        // Check the bytes that go into the DIB
+        let bgra = hbitmap::rgba_to_bgra(thumbnail.as_raw());
+        for pixel in bgra.chunks_exact(4) {
+            assert_eq!(pixel[0], pixel[1]);
+            assert_eq!(pixel[1], pixel[2]);
+            assert_eq!(pixel[3], 255);
+        }
+    }
+
+    #[test]
+    fn test_opaque_grayscale_detection() {
+        let gray_rgb =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(4, 4, image::Rgb([90, 90, 90])));
+        let luma = opaque_grayscale(&gray_rgb).unwrap();
+        assert!(luma.pixels().all(|p| p[0] == 90));
+
+        let color =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(4, 4, image::Rgb([90, 91, 90])));
+        assert!(opaque_grayscale(&color).is_none());
+
+        // Alpha must be composited on the background, so use the RGBA path
+        let gray_alpha = DynamicImage::ImageLumaA8(image::GrayAlphaImage::from_pixel(
+            4,
+            4,
+            image::LumaA([90, 128]),
+        ));
+        assert!(opaque_grayscale(&gray_alpha).is_none());
+    }
+
     #[test]
     fn test_config_default_values() {
         let config = ThumbnailConfig::default();
@@ -379,6 +1050,117 @@ mod tests {
         assert_eq!(config.max_height, 256);
         assert_eq!(config.background_color, (255, 255, 255, 255));
         assert_eq!(config.resize_filter, ResizeFilter::Triangle);
+        assert!(!config.grayscale);
+        assert!(!config.preserve_alpha);
+        assert_eq!(config.corner_radius, 0);
+        assert!(!config.book_effect);
+    }
+
+    #[test]
+    fn test_rounded_corners() {
+        let img = RgbaImage::from_pixel(100, 100, Rgba([255, 0, 0, 255]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+
+        // Opaque: corners filled with the background, center untouched
+        let config = ThumbnailConfig {
+            corner_radius: 20,
+            background_color: (0, 0, 255, 255),
+            ..Default::default()
+        };
+        let thumbnail = render_thumbnail(png.get_ref(), &config).unwrap();
+        for (x, y) in [(0, 0), (99, 0), (0, 99), (99, 99)] {
+            assert_eq!(thumbnail.get_pixel(x, y), &Rgba([0, 0, 255, 255]));
+        }
+        assert_eq!(thumbnail.get_pixel(50, 50), &Rgba([255, 0, 0, 255]));
+        assert_eq!(thumbnail.get_pixel(50, 0), &Rgba([255, 0, 0, 255]));
+
+        // Anti-aliased edge: partially blended along the arc
+        let edge = thumbnail.get_pixel(5, 6);
+        assert!(edge[0] > 0 && edge[0] < 255);
+
+        // Alpha preserved: corners cut transparent
+        let config = ThumbnailConfig {
+            corner_radius: 20,
+            preserve_alpha: true,
+            ..Default::default()
+        };
+        let thumbnail = render_thumbnail(png.get_ref(), &config).unwrap();
+        assert_eq!(thumbnail.get_pixel(0, 0)[3], 0);
+        assert_eq!(thumbnail.get_pixel(99, 99)[3], 0);
+        assert_eq!(thumbnail.get_pixel(50, 50), &Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_allow_upscale_to() {
+        let img = RgbaImage::from_pixel(600, 900, Rgba([0, 0, 255, 255]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+
+        // Default: a smaller cover comes back at its own size
+        let config = ThumbnailConfig {
+            max_width: 1024,
+            max_height: 1024,
+            ..Default::default()
+        };
+        let thumbnail = render_thumbnail(png.get_ref(), &config).unwrap();
+        assert_eq!(thumbnail.dimensions(), (600, 900));
+
+        let config = ThumbnailConfig {
+            allow_upscale_to: Some(1024),
+            ..config
+        };
+        let thumbnail = render_thumbnail(png.get_ref(), &config).unwrap();
+        assert_eq!(thumbnail.dimensions(), (683, 1024));
+        assert_eq!(thumbnail.get_pixel(341, 512), &Rgba([0, 0, 255, 255]));
+
+        // Small requests are unaffected by the option
+        let config = ThumbnailConfig {
+            max_width: 256,
+            max_height: 256,
+            ..config
+        };
+        let thumbnail = render_thumbnail(png.get_ref(), &config).unwrap();
+        assert_eq!(thumbnail.dimensions(), (171, 256));
+    }
+
+    #[test]
+    fn test_book_effect_frames_cover() {
+        let img = RgbaImage::from_pixel(200, 200, Rgba([255, 0, 0, 255]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+
+        let config = ThumbnailConfig {
+            max_width: 100,
+            max_height: 100,
+            book_effect: true,
+            ..Default::default()
+        };
+        let thumbnail = render_thumbnail(png.get_ref(), &config).unwrap();
+
+        // Cover shrinks by the shadow margin; the canvas still fits the bounds
+        assert_eq!(thumbnail.dimensions(), (100, 100));
+        let cover_edge = 100 - BOOK_SHADOW_OFFSET;
+
+        // Interior untouched, border darkens the outermost cover pixels
+        assert_eq!(thumbnail.get_pixel(50, 50), &Rgba([255, 0, 0, 255]));
+        for (x, y) in [(0, 0), (50, 0), (0, 50), (cover_edge - 1, cover_edge - 1)] {
+            let border = thumbnail.get_pixel(x, y);
+            assert_eq!(border[3], 255);
+            assert!(border[0] < 255 && border[0] > 0);
+            assert_eq!((border[1], border[2]), (0, 0));
+        }
+
+        // Shadow: translucent black, faded at the outer corner
+        let shadow = thumbnail.get_pixel(cover_edge + 1, 50);
+        assert_eq!((shadow[0], shadow[1], shadow[2]), (0, 0, 0));
+        assert!(shadow[3] > 0 && shadow[3] < 255);
+        let corner = thumbnail.get_pixel(99, 99);
+        assert!(corner[3] > 0 && corner[3] < shadow[3]);
+
+        // Margin not covered by the shifted shadow stays transparent
+        assert_eq!(thumbnail.get_pixel(99, 0)[3], 0);
+        assert_eq!(thumbnail.get_pixel(0, 99)[3], 0);
     }
 
     #[test]
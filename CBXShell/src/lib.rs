@@ -13,13 +13,30 @@ use windows::{core::*, Win32::Foundation::*};
 
 mod archive;
 pub mod com;
+mod cover;
 mod image_processor;
+mod preview;
 pub mod registry;
+mod service;
+mod settings;
 mod utils;
 
+pub use archive::{
+    open_archive_from_bytes, open_classified, supported_archive_types, validate_archive, Archive,
+    ArchiveEntry, ArchiveMetadata, ArchiveType, ContentKind, OpenedArchive,
+};
 pub use com::CBXShell;
-pub use image_processor::thumbnail::create_thumbnail_with_size;
-pub use utils::error::CbxError;
+pub use cover::{cover_png, embed_preview, extract_cover};
+pub use image_processor::magic::{detect_image, DetectedImage, ImageFormat, WebPCompression};
+pub use image_processor::phash::{compute_cover_phash, compute_cover_phash_fast, hamming_distance};
+pub use image_processor::placeholder::cover_placeholder;
+pub use image_processor::supported_image_formats;
+pub use image_processor::thumbnail::{
+    create_thumbnail_with_size, render_thumbnail, SpreadHalf, ThumbnailConfig,
+};
+pub use preview::PagePrefetcher;
+pub use settings::Settings;
+pub use utils::error::{CbxError, ErrorKind};
 
 /// Global reference count for COM objects
 /// Used to determine when DLL can be safely unloaded
@@ -77,16 +94,23 @@ pub extern "system" fn DllMain(
             tracing::info!("CBXShell DLL loaded");
 
             // CRITICAL: File-based debug logging to diagnose Explorer integration
-            utils::debug_log::debug_log(
-                "===== DLL_PROCESS_ATTACH - CBXShell DLL loaded by Explorer =====",
-            );
-            utils::debug_log::debug_log(&format!("DLL HINSTANCE: {:?}", hinst_dll));
+            crate::debug_log!("===== DLL_PROCESS_ATTACH - CBXShell DLL loaded by Explorer =====");
+            crate::debug_log!("DLL HINSTANCE: {:?}", hinst_dll);
 
             TRUE
         }
         DLL_PROCESS_DETACH => {
             tracing::info!("CBXShell DLL unloaded");
-            utils::debug_log::debug_log("===== DLL_PROCESS_DETACH - CBXShell DLL unloaded =====");
+            // Workers and the warm-up thread stop at their next check rather
+            // than starting new work while the module goes away
+            service::stop_global();
+            image_processor::codecs::stop_codec_warmup();
+            // Flush before the module goes away; the last lines matter most
+            // when diagnosing a crash
+            utils::debug_log::shutdown_logging(&format!(
+                "===== DLL_PROCESS_DETACH - CBXShell DLL unloaded (ref count = {}) =====",
+                get_dll_ref_count()
+            ));
             TRUE
         }
         _ => TRUE,
@@ -102,14 +126,11 @@ pub extern "system" fn DllCanUnloadNow() -> HRESULT {
     let ref_count = get_dll_ref_count();
     if ref_count == 0 {
         tracing::debug!("DllCanUnloadNow: S_OK (ref count = 0)");
-        utils::debug_log::debug_log("DllCanUnloadNow: S_OK (ref count = 0)");
+        crate::debug_log!("DllCanUnloadNow: S_OK (ref count = 0)");
         S_OK
     } else {
         tracing::debug!("DllCanUnloadNow: S_FALSE (ref count = {})", ref_count);
-        utils::debug_log::debug_log(&format!(
-            "DllCanUnloadNow: S_FALSE (ref count = {})",
-            ref_count
-        ));
+        crate::debug_log!("DllCanUnloadNow: S_FALSE (ref count = {})", ref_count);
         S_FALSE
     }
 }
@@ -123,7 +144,7 @@ pub extern "system" fn DllGetClassObject(
     riid: *const GUID,
     ppv: *mut *mut std::ffi::c_void,
 ) -> HRESULT {
-    utils::debug_log::debug_log("===== DllGetClassObject CALLED =====");
+    crate::debug_log!("===== DllGetClassObject CALLED =====");
 
     // UNAVOIDABLE UNSAFE: Dereferencing COM raw pointers for logging
     // Why unsafe is required:
@@ -133,11 +154,11 @@ pub extern "system" fn DllGetClassObject(
     // - COM runtime ensures pointers are valid
     // - Null check performed below (line 127)
     // - Only dereferencing for read (no mutation)
-    utils::debug_log::debug_log(&format!("CLSID requested: {:?}", unsafe { *rclsid }));
-    utils::debug_log::debug_log(&format!("IID requested: {:?}", unsafe { *riid }));
+    crate::debug_log!("CLSID requested: {:?}", unsafe { *rclsid });
+    crate::debug_log!("IID requested: {:?}", unsafe { *riid });
 
     if ppv.is_null() {
-        utils::debug_log::debug_log("ERROR: ppv is null pointer");
+        crate::debug_log!("ERROR: ppv is null pointer");
         return E_POINTER;
     }
 
@@ -157,30 +178,30 @@ pub extern "system" fn DllGetClassObject(
     unsafe {
         *ppv = std::ptr::null_mut();
 
-        if *rclsid != com::CLSID_CBXSHELL {
+        if *rclsid != com::CLSID_CBXSHELL && *rclsid != com::CLSID_SET_COVER_COMMAND {
             tracing::warn!("DllGetClassObject: CLASS_E_CLASSNOTAVAILABLE");
-            utils::debug_log::debug_log("ERROR: CLSID does not match CLSID_CBXSHELL");
-            utils::debug_log::debug_log(&format!("Expected: {:?}", com::CLSID_CBXSHELL));
+            crate::debug_log!("ERROR: CLSID does not match CLSID_CBXSHELL");
+            crate::debug_log!("Expected: {:?}", com::CLSID_CBXSHELL);
             return CLASS_E_CLASSNOTAVAILABLE;
         }
 
-        utils::debug_log::debug_log("CLSID matches - creating ClassFactory");
+        crate::debug_log!("CLSID matches - creating ClassFactory");
 
         // Create and return class factory
-        match com::ClassFactory::new() {
+        match com::ClassFactory::for_clsid(*rclsid) {
             Ok(factory) => {
-                utils::debug_log::debug_log("ClassFactory created successfully");
+                crate::debug_log!("ClassFactory created successfully");
 
                 // Cast to IUnknown and query for the requested interface
                 match factory.cast::<IUnknown>() {
                     Ok(iunknown) => {
-                        utils::debug_log::debug_log("ClassFactory cast to IUnknown succeeded");
+                        crate::debug_log!("ClassFactory cast to IUnknown succeeded");
 
                         match iunknown.query(riid, ppv as *mut _) {
                             S_OK => {
                                 tracing::debug!("DllGetClassObject: S_OK");
-                                utils::debug_log::debug_log(
-                                    "DllGetClassObject: SUCCESS - Returning class factory",
+                                crate::debug_log!(
+                                    "DllGetClassObject: SUCCESS - Returning class factory"
                                 );
                                 S_OK
                             }
@@ -189,27 +210,24 @@ pub extern "system" fn DllGetClassObject(
                                     "DllGetClassObject QueryInterface failed: {:?}",
                                     hr
                                 );
-                                utils::debug_log::debug_log(&format!(
+                                crate::debug_log!(
                                     "ERROR: QueryInterface failed with HRESULT: {:?}",
                                     hr
-                                ));
+                                );
                                 hr
                             }
                         }
                     }
                     Err(e) => {
                         tracing::error!("DllGetClassObject cast to IUnknown failed: {:?}", e);
-                        utils::debug_log::debug_log(&format!(
-                            "ERROR: Cast to IUnknown failed: {:?}",
-                            e
-                        ));
+                        crate::debug_log!("ERROR: Cast to IUnknown failed: {:?}", e);
                         E_NOINTERFACE
                     }
                 }
             }
             Err(e) => {
                 tracing::error!("DllGetClassObject factory creation failed: {}", e);
-                utils::debug_log::debug_log(&format!("ERROR: ClassFactory creation failed: {}", e));
+                crate::debug_log!("ERROR: ClassFactory creation failed: {}", e);
                 e.code()
             }
         }
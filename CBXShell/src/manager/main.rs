@@ -1,12 +1,16 @@
 #![windows_subsystem = "windows"]
 
 mod registry_ops;
+mod repair;
+mod settings_file;
 ///! CBXManager - Native configuration utility for CBXShell
 ///!
 ///! Built with native-windows-gui for a Windows-native interface
 mod state;
+mod strings;
 mod ui;
 mod utils;
+mod worker;
 
 use native_windows_gui as nwg;
 use native_windows_gui::NativeUi;
@@ -17,6 +21,8 @@ fn main() -> Result<(), nwg::NwgError> {
 
     let app = ui::CBXManagerApp::build_ui(Default::default())?;
     app.initialize_state();
+    app.check_dll_path();
+    app.check_handlers();
 
     nwg::dispatch_thread_events();
     Ok(())
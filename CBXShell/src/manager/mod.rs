@@ -1,6 +1,10 @@
 ///! CBXManager - Configuration utility modules
 
 pub mod state;
+pub mod strings;
 pub mod registry_ops;
+pub mod repair;
+pub mod settings_file;
 pub mod ui;
 pub mod utils;
+pub mod worker;
@@ -1,8 +1,19 @@
 ///! Registry operations for CBXManager
 ///!
 ///! Read and write configuration from/to Windows registry
-use super::state::AppState;
+use super::repair;
+use super::state::{AdvancedSettings, AppState, ExtensionConfig};
 use anyhow::{Context, Result};
+use cbxshell::registry::{
+    ALLOW_UPSCALE_TO_VALUE, BOOK_EFFECT_VALUE, COVER_SKIP_STEMS_VALUE,
+    COVER_STRATEGY_BY_EXTENSION_VALUE, COVER_STRATEGY_VALUE, DECODE_TIMEOUT_VALUE,
+    EMBEDDED_PREVIEW_NAMES_VALUE, EMBEDDED_PREVIEW_VALUE, ENABLED_VALUE, EVENT_LOG_VALUE,
+    FORCE_THUMBNAIL_SIZE_VALUE, GDI_HALFTONE_VALUE, GRAYSCALE_VALUE, LENIENT_DECODE_VALUE,
+    MAX_ARCHIVE_SIZE_VALUE, MAX_NON_IMAGE_SCAN_VALUE, MAX_THUMBNAIL_DIM_VALUE,
+    NATURAL_SORT_MODE_VALUE, NEXT_IMAGE_ON_MISSING_CODEC_VALUE, NO_SORT_VALUE, SORT_BY_VALUE,
+    VERIFY_FIRST_IMAGE_VALUE, WARM_CODECS_VALUE,
+};
+use std::path::PathBuf;
 use winreg::enums::*;
 use winreg::RegKey;
 
@@ -17,6 +28,38 @@ const IID_ITHUMBNAILPROVIDER: &str = "{E357FCCD-A995-4576-B01F-234630154E96}";
 /// IQueryInfo interface GUID (tooltips)
 const IID_IQUERYINFO: &str = "{00021500-0000-0000-C000-000000000046}";
 
+/// Config value recording which handlers the user enabled (see `repair`)
+const ENABLED_HANDLERS_VALUE: &str = "EnabledHandlers";
+
+/// Preview pane sorting preference (inverted like `NoSort`)
+const NO_SORT_PREVIEW_VALUE: &str = "NoSortPreview";
+
+/// Advanced DWORD config values carried by settings export/import
+pub const ADVANCED_DWORD_VALUES: &[&str] = &[
+    COVER_STRATEGY_VALUE,
+    DECODE_TIMEOUT_VALUE,
+    VERIFY_FIRST_IMAGE_VALUE,
+    NEXT_IMAGE_ON_MISSING_CODEC_VALUE,
+    EVENT_LOG_VALUE,
+    GDI_HALFTONE_VALUE,
+    WARM_CODECS_VALUE,
+    EMBEDDED_PREVIEW_VALUE,
+    NATURAL_SORT_MODE_VALUE,
+    SORT_BY_VALUE,
+    MAX_NON_IMAGE_SCAN_VALUE,
+    ALLOW_UPSCALE_TO_VALUE,
+    LENIENT_DECODE_VALUE,
+    GRAYSCALE_VALUE,
+    BOOK_EFFECT_VALUE,
+];
+
+/// Advanced string config values carried by settings export/import
+pub const ADVANCED_STRING_VALUES: &[&str] = &[
+    COVER_SKIP_STEMS_VALUE,
+    EMBEDDED_PREVIEW_NAMES_VALUE,
+    COVER_STRATEGY_BY_EXTENSION_VALUE,
+];
+
 /// Read current application state from registry
 pub fn read_app_state() -> Result<AppState> {
     let mut state = AppState::default();
@@ -24,9 +67,15 @@ pub fn read_app_state() -> Result<AppState> {
     // 1. Check DLL registration
     state.dll_registered = check_dll_registration();
 
-    // 2. Read sort setting
-    state.sort_enabled = read_sort_setting()?;
-    state.sort_preview_enabled = read_sort_preview_setting()?;
+    // 2. Read master switch and sort settings
+    // Defaults: enabled, sorting disabled (NoSort=1) for better performance,
+    // and 0 (unlimited / Explorer's size / uncapped) for the size settings
+    state.thumbnails_enabled = read_dword(ENABLED_VALUE, 1) != 0;
+    state.sort_enabled = sort_enabled_from_no_sort(read_dword(NO_SORT_VALUE, 1));
+    state.sort_preview_enabled = sort_enabled_from_no_sort(read_dword(NO_SORT_PREVIEW_VALUE, 1));
+    state.max_archive_size_mb = read_dword(MAX_ARCHIVE_SIZE_VALUE, 0);
+    state.force_thumbnail_size = read_dword(FORCE_THUMBNAIL_SIZE_VALUE, 0);
+    state.max_thumbnail_dim = read_dword(MAX_THUMBNAIL_DIM_VALUE, 0);
 
     // 3. Check each extension's handler registration
     for ext_config in &mut state.extensions {
@@ -40,9 +89,16 @@ pub fn read_app_state() -> Result<AppState> {
 
 /// Write application state to registry
 pub fn write_app_state(state: &AppState) -> Result<()> {
-    // 1. Write sort settings
-    write_sort_setting(state.sort_enabled)?;
-    write_sort_preview_setting(state.sort_preview_enabled)?;
+    // 1. Write master switch and sort settings
+    write_dword(ENABLED_VALUE, u32::from(state.thumbnails_enabled))?;
+    write_dword(NO_SORT_VALUE, no_sort_value(state.sort_enabled))?;
+    write_dword(
+        NO_SORT_PREVIEW_VALUE,
+        no_sort_value(state.sort_preview_enabled),
+    )?;
+    write_dword(MAX_ARCHIVE_SIZE_VALUE, state.max_archive_size_mb)?;
+    write_dword(FORCE_THUMBNAIL_SIZE_VALUE, state.force_thumbnail_size)?;
+    write_dword(MAX_THUMBNAIL_DIM_VALUE, state.max_thumbnail_dim)?;
 
     // 2. Update extension handlers
     for ext_config in &state.extensions {
@@ -53,6 +109,9 @@ pub fn write_app_state(state: &AppState) -> Result<()> {
         )?;
     }
 
+    // 3. Record enabled handlers so wiped registrations can be repaired
+    write_enabled_handlers(&state.extensions)?;
+
     Ok(())
 }
 
@@ -64,6 +123,49 @@ pub fn check_dll_registration() -> bool {
     hkcu.open_subkey(clsid_path).is_ok()
 }
 
+/// State of the registered `InprocServer32` DLL path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InprocServerStatus {
+    /// No `InprocServer32` path registered
+    NotRegistered,
+    /// Registered DLL exists
+    Valid(PathBuf),
+    /// Registered DLL no longer exists (moved or deleted after registration)
+    Stale(PathBuf),
+}
+
+/// Read the registered `InprocServer32` DLL path
+pub fn read_inproc_server_path() -> Option<PathBuf> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let key_path = format!("Software\\Classes\\CLSID\\{}\\InprocServer32", CLSID_STR);
+    read_inproc_server_path_at(&hkcu, &key_path)
+}
+
+fn read_inproc_server_path_at(root: &RegKey, key_path: &str) -> Option<PathBuf> {
+    let key = root.open_subkey(key_path).ok()?;
+    let value = key.get_value::<String, _>("").ok()?;
+    if value.is_empty() {
+        return None;
+    }
+    Some(PathBuf::from(value))
+}
+
+/// Check that the registered DLL still exists
+///
+/// Thumbnails silently stop working when the DLL is moved after
+/// registration; `Stale` means the user needs to register again.
+pub fn check_inproc_server() -> InprocServerStatus {
+    inproc_server_status(read_inproc_server_path())
+}
+
+fn inproc_server_status(path: Option<PathBuf>) -> InprocServerStatus {
+    match path {
+        None => InprocServerStatus::NotRegistered,
+        Some(path) if path.is_file() => InprocServerStatus::Valid(path),
+        Some(path) => InprocServerStatus::Stale(path),
+    }
+}
+
 /// Check if handlers are registered for an extension
 ///
 /// Returns (thumbnail_enabled, infotip_enabled)
@@ -98,54 +200,55 @@ pub fn check_extension_handlers(extension: &str) -> Result<(bool, bool)> {
 }
 
 /// Set handlers for an extension
+///
+/// Shares the library's registration, which backs up a handler we replace on
+/// shared extensions and restores it when ours is disabled.
 fn set_extension_handlers(extension: &str, thumbnail: bool, infotip: bool) -> Result<()> {
+    cbxshell::registry::set_extension_handlers(extension, thumbnail, infotip)
+        .map_err(|e| anyhow::anyhow!("Failed to set handlers for {}: {}", extension, e))
+}
+
+/// Read the handlers recorded by the last save (`None` if never saved)
+fn read_enabled_handlers() -> Option<Vec<ExtensionConfig>> {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let base_path = format!("Software\\Classes\\{}", extension);
-
-    // Ensure base extension key exists with PerceivedType
-    let (ext_key, _) = hkcu
-        .create_subkey(&base_path)
-        .context("Failed to create extension key")?;
-    ext_key
-        .set_value("PerceivedType", &"image")
-        .context("Failed to set PerceivedType")?;
-
-    // Create shellex key
-    let shellex_path = format!("{}\\shellex", base_path);
-    hkcu.create_subkey(&shellex_path)
-        .context("Failed to create shellex key")?;
-
-    // Handle thumbnail provider
-    let thumbnail_path = format!("{}\\shellex\\{}", base_path, IID_ITHUMBNAILPROVIDER);
-    if thumbnail {
-        let (thumb_key, _) = hkcu
-            .create_subkey(&thumbnail_path)
-            .context("Failed to create thumbnail key")?;
-        thumb_key
-            .set_value("", &CLSID_STR)
-            .context("Failed to set thumbnail CLSID")?;
-    } else {
-        // Remove thumbnail handler
-        let _ = hkcu.delete_subkey_all(&thumbnail_path);
-    }
+    let key = hkcu.open_subkey(CONFIG_KEY_PATH).ok()?;
+    let text = key.get_value::<String, _>(ENABLED_HANDLERS_VALUE).ok()?;
+    Some(repair::parse_enabled_handlers(&text))
+}
 
-    // Handle infotip provider
-    let infotip_path = format!("{}\\shellex\\{}", base_path, IID_IQUERYINFO);
-    if infotip {
-        let (info_key, _) = hkcu
-            .create_subkey(&infotip_path)
-            .context("Failed to create infotip key")?;
-        info_key
-            .set_value("", &CLSID_STR)
-            .context("Failed to set infotip CLSID")?;
-    } else {
-        // Remove infotip handler
-        let _ = hkcu.delete_subkey_all(&infotip_path);
-    }
+/// Record the enabled handlers in the config key
+fn write_enabled_handlers(extensions: &[ExtensionConfig]) -> Result<()> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let (key, _) = hkcu
+        .create_subkey(CONFIG_KEY_PATH)
+        .context("Failed to create config key")?;
+
+    key.set_value(
+        ENABLED_HANDLERS_VALUE,
+        &repair::format_enabled_handlers(extensions),
+    )
+    .context("Failed to set EnabledHandlers value")?;
 
     Ok(())
 }
 
+/// Rewrite enabled handlers whose shellex keys have been removed
+///
+/// Does nothing while the DLL is unregistered or before settings were first
+/// saved, since there is then no recorded intent to restore.
+///
+/// Returns the handlers that were restored.
+pub fn repair_extension_handlers() -> Result<Vec<ExtensionConfig>> {
+    if !check_dll_registration() {
+        return Ok(Vec::new());
+    }
+    let Some(recorded) = read_enabled_handlers() else {
+        return Ok(Vec::new());
+    };
+
+    repair::repair_handlers(&recorded, check_extension_handlers, set_extension_handlers)
+}
+
 /// Read the sorting preference from registry
 fn sort_enabled_from_no_sort(value: u32) -> bool {
     value == 0
@@ -159,57 +262,67 @@ fn no_sort_value(sort_enabled: bool) -> u32 {
     }
 }
 
-fn read_sort_setting() -> Result<bool> {
+/// Read a DWORD config value, `default` if the key or value is missing
+fn read_dword(name: &str, default: u32) -> u32 {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-
-    match hkcu.open_subkey(CONFIG_KEY_PATH) {
-        Ok(key) => {
-            match key.get_value::<u32, _>("NoSort") {
-                Ok(value) => Ok(sort_enabled_from_no_sort(value)), // NoSort=0 means sort enabled
-                Err(_) => Ok(false), // Default: sorting disabled (NoSort=1) for better performance
-            }
-        }
-        Err(_) => Ok(false), // Default: sorting disabled (NoSort=1) for better performance
-    }
+    hkcu.open_subkey(CONFIG_KEY_PATH)
+        .and_then(|key| key.get_value::<u32, _>(name))
+        .unwrap_or(default)
 }
 
-/// Read the preview sorting preference from registry
-fn read_sort_preview_setting() -> Result<bool> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-
-    match hkcu.open_subkey(CONFIG_KEY_PATH) {
-        Ok(key) => match key.get_value::<u32, _>("NoSortPreview") {
-            Ok(value) => Ok(value == 0),
-            Err(_) => Ok(false),
-        },
-        Err(_) => Ok(false),
-    }
-}
-
-/// Write the sorting preference to registry
-fn write_sort_setting(sort_enabled: bool) -> Result<()> {
+/// Write a DWORD config value, creating the config key if needed
+fn write_dword(name: &str, value: u32) -> Result<()> {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let (key, _) = hkcu
         .create_subkey(CONFIG_KEY_PATH)
         .context("Failed to create config key")?;
 
-    let no_sort_value: u32 = no_sort_value(sort_enabled);
-    key.set_value("NoSort", &no_sort_value)
-        .context("Failed to set NoSort value")?;
+    key.set_value(name, &value)
+        .with_context(|| format!("Failed to set {} value", name))?;
 
     Ok(())
 }
 
-/// Write the preview sorting preference to registry
-fn write_sort_preview_setting(sort_enabled: bool) -> Result<()> {
+/// Read the advanced config values that are currently set
+///
+/// Missing values are left out so an import on another machine keeps that
+/// machine's defaults.
+pub fn read_advanced_settings() -> Result<AdvancedSettings> {
+    let mut advanced = AdvancedSettings::default();
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let Ok(key) = hkcu.open_subkey(CONFIG_KEY_PATH) else {
+        return Ok(advanced);
+    };
+
+    for name in ADVANCED_DWORD_VALUES {
+        if let Ok(value) = key.get_value::<u32, _>(name) {
+            advanced.dwords.insert(name.to_string(), value);
+        }
+    }
+    for name in ADVANCED_STRING_VALUES {
+        if let Ok(value) = key.get_value::<String, _>(name) {
+            advanced.strings.insert(name.to_string(), value);
+        }
+    }
+
+    Ok(advanced)
+}
+
+/// Write advanced config values to registry
+pub fn write_advanced_settings(advanced: &AdvancedSettings) -> Result<()> {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let (key, _) = hkcu
         .create_subkey(CONFIG_KEY_PATH)
         .context("Failed to create config key")?;
 
-    let no_sort_value: u32 = no_sort_value(sort_enabled);
-    key.set_value("NoSortPreview", &no_sort_value)
-        .context("Failed to set NoSortPreview value")?;
+    for (name, value) in &advanced.dwords {
+        key.set_value(name, value)
+            .with_context(|| format!("Failed to set {} value", name))?;
+    }
+    for (name, value) in &advanced.strings {
+        key.set_value(name, value)
+            .with_context(|| format!("Failed to set {} value", name))?;
+    }
 
     Ok(())
 }
@@ -265,6 +378,28 @@ mod tests {
         assert_eq!(no_sort_value(true), 0);
         assert_eq!(no_sort_value(false), 1);
     }
+
+    #[test]
+    fn test_inproc_server_status() {
+        assert_eq!(
+            inproc_server_status(None),
+            InprocServerStatus::NotRegistered
+        );
+
+        let missing = std::env::temp_dir()
+            .join("cbxshell-moved-away")
+            .join("cbxshell.dll");
+        assert_eq!(
+            inproc_server_status(Some(missing.clone())),
+            InprocServerStatus::Stale(missing)
+        );
+
+        let exe = std::env::current_exe().unwrap();
+        assert_eq!(
+            inproc_server_status(Some(exe.clone())),
+            InprocServerStatus::Valid(exe)
+        );
+    }
 }
 
 #[cfg(all(test, windows, feature = "e2e-windows"))]
@@ -278,6 +413,24 @@ mod windows_registry_tests {
         let _registered = check_dll_registration();
     }
 
+    #[test]
+    #[ignore = "requires live HKCU registry state"]
+    fn test_stale_inproc_server_path() {
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let key_path = "Software\\CBXShell-rs-test\\InprocServer32";
+        let (key, _) = hkcu.create_subkey(key_path).unwrap();
+        key.set_value("", &"C:\\does-not-exist\\cbxshell.dll")
+            .unwrap();
+
+        let path = read_inproc_server_path_at(&hkcu, key_path);
+        let _ = hkcu.delete_subkey_all("Software\\CBXShell-rs-test");
+
+        assert!(matches!(
+            inproc_server_status(path),
+            InprocServerStatus::Stale(_)
+        ));
+    }
+
     #[test]
     #[ignore = "requires live HKCU registry state"]
     fn test_check_extension_handlers() {
@@ -313,6 +466,25 @@ mod windows_registry_tests {
         assert_eq!(state.extensions.len(), 6);
     }
 
+    #[test]
+    #[ignore = "mutates live HKCU and HKLM registry keys (run elevated)"]
+    fn test_set_extension_handlers_writes_kind_map() {
+        let (thumbnail, infotip) = check_extension_handlers(".cbz").unwrap();
+        set_extension_handlers(".cbz", thumbnail, infotip).unwrap();
+
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let ext_key = hkcu.open_subkey("Software\\Classes\\.cbz").unwrap();
+        let perceived: String = ext_key.get_value("PerceivedType").unwrap();
+        assert_eq!(perceived, "image");
+
+        // The property system reads the kind map from HKLM only
+        let kind_map = RegKey::predef(HKEY_LOCAL_MACHINE)
+            .open_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\KindMap")
+            .unwrap();
+        let kind: String = kind_map.get_value(".cbz").unwrap();
+        assert_eq!(kind, "picture");
+    }
+
     #[test]
     #[ignore = "mutates live HKCU registry keys"]
     fn test_write_and_read_sort_setting() {
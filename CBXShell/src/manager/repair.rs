@@ -0,0 +1,183 @@
+///! Shell handler repair for CBXManager
+///!
+///! Windows updates and other apps sometimes delete our shellex keys, which
+///! shows up as "thumbnails stopped working". The handlers the user enabled are
+///! recorded in the config key on every save so missing ones can be rewritten.
+use super::state::ExtensionConfig;
+use anyhow::Result;
+
+/// Recorded handler flag: thumbnail provider
+const THUMBNAIL_FLAG: u32 = 1;
+
+/// Recorded handler flag: infotip (tooltip) handler
+const INFOTIP_FLAG: u32 = 2;
+
+/// Serialize enabled handlers as `.cbz=3;.cbr=1` (1 = thumbnail, 2 = infotip)
+///
+/// Extensions without any handler enabled are left out.
+pub fn format_enabled_handlers(extensions: &[ExtensionConfig]) -> String {
+    extensions
+        .iter()
+        .filter_map(|ext| {
+            let mut flags = 0;
+            if ext.thumbnail_enabled {
+                flags |= THUMBNAIL_FLAG;
+            }
+            if ext.infotip_enabled {
+                flags |= INFOTIP_FLAG;
+            }
+            (flags != 0).then(|| format!("{}={}", ext.extension, flags))
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Parse recorded handlers, skipping malformed entries
+pub fn parse_enabled_handlers(text: &str) -> Vec<ExtensionConfig> {
+    text.split(';')
+        .filter_map(|item| {
+            let (extension, flags) = item.trim().split_once('=')?;
+            let flags = flags.trim().parse::<u32>().ok()?;
+            let extension = extension.trim();
+            if !extension.starts_with('.') || flags == 0 {
+                return None;
+            }
+            Some(ExtensionConfig {
+                extension: extension.to_string(),
+                thumbnail_enabled: flags & THUMBNAIL_FLAG != 0,
+                infotip_enabled: flags & INFOTIP_FLAG != 0,
+            })
+        })
+        .collect()
+}
+
+/// Rewrite recorded handlers that are missing from the registry
+///
+/// `check` and `set` have the shape of `check_extension_handlers` and
+/// `set_extension_handlers`, so tests can run against an in-memory map.
+/// Handlers registered but not recorded are kept as they are.
+///
+/// # Returns
+/// * `Ok(Vec<ExtensionConfig>)` - Extensions repaired, flagged with the handlers that were restored
+/// * `Err` - Reading or writing a registration failed
+pub fn repair_handlers<C, S>(
+    recorded: &[ExtensionConfig],
+    mut check: C,
+    mut set: S,
+) -> Result<Vec<ExtensionConfig>>
+where
+    C: FnMut(&str) -> Result<(bool, bool)>,
+    S: FnMut(&str, bool, bool) -> Result<()>,
+{
+    let mut repaired = Vec::new();
+
+    for ext in recorded {
+        let (thumbnail, infotip) = check(&ext.extension)?;
+        let missing_thumbnail = ext.thumbnail_enabled && !thumbnail;
+        let missing_infotip = ext.infotip_enabled && !infotip;
+        if !missing_thumbnail && !missing_infotip {
+            continue;
+        }
+
+        set(
+            &ext.extension,
+            ext.thumbnail_enabled || thumbnail,
+            ext.infotip_enabled || infotip,
+        )?;
+        repaired.push(ExtensionConfig {
+            extension: ext.extension.clone(),
+            thumbnail_enabled: missing_thumbnail,
+            infotip_enabled: missing_infotip,
+        });
+    }
+
+    Ok(repaired)
+}
+
+/// List repaired handlers, one extension per line (e.g. `.cbz: thumbnail, tooltip`)
+pub fn describe_repairs(repaired: &[ExtensionConfig]) -> String {
+    repaired
+        .iter()
+        .map(|ext| {
+            let handlers: Vec<&str> = [
+                (ext.thumbnail_enabled, "thumbnail"),
+                (ext.infotip_enabled, "tooltip"),
+            ]
+            .into_iter()
+            .filter_map(|(enabled, name)| enabled.then_some(name))
+            .collect();
+            format!("{}: {}", ext.extension, handlers.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// In-memory stand-in for the shellex registrations
+    type Registry = HashMap<String, (bool, bool)>;
+
+    fn run_repair(recorded: &[ExtensionConfig], registry: &mut Registry) -> Vec<ExtensionConfig> {
+        let snapshot = registry.clone();
+        repair_handlers(
+            recorded,
+            |ext| Ok(snapshot.get(ext).copied().unwrap_or((false, false))),
+            |ext, thumbnail, infotip| {
+                registry.insert(ext.to_string(), (thumbnail, infotip));
+                Ok(())
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_enabled_handlers_round_trip() {
+        let mut cbr = ExtensionConfig::new(".cbr");
+        cbr.thumbnail_enabled = true;
+        let extensions = vec![
+            ExtensionConfig::enabled(".cbz"),
+            cbr,
+            ExtensionConfig::new(".zip"),
+        ];
+
+        let text = format_enabled_handlers(&extensions);
+        assert_eq!(text, ".cbz=3;.cbr=1");
+        assert_eq!(parse_enabled_handlers(&text), extensions[..2].to_vec());
+
+        assert!(parse_enabled_handlers("").is_empty());
+        assert!(parse_enabled_handlers("garbage;.cbz=x;cbz=1;.cb7=0").is_empty());
+    }
+
+    #[test]
+    fn test_repair_restores_missing_handlers() {
+        let recorded = parse_enabled_handlers(".cbz=3;.cbr=1");
+        let mut registry = Registry::new();
+        registry.insert(".cbz".to_string(), (true, false));
+
+        let repaired = run_repair(&recorded, &mut registry);
+
+        assert_eq!(registry[".cbz"], (true, true));
+        assert_eq!(registry[".cbr"], (true, false));
+        assert_eq!(
+            describe_repairs(&repaired),
+            ".cbz: tooltip\n.cbr: thumbnail"
+        );
+    }
+
+    #[test]
+    fn test_repair_leaves_intact_and_unrecorded_handlers() {
+        let recorded = parse_enabled_handlers(".cbz=1");
+        let mut registry = Registry::new();
+        registry.insert(".cbz".to_string(), (true, true));
+        registry.insert(".zip".to_string(), (true, false));
+        let before = registry.clone();
+
+        let repaired = run_repair(&recorded, &mut registry);
+
+        assert!(repaired.is_empty());
+        assert_eq!(registry, before);
+    }
+}
@@ -0,0 +1,212 @@
+///! Settings export/import for CBXManager
+///!
+///! Settings are written as a versioned JSON document so they can be copied
+///! between machines. DLL registration is machine state and is not exported.
+use super::registry_ops::{ADVANCED_DWORD_VALUES, ADVANCED_STRING_VALUES};
+use super::state::{AdvancedSettings, AppState, ExtensionConfig};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Current settings file format version
+const SETTINGS_VERSION: u32 = 1;
+
+/// On-disk representation of exported settings
+#[derive(Debug, Serialize, Deserialize)]
+struct SettingsFile {
+    version: u32,
+    extensions: Vec<ExtensionConfig>,
+    /// Missing in files exported before the master switch existed
+    #[serde(default = "default_thumbnails_enabled")]
+    thumbnails_enabled: bool,
+    sort_enabled: bool,
+    sort_preview_enabled: bool,
+    max_archive_size_mb: u32,
+    force_thumbnail_size: u32,
+    /// Missing in files exported before the size cap existed
+    #[serde(default)]
+    max_thumbnail_dim: u32,
+    #[serde(default)]
+    advanced: AdvancedSettings,
+}
+
+fn default_thumbnails_enabled() -> bool {
+    true
+}
+
+/// Serialize settings to pretty-printed JSON
+pub fn export_settings(state: &AppState, advanced: &AdvancedSettings) -> Result<String> {
+    let file = SettingsFile {
+        version: SETTINGS_VERSION,
+        extensions: state.extensions.clone(),
+        thumbnails_enabled: state.thumbnails_enabled,
+        sort_enabled: state.sort_enabled,
+        sort_preview_enabled: state.sort_preview_enabled,
+        max_archive_size_mb: state.max_archive_size_mb,
+        force_thumbnail_size: state.force_thumbnail_size,
+        max_thumbnail_dim: state.max_thumbnail_dim,
+        advanced: advanced.clone(),
+    };
+
+    serde_json::to_string_pretty(&file).context("Failed to serialize settings")
+}
+
+/// Parse and validate exported settings
+///
+/// # Arguments
+/// * `json` - Settings file contents
+/// * `dll_registered` - Current DLL registration state of this machine
+///
+/// # Returns
+/// * `Ok((AppState, AdvancedSettings))` - Settings ready to be written to registry
+/// * `Err` - Malformed file, unknown values, or handlers enabled without the DLL
+pub fn import_settings(json: &str, dll_registered: bool) -> Result<(AppState, AdvancedSettings)> {
+    let file: SettingsFile = serde_json::from_str(json).context("Invalid settings file")?;
+
+    if file.version > SETTINGS_VERSION {
+        bail!(
+            "Settings file version {} is newer than supported version {}",
+            file.version,
+            SETTINGS_VERSION
+        );
+    }
+
+    let mut state = AppState {
+        thumbnails_enabled: file.thumbnails_enabled,
+        sort_enabled: file.sort_enabled,
+        sort_preview_enabled: file.sort_preview_enabled,
+        max_archive_size_mb: file.max_archive_size_mb,
+        force_thumbnail_size: file.force_thumbnail_size,
+        max_thumbnail_dim: file.max_thumbnail_dim,
+        dll_registered,
+        ..AppState::default()
+    };
+
+    for imported in file.extensions {
+        let Some(ext) = state.get_extension_mut(&imported.extension) else {
+            bail!(
+                "Unsupported extension in settings file: {}",
+                imported.extension
+            );
+        };
+        ext.thumbnail_enabled = imported.thumbnail_enabled;
+        ext.infotip_enabled = imported.infotip_enabled;
+    }
+
+    // Only known names are written back to avoid polluting the config key
+    let advanced = file.advanced;
+    if let Some(name) = advanced
+        .dwords
+        .keys()
+        .find(|name| !ADVANCED_DWORD_VALUES.contains(&name.as_str()))
+    {
+        bail!("Unknown setting in settings file: {}", name);
+    }
+    if let Some(name) = advanced
+        .strings
+        .keys()
+        .find(|name| !ADVANCED_STRING_VALUES.contains(&name.as_str()))
+    {
+        bail!("Unknown setting in settings file: {}", name);
+    }
+
+    if !state.is_valid() {
+        bail!(
+            "Settings enable shell handlers but the DLL is not registered. Register the DLL first."
+        );
+    }
+
+    Ok((state, advanced))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_state() -> AppState {
+        let mut state = AppState {
+            thumbnails_enabled: false,
+            sort_enabled: true,
+            max_archive_size_mb: 512,
+            force_thumbnail_size: 256,
+            max_thumbnail_dim: 512,
+            dll_registered: true,
+            ..AppState::default()
+        };
+        if let Some(ext) = state.get_extension_mut(".cbz") {
+            ext.thumbnail_enabled = true;
+            ext.infotip_enabled = true;
+        }
+        state
+    }
+
+    fn sample_advanced() -> AdvancedSettings {
+        let mut advanced = AdvancedSettings::default();
+        advanced.dwords.insert("EventLog".to_string(), 1);
+        advanced.dwords.insert("MaxNonImageScan".to_string(), 50);
+        advanced
+            .strings
+            .insert("CoverSkipStems".to_string(), "credits;toc".to_string());
+        advanced
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let state = sample_state();
+        let advanced = sample_advanced();
+
+        let json = export_settings(&state, &advanced).unwrap();
+        let (imported, imported_advanced) = import_settings(&json, true).unwrap();
+
+        assert_eq!(imported.extensions, state.extensions);
+        assert_eq!(imported.thumbnails_enabled, state.thumbnails_enabled);
+        assert_eq!(imported.sort_enabled, state.sort_enabled);
+        assert_eq!(imported.sort_preview_enabled, state.sort_preview_enabled);
+        assert_eq!(imported.max_archive_size_mb, state.max_archive_size_mb);
+        assert_eq!(imported.force_thumbnail_size, state.force_thumbnail_size);
+        assert_eq!(imported.max_thumbnail_dim, state.max_thumbnail_dim);
+        assert_eq!(imported_advanced, advanced);
+    }
+
+    #[test]
+    fn test_import_requires_registered_dll() {
+        let json = export_settings(&sample_state(), &AdvancedSettings::default()).unwrap();
+        assert!(import_settings(&json, false).is_err());
+
+        // Without enabled handlers the DLL is not needed
+        let json = export_settings(&AppState::default(), &AdvancedSettings::default()).unwrap();
+        assert!(import_settings(&json, false).is_ok());
+    }
+
+    #[test]
+    fn test_import_rejects_unknown_values() {
+        let mut advanced = AdvancedSettings::default();
+        advanced.dwords.insert("Bogus".to_string(), 1);
+        let json = export_settings(&AppState::default(), &advanced).unwrap();
+        assert!(import_settings(&json, true).is_err());
+
+        let mut state = AppState::default();
+        state.extensions.push(ExtensionConfig::enabled(".exe"));
+        let json = export_settings(&state, &AdvancedSettings::default()).unwrap();
+        assert!(import_settings(&json, true).is_err());
+    }
+
+    #[test]
+    fn test_import_without_master_switch_stays_enabled() {
+        let json = export_settings(&AppState::default(), &AdvancedSettings::default())
+            .unwrap()
+            .replace("\"thumbnails_enabled\": true,", "");
+        assert!(!json.contains("thumbnails_enabled"));
+
+        let (imported, _) = import_settings(&json, true).unwrap();
+        assert!(imported.thumbnails_enabled);
+    }
+
+    #[test]
+    fn test_import_rejects_newer_version_and_garbage() {
+        let json = export_settings(&AppState::default(), &AdvancedSettings::default())
+            .unwrap()
+            .replace("\"version\": 1", "\"version\": 99");
+        assert!(import_settings(&json, true).is_err());
+        assert!(import_settings("not json", true).is_err());
+    }
+}
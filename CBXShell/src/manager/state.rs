@@ -1,9 +1,11 @@
 ///! Application state management for CBXManager
 ///!
 ///! Defines the configuration state for the CBXShell extension
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Configuration for a single file extension
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExtensionConfig {
     /// File extension (e.g., ".cbz", ".cbr")
     pub extension: String,
@@ -34,15 +36,37 @@ impl ExtensionConfig {
     }
 }
 
+/// Config values without a control in the manager (CoverStrategy, EventLog, ...)
+///
+/// Kept by registry value name so they can be carried over by settings
+/// export/import without the manager having to understand each one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdvancedSettings {
+    /// DWORD values by registry value name
+    #[serde(default)]
+    pub dwords: BTreeMap<String, u32>,
+    /// String values by registry value name
+    #[serde(default)]
+    pub strings: BTreeMap<String, String>,
+}
+
 /// Application state for the manager
 #[derive(Debug, Clone)]
 pub struct AppState {
     /// Supported extensions with their handler states
     pub extensions: Vec<ExtensionConfig>,
+    /// Master switch: when false the DLL stays registered but produces no thumbnails
+    pub thumbnails_enabled: bool,
     /// Whether alphabetical sorting is enabled (true) or first-found mode (false)
     pub sort_enabled: bool,
     /// Whether preview pages are sorted alphabetically
     pub sort_preview_enabled: bool,
+    /// Skip archives larger than this many megabytes (0 = unlimited)
+    pub max_archive_size_mb: u32,
+    /// Render every thumbnail at this size in pixels (0 = size requested by Explorer)
+    pub force_thumbnail_size: u32,
+    /// Never render thumbnails larger than this many pixels (0 = uncapped)
+    pub max_thumbnail_dim: u32,
     /// Whether the DLL is registered as a COM server
     pub dll_registered: bool,
 }
@@ -50,16 +74,16 @@ pub struct AppState {
 impl Default for AppState {
     fn default() -> Self {
         Self {
-            extensions: vec![
-                ExtensionConfig::new(".cbz"),
-                ExtensionConfig::new(".cbr"),
-                ExtensionConfig::new(".zip"),
-                ExtensionConfig::new(".rar"),
-                ExtensionConfig::new(".7z"),
-                ExtensionConfig::new(".cb7"),
-            ],
+            extensions: cbxshell::registry::SHELL_EXTENSIONS
+                .iter()
+                .map(|extension| ExtensionConfig::new(*extension))
+                .collect(),
+            thumbnails_enabled: true,
             sort_enabled: false, // Default: sort disabled (NoSort=1) for better performance with large archives
             sort_preview_enabled: false,
+            max_archive_size_mb: 0,  // Default: unlimited
+            force_thumbnail_size: 0, // Default: honor Explorer's requested size
+            max_thumbnail_dim: 0,    // Default: uncapped
             dll_registered: false,
         }
     }
@@ -88,6 +112,17 @@ impl AppState {
             .find(|ext| ext.extension == extension)
     }
 
+    /// Parse a numeric setting (archive size limit, forced size) from user input
+    ///
+    /// Empty input means 0 (unlimited / automatic). Returns `None` for non-numeric input.
+    pub fn parse_numeric_setting(text: &str) -> Option<u32> {
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            return Some(0);
+        }
+        trimmed.parse::<u32>().ok()
+    }
+
     /// Validate state (ensure DLL is registered if handlers are enabled)
     pub fn is_valid(&self) -> bool {
         if self.has_any_handlers_enabled() && !self.dll_registered {
@@ -123,9 +158,22 @@ mod tests {
         assert_eq!(state.extensions.len(), 6);
         assert!(!state.sort_enabled); // Default: sort disabled for performance
         assert!(!state.dll_registered);
+        assert_eq!(state.max_archive_size_mb, 0); // Default: unlimited
+        assert_eq!(state.force_thumbnail_size, 0); // Default: honor cx
+        assert_eq!(state.max_thumbnail_dim, 0); // Default: uncapped
         assert!(!state.has_any_handlers_enabled());
     }
 
+    #[test]
+    fn test_parse_numeric_setting() {
+        assert_eq!(AppState::parse_numeric_setting(""), Some(0));
+        assert_eq!(AppState::parse_numeric_setting("  "), Some(0));
+        assert_eq!(AppState::parse_numeric_setting("0"), Some(0));
+        assert_eq!(AppState::parse_numeric_setting(" 512 "), Some(512));
+        assert_eq!(AppState::parse_numeric_setting("-1"), None);
+        assert_eq!(AppState::parse_numeric_setting("abc"), None);
+    }
+
     #[test]
     fn test_has_any_handlers_enabled() {
         let mut state = AppState::default();
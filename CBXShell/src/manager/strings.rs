@@ -0,0 +1,331 @@
+///! Localized UI strings for CBXManager
+///!
+///! Static tables selected by the OS UI language, with English as fallback
+///! for languages without a translation.
+use std::sync::OnceLock;
+
+/// UI language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    English,
+    Korean,
+    Japanese,
+}
+
+impl Lang {
+    /// Map a Windows LANGID to a supported language (English if unsupported)
+    pub fn from_langid(langid: u16) -> Self {
+        // PRIMARYLANGID: low 10 bits
+        match langid & 0x3FF {
+            0x12 => Lang::Korean,
+            0x11 => Lang::Japanese,
+            _ => Lang::English,
+        }
+    }
+
+    /// Language of the current user's Windows UI
+    #[cfg(windows)]
+    pub fn current() -> Self {
+        // UNAVOIDABLE UNSAFE: GetUserDefaultUILanguage is Windows FFI
+        // Safety: no arguments, returns a LANGID by value
+        let langid = unsafe { windows::Win32::Globalization::GetUserDefaultUILanguage() };
+        Self::from_langid(langid)
+    }
+
+    #[cfg(not(windows))]
+    pub fn current() -> Self {
+        Lang::English
+    }
+}
+
+/// Translatable UI text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Text {
+    DllRegistered,
+    DllNotRegistered,
+    ThumbnailsEnabled,
+    FileTypes,
+    CbzArchives,
+    ZipArchives,
+    CbrArchives,
+    RarArchives,
+    Cb7Archives,
+    SevenZipArchives,
+    Advanced,
+    SortImages,
+    SortImagesHelp,
+    SortPreview,
+    SortPreviewHelp,
+    MaxArchiveSize,
+    ForceThumbnailSize,
+    MaxThumbnailDim,
+    Ok,
+    Cancel,
+    Apply,
+    RestartTitle,
+    RestartMessage,
+    StaleDllTitle,
+    StaleDllMessage,
+    RepairTitle,
+    RepairMessage,
+    RepairNothing,
+    RepairFailed,
+    ToolsMenu,
+    RegisterDll,
+    UnregisterDll,
+    ExportSettings,
+    ExportSettingsMenu,
+    ExportDone,
+    ExportFailed,
+    ImportSettings,
+    ImportSettingsMenu,
+    ImportDone,
+    ImportFailed,
+    EmbedPreview,
+    EmbedPreviewMenu,
+    EmbedPreviewDone,
+    EmbedPreviewFailed,
+    TestThumbnail,
+    TestThumbnailMenu,
+    TestThumbnailDone,
+    TestThumbnailFailed,
+    About,
+}
+
+/// Look up text in the current UI language
+pub fn tr(key: Text) -> &'static str {
+    static LANG: OnceLock<Lang> = OnceLock::new();
+    lookup(*LANG.get_or_init(Lang::current), key)
+}
+
+/// Look up text in a specific language
+pub fn lookup(lang: Lang, key: Text) -> &'static str {
+    match lang {
+        Lang::English => english(key),
+        Lang::Korean => korean(key),
+        Lang::Japanese => japanese(key),
+    }
+}
+
+fn english(key: Text) -> &'static str {
+    match key {
+        Text::DllRegistered => "DLL Registered",
+        Text::DllNotRegistered => "DLL Not Registered",
+        Text::ThumbnailsEnabled => "Show archive thumbnails",
+        Text::FileTypes => "File types",
+        Text::CbzArchives => "CBZ Image Archives",
+        Text::ZipArchives => "ZIP Archives",
+        Text::CbrArchives => "CBR Image Archives",
+        Text::RarArchives => "RAR Archives",
+        Text::Cb7Archives => "CB7 Image Archives",
+        Text::SevenZipArchives => "7Z Archives",
+        Text::Advanced => "Advanced",
+        Text::SortImages => "Sort images alphabetically",
+        Text::SortImagesHelp => {
+            "Uncheck to sort images by archive order.\r\nRequired to display custom thumbnail."
+        }
+        Text::SortPreview => "Sort preview pages alphabetically",
+        Text::SortPreviewHelp => "Uncheck to use archive order in the preview pane.",
+        Text::MaxArchiveSize => "Max archive size (MB, 0 = unlimited):",
+        Text::ForceThumbnailSize => "Force thumbnail size (px, 0 = auto):",
+        Text::MaxThumbnailDim => "Max thumbnail size (px, 0 = no limit):",
+        Text::Ok => "OK",
+        Text::Cancel => "Cancel",
+        Text::Apply => "Apply",
+        Text::RestartTitle => "Restart Explorer?",
+        Text::RestartMessage => {
+            "Changes have been saved. Would you like to restart Windows Explorer now to apply them?\n\n(You can also restart manually later)"
+        }
+        Text::StaleDllTitle => "DLL Not Found",
+        Text::StaleDllMessage => {
+            "The registered CBXShell DLL no longer exists, so thumbnails will not be shown.\nUse Tools > Register DLL to register it again from its current location."
+        }
+        Text::RepairTitle => "Repair Handlers",
+        Text::RepairMessage => {
+            "Some thumbnail/tooltip registrations had been removed (for example by a Windows update) and were restored:"
+        }
+        Text::RepairNothing => "All enabled handlers are registered. Nothing to repair.",
+        Text::RepairFailed => "Failed to repair handlers:",
+        Text::ToolsMenu => "&Tools",
+        Text::RegisterDll => "Register DLL",
+        Text::UnregisterDll => "Unregister DLL",
+        Text::ExportSettings => "Export Settings",
+        Text::ExportSettingsMenu => "Export Settings...",
+        Text::ExportDone => "Settings exported successfully.",
+        Text::ExportFailed => "Failed to export settings:",
+        Text::ImportSettings => "Import Settings",
+        Text::ImportSettingsMenu => "Import Settings...",
+        Text::ImportDone => "Settings imported successfully.",
+        Text::ImportFailed => "Failed to import settings:",
+        Text::EmbedPreview => "Embed Preview",
+        Text::EmbedPreviewMenu => "Embed Preview...",
+        Text::EmbedPreviewDone => {
+            "Preview embedded. Explorer uses it when EmbeddedPreview is enabled."
+        }
+        Text::EmbedPreviewFailed => "Failed to embed preview:",
+        Text::TestThumbnail => "Test Thumbnail",
+        Text::TestThumbnailMenu => "Test Thumbnail...",
+        Text::TestThumbnailDone => "Cover extracted:",
+        Text::TestThumbnailFailed => "Failed to extract the cover:",
+        Text::About => "About",
+    }
+}
+
+fn korean(key: Text) -> &'static str {
+    match key {
+        Text::DllRegistered => "DLL 등록됨",
+        Text::DllNotRegistered => "DLL 등록되지 않음",
+        Text::ThumbnailsEnabled => "압축 파일 썸네일 표시",
+        Text::FileTypes => "파일 형식",
+        Text::CbzArchives => "CBZ 이미지 압축 파일",
+        Text::ZipArchives => "ZIP 압축 파일",
+        Text::CbrArchives => "CBR 이미지 압축 파일",
+        Text::RarArchives => "RAR 압축 파일",
+        Text::Cb7Archives => "CB7 이미지 압축 파일",
+        Text::SevenZipArchives => "7Z 압축 파일",
+        Text::Advanced => "고급",
+        Text::SortImages => "이미지를 이름순으로 정렬",
+        Text::SortImagesHelp => {
+            "해제하면 압축 파일 내 순서를 사용합니다.\r\n사용자 지정 썸네일을 표시하려면 필요합니다."
+        }
+        Text::SortPreview => "미리 보기 페이지를 이름순으로 정렬",
+        Text::SortPreviewHelp => "해제하면 미리 보기 창에서 압축 파일 내 순서를 사용합니다.",
+        Text::MaxArchiveSize => "최대 압축 파일 크기 (MB, 0 = 무제한):",
+        Text::ForceThumbnailSize => "썸네일 크기 고정 (px, 0 = 자동):",
+        Text::MaxThumbnailDim => "최대 썸네일 크기 (px, 0 = 제한 없음):",
+        Text::Ok => "확인",
+        Text::Cancel => "취소",
+        Text::Apply => "적용",
+        Text::RestartTitle => "탐색기를 다시 시작할까요?",
+        Text::RestartMessage => {
+            "변경 사항이 저장되었습니다. 적용하려면 지금 Windows 탐색기를 다시 시작하시겠습니까?\n\n(나중에 직접 다시 시작할 수도 있습니다)"
+        }
+        Text::StaleDllTitle => "DLL을 찾을 수 없음",
+        Text::StaleDllMessage => {
+            "등록된 CBXShell DLL이 더 이상 존재하지 않아 썸네일이 표시되지 않습니다.\n도구 > DLL 등록으로 현재 위치에서 다시 등록하세요."
+        }
+        Text::RepairTitle => "핸들러 복구",
+        Text::RepairMessage => {
+            "일부 썸네일/도구 설명 등록이 제거되어 (예: Windows 업데이트) 다시 등록했습니다:"
+        }
+        Text::RepairNothing => "사용하도록 설정한 핸들러가 모두 등록되어 있습니다. 복구할 항목이 없습니다.",
+        Text::RepairFailed => "핸들러를 복구하지 못했습니다:",
+        Text::ToolsMenu => "도구(&T)",
+        Text::RegisterDll => "DLL 등록",
+        Text::UnregisterDll => "DLL 등록 해제",
+        Text::ExportSettings => "설정 내보내기",
+        Text::ExportSettingsMenu => "설정 내보내기...",
+        Text::ExportDone => "설정을 내보냈습니다.",
+        Text::ExportFailed => "설정을 내보내지 못했습니다:",
+        Text::ImportSettings => "설정 가져오기",
+        Text::ImportSettingsMenu => "설정 가져오기...",
+        Text::ImportDone => "설정을 가져왔습니다.",
+        Text::ImportFailed => "설정을 가져오지 못했습니다:",
+        Text::EmbedPreview => "미리 보기 포함",
+        Text::EmbedPreviewMenu => "미리 보기 포함...",
+        Text::EmbedPreviewDone => {
+            "미리 보기를 포함했습니다. EmbeddedPreview가 켜져 있으면 탐색기가 이를 사용합니다."
+        }
+        Text::EmbedPreviewFailed => "미리 보기를 포함하지 못했습니다:",
+        Text::TestThumbnail => "썸네일 테스트",
+        Text::TestThumbnailMenu => "썸네일 테스트...",
+        Text::TestThumbnailDone => "표지를 추출했습니다:",
+        Text::TestThumbnailFailed => "표지를 추출하지 못했습니다:",
+        Text::About => "정보",
+    }
+}
+
+fn japanese(key: Text) -> &'static str {
+    match key {
+        Text::DllRegistered => "DLL 登録済み",
+        Text::DllNotRegistered => "DLL 未登録",
+        Text::ThumbnailsEnabled => "アーカイブのサムネイルを表示する",
+        Text::FileTypes => "ファイルの種類",
+        Text::CbzArchives => "CBZ 画像アーカイブ",
+        Text::ZipArchives => "ZIP アーカイブ",
+        Text::CbrArchives => "CBR 画像アーカイブ",
+        Text::RarArchives => "RAR アーカイブ",
+        Text::Cb7Archives => "CB7 画像アーカイブ",
+        Text::SevenZipArchives => "7Z アーカイブ",
+        Text::Advanced => "詳細設定",
+        Text::SortImages => "画像を名前順に並べ替える",
+        Text::SortImagesHelp => {
+            "オフにするとアーカイブ内の順序を使用します。\r\nカスタムサムネイルの表示に必要です。"
+        }
+        Text::SortPreview => "プレビューのページを名前順に並べ替える",
+        Text::SortPreviewHelp => "オフにするとプレビューでアーカイブ内の順序を使用します。",
+        Text::MaxArchiveSize => "最大アーカイブサイズ (MB、0 = 無制限):",
+        Text::ForceThumbnailSize => "サムネイルサイズを固定 (px、0 = 自動):",
+        Text::MaxThumbnailDim => "最大サムネイルサイズ (px、0 = 制限なし):",
+        Text::Ok => "OK",
+        Text::Cancel => "キャンセル",
+        Text::Apply => "適用",
+        Text::RestartTitle => "エクスプローラーを再起動しますか?",
+        Text::RestartMessage => {
+            "変更を保存しました。適用するために今すぐ Windows エクスプローラーを再起動しますか?\n\n(後で手動で再起動することもできます)"
+        }
+        Text::StaleDllTitle => "DLL が見つかりません",
+        Text::StaleDllMessage => {
+            "登録された CBXShell DLL が存在しないため、サムネイルは表示されません。\nツール > DLL を登録 で現在の場所から再登録してください。"
+        }
+        Text::RepairTitle => "ハンドラーの修復",
+        Text::RepairMessage => {
+            "一部のサムネイル/ツールチップの登録が削除されていたため (Windows Update など)、復元しました:"
+        }
+        Text::RepairNothing => "有効なハンドラーはすべて登録されています。修復する項目はありません。",
+        Text::RepairFailed => "ハンドラーを修復できませんでした:",
+        Text::ToolsMenu => "ツール(&T)",
+        Text::RegisterDll => "DLL を登録",
+        Text::UnregisterDll => "DLL の登録を解除",
+        Text::ExportSettings => "設定のエクスポート",
+        Text::ExportSettingsMenu => "設定のエクスポート...",
+        Text::ExportDone => "設定をエクスポートしました。",
+        Text::ExportFailed => "設定をエクスポートできませんでした:",
+        Text::ImportSettings => "設定のインポート",
+        Text::ImportSettingsMenu => "設定のインポート...",
+        Text::ImportDone => "設定をインポートしました。",
+        Text::ImportFailed => "設定をインポートできませんでした:",
+        Text::EmbedPreview => "プレビューを埋め込む",
+        Text::EmbedPreviewMenu => "プレビューを埋め込む...",
+        Text::EmbedPreviewDone => {
+            "プレビューを埋め込みました。EmbeddedPreview が有効な場合、エクスプローラーはこれを使用します。"
+        }
+        Text::EmbedPreviewFailed => "プレビューを埋め込めませんでした:",
+        Text::TestThumbnail => "サムネイルのテスト",
+        Text::TestThumbnailMenu => "サムネイルのテスト...",
+        Text::TestThumbnailDone => "表紙を抽出しました:",
+        Text::TestThumbnailFailed => "表紙を抽出できませんでした:",
+        Text::About => "バージョン情報",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_langid() {
+        assert_eq!(Lang::from_langid(0x0409), Lang::English); // en-US
+        assert_eq!(Lang::from_langid(0x0412), Lang::Korean); // ko-KR
+        assert_eq!(Lang::from_langid(0x0411), Lang::Japanese); // ja-JP
+        assert_eq!(Lang::from_langid(0x0407), Lang::English); // de-DE falls back
+    }
+
+    #[test]
+    fn test_lookup_translates_and_falls_back() {
+        assert_eq!(lookup(Lang::Korean, Text::Cancel), "취소");
+        assert_eq!(lookup(Lang::Japanese, Text::Apply), "適用");
+        assert_ne!(
+            lookup(Lang::Korean, Text::SortImages),
+            lookup(Lang::English, Text::SortImages)
+        );
+
+        // Menu items and dialogs are translated too
+        assert_eq!(lookup(Lang::Korean, Text::EmbedPreview), "미리 보기 포함");
+        assert_eq!(lookup(Lang::Japanese, Text::ToolsMenu), "ツール(&T)");
+
+        let german = Lang::from_langid(0x0407);
+        assert_eq!(lookup(german, Text::Cancel), "Cancel");
+        assert_eq!(lookup(german, Text::EmbedPreviewMenu), "Embed Preview...");
+    }
+}
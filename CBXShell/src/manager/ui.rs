@@ -1,37 +1,49 @@
 ///! Native-windows-gui based UI for CBXManager
 ///!
 ///! Compact, professional interface with proper alignment and spacing
-use super::{registry_ops, state::AppState, utils};
+use super::strings::{tr, Text};
+use super::{registry_ops, repair, settings_file, state::AppState, utils, worker};
 use native_windows_derive as nwd;
 use native_windows_gui as nwg;
 use nwd::NwgUi;
 use std::cell::{Cell, RefCell};
 
 const WINDOW_WIDTH: i32 = 360;
-const WINDOW_HEIGHT: i32 = 370;
+const WINDOW_HEIGHT: i32 = 474;
 
 const MARGIN_X: i32 = 10;
 const STATUS_Y: i32 = 12;
+const MASTER_TOGGLE_Y: i32 = 38;
 
 const GROUP_WIDTH: i32 = 320;
-const FILE_GROUP_Y: i32 = 44;
+const FILE_GROUP_Y: i32 = 68;
 const FILE_GROUP_HEIGHT: i32 = 138;
 const ADVANCED_GROUP_Y: i32 = FILE_GROUP_Y + FILE_GROUP_HEIGHT + 10;
-const ADVANCED_GROUP_HEIGHT: i32 = 120;
+const ADVANCED_GROUP_HEIGHT: i32 = 200;
 
 const BUTTON_WIDTH: i32 = 80;
 const BUTTON_HEIGHT: i32 = 24;
 const BUTTON_SPACING: i32 = 8;
-const BUTTON_Y: i32 = 320;
+const BUTTON_Y: i32 = 424;
 const BUTTON_ROW_X: i32 = WINDOW_WIDTH - MARGIN_X - (BUTTON_WIDTH * 3 + BUTTON_SPACING * 2);
 
 const CHECKBOX_X: i32 = 12;
 const CHECKBOX_Y_START: i32 = 18;
 const CHECKBOX_STEP: i32 = 17;
 
+/// Longest side of previews embedded by Tools > Embed Preview (extra large
+/// icons at 200% scaling)
+const EMBEDDED_PREVIEW_SIZE: u32 = 512;
+
+/// Longest side of the cover extracted by Tools > Test Thumbnail (Explorer's
+/// largest icon size)
+const TEST_THUMBNAIL_SIZE: u32 = 256;
+
 thread_local! {
     static APP_STATE: RefCell<AppState> = RefCell::new(AppState::default());
     static NEEDS_RESTART: Cell<bool> = Cell::new(false);
+    static PENDING_EMBED: worker::Pending<()> = worker::Pending::default();
+    static PENDING_TEST: worker::Pending<image::RgbaImage> = worker::Pending::default();
 }
 
 #[derive(Default, NwgUi)]
@@ -45,24 +57,80 @@ pub struct CBXManagerApp {
     #[nwg_events(OnWindowClose: [CBXManagerApp::exit])]
     window: nwg::Window,
 
-    #[nwg_control(parent: window, text: "&Tools")]
+    #[nwg_control(parent: window, text: tr(Text::ToolsMenu))]
     tools_menu: nwg::Menu,
 
-    #[nwg_control(parent: tools_menu, text: "Register DLL")]
+    #[nwg_control(parent: tools_menu, text: tr(Text::RegisterDll))]
     #[nwg_events(OnMenuItemSelected: [CBXManagerApp::on_register_dll])]
     register_menu: nwg::MenuItem,
 
-    #[nwg_control(parent: tools_menu, text: "Unregister DLL")]
+    #[nwg_control(parent: tools_menu, text: tr(Text::UnregisterDll))]
     #[nwg_events(OnMenuItemSelected: [CBXManagerApp::on_unregister_dll])]
     unregister_menu: nwg::MenuItem,
 
+    #[nwg_control(parent: tools_menu, text: tr(Text::RepairTitle))]
+    #[nwg_events(OnMenuItemSelected: [CBXManagerApp::on_repair_handlers])]
+    repair_menu: nwg::MenuItem,
+
+    #[nwg_control(parent: tools_menu, text: tr(Text::ExportSettingsMenu))]
+    #[nwg_events(OnMenuItemSelected: [CBXManagerApp::on_export_settings])]
+    export_menu: nwg::MenuItem,
+
+    #[nwg_control(parent: tools_menu, text: tr(Text::ImportSettingsMenu))]
+    #[nwg_events(OnMenuItemSelected: [CBXManagerApp::on_import_settings])]
+    import_menu: nwg::MenuItem,
+
+    #[nwg_control(parent: tools_menu, text: tr(Text::EmbedPreviewMenu))]
+    #[nwg_events(OnMenuItemSelected: [CBXManagerApp::on_embed_preview])]
+    embed_preview_menu: nwg::MenuItem,
+
+    #[nwg_control(parent: window)]
+    #[nwg_events(OnNotice: [CBXManagerApp::on_embed_preview_done])]
+    embed_preview_notice: nwg::Notice,
+
+    #[nwg_control(parent: tools_menu, text: tr(Text::TestThumbnailMenu))]
+    #[nwg_events(OnMenuItemSelected: [CBXManagerApp::on_test_thumbnail])]
+    test_thumbnail_menu: nwg::MenuItem,
+
+    #[nwg_control(parent: window)]
+    #[nwg_events(OnNotice: [CBXManagerApp::on_test_thumbnail_done])]
+    test_thumbnail_notice: nwg::Notice,
+
     #[nwg_control(parent: tools_menu)]
     tools_separator: nwg::MenuSeparator,
 
-    #[nwg_control(parent: tools_menu, text: "About")]
+    #[nwg_control(parent: tools_menu, text: tr(Text::About))]
     #[nwg_events(OnMenuItemSelected: [CBXManagerApp::on_about])]
     about_menu: nwg::MenuItem,
 
+    #[nwg_resource(
+        title: tr(Text::ExportSettings),
+        action: nwg::FileDialogAction::Save,
+        filters: "JSON(*.json)|All files(*.*)"
+    )]
+    export_dialog: nwg::FileDialog,
+
+    #[nwg_resource(
+        title: tr(Text::ImportSettings),
+        action: nwg::FileDialogAction::Open,
+        filters: "JSON(*.json)|All files(*.*)"
+    )]
+    import_dialog: nwg::FileDialog,
+
+    #[nwg_resource(
+        title: tr(Text::EmbedPreview),
+        action: nwg::FileDialogAction::Open,
+        filters: "CBZ/ZIP archives(*.cbz;*.zip)"
+    )]
+    embed_preview_dialog: nwg::FileDialog,
+
+    #[nwg_resource(
+        title: tr(Text::TestThumbnail),
+        action: nwg::FileDialogAction::Open,
+        filters: "Comic archives(*.cbz;*.cbr;*.cb7;*.zip;*.rar;*.7z)|All files(*.*)"
+    )]
+    test_thumbnail_dialog: nwg::FileDialog,
+
     #[nwg_resource(family: "Segoe UI", size: 16)]
     ui_font: nwg::Font,
 
@@ -82,6 +150,14 @@ pub struct CBXManagerApp {
     )]
     status_text: nwg::Label,
 
+    #[nwg_control(
+        parent: window,
+        text: "Show archive thumbnails",
+        position: (MARGIN_X, MASTER_TOGGLE_Y),
+        size: (300, 18)
+    )]
+    enabled_checkbox: nwg::CheckBox,
+
     #[nwg_control(
         parent: window,
         position: (MARGIN_X, FILE_GROUP_Y),
@@ -194,6 +270,57 @@ pub struct CBXManagerApp {
     )]
     sort_preview_help_label: nwg::Label,
 
+    #[nwg_control(
+        parent: advanced_group_frame,
+        text: "Max archive size (MB, 0 = unlimited):",
+        position: (CHECKBOX_X, 114),
+        size: (220, 20)
+    )]
+    max_size_label: nwg::Label,
+
+    #[nwg_control(
+        parent: advanced_group_frame,
+        text: "0",
+        position: (CHECKBOX_X + 224, 112),
+        size: (70, 22),
+        flags: "VISIBLE|NUMBER"
+    )]
+    max_size_input: nwg::TextInput,
+
+    #[nwg_control(
+        parent: advanced_group_frame,
+        text: "Force thumbnail size (px, 0 = auto):",
+        position: (CHECKBOX_X, 140),
+        size: (220, 20)
+    )]
+    force_size_label: nwg::Label,
+
+    #[nwg_control(
+        parent: advanced_group_frame,
+        text: "0",
+        position: (CHECKBOX_X + 224, 138),
+        size: (70, 22),
+        flags: "VISIBLE|NUMBER"
+    )]
+    force_size_input: nwg::TextInput,
+
+    #[nwg_control(
+        parent: advanced_group_frame,
+        text: "Max thumbnail size (px, 0 = no limit):",
+        position: (CHECKBOX_X, 166),
+        size: (220, 20)
+    )]
+    max_dim_label: nwg::Label,
+
+    #[nwg_control(
+        parent: advanced_group_frame,
+        text: "0",
+        position: (CHECKBOX_X + 224, 164),
+        size: (70, 22),
+        flags: "VISIBLE|NUMBER"
+    )]
+    max_dim_input: nwg::TextInput,
+
     #[nwg_control(
         parent: window,
         text: "OK",
@@ -229,19 +356,62 @@ impl CBXManagerApp {
             *stored.borrow_mut() = state;
         });
         self.apply_font();
+        self.apply_strings();
         self.sync_controls_from_state();
     }
 
+    /// Warn if the registered DLL was moved or deleted after registration
+    pub fn check_dll_path(&self) {
+        if let registry_ops::InprocServerStatus::Stale(path) = registry_ops::check_inproc_server() {
+            utils::show_error(
+                tr(Text::StaleDllTitle),
+                &format!("{}\n\n{}", tr(Text::StaleDllMessage), path.display()),
+            );
+        }
+    }
+
+    /// Restore enabled handlers whose registry keys were removed since the last save
+    pub fn check_handlers(&self) {
+        self.repair_handlers(false);
+    }
+
+    fn repair_handlers(&self, report_nothing: bool) {
+        match registry_ops::repair_extension_handlers() {
+            Ok(repaired) if repaired.is_empty() => {
+                if report_nothing {
+                    utils::show_success(tr(Text::RepairTitle), tr(Text::RepairNothing));
+                }
+            }
+            Ok(repaired) => {
+                self.initialize_state();
+                self.set_needs_restart(true);
+                utils::show_success(
+                    tr(Text::RepairTitle),
+                    &format!(
+                        "{}\n\n{}",
+                        tr(Text::RepairMessage),
+                        repair::describe_repairs(&repaired)
+                    ),
+                );
+            }
+            Err(e) => utils::show_error(
+                tr(Text::RepairTitle),
+                &format!("{} {:#}", tr(Text::RepairFailed), e),
+            ),
+        }
+    }
+
     fn sync_controls_from_state(&self) {
         let state = self.get_state();
 
         self.status_icon
             .set_text(if state.dll_registered { "✓" } else { "⚠" });
-        self.status_text.set_text(if state.dll_registered {
-            "DLL Registered"
+        self.status_text.set_text(tr(if state.dll_registered {
+            Text::DllRegistered
         } else {
-            "DLL Not Registered"
-        });
+            Text::DllNotRegistered
+        }));
+        self.set_checkbox(&self.enabled_checkbox, state.thumbnails_enabled);
 
         let zip_family_enabled =
             self.extension_enabled(&state, ".zip") || self.extension_enabled(&state, ".cbz");
@@ -257,6 +427,12 @@ impl CBXManagerApp {
         self.set_checkbox(&self.sevenz_checkbox, sevenz_family_enabled);
         self.set_checkbox(&self.sort_checkbox, state.sort_enabled);
         self.set_checkbox(&self.sort_preview_checkbox, state.sort_preview_enabled);
+        self.max_size_input
+            .set_text(&state.max_archive_size_mb.to_string());
+        self.force_size_input
+            .set_text(&state.force_thumbnail_size.to_string());
+        self.max_dim_input
+            .set_text(&state.max_thumbnail_dim.to_string());
     }
 
     fn extension_enabled(&self, state: &AppState, extension: &str) -> bool {
@@ -271,6 +447,7 @@ impl CBXManagerApp {
 
         self.status_icon.set_font(font);
         self.status_text.set_font(font);
+        self.enabled_checkbox.set_font(font);
         self.file_group_label.set_font(font);
         self.cbz_checkbox.set_font(font);
         self.zip_checkbox.set_font(font);
@@ -283,11 +460,40 @@ impl CBXManagerApp {
         self.sort_help_label.set_font(font);
         self.sort_preview_checkbox.set_font(font);
         self.sort_preview_help_label.set_font(font);
+        self.max_size_label.set_font(font);
+        self.max_size_input.set_font(font);
+        self.force_size_label.set_font(font);
+        self.force_size_input.set_font(font);
+        self.max_dim_label.set_font(font);
+        self.max_dim_input.set_font(font);
         self.ok_button.set_font(font);
         self.cancel_button.set_font(font);
         self.apply_button.set_font(font);
     }
 
+    fn apply_strings(&self) {
+        self.enabled_checkbox.set_text(tr(Text::ThumbnailsEnabled));
+        self.file_group_label.set_text(tr(Text::FileTypes));
+        self.cbz_checkbox.set_text(tr(Text::CbzArchives));
+        self.zip_checkbox.set_text(tr(Text::ZipArchives));
+        self.cbr_checkbox.set_text(tr(Text::CbrArchives));
+        self.rar_checkbox.set_text(tr(Text::RarArchives));
+        self.cb7_checkbox.set_text(tr(Text::Cb7Archives));
+        self.sevenz_checkbox.set_text(tr(Text::SevenZipArchives));
+        self.advanced_group_label.set_text(tr(Text::Advanced));
+        self.sort_checkbox.set_text(tr(Text::SortImages));
+        self.sort_help_label.set_text(tr(Text::SortImagesHelp));
+        self.sort_preview_checkbox.set_text(tr(Text::SortPreview));
+        self.sort_preview_help_label
+            .set_text(tr(Text::SortPreviewHelp));
+        self.max_size_label.set_text(tr(Text::MaxArchiveSize));
+        self.force_size_label.set_text(tr(Text::ForceThumbnailSize));
+        self.max_dim_label.set_text(tr(Text::MaxThumbnailDim));
+        self.ok_button.set_text(tr(Text::Ok));
+        self.cancel_button.set_text(tr(Text::Cancel));
+        self.apply_button.set_text(tr(Text::Apply));
+    }
+
     fn set_checkbox(&self, checkbox: &nwg::CheckBox, enabled: bool) {
         let state = if enabled {
             nwg::CheckBoxState::Checked
@@ -304,8 +510,19 @@ impl CBXManagerApp {
     fn build_state_from_controls(&self) -> AppState {
         let mut state = self.get_state();
 
+        state.thumbnails_enabled = self.checkbox_value(&self.enabled_checkbox);
         state.sort_enabled = self.checkbox_value(&self.sort_checkbox);
         state.sort_preview_enabled = self.checkbox_value(&self.sort_preview_checkbox);
+        // Keep the previous limit if the input is not a valid number
+        if let Some(max_mb) = AppState::parse_numeric_setting(&self.max_size_input.text()) {
+            state.max_archive_size_mb = max_mb;
+        }
+        if let Some(size) = AppState::parse_numeric_setting(&self.force_size_input.text()) {
+            state.force_thumbnail_size = size;
+        }
+        if let Some(size) = AppState::parse_numeric_setting(&self.max_dim_input.text()) {
+            state.max_thumbnail_dim = size;
+        }
 
         let zip_family_enabled =
             self.checkbox_value(&self.zip_checkbox) || self.checkbox_value(&self.cbz_checkbox);
@@ -380,6 +597,141 @@ impl CBXManagerApp {
         }
     }
 
+    fn on_repair_handlers(&self) {
+        self.repair_handlers(true);
+    }
+
+    fn on_export_settings(&self) {
+        if !self.export_dialog.run(Some(&self.window)) {
+            return;
+        }
+        let Ok(path) = self.export_dialog.get_selected_item() else {
+            return;
+        };
+
+        let state = self.build_state_from_controls();
+        let result = registry_ops::read_advanced_settings()
+            .and_then(|advanced| settings_file::export_settings(&state, &advanced))
+            .and_then(|json| std::fs::write(&path, json).map_err(Into::into));
+
+        match result {
+            Ok(()) => utils::show_success(tr(Text::ExportSettings), tr(Text::ExportDone)),
+            Err(e) => utils::show_error(
+                tr(Text::ExportSettings),
+                &format!("{} {:#}", tr(Text::ExportFailed), e),
+            ),
+        }
+    }
+
+    fn on_import_settings(&self) {
+        if !self.import_dialog.run(Some(&self.window)) {
+            return;
+        }
+        let Ok(path) = self.import_dialog.get_selected_item() else {
+            return;
+        };
+
+        let dll_registered = self.get_state().dll_registered;
+        let result = std::fs::read_to_string(&path)
+            .map_err(Into::into)
+            .and_then(|json| settings_file::import_settings(&json, dll_registered))
+            .and_then(|(state, advanced)| {
+                registry_ops::write_app_state(&state)?;
+                registry_ops::write_advanced_settings(&advanced)?;
+                Ok(state)
+            });
+
+        match result {
+            Ok(state) => {
+                APP_STATE.with(|stored| {
+                    *stored.borrow_mut() = state;
+                });
+                self.sync_controls_from_state();
+                self.set_needs_restart(true);
+                utils::show_success(tr(Text::ImportSettings), tr(Text::ImportDone));
+            }
+            Err(e) => utils::show_error(
+                tr(Text::ImportSettings),
+                &format!("{} {:#}", tr(Text::ImportFailed), e),
+            ),
+        }
+    }
+
+    /// Write the cover of a chosen CBZ/ZIP into it as `__cover.jpg`
+    ///
+    /// Large archives take a while to rewrite, so this runs on a worker
+    /// thread; the menu item stays disabled until `on_embed_preview_done`.
+    fn on_embed_preview(&self) {
+        if !self.embed_preview_dialog.run(Some(&self.window)) {
+            return;
+        }
+        let Ok(path) = self.embed_preview_dialog.get_selected_item() else {
+            return;
+        };
+
+        self.embed_preview_menu.set_enabled(false);
+        let on_done =
+            PENDING_EMBED.with(|pending| pending.completion(self.embed_preview_notice.sender()));
+        worker::spawn_preview_embedding(path.into(), EMBEDDED_PREVIEW_SIZE, on_done);
+    }
+
+    fn on_embed_preview_done(&self) {
+        let Some(result) = PENDING_EMBED.with(|pending| pending.take()) else {
+            return;
+        };
+        self.embed_preview_menu.set_enabled(true);
+
+        match result {
+            Ok(()) => utils::show_success(tr(Text::EmbedPreview), tr(Text::EmbedPreviewDone)),
+            Err(e) => utils::show_error(
+                tr(Text::EmbedPreview),
+                &format!("{} {:#}", tr(Text::EmbedPreviewFailed), e),
+            ),
+        }
+    }
+
+    /// Extract the cover of a chosen archive the way Explorer would, to check
+    /// that thumbnails work for it
+    ///
+    /// Runs on a worker thread; the menu item stays disabled until
+    /// `on_test_thumbnail_done`.
+    fn on_test_thumbnail(&self) {
+        if !self.test_thumbnail_dialog.run(Some(&self.window)) {
+            return;
+        }
+        let Ok(path) = self.test_thumbnail_dialog.get_selected_item() else {
+            return;
+        };
+
+        self.test_thumbnail_menu.set_enabled(false);
+        let on_done =
+            PENDING_TEST.with(|pending| pending.completion(self.test_thumbnail_notice.sender()));
+        worker::spawn_cover_extraction(path.into(), TEST_THUMBNAIL_SIZE, on_done);
+    }
+
+    fn on_test_thumbnail_done(&self) {
+        let Some(result) = PENDING_TEST.with(|pending| pending.take()) else {
+            return;
+        };
+        self.test_thumbnail_menu.set_enabled(true);
+
+        match result {
+            Ok(cover) => utils::show_success(
+                tr(Text::TestThumbnail),
+                &format!(
+                    "{} {} x {}",
+                    tr(Text::TestThumbnailDone),
+                    cover.width(),
+                    cover.height()
+                ),
+            ),
+            Err(e) => utils::show_error(
+                tr(Text::TestThumbnail),
+                &format!("{} {:#}", tr(Text::TestThumbnailFailed), e),
+            ),
+        }
+    }
+
     fn on_ok(&self) {
         self.apply_settings();
         if self.needs_restart() && utils::prompt_restart_explorer() {
@@ -397,10 +749,20 @@ impl CBXManagerApp {
     }
 
     fn on_about(&self) {
-        utils::show_success(
-            "CBXShell Manager",
-            "CBXShell AVIF policy (v5.1.2)\n\nSupported AVIF path:\n- Windows WIC codec pipeline\n\nRequired codecs:\n- HEIF Image Extensions\n- AV1 Video Extension\n\nIf AVIF thumbnails fail, install/update both codecs and restart Explorer.\n\nNote: Software fallback may exist for compatibility, but support triage is based on the WIC path first.",
+        let archives: Vec<&str> = cbxshell::supported_archive_types()
+            .iter()
+            .map(|t| t.as_str())
+            .collect();
+        let images: Vec<&str> = cbxshell::supported_image_formats()
+            .iter()
+            .map(|f| f.as_str())
+            .collect();
+        let message = format!(
+            "CBXShell AVIF policy (v5.1.2)\n\nSupported AVIF path:\n- Windows WIC codec pipeline\n\nRequired codecs:\n- HEIF Image Extensions\n- AV1 Video Extension\n\nIf AVIF thumbnails fail, install/update both codecs and restart Explorer.\n\nNote: Software fallback may exist for compatibility, but support triage is based on the WIC path first.\n\nSupported archives: {}\nSupported images: {}",
+            archives.join(", "),
+            images.join(", ")
         );
+        utils::show_success("CBXShell Manager", &message);
     }
 
     fn exit(&self) {
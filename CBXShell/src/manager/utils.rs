@@ -1,14 +1,17 @@
 ///! Utility functions for CBXManager
 ///!
 ///! Helper functions for Explorer restart and other operations
+use super::strings::{tr, Text};
 use windows::Win32::UI::WindowsAndMessaging::{
     MessageBoxW, IDYES, MB_ICONERROR, MB_ICONINFORMATION, MB_ICONQUESTION, MB_OK, MB_YESNO,
 };
 
 /// Prompt user to restart Explorer to apply changes
 pub fn prompt_restart_explorer() -> bool {
-    let title = "Restart Explorer?\0".encode_utf16().collect::<Vec<_>>();
-    let message = "Changes have been saved. Would you like to restart Windows Explorer now to apply them?\n\n(You can also restart manually later)\0"
+    let title = format!("{}\0", tr(Text::RestartTitle))
+        .encode_utf16()
+        .collect::<Vec<_>>();
+    let message = format!("{}\0", tr(Text::RestartMessage))
         .encode_utf16()
         .collect::<Vec<_>>();
 
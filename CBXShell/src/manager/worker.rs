@@ -0,0 +1,112 @@
+///! Background workers for CBXManager
+///!
+///! Keeps archive work (cover extraction, preview embedding) off the UI
+///! thread so the window stays responsive
+use anyhow::Result;
+use image::RgbaImage;
+use native_windows_gui as nwg;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Extract an archive cover on a worker thread
+///
+/// `on_done` runs on the worker thread and receives the thumbnail or the
+/// extraction error. Use [`Pending::completion`] to hand the result back to
+/// the UI thread through an nwg notice.
+pub fn spawn_cover_extraction<F>(path: PathBuf, size: u32, on_done: F) -> JoinHandle<()>
+where
+    F: FnOnce(Result<RgbaImage>) + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let result = cbxshell::extract_cover(&path, size)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e));
+        on_done(result);
+    })
+}
+
+/// Render the cover of a ZIP/CBZ archive and embed it, on a worker thread
+///
+/// `on_done` runs on the worker thread and receives the outcome. Use
+/// [`Pending::completion`] to hand it back to the UI thread through an nwg
+/// notice.
+pub fn spawn_preview_embedding<F>(path: PathBuf, size: u32, on_done: F) -> JoinHandle<()>
+where
+    F: FnOnce(Result<()>) + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let result = cbxshell::embed_preview(&path, size)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e));
+        on_done(result);
+    })
+}
+
+/// Result slot shared between a worker and the UI thread
+pub struct Pending<T> {
+    slot: Arc<Mutex<Option<Result<T>>>>,
+}
+
+impl<T> Default for Pending<T> {
+    fn default() -> Self {
+        Self {
+            slot: Arc::default(),
+        }
+    }
+}
+
+impl<T: Send + 'static> Pending<T> {
+    /// Build a completion callback that stores the result and wakes the UI thread
+    ///
+    /// The notice fires the owning control's `OnNotice` event, where the
+    /// handler should call [`Pending::take`].
+    pub fn completion(&self, sender: nwg::NoticeSender) -> impl FnOnce(Result<T>) + Send + 'static {
+        let slot = Arc::clone(&self.slot);
+        move |result| {
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(result);
+            }
+            sender.notice();
+        }
+    }
+
+    /// Take the finished result, if any (call from the `OnNotice` handler)
+    pub fn take(&self) -> Option<Result<T>> {
+        self.slot.lock().ok()?.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_spawn_cover_extraction_reports_errors() {
+        let (tx, rx) = mpsc::channel();
+        let handle = spawn_cover_extraction(PathBuf::from("missing.cbz"), 256, move |result| {
+            tx.send(result.map(|img| img.dimensions())).unwrap();
+        });
+        handle.join().unwrap();
+
+        let err = rx.recv().unwrap().unwrap_err();
+        assert!(err.to_string().contains("missing.cbz"));
+    }
+
+    #[test]
+    fn test_spawn_preview_embedding_reports_errors() {
+        let (tx, rx) = mpsc::channel();
+        let handle = spawn_preview_embedding(PathBuf::from("missing.cbz"), 256, move |result| {
+            tx.send(result).unwrap();
+        });
+        handle.join().unwrap();
+
+        let err = rx.recv().unwrap().unwrap_err();
+        assert!(err.to_string().contains("missing.cbz"));
+    }
+
+    #[test]
+    fn test_pending_take_is_empty_until_done() {
+        let pending = Pending::<()>::default();
+        assert!(pending.take().is_none());
+    }
+}
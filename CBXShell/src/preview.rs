@@ -0,0 +1,362 @@
+//! Page decoding for the preview pane
+//!
+//! Decoding a page on demand makes paging through a book feel laggy, so
+//! `PagePrefetcher` decodes the next pages in the background into a small
+//! LRU cache keyed by page index. Navigating to another page cancels any
+//! prefetch that is no longer wanted.
+//!
+//! Prefetches run on the `ThumbnailService` workers, so they count against
+//! the same concurrency cap as thumbnail requests and stop with the service
+//! on DLL unload. Archive readers are not `Send`, so every prefetch opens its
+//! own reader.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use image::RgbaImage;
+
+use crate::archive::{open_archive_with, ArchiveEntry};
+use crate::image_processor::decoder::decode_image_with;
+use crate::service::ThumbnailService;
+use crate::settings::Settings;
+use crate::utils::error::{CbxError, Result};
+
+/// Decoded pages kept in memory
+const PAGE_CACHE_CAPACITY: usize = 4;
+
+/// Pages decoded ahead of the current one
+const PREFETCH_AHEAD: usize = 2;
+
+/// Bounded LRU cache of decoded pages
+pub struct PageCache {
+    capacity: usize,
+    pages: HashMap<usize, Arc<RgbaImage>>,
+    /// Page indices, least recently used first
+    order: VecDeque<usize>,
+}
+
+impl PageCache {
+    /// Create a cache holding at most `capacity` pages (minimum 1)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            pages: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Look up a page and mark it as most recently used
+    pub fn get(&mut self, index: usize) -> Option<Arc<RgbaImage>> {
+        let page = self.pages.get(&index).cloned()?;
+        self.touch(index);
+        Some(page)
+    }
+
+    /// Insert a page, evicting the least recently used one when full
+    pub fn insert(&mut self, index: usize, page: Arc<RgbaImage>) {
+        if self.pages.insert(index, page).is_some() {
+            self.touch(index);
+            return;
+        }
+
+        self.order.push_back(index);
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.pages.remove(&evicted);
+            }
+        }
+    }
+
+    /// Check for a page without affecting its recency
+    pub fn contains(&self, index: usize) -> bool {
+        self.pages.contains_key(&index)
+    }
+
+    /// Number of cached pages
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn touch(&mut self, index: usize) {
+        if let Some(position) = self.order.iter().position(|&i| i == index) {
+            self.order.remove(position);
+        }
+        self.order.push_back(index);
+    }
+}
+
+/// State shared with queued prefetches
+struct Shared {
+    cache: Mutex<PageCache>,
+    /// Pages queued or being decoded by a prefetch
+    pending: Mutex<HashSet<usize>>,
+    /// Bumped on every navigation; prefetches from older generations give up
+    generation: AtomicU64,
+}
+
+impl Shared {
+    /// The page cache, still usable after a prefetch panicked holding it
+    fn cache(&self) -> MutexGuard<'_, PageCache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn pending(&self) -> MutexGuard<'_, HashSet<usize>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Serves preview pages and decodes the following ones in the background
+pub struct PagePrefetcher {
+    path: PathBuf,
+    settings: Settings,
+    pages: Vec<ArchiveEntry>,
+    service: ThumbnailService,
+    shared: Arc<Shared>,
+}
+
+impl PagePrefetcher {
+    /// Open an archive and list its pages (in `find_images` order)
+    ///
+    /// Prefetches run on the shared `ThumbnailService`.
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::with_service(path, Settings::load(), ThumbnailService::global().clone())
+    }
+
+    /// Open with explicit settings, prefetching on `service`
+    fn with_service(path: &Path, settings: Settings, service: ThumbnailService) -> Result<Self> {
+        let pages = open_archive_with(path, &settings)?.find_images()?;
+        if pages.is_empty() {
+            return Err(CbxError::NoImages);
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            settings,
+            pages,
+            service,
+            shared: Arc::new(Shared {
+                cache: Mutex::new(PageCache::new(PAGE_CACHE_CAPACITY)),
+                pending: Mutex::new(HashSet::new()),
+                generation: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    /// Number of pages in the archive
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Navigate to a page and return it decoded
+    ///
+    /// Served from the cache when a prefetch already decoded it; otherwise
+    /// decoded on the calling thread. Either way, prefetch of the following
+    /// pages is (re)started.
+    pub fn page(&self, index: usize) -> Result<Arc<RgbaImage>> {
+        let entry = self
+            .pages
+            .get(index)
+            .ok_or_else(|| CbxError::Archive(format!("Page {} out of range", index)))?;
+
+        let generation = self.shared.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let cached = self.shared.cache().get(index);
+        let page = match cached {
+            Some(page) => page,
+            None => {
+                let page = Arc::new(decode_page(&self.path, entry, &self.settings)?);
+                self.shared.cache().insert(index, Arc::clone(&page));
+                page
+            }
+        };
+
+        for next in (index + 1..self.pages.len()).take(PREFETCH_AHEAD) {
+            self.prefetch(next, generation);
+        }
+
+        Ok(page)
+    }
+
+    /// Cancel outstanding prefetches (e.g. when the preview is unloaded)
+    pub fn cancel(&self) {
+        self.shared.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Queue the decode of page `index` unless it is cached or queued already
+    fn prefetch(&self, index: usize, generation: u64) {
+        if self.shared.cache().contains(index) {
+            return;
+        }
+        if !self.shared.pending().insert(index) {
+            return; // Already queued
+        }
+
+        /// Clears the pending mark however the prefetch ends, including
+        /// being dropped unrun from the service queue
+        struct PendingGuard {
+            shared: Arc<Shared>,
+            index: usize,
+        }
+        impl Drop for PendingGuard {
+            fn drop(&mut self) {
+                self.shared.pending().remove(&self.index);
+            }
+        }
+
+        let path = self.path.clone();
+        let entry = self.pages[index].clone();
+        let settings = self.settings.clone();
+        let shared = Arc::clone(&self.shared);
+        let guard = PendingGuard {
+            shared: Arc::clone(&shared),
+            index,
+        };
+
+        let queued = self.service.spawn(Some(&self.path), move || {
+            let _guard = guard;
+            let is_current = || shared.generation.load(Ordering::SeqCst) == generation;
+
+            if !is_current() {
+                return;
+            }
+
+            match decode_page(&path, &entry, &settings) {
+                Ok(page) if is_current() => {
+                    shared.cache().insert(index, Arc::new(page));
+                }
+                Ok(_) => tracing::debug!("Prefetch of page {} cancelled", index),
+                Err(e) => tracing::debug!("Prefetch of page {} failed: {}", index, e),
+            }
+        });
+
+        // A busy service drops the job (and its guard); the page is decoded
+        // on demand instead
+        if let Err(e) = queued {
+            tracing::debug!("Prefetch of page {} not queued: {}", index, e);
+        }
+    }
+}
+
+impl Drop for PagePrefetcher {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Extract and decode a single page
+fn decode_page(path: &Path, entry: &ArchiveEntry, settings: &Settings) -> Result<RgbaImage> {
+    let data = open_archive_with(path, settings)?.extract_entry(entry)?;
+    Ok(decode_image_with(&data, &settings.decode_options())?.to_rgba8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::config::MemoryConfigSource;
+    use crate::service::ServiceConfig;
+    use crate::utils::crash_guard::CrashGuard;
+    use std::io::Write;
+    use std::time::{Duration, Instant};
+    use tempfile::Builder;
+    use zip::write::{FileOptions, ZipWriter};
+
+    fn page(width: u32) -> Arc<RgbaImage> {
+        Arc::new(RgbaImage::new(width, 1))
+    }
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_pixel(width, height, image::Rgba([255, 0, 0, 255]));
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut buffer, image::ImageFormat::Png).unwrap();
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn test_page_cache_evicts_least_recently_used() {
+        let mut cache = PageCache::new(2);
+        cache.insert(0, page(1));
+        cache.insert(1, page(2));
+
+        // Touch 0 so 1 becomes the eviction candidate
+        assert!(cache.get(0).is_some());
+        cache.insert(2, page(3));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(0));
+        assert!(!cache.contains(1));
+        assert!(cache.contains(2));
+    }
+
+    #[test]
+    fn test_page_cache_reinsert_does_not_grow() {
+        let mut cache = PageCache::new(2);
+        cache.insert(0, page(1));
+        cache.insert(0, page(5));
+        cache.insert(1, page(2));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(0).unwrap().width(), 5);
+    }
+
+    #[test]
+    fn test_page_cache_minimum_capacity() {
+        let mut cache = PageCache::new(0);
+        cache.insert(0, page(1));
+        cache.insert(1, page(2));
+
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains(1));
+    }
+
+    #[test]
+    fn test_prefetches_following_pages() {
+        let temp_file = Builder::new()
+            .prefix("test_prefetch_")
+            .suffix(".cbz")
+            .tempfile()
+            .unwrap();
+
+        {
+            let mut zip = ZipWriter::new(std::fs::File::create(temp_file.path()).unwrap());
+            for i in 1..=4 {
+                zip.start_file(format!("page{}.png", i), FileOptions::default())
+                    .unwrap();
+                zip.write_all(&png_bytes(i, 1)).unwrap();
+            }
+            zip.finish().unwrap();
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let service = ThumbnailService::new(
+            ServiceConfig {
+                workers: 1,
+                max_queued: 8,
+            },
+            CrashGuard::new(dir.path().join("crumbs"), 3),
+        );
+        let settings = Settings::load_from(&MemoryConfigSource::default());
+        let prefetcher = PagePrefetcher::with_service(temp_file.path(), settings, service).unwrap();
+        assert_eq!(prefetcher.page_count(), 4);
+        assert_eq!(prefetcher.page(0).unwrap().width(), 1);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let done = {
+                let cache = prefetcher.shared.cache();
+                cache.contains(1) && cache.contains(2)
+            };
+            if done {
+                break;
+            }
+            assert!(Instant::now() < deadline, "prefetch did not complete");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // Only PREFETCH_AHEAD pages are decoded ahead
+        assert!(!prefetcher.shared.cache().contains(3));
+        assert!(prefetcher.page(4).is_err());
+    }
+}
@@ -4,6 +4,7 @@
 //! - CLSID registration
 //! - Shell extension handlers (.cbz, .cbr, .zip, .cb7)
 //! - Approved shell extensions
+//! - "Set as cover" context menu command
 //!
 //! Based on CBXShell.rgs from the C++ implementation
 
@@ -12,6 +13,7 @@ use windows::core::GUID;
 use windows::Win32::System::Registry::*;
 
 pub const CLSID_CBXSHELL: GUID = GUID::from_u128(0x9E6ECB90_5A61_42BD_B851_D3297D9C7F39);
+pub const CLSID_SET_COVER_COMMAND: GUID = GUID::from_u128(0x4F0C2A7E_8D3B_4C61_9A52_6E1B7D03C5A4);
 
 fn clsid_string(clsid: GUID) -> String {
     format!("{{{:?}}}", clsid)
@@ -146,6 +148,67 @@ fn set_string_value(hkey: HKEY, value_name: Option<&str>, data: &str) -> Result<
     Ok(())
 }
 
+/// Read a registry string value, returning `None` if the key or value is missing
+fn get_string_value(hkey: HKEY, subkey: &str, value_name: Option<&str>) -> Option<String> {
+    let subkey_wide: Vec<u16> = subkey.encode_utf16().chain(Some(0)).collect();
+    let value_name_wide: Vec<u16> = value_name
+        .map(|s| s.encode_utf16().chain(Some(0)).collect())
+        .unwrap_or_else(|| vec![0]);
+
+    // UNAVOIDABLE UNSAFE: RegGetValueW is a Windows FFI call
+    // Safety guarantees:
+    // - subkey_wide and value_name_wide have null terminators
+    // - The first call only queries the size; the buffer passed to the second
+    //   call is at least that many bytes
+    unsafe {
+        let mut size = 0u32;
+        RegGetValueW(
+            hkey,
+            windows::core::PCWSTR(subkey_wide.as_ptr()),
+            windows::core::PCWSTR(value_name_wide.as_ptr()),
+            RRF_RT_REG_SZ,
+            None,
+            None,
+            Some(&mut size),
+        )
+        .ok()?;
+
+        let mut buffer = vec![0u16; size as usize / 2 + 1];
+        RegGetValueW(
+            hkey,
+            windows::core::PCWSTR(subkey_wide.as_ptr()),
+            windows::core::PCWSTR(value_name_wide.as_ptr()),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr().cast()),
+            Some(&mut size),
+        )
+        .ok()?;
+
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        Some(String::from_utf16_lossy(&buffer[..len]))
+    }
+}
+
+/// Set a registry DWORD value (helper function)
+fn set_dword_value(hkey: HKEY, value_name: &str, data: u32) -> Result<()> {
+    let value_name_wide: Vec<u16> = value_name.encode_utf16().chain(Some(0)).collect();
+
+    // UNAVOIDABLE UNSAFE: RegSetValueExW is a Windows FFI call
+    unsafe {
+        RegSetValueExW(
+            hkey,
+            windows::core::PCWSTR(value_name_wide.as_ptr()),
+            0,
+            REG_DWORD,
+            Some(&data.to_le_bytes()),
+        )
+        .map_err(|e| CbxError::Windows(e))?;
+    }
+
+    Ok(())
+}
+
 /// Delete a registry key recursively
 fn delete_key_recursive(hkey: HKEY, subkey: &str) -> Result<()> {
     // UNAVOIDABLE UNSAFE: RegDeleteTreeW is a Windows FFI call
@@ -178,55 +241,288 @@ fn delete_key_recursive(hkey: HKEY, subkey: &str) -> Result<()> {
     }
 }
 
-/// Register shell extension handler for a file extension
-#[allow(dead_code)] // Helper function for per-extension registration
-fn register_extension(extension: &str, clsid_str: &str) -> Result<()> {
-    let base_key = format!("Software\\Classes\\{}", extension);
+/// Per-user registry root for file associations
+const CLASSES_KEY_PATH: &str = "Software\\Classes";
+
+/// Kind map the property system derives `System.Kind` from
+///
+/// Only the HKLM copy is read; an HKCU key of the same name has no effect.
+const KIND_MAP_KEY_PATH: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\KindMap";
+
+/// `System.Kind` value for comic archives (shown as pictures in galleries)
+const KIND_PICTURE: &str = "picture";
+
+/// Extensions CBXManager can attach our handlers to
+pub const SHELL_EXTENSIONS: &[&str] = &[".cbz", ".cbr", ".zip", ".rar", ".7z", ".cb7"];
+
+/// Configuration value names, so CBXManager writes what the extension reads
+pub use crate::archive::config::{
+    ALLOW_UPSCALE_TO_VALUE, BOOK_EFFECT_VALUE, COVER_SKIP_STEMS_VALUE,
+    COVER_STRATEGY_BY_EXTENSION_VALUE, COVER_STRATEGY_VALUE, DECODE_TIMEOUT_VALUE,
+    EMBEDDED_PREVIEW_NAMES_VALUE, EMBEDDED_PREVIEW_VALUE, ENABLED_VALUE, EVENT_LOG_VALUE,
+    FORCE_THUMBNAIL_SIZE_VALUE, GDI_HALFTONE_VALUE, GRAYSCALE_VALUE, LENIENT_DECODE_VALUE,
+    MAX_ARCHIVE_SIZE_VALUE, MAX_NON_IMAGE_SCAN_VALUE, MAX_THUMBNAIL_DIM_VALUE,
+    NATURAL_SORT_MODE_VALUE, NEXT_IMAGE_ON_MISSING_CODEC_VALUE, NO_SORT_VALUE, SORT_BY_VALUE,
+    VERIFY_FIRST_IMAGE_VALUE, WARM_CODECS_VALUE,
+};
+
+/// Enable or disable our handlers for a file extension in the per-user hive
+///
+/// This is what CBXManager applies when settings are saved. A handler we
+/// replace is backed up and put back when ours is disabled. The extension is
+/// also mapped to `System.Kind=picture`, which lives under HKLM and so only
+/// succeeds when elevated; thumbnails and tooltips work without it.
+pub fn set_extension_handlers(extension: &str, thumbnail: bool, infotip: bool) -> Result<()> {
+    set_extension_handlers_at(
+        HKEY_CURRENT_USER,
+        CLASSES_KEY_PATH,
+        extension,
+        &clsid_string(CLSID_CBXSHELL),
+        thumbnail,
+        infotip,
+    )?;
+
+    if let Err(e) = map_kind_at(HKEY_LOCAL_MACHINE, KIND_MAP_KEY_PATH, extension) {
+        tracing::warn!(
+            "Could not map {} to the picture kind (requires elevation): {}",
+            extension,
+            e
+        );
+    }
+    Ok(())
+}
+
+/// Map `extension` to `System.Kind=picture` so galleries and Photos "Open
+/// with" treat the archive as an image
+///
+/// `kind_map_path` is relative to `root`; tests point it at a scratch key.
+fn map_kind_at(root: HKEY, kind_map_path: &str, extension: &str) -> Result<()> {
+    let kind_map_key = create_key(root, kind_map_path)?;
+    let result = set_string_value(kind_map_key, Some(extension), KIND_PICTURE);
+    unsafe {
+        RegCloseKey(kind_map_key).ok();
+    }
+    result
+}
+
+/// Enable or disable our handlers under an explicit classes root
+///
+/// `classes_path` is relative to `root`; tests point it at a scratch key
+/// instead of the live per-user hive.
+fn set_extension_handlers_at(
+    root: HKEY,
+    classes_path: &str,
+    extension: &str,
+    clsid_str: &str,
+    thumbnail: bool,
+    infotip: bool,
+) -> Result<()> {
+    let base_key = format!("{}\\{}", classes_path, extension);
 
     // 1. Register PerceivedType as "image" so Windows treats these as media files
     // This is CRITICAL for Windows 11 to show thumbnails in folder views
-    let ext_key = create_key(HKEY_CURRENT_USER, &base_key)?;
-    set_string_value(ext_key, Some("PerceivedType"), "image")?;
-    set_string_value(ext_key, Some("Content Type"), "application/x-cbz")?;
+    let ext_key = create_key(root, &base_key)?;
+    let result = set_string_value(ext_key, Some("PerceivedType"), "image");
     unsafe {
         RegCloseKey(ext_key).ok();
     }
+    result?;
 
     // 2. Create .ext\shellex key
-    let shellex_key_path = format!("{}\\shellex", base_key);
-    let shellex_key = create_key(HKEY_CURRENT_USER, &shellex_key_path)?;
-
-    // 3. Register IThumbnailProvider handler (thumbnails - modern API)
-    let thumbnail_key_path = format!("{}\\shellex\\{}", base_key, IID_ITHUMBNAILPROVIDER);
-    let thumbnail_key = create_key(HKEY_CURRENT_USER, &thumbnail_key_path)?;
-    set_string_value(thumbnail_key, None, clsid_str)?;
+    let shellex_key = create_key(root, &format!("{}\\shellex", base_key))?;
     unsafe {
-        RegCloseKey(thumbnail_key).ok();
+        RegCloseKey(shellex_key).ok();
     }
 
-    // 4. Register IQueryInfo handler (tooltips)
-    let infotip_key_path = format!("{}\\shellex\\{}", base_key, IID_IQUERYINFO);
-    let infotip_key = create_key(HKEY_CURRENT_USER, &infotip_key_path)?;
-    set_string_value(infotip_key, None, clsid_str)?;
+    // 3. IThumbnailProvider (thumbnails) and IQueryInfo (tooltips) handlers
+    for (iid, enabled) in [
+        (IID_ITHUMBNAILPROVIDER, thumbnail),
+        (IID_IQUERYINFO, infotip),
+    ] {
+        let handler_key_path = format!("{}\\shellex\\{}", base_key, iid);
+        if enabled {
+            register_handler_at(root, &handler_key_path, clsid_str)?;
+        } else {
+            unregister_handler_at(root, &handler_key_path, clsid_str)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Value on a shellex handler key holding the CLSID we replaced
+///
+/// Shared extensions such as `.zip` and `.7z` often already have a thumbnail
+/// provider; it is put back when we unregister.
+const PREVIOUS_HANDLER_VALUE: &str = "CBXShell.PreviousHandler";
+
+/// Decide which existing handler CLSID, if any, must be backed up before
+/// writing `clsid_str` over it
+///
+/// Empty values and our own CLSID (re-registration) are not worth keeping.
+fn handler_to_back_up<'a>(existing: Option<&'a str>, clsid_str: &str) -> Option<&'a str> {
+    existing
+        .map(str::trim)
+        .filter(|clsid| !clsid.is_empty() && !clsid.eq_ignore_ascii_case(clsid_str))
+}
+
+/// Point the shellex handler key at `key_path` to `clsid_str`, backing up the
+/// handler it replaces
+fn register_handler_at(root: HKEY, key_path: &str, clsid_str: &str) -> Result<()> {
+    let existing = get_string_value(root, key_path, None);
+    let key = create_key(root, key_path)?;
+
+    let result = (|| {
+        if let Some(previous) = handler_to_back_up(existing.as_deref(), clsid_str) {
+            tracing::info!("Backing up existing handler {} at {}", previous, key_path);
+            set_string_value(key, Some(PREVIOUS_HANDLER_VALUE), previous)?;
+        }
+        set_string_value(key, None, clsid_str)
+    })();
+
     unsafe {
-        RegCloseKey(infotip_key).ok();
+        RegCloseKey(key).ok();
+    }
+
+    result
+}
+
+/// What unregistering does to a shellex handler key
+#[derive(Debug, PartialEq, Eq)]
+enum HandlerRemoval<'a> {
+    /// Another extension took the key over after us
+    Keep,
+    /// Put back the handler we replaced
+    Restore(&'a str),
+    /// Nothing to put back
+    Delete,
+}
+
+/// Decide how to remove our handler from a key whose default value is
+/// `current` and whose backup value is `previous`
+fn handler_removal<'a>(
+    current: Option<&str>,
+    previous: Option<&'a str>,
+    clsid_str: &str,
+) -> HandlerRemoval<'a> {
+    if current.is_some_and(|current| !current.trim().eq_ignore_ascii_case(clsid_str)) {
+        return HandlerRemoval::Keep;
     }
+    match previous
+        .map(str::trim)
+        .filter(|previous| !previous.is_empty())
+    {
+        Some(previous) => HandlerRemoval::Restore(previous),
+        None => HandlerRemoval::Delete,
+    }
+}
+
+/// Remove our shellex handler at `key_path`, restoring a backed-up handler
+///
+/// A key whose default value no longer names `clsid_str` was taken over by
+/// another extension after us and is left alone.
+fn unregister_handler_at(root: HKEY, key_path: &str, clsid_str: &str) -> Result<()> {
+    let current = get_string_value(root, key_path, None);
+    let previous = get_string_value(root, key_path, Some(PREVIOUS_HANDLER_VALUE));
+
+    match handler_removal(current.as_deref(), previous.as_deref(), clsid_str) {
+        HandlerRemoval::Keep => Ok(()),
+        HandlerRemoval::Restore(previous) => {
+            tracing::info!("Restoring previous handler {} at {}", previous, key_path);
+            let key = create_key(root, key_path)?;
+            let result = set_string_value(key, None, previous);
+            unsafe {
+                RegCloseKey(key).ok();
+            }
+            result?;
+            delete_value(root, key_path, PREVIOUS_HANDLER_VALUE);
+            Ok(())
+        }
+        HandlerRemoval::Delete => delete_key_recursive(root, key_path),
+    }
+}
 
+/// Remove our handlers from every extension under `classes_path`
+///
+/// Handlers we replaced are put back, so shared extensions such as `.zip`
+/// keep working once the DLL is gone.
+fn release_extension_handlers_at(root: HKEY, classes_path: &str, clsid_str: &str) -> Result<()> {
+    for extension in SHELL_EXTENSIONS {
+        for iid in [IID_ITHUMBNAILPROVIDER, IID_IQUERYINFO] {
+            let handler_key_path = format!("{}\\{}\\shellex\\{}", classes_path, extension, iid);
+            unregister_handler_at(root, &handler_key_path, clsid_str)?;
+        }
+    }
+    Ok(())
+}
+
+/// Verb key for the "Set as cover" command
+///
+/// Registered for every file with PerceivedType=image, which covers both the
+/// pages of an expanded archive and the archives themselves. The command hides
+/// itself unless the selection is a page inside an archive.
+const SET_COVER_VERB_KEY_PATH: &str = "SystemFileAssociations\\image\\shell\\CBXShell.SetCover";
+
+/// Register the "Set as cover" IExplorerCommand verb under `classes_path`
+fn register_set_cover_command_at(root: HKEY, classes_path: &str, clsid_str: &str) -> Result<()> {
+    let verb_key_path = format!("{}\\{}", classes_path, SET_COVER_VERB_KEY_PATH);
+    let verb_key = create_key(root, &verb_key_path)?;
+
+    // Explorer instantiates IExplorerCommand verbs through ExplorerCommandHandler
+    set_string_value(verb_key, Some("ExplorerCommandHandler"), clsid_str)?;
+    set_string_value(verb_key, Some("MUIVerb"), "Set as cover")?;
     unsafe {
-        RegCloseKey(shellex_key).ok();
+        RegCloseKey(verb_key).ok();
     }
 
     Ok(())
 }
 
-/// Unregister shell extension handler for a file extension
-#[allow(dead_code)] // Helper function for per-extension unregistration
-fn unregister_extension(extension: &str) -> Result<()> {
-    let base_key = format!("Software\\Classes\\{}\\shellex", extension);
-    delete_key_recursive(HKEY_CURRENT_USER, &base_key)?;
+/// Application log event sources (machine-wide)
+const EVENT_LOG_SOURCES_KEY_PATH: &str =
+    "SYSTEM\\CurrentControlSet\\Services\\EventLog\\Application";
+
+/// Event types the source emits: error, warning and information
+const EVENT_LOG_TYPES_SUPPORTED: u32 = 0x7;
+
+/// Register the "CBXShell" event source under `sources_path`
+///
+/// No `EventMessageFile` is registered: the DLL has no message table, so
+/// Event Viewer notes that the event description is missing and then shows
+/// the failure text, which is passed as the event's only string. One left
+/// by an earlier registration is removed.
+fn register_event_source_at(root: HKEY, sources_path: &str) -> Result<()> {
+    let source_key_path = format!(
+        "{}\\{}",
+        sources_path,
+        crate::utils::event_log::EVENT_SOURCE
+    );
+    let source_key = create_key(root, &source_key_path)?;
+
+    set_dword_value(source_key, "TypesSupported", EVENT_LOG_TYPES_SUPPORTED)?;
+    unsafe {
+        RegCloseKey(source_key).ok();
+    }
+    delete_value(root, &source_key_path, "EventMessageFile");
+
     Ok(())
 }
 
+/// Delete a registry value, ignoring missing keys or values
+fn delete_value(hkey: HKEY, subkey: &str, value_name: &str) {
+    if let Ok(key) = create_key(hkey, subkey) {
+        let value_name_wide: Vec<u16> = value_name.encode_utf16().chain(Some(0)).collect();
+        // UNAVOIDABLE UNSAFE: RegDeleteValueW/RegCloseKey are Windows FFI calls
+        // Safety guarantees:
+        // - value_name_wide has null terminator
+        // - key was opened by create_key and is closed exactly once
+        unsafe {
+            let _ = RegDeleteValueW(key, windows::core::PCWSTR(value_name_wide.as_ptr()));
+            RegCloseKey(key).ok();
+        }
+    }
+}
+
 /// Register the COM server and shell extension handlers
 ///
 /// # Arguments
@@ -240,6 +536,25 @@ pub fn register_server(dll_path: Option<&str>) -> Result<()> {
     };
 
     register_clsid(&module_path, CLSID_CBXSHELL, "CBXShell Class")?;
+    register_clsid(
+        &module_path,
+        CLSID_SET_COVER_COMMAND,
+        "CBXShell Set Cover Command",
+    )?;
+    register_set_cover_command_at(
+        HKEY_CURRENT_USER,
+        CLASSES_KEY_PATH,
+        &clsid_string(CLSID_SET_COVER_COMMAND),
+    )?;
+
+    // The event source lives under HKLM, so this only succeeds when elevated;
+    // event log reporting is opt-in and the thumbnail handler works without it
+    if let Err(e) = register_event_source_at(HKEY_LOCAL_MACHINE, EVENT_LOG_SOURCES_KEY_PATH) {
+        tracing::warn!(
+            "Could not register event log source (requires elevation): {}",
+            e
+        );
+    }
 
     let clsid_str = clsid_string(CLSID_CBXSHELL);
     let progid_key = create_key(HKEY_CURRENT_USER, "Software\\Classes\\CBXShell.CBXShell.1")?;
@@ -263,12 +578,20 @@ pub fn register_server(dll_path: Option<&str>) -> Result<()> {
 
 /// Unregister the COM server and shell extension handlers
 pub fn unregister_server() -> Result<()> {
-    // Note: File extension cleanup is handled by CBXManager via registry_ops
+    // Shellex keys would otherwise point at a CLSID that no longer exists
+    if let Err(e) = release_extension_handlers_at(
+        HKEY_CURRENT_USER,
+        CLASSES_KEY_PATH,
+        &clsid_string(CLSID_CBXSHELL),
+    ) {
+        tracing::warn!("Could not release extension handlers: {}", e);
+    }
+
     let approved_key_path =
         "Software\\Microsoft\\Windows\\CurrentVersion\\Shell Extensions\\Approved";
     if let Ok(approved_key) = create_key(HKEY_CURRENT_USER, approved_key_path) {
         unsafe {
-            for clsid in [CLSID_CBXSHELL] {
+            for clsid in [CLSID_CBXSHELL, CLSID_SET_COVER_COMMAND] {
                 let clsid_str = clsid_string(clsid);
                 let value_name_wide: Vec<u16> = clsid_str.encode_utf16().chain(Some(0)).collect();
                 let _ = RegDeleteValueW(
@@ -280,11 +603,25 @@ pub fn unregister_server() -> Result<()> {
         }
     }
 
-    for clsid in [CLSID_CBXSHELL] {
+    for clsid in [CLSID_CBXSHELL, CLSID_SET_COVER_COMMAND] {
         let clsid_key_path = format!("Software\\Classes\\CLSID\\{}", clsid_string(clsid));
         delete_key_recursive(HKEY_CURRENT_USER, &clsid_key_path)?;
     }
 
+    let _ = delete_key_recursive(
+        HKEY_CURRENT_USER,
+        &format!("{}\\{}", CLASSES_KEY_PATH, SET_COVER_VERB_KEY_PATH),
+    );
+
+    let _ = delete_key_recursive(
+        HKEY_LOCAL_MACHINE,
+        &format!(
+            "{}\\{}",
+            EVENT_LOG_SOURCES_KEY_PATH,
+            crate::utils::event_log::EVENT_SOURCE
+        ),
+    );
+
     // 3. Delete ProgID
     let _ = delete_key_recursive(HKEY_CURRENT_USER, "Software\\Classes\\CBXShell.CBXShell.1");
     let _ = delete_key_recursive(HKEY_CURRENT_USER, "Software\\Classes\\CBXShell.CBXShell");
@@ -305,6 +642,60 @@ mod tests {
         assert_eq!(clsid_str, "{9E6ECB90-5A61-42BD-B851-D3297D9C7F39}");
     }
 
+    #[test]
+    fn test_registry_clsids_match_com() {
+        assert_eq!(CLSID_CBXSHELL, crate::com::CLSID_CBXSHELL);
+        assert_eq!(CLSID_SET_COVER_COMMAND, crate::com::CLSID_SET_COVER_COMMAND);
+    }
+
+    #[test]
+    fn test_handler_to_back_up() {
+        let ours = clsid_string(CLSID_CBXSHELL);
+        let other = "{C3A1D4E2-0000-4000-8000-000000000001}";
+
+        assert_eq!(handler_to_back_up(Some(other), &ours), Some(other));
+        assert_eq!(handler_to_back_up(None, &ours), None);
+        assert_eq!(handler_to_back_up(Some(""), &ours), None);
+        assert_eq!(handler_to_back_up(Some(&ours), &ours), None);
+        assert_eq!(handler_to_back_up(Some(&ours.to_lowercase()), &ours), None);
+    }
+
+    #[test]
+    fn test_handler_removal() {
+        let ours = clsid_string(CLSID_CBXSHELL);
+        let other = "{C3A1D4E2-0000-4000-8000-000000000001}";
+        let later = "{C3A1D4E2-0000-4000-8000-000000000002}";
+
+        // Ours, with a backup: the replaced handler comes back
+        assert_eq!(
+            handler_removal(Some(&ours), Some(other), &ours),
+            HandlerRemoval::Restore(other)
+        );
+        assert_eq!(
+            handler_removal(Some(&ours.to_lowercase()), Some(other), &ours),
+            HandlerRemoval::Restore(other)
+        );
+        // Ours, nothing replaced (or the default value is gone)
+        assert_eq!(
+            handler_removal(Some(&ours), None, &ours),
+            HandlerRemoval::Delete
+        );
+        assert_eq!(
+            handler_removal(Some(&ours), Some(" "), &ours),
+            HandlerRemoval::Delete
+        );
+        assert_eq!(handler_removal(None, None, &ours), HandlerRemoval::Delete);
+        assert_eq!(
+            handler_removal(None, Some(other), &ours),
+            HandlerRemoval::Restore(other)
+        );
+        // Taken over by another extension after us
+        assert_eq!(
+            handler_removal(Some(later), Some(other), &ours),
+            HandlerRemoval::Keep
+        );
+    }
+
     #[test]
     fn test_get_module_path() {
         // This test only works when running as a DLL (not in test executable)
@@ -321,3 +712,195 @@ mod tests {
         }
     }
 }
+
+#[cfg(all(test, windows, feature = "e2e-windows"))]
+mod windows_registry_tests {
+    use super::*;
+    use winreg::enums::HKEY_CURRENT_USER as WINREG_HKCU;
+    use winreg::RegKey;
+
+    const SCRATCH_ROOT: &str = "Software\\CBXShell-rs-test";
+
+    #[test]
+    #[ignore = "mutates live HKCU registry keys"]
+    fn test_set_extension_handlers_writes_kind_and_perceived_type() {
+        let scratch = format!("{}\\{}", SCRATCH_ROOT, std::process::id());
+        let classes_path = format!("{}\\Classes", scratch);
+        let kind_map_path = format!("{}\\KindMap", scratch);
+        let clsid_str = clsid_string(CLSID_CBXSHELL);
+
+        set_extension_handlers_at(
+            HKEY_CURRENT_USER,
+            &classes_path,
+            ".cbz",
+            &clsid_str,
+            true,
+            false,
+        )
+        .unwrap();
+        map_kind_at(HKEY_CURRENT_USER, &kind_map_path, ".cbz").unwrap();
+
+        let hkcu = RegKey::predef(WINREG_HKCU);
+        let ext_key = hkcu.open_subkey(format!("{}\\.cbz", classes_path)).unwrap();
+        let perceived: String = ext_key.get_value("PerceivedType").unwrap();
+        assert_eq!(perceived, "image");
+
+        let kind_map = hkcu.open_subkey(&kind_map_path).unwrap();
+        let kind: String = kind_map.get_value(".cbz").unwrap();
+        assert_eq!(kind, KIND_PICTURE);
+
+        let thumb_key = hkcu
+            .open_subkey(format!(
+                "{}\\.cbz\\shellex\\{}",
+                classes_path, IID_ITHUMBNAILPROVIDER
+            ))
+            .unwrap();
+        let handler: String = thumb_key.get_value("").unwrap();
+        assert_eq!(handler, clsid_str);
+        assert!(hkcu
+            .open_subkey(format!(
+                "{}\\.cbz\\shellex\\{}",
+                classes_path, IID_IQUERYINFO
+            ))
+            .is_err());
+
+        let _ = hkcu.delete_subkey_all(&scratch);
+    }
+
+    #[test]
+    #[ignore = "mutates live HKCU registry keys"]
+    fn test_set_extension_handlers_backs_up_and_restores_previous_handler() {
+        let scratch = format!("{}\\{}-shared", SCRATCH_ROOT, std::process::id());
+        let classes_path = format!("{}\\Classes", scratch);
+        let clsid_str = clsid_string(CLSID_CBXSHELL);
+        let other = "{C3A1D4E2-0000-4000-8000-000000000001}";
+        let thumb_path = format!(
+            "{}\\.zip\\shellex\\{}",
+            classes_path, IID_ITHUMBNAILPROVIDER
+        );
+        let infotip_path = format!("{}\\.zip\\shellex\\{}", classes_path, IID_IQUERYINFO);
+        let set_handlers = |enabled: bool| {
+            set_extension_handlers_at(
+                HKEY_CURRENT_USER,
+                &classes_path,
+                ".zip",
+                &clsid_str,
+                enabled,
+                enabled,
+            )
+            .unwrap()
+        };
+
+        let hkcu = RegKey::predef(WINREG_HKCU);
+        let (existing, _) = hkcu.create_subkey(&thumb_path).unwrap();
+        existing.set_value("", &other).unwrap();
+
+        set_handlers(true);
+        let thumb_key = hkcu.open_subkey(&thumb_path).unwrap();
+        let handler: String = thumb_key.get_value("").unwrap();
+        assert_eq!(handler, clsid_str);
+        let backup: String = thumb_key.get_value(PREVIOUS_HANDLER_VALUE).unwrap();
+        assert_eq!(backup, other);
+
+        // Re-registering must not overwrite the backup with our own CLSID
+        set_handlers(true);
+        let backup: String = thumb_key.get_value(PREVIOUS_HANDLER_VALUE).unwrap();
+        assert_eq!(backup, other);
+
+        set_handlers(false);
+        let thumb_key = hkcu.open_subkey(&thumb_path).unwrap();
+        let handler: String = thumb_key.get_value("").unwrap();
+        assert_eq!(handler, other);
+        assert!(thumb_key
+            .get_value::<String, _>(PREVIOUS_HANDLER_VALUE)
+            .is_err());
+        // No handler was there before us, so ours is simply removed
+        assert!(hkcu.open_subkey(&infotip_path).is_err());
+
+        let _ = hkcu.delete_subkey_all(&scratch);
+    }
+
+    #[test]
+    #[ignore = "mutates live HKCU registry keys"]
+    fn test_release_extension_handlers_restores_previous_handler() {
+        let scratch = format!("{}\\{}-release", SCRATCH_ROOT, std::process::id());
+        let classes_path = format!("{}\\Classes", scratch);
+        let clsid_str = clsid_string(CLSID_CBXSHELL);
+        let other = "{C3A1D4E2-0000-4000-8000-000000000001}";
+        let thumb_path = format!("{}\\.7z\\shellex\\{}", classes_path, IID_ITHUMBNAILPROVIDER);
+
+        let hkcu = RegKey::predef(WINREG_HKCU);
+        let (existing, _) = hkcu.create_subkey(&thumb_path).unwrap();
+        existing.set_value("", &other).unwrap();
+        for extension in [".7z", ".cbz"] {
+            set_extension_handlers_at(
+                HKEY_CURRENT_USER,
+                &classes_path,
+                extension,
+                &clsid_str,
+                true,
+                true,
+            )
+            .unwrap();
+        }
+
+        release_extension_handlers_at(HKEY_CURRENT_USER, &classes_path, &clsid_str).unwrap();
+
+        let thumb_key = hkcu.open_subkey(&thumb_path).unwrap();
+        let handler: String = thumb_key.get_value("").unwrap();
+        assert_eq!(handler, other);
+        assert!(hkcu
+            .open_subkey(format!(
+                "{}\\.cbz\\shellex\\{}",
+                classes_path, IID_ITHUMBNAILPROVIDER
+            ))
+            .is_err());
+
+        let _ = hkcu.delete_subkey_all(&scratch);
+    }
+
+    #[test]
+    #[ignore = "mutates live HKCU registry keys"]
+    fn test_register_set_cover_command_writes_verb() {
+        let scratch = format!("{}\\{}-verb", SCRATCH_ROOT, std::process::id());
+        let classes_path = format!("{}\\Classes", scratch);
+        let clsid_str = clsid_string(CLSID_SET_COVER_COMMAND);
+
+        register_set_cover_command_at(HKEY_CURRENT_USER, &classes_path, &clsid_str).unwrap();
+
+        let hkcu = RegKey::predef(WINREG_HKCU);
+        let verb_key = hkcu
+            .open_subkey(format!("{}\\{}", classes_path, SET_COVER_VERB_KEY_PATH))
+            .unwrap();
+        let handler: String = verb_key.get_value("ExplorerCommandHandler").unwrap();
+        assert_eq!(handler, clsid_str);
+
+        let _ = hkcu.delete_subkey_all(&scratch);
+    }
+
+    #[test]
+    #[ignore = "mutates live HKCU registry keys"]
+    fn test_register_event_source() {
+        let scratch = format!("{}\\{}-eventlog", SCRATCH_ROOT, std::process::id());
+        let source_path = format!("{}\\{}", scratch, crate::utils::event_log::EVENT_SOURCE);
+
+        // An earlier registration pointed at the DLL
+        let hkcu = RegKey::predef(WINREG_HKCU);
+        let (stale, _) = hkcu.create_subkey(&source_path).unwrap();
+        stale
+            .set_value("EventMessageFile", &"C:\\CBXShell\\cbxshell.dll")
+            .unwrap();
+
+        register_event_source_at(HKEY_CURRENT_USER, &scratch).unwrap();
+
+        let source_key = hkcu.open_subkey(&source_path).unwrap();
+        // The DLL has no message table to point at
+        assert!(source_key
+            .get_value::<String, _>("EventMessageFile")
+            .is_err());
+        let types: u32 = source_key.get_value("TypesSupported").unwrap();
+        assert_eq!(types, EVENT_LOG_TYPES_SUPPORTED);
+
+        let _ = hkcu.delete_subkey_all(&scratch);
+    }
+}
@@ -0,0 +1,825 @@
+//! Thumbnail service: bounded worker pool for thumbnail requests
+//!
+//! Explorer asks for thumbnails from many threads at once. Rather than every
+//! request extracting on its own thread, archives on disk are queued to at most
+//! `ServiceConfig::workers` worker threads, so a folder of large archives can't
+//! saturate the machine. The service also owns the other per-request limits:
+//! the crash guard breadcrumb and the time a caller waits for its result.
+//!
+//! Workers start on demand and exit as soon as the queue is empty, each holding
+//! a DLL reference while it runs, so an idle host can still unload the DLL. A
+//! request that times out is dropped if it hasn't started. A running one can't
+//! be stopped: its worker gives up its slot to a fresh worker, finishes the
+//! request in the background, discards the result and exits, so hung decodes
+//! don't starve the queue.
+//!
+//! Stream-backed requests run on the calling thread, since the `IStream`
+//! belongs to the caller's apartment, but they still wait their turn in the
+//! queue: a worker hands its slot to the caller and waits for it to finish.
+//!
+//! On DLL unload `stop_global` refuses new requests, drops queued ones and
+//! tells each worker to exit once its current request returns.
+
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use windows::Win32::System::Com::IStream;
+
+use crate::com::{extract_from_path, extract_from_stream, RenderedThumbnail};
+use crate::settings::Settings;
+use crate::utils::crash_guard::{Breadcrumb, CrashGuard};
+use crate::utils::error::{CbxError, Result};
+
+/// Upper bound on worker threads, whatever the core count
+const MAX_WORKERS: usize = 4;
+
+/// Requests waiting for a worker before new ones are refused
+const MAX_QUEUED: usize = 64;
+
+/// Time allowed on top of `DecodeTimeoutMs` for queueing and archive I/O
+const REQUEST_TIMEOUT_SLACK_MS: u64 = 25_000;
+
+/// Resource limits of a `ThumbnailService`
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    /// Worker threads running requests at once
+    pub workers: usize,
+    /// Requests allowed to wait for a worker
+    pub max_queued: usize,
+}
+
+impl Default for ServiceConfig {
+    /// Limits used by the shell extension: one worker per core, up to
+    /// `MAX_WORKERS`
+    fn default() -> Self {
+        let workers = std::thread::available_parallelism()
+            .map(|cores| cores.get())
+            .unwrap_or(1)
+            .clamp(1, MAX_WORKERS);
+
+        Self {
+            workers,
+            max_queued: MAX_QUEUED,
+        }
+    }
+}
+
+/// Time a caller waits for a thumbnail (`None` = no limit)
+///
+/// Follows `DecodeTimeoutMs` plus `REQUEST_TIMEOUT_SLACK_MS`; 0 disables both.
+fn request_timeout(decode_timeout_ms: u32) -> Option<Duration> {
+    match decode_timeout_ms {
+        0 => None,
+        ms => Some(Duration::from_millis(
+            u64::from(ms) + REQUEST_TIMEOUT_SLACK_MS,
+        )),
+    }
+}
+
+/// Where a thumbnail is extracted from
+pub enum ThumbnailSource {
+    /// Archive on disk, extracted on a worker thread
+    File(PathBuf),
+    /// Stream from `IInitializeWithStream`, extracted on the calling thread
+    ///
+    /// `path` is the file behind the stream when known, for the cover cache
+    /// and the crash guard.
+    Stream {
+        stream: IStream,
+        path: Option<PathBuf>,
+    },
+}
+
+/// `Job::state`: waiting for a worker
+const QUEUED: u8 = 0;
+/// `Job::state`: running on a worker
+const RUNNING: u8 = 1;
+/// `Job::state`: returned while its caller was still waiting
+const FINISHED: u8 = 2;
+/// `Job::state`: the caller gave up before it started, so it is skipped
+const ABANDONED: u8 = 3;
+/// `Job::state`: the caller gave up while it ran and its worker's slot went
+/// to another worker; the worker exits when the job returns
+const DETACHED: u8 = 4;
+
+/// Request waiting for a worker
+struct Job {
+    /// Where the request is in its lifecycle, shared with the caller
+    state: Arc<AtomicU8>,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+/// Pending jobs and the number of workers draining them
+///
+/// Kept under one lock so a worker never exits while a job it should have
+/// picked up is being queued.
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    workers: usize,
+}
+
+/// State shared between the service and its workers
+struct Shared {
+    config: ServiceConfig,
+    crash_guard: CrashGuard,
+    queue: Mutex<Queue>,
+    /// Set by `stop`: no new requests, and workers exit between requests
+    stopping: AtomicBool,
+}
+
+/// Service shared by every COM object, see `ThumbnailService::global`
+static SERVICE: OnceLock<ThumbnailService> = OnceLock::new();
+
+/// Stop the shared service, if it was ever started
+///
+/// Safe to call from `DllMain`: never creates the service nor blocks.
+pub fn stop_global() {
+    if let Some(service) = SERVICE.get() {
+        service.stop();
+    }
+}
+
+/// Runs thumbnail requests within the configured resource limits
+///
+/// Clones share the same workers, queue and limits.
+#[derive(Clone)]
+pub struct ThumbnailService {
+    shared: Arc<Shared>,
+}
+
+impl ThumbnailService {
+    /// Service with the given limits, guarding files with `crash_guard`
+    pub fn new(config: ServiceConfig, crash_guard: CrashGuard) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                config,
+                crash_guard,
+                queue: Mutex::new(Queue::default()),
+                stopping: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Service shared by every COM object, created on first use
+    pub fn global() -> &'static Self {
+        SERVICE.get_or_init(|| {
+            let crash_guard = CrashGuard::in_temp_dir();
+            // Scanned on the first request rather than under the loader lock
+            // in DllMain
+            crash_guard.log_blocked_files();
+            Self::new(ServiceConfig::default(), crash_guard)
+        })
+    }
+
+    /// Extract the thumbnail of `source` at Explorer's requested size `cx`
+    ///
+    /// `settings` are those loaded for this request; a worker gets a copy,
+    /// and `DecodeTimeoutMs` bounds the wait (see `request_timeout`).
+    ///
+    /// # Returns
+    /// * `Ok(RenderedThumbnail)` - Thumbnail pixels
+    /// * `Err(CbxError::CrashGuard)` - The file crashed the host too often
+    /// * `Err(CbxError::Busy)` / `Err(CbxError::TimedOut)` - Limits reached
+    /// * `Err(CbxError)` - Failed to extract or render the cover
+    pub fn request_thumbnail(
+        &self,
+        source: ThumbnailSource,
+        cx: u32,
+        settings: &Settings,
+    ) -> Result<RenderedThumbnail> {
+        match source {
+            ThumbnailSource::File(path) => {
+                let guard_path = path.clone();
+                let timeout = request_timeout(settings.decode_timeout_ms);
+                let settings = settings.clone();
+                self.run(Some(&guard_path), timeout, move || {
+                    extract_from_path(&path, cx, &settings)
+                })
+            }
+            ThumbnailSource::Stream { stream, path } => {
+                let timeout = request_timeout(settings.decode_timeout_ms);
+                self.run_inline(path.as_deref(), timeout, || {
+                    extract_from_stream(stream, path.as_deref(), cx, settings)
+                })
+            }
+        }
+    }
+
+    /// Run `job` on a worker and wait at most `timeout` for its result
+    ///
+    /// `guard_path` is the file the job reads, recorded by the crash guard
+    /// until the job returns.
+    pub fn run<T, F>(
+        &self,
+        guard_path: Option<&Path>,
+        timeout: Option<Duration>,
+        job: F,
+    ) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        let breadcrumb = self.begin(guard_path)?;
+        let (sender, receiver) = mpsc::channel();
+        let state = Arc::new(AtomicU8::new(QUEUED));
+
+        self.submit(Job {
+            state: Arc::clone(&state),
+            run: Box::new(move || {
+                let result = job();
+                // Clear the breadcrumb before the caller can see the result
+                drop(breadcrumb);
+                // Receiver is gone if the caller already gave up
+                let _ = sender.send(result);
+            }),
+        })?;
+
+        let received = match timeout {
+            Some(timeout) => receiver.recv_timeout(timeout),
+            None => receiver
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.abandon(&state);
+                tracing::warn!("Thumbnail request timed out after {:?}", timeout);
+                crate::debug_log!("Thumbnail request timed out: {:?}", guard_path);
+                Err(CbxError::TimedOut)
+            }
+            // Dropped unrun (see `stop`), or the job panicked
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(CbxError::Cancelled),
+        }
+    }
+
+    /// Run `job` on the calling thread once a worker slot is free
+    ///
+    /// The request queues like any other; the worker that picks it up hands
+    /// its slot to this thread until `job` returns. `timeout` bounds only the
+    /// wait for the slot, since a job on the caller's thread can't be left
+    /// behind.
+    pub fn run_inline<T>(
+        &self,
+        guard_path: Option<&Path>,
+        timeout: Option<Duration>,
+        job: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let _breadcrumb = self.begin(guard_path)?;
+        let (turn_sender, turn) = mpsc::channel::<mpsc::Sender<()>>();
+        let state = Arc::new(AtomicU8::new(QUEUED));
+
+        self.submit(Job {
+            state: Arc::clone(&state),
+            run: Box::new(move || {
+                // Hold the slot until the caller drops `done`, or gave up
+                let (done, finished) = mpsc::channel();
+                if turn_sender.send(done).is_ok() {
+                    let _ = finished.recv();
+                }
+            }),
+        })?;
+
+        let received = match timeout {
+            Some(timeout) => turn.recv_timeout(timeout),
+            None => turn
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(_done) => job(),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                // A worker that picked the request up meanwhile is released
+                // when `turn` is dropped
+                let _ =
+                    state.compare_exchange(QUEUED, ABANDONED, Ordering::SeqCst, Ordering::SeqCst);
+                tracing::warn!("Thumbnail request waited {:?} for a worker", timeout);
+                crate::debug_log!("Thumbnail request timed out in queue: {:?}", guard_path);
+                Err(CbxError::TimedOut)
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(CbxError::Cancelled),
+        }
+    }
+
+    /// Queue `job` on a worker without waiting for it
+    ///
+    /// For background work such as page prefetch: it shares the worker cap
+    /// and queue with thumbnail requests, and is dropped unrun, like them, by
+    /// `cancel_pending` and `stop`.
+    ///
+    /// # Returns
+    /// * `Ok(())` - Queued
+    /// * `Err(CbxError::CrashGuard)` - `guard_path` crashed the host too often
+    /// * `Err(CbxError::Busy)` - The queue is full
+    pub fn spawn<F>(&self, guard_path: Option<&Path>, job: F) -> Result<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let breadcrumb = self.begin(guard_path)?;
+        self.submit(Job {
+            state: Arc::new(AtomicU8::new(QUEUED)),
+            run: Box::new(move || {
+                job();
+                drop(breadcrumb);
+            }),
+        })
+    }
+
+    /// Drop every request still waiting for a worker
+    ///
+    /// Their callers get `CbxError::Cancelled`; running requests finish.
+    #[cfg(test)]
+    pub fn cancel_pending(&self) {
+        let dropped: Vec<Job> = self.shared.queue.lock().unwrap().jobs.drain(..).collect();
+        if !dropped.is_empty() {
+            tracing::debug!("Cancelled {} queued thumbnail requests", dropped.len());
+        }
+    }
+
+    /// Refuse new requests and let the workers exit
+    ///
+    /// Queued requests are dropped, so their callers get
+    /// `CbxError::Cancelled`; running ones finish, after which their workers
+    /// exit. Only tries the queue lock, since on unload the thread holding it
+    /// may already be gone.
+    pub fn stop(&self) {
+        self.shared.stopping.store(true, Ordering::SeqCst);
+        let dropped: Vec<Job> = match self.shared.queue.try_lock() {
+            Ok(mut queue) => queue.jobs.drain(..).collect(),
+            Err(_) => return,
+        };
+        if !dropped.is_empty() {
+            tracing::debug!(
+                "Dropped {} queued thumbnail requests on stop",
+                dropped.len()
+            );
+        }
+    }
+
+    /// Record an attempt at `guard_path`, refusing files the guard blocks
+    fn begin(&self, guard_path: Option<&Path>) -> Result<Option<Breadcrumb>> {
+        let Some(path) = guard_path else {
+            return Ok(None);
+        };
+        match self.shared.crash_guard.begin(path) {
+            Some(breadcrumb) => Ok(Some(breadcrumb)),
+            None => {
+                crate::debug_log!("Crash guard skipping {:?}", path);
+                Err(CbxError::CrashGuard(self.shared.crash_guard.limit()))
+            }
+        }
+    }
+
+    /// Give up on the job whose lifecycle is `state` after its caller timed out
+    ///
+    /// A job still queued is skipped. A running one is detached: its worker no
+    /// longer counts against the cap, and a new worker takes over the queue.
+    fn abandon(&self, state: &AtomicU8) {
+        if state
+            .compare_exchange(QUEUED, ABANDONED, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            return;
+        }
+        if state
+            .compare_exchange(RUNNING, DETACHED, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            // Finished just now; its result is dropped with the receiver
+            return;
+        }
+
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.workers -= 1;
+        tracing::warn!("Detached a thumbnail worker from a request that timed out");
+        if !queue.jobs.is_empty() {
+            if let Err(e) = self.spawn_worker(&mut queue) {
+                tracing::warn!("Failed to replace detached thumbnail worker: {}", e);
+            }
+        }
+    }
+
+    /// Queue `job`, starting a worker if the cap allows
+    fn submit(&self, job: Job) -> Result<()> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if self.shared.stopping.load(Ordering::SeqCst) {
+            return Err(CbxError::Cancelled);
+        }
+        if queue.jobs.len() >= self.shared.config.max_queued {
+            tracing::warn!("Thumbnail queue full ({} requests)", queue.jobs.len());
+            return Err(CbxError::Busy);
+        }
+        queue.jobs.push_back(job);
+
+        if queue.workers < self.shared.config.workers {
+            if let Err(e) = self.spawn_worker(&mut queue) {
+                // Running workers will still pick the job up
+                if queue.workers == 0 {
+                    queue.jobs.pop_back();
+                    return Err(CbxError::Image(format!(
+                        "Failed to spawn thumbnail worker: {}",
+                        e
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Start a worker draining `queue`, counting it against the cap
+    fn spawn_worker(&self, queue: &mut Queue) -> std::io::Result<()> {
+        let shared = Arc::clone(&self.shared);
+        crate::add_dll_ref();
+        let spawned = std::thread::Builder::new()
+            .name("cbx-thumbnail".to_string())
+            .spawn(move || worker_loop(shared));
+        match spawned {
+            Ok(_) => {
+                queue.workers += 1;
+                Ok(())
+            }
+            Err(e) => {
+                crate::release_dll_ref();
+                Err(e)
+            }
+        }
+    }
+
+    /// Requests waiting for a worker
+    #[cfg(test)]
+    fn queued(&self) -> usize {
+        self.shared.queue.lock().unwrap().jobs.len()
+    }
+}
+
+/// Run queued jobs until the queue is empty or the service stops
+fn worker_loop(shared: Arc<Shared>) {
+    /// Releases the DLL reference taken in `submit` when the worker exits
+    struct DllRefGuard;
+    impl Drop for DllRefGuard {
+        fn drop(&mut self) {
+            crate::release_dll_ref();
+        }
+    }
+    let _dll_ref = DllRefGuard;
+
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            let next = if shared.stopping.load(Ordering::SeqCst) {
+                None
+            } else {
+                queue.jobs.pop_front()
+            };
+            match next {
+                Some(job) => job,
+                None => {
+                    queue.workers -= 1;
+                    return;
+                }
+            }
+        };
+
+        if job
+            .state
+            .compare_exchange(QUEUED, RUNNING, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            continue;
+        }
+        // A panicking job must not take its worker slot with it; the caller
+        // sees the dropped result as a cancellation. This only applies where
+        // panics unwind (tests, debug builds): the release profile sets
+        // `panic = "abort"`, so there a panic ends the host process and the
+        // crash guard's breadcrumb is what keeps the file from being retried.
+        let _ = catch_unwind(AssertUnwindSafe(job.run));
+        if job
+            .state
+            .compare_exchange(RUNNING, FINISHED, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            // Detached by `abandon`; the slot already belongs to another worker
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize};
+    use std::time::Instant;
+
+    fn service(
+        dir: &tempfile::TempDir,
+        workers: usize,
+        max_queued: usize,
+    ) -> Arc<ThumbnailService> {
+        let guard = CrashGuard::new(dir.path().join("crumbs"), 3);
+        Arc::new(ThumbnailService::new(
+            ServiceConfig {
+                workers,
+                max_queued,
+            },
+            guard,
+        ))
+    }
+
+    /// Occupy the only worker until the returned sender is dropped
+    fn block_worker(
+        service: &Arc<ThumbnailService>,
+        timeout: Option<Duration>,
+    ) -> (mpsc::Sender<()>, std::thread::JoinHandle<Result<()>>) {
+        let (release, gate) = mpsc::channel::<()>();
+        let (started_tx, started) = mpsc::channel();
+        let blocker = Arc::clone(service);
+        let handle = std::thread::spawn(move || {
+            blocker.run(None, timeout, move || {
+                started_tx.send(()).unwrap();
+                let _ = gate.recv();
+                Ok(())
+            })
+        });
+        started.recv().unwrap();
+        (release, handle)
+    }
+
+    /// Wait until `count` requests are queued
+    fn wait_queued(service: &ThumbnailService, count: usize) {
+        let start = Instant::now();
+        while service.queued() < count {
+            assert!(start.elapsed() < Duration::from_secs(5), "never queued");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_run_returns_job_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 2, 8);
+        let timeout = Some(Duration::from_secs(5));
+
+        assert_eq!(service.run(None, timeout, || Ok(42u32)).unwrap(), 42);
+        let result: Result<u32> = service.run(None, timeout, || Err(CbxError::NoImages));
+        assert!(matches!(result, Err(CbxError::NoImages)));
+    }
+
+    #[test]
+    fn test_cap_under_concurrent_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 2, 64);
+        let timeout = Some(Duration::from_secs(10));
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let callers: Vec<_> = (0..8)
+            .map(|i| {
+                let service = Arc::clone(&service);
+                let active = Arc::clone(&active);
+                let peak = Arc::clone(&peak);
+                std::thread::spawn(move || {
+                    service.run(None, timeout, move || {
+                        let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(20));
+                        active.fetch_sub(1, Ordering::SeqCst);
+                        Ok(i)
+                    })
+                })
+            })
+            .collect();
+
+        let mut results: Vec<i32> = callers
+            .into_iter()
+            .map(|caller| caller.join().unwrap().unwrap())
+            .collect();
+        results.sort_unstable();
+        assert_eq!(results, (0..8).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn test_queued_requests_run_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 8);
+        let timeout = Some(Duration::from_secs(10));
+        let (release, blocker) = block_worker(&service, timeout);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let waiting: Vec<_> = (1..=3)
+            .map(|i| {
+                let service_ref = Arc::clone(&service);
+                let order = Arc::clone(&order);
+                let caller = std::thread::spawn(move || {
+                    service_ref.run(None, timeout, move || {
+                        order.lock().unwrap().push(i);
+                        Ok(())
+                    })
+                });
+                wait_queued(&service, i);
+                caller
+            })
+            .collect();
+
+        drop(release);
+        blocker.join().unwrap().unwrap();
+        for caller in waiting {
+            caller.join().unwrap().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_timed_out_request_never_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 8);
+        let (release, blocker) = block_worker(&service, None);
+
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&ran);
+        let timeout = Some(Duration::from_millis(100));
+        let result = service.run(None, timeout, move || {
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        });
+        assert!(matches!(result, Err(CbxError::TimedOut)));
+
+        drop(release);
+        blocker.join().unwrap().unwrap();
+        service.run(None, timeout, || Ok(())).unwrap();
+        assert!(!ran.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_timed_out_running_request_frees_worker() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 8);
+        let (release, blocker) = block_worker(&service, Some(Duration::from_millis(50)));
+        assert!(matches!(blocker.join().unwrap(), Err(CbxError::TimedOut)));
+
+        // The hung request still holds its thread, but not the only slot
+        let timeout = Some(Duration::from_secs(5));
+        assert_eq!(service.run(None, timeout, || Ok(7)).unwrap(), 7);
+        drop(release);
+        assert_eq!(service.run(None, timeout, || Ok(8)).unwrap(), 8);
+    }
+
+    #[test]
+    fn test_cancel_pending_drops_queued_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 8);
+        let timeout = None;
+        let (release, blocker) = block_worker(&service, timeout);
+
+        let queued = Arc::clone(&service);
+        let caller = std::thread::spawn(move || queued.run(None, timeout, || Ok(())));
+        wait_queued(&service, 1);
+
+        service.cancel_pending();
+        assert!(matches!(caller.join().unwrap(), Err(CbxError::Cancelled)));
+        assert_eq!(service.queued(), 0);
+
+        drop(release);
+        blocker.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_stop_drops_queue_and_refuses_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 8);
+        let timeout = None;
+        let (release, blocker) = block_worker(&service, timeout);
+
+        let queued = Arc::clone(&service);
+        let caller = std::thread::spawn(move || queued.run(None, timeout, || Ok(())));
+        wait_queued(&service, 1);
+
+        service.stop();
+        assert!(matches!(caller.join().unwrap(), Err(CbxError::Cancelled)));
+        assert!(matches!(
+            service.run(None, timeout, || Ok(())),
+            Err(CbxError::Cancelled)
+        ));
+
+        // The running request still finishes
+        drop(release);
+        blocker.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_spawned_job_shares_the_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 8);
+        let timeout = None;
+        let (release, blocker) = block_worker(&service, timeout);
+
+        // Queued behind the request holding the only worker
+        let (done_tx, done) = mpsc::channel();
+        service
+            .spawn(None, move || done_tx.send(()).unwrap())
+            .unwrap();
+        wait_queued(&service, 1);
+        assert!(done.try_recv().is_err());
+
+        drop(release);
+        blocker.join().unwrap().unwrap();
+        done.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn test_full_queue_is_busy() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 1);
+        let timeout = None;
+        let (release, blocker) = block_worker(&service, timeout);
+
+        let queued = Arc::clone(&service);
+        let caller = std::thread::spawn(move || queued.run(None, timeout, || Ok(())));
+        wait_queued(&service, 1);
+
+        assert!(matches!(
+            service.run(None, timeout, || Ok(())),
+            Err(CbxError::Busy)
+        ));
+
+        drop(release);
+        blocker.join().unwrap().unwrap();
+        caller.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_inline_requests_share_the_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 8);
+        let (release, blocker) = block_worker(&service, None);
+
+        // A stream request waits for the busy slot like any other
+        let timeout = Some(Duration::from_millis(100));
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&ran);
+        let result = service.run_inline(None, timeout, move || {
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        });
+        assert!(matches!(result, Err(CbxError::TimedOut)));
+        assert!(!ran.load(Ordering::SeqCst));
+
+        drop(release);
+        blocker.join().unwrap().unwrap();
+
+        // Once the slot is free it runs on the calling thread, holding the slot
+        let caller = std::thread::current().id();
+        let (ran_on, waiting) = service
+            .run_inline(None, timeout, || {
+                let queued = Arc::clone(&service);
+                let waiting = std::thread::spawn(move || {
+                    queued.run(None, Some(Duration::from_secs(5)), || Ok(()))
+                });
+                wait_queued(&service, 1);
+                Ok((std::thread::current().id(), waiting))
+            })
+            .unwrap();
+        assert_eq!(ran_on, caller);
+        waiting.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_panicking_job_keeps_worker() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 8);
+        let timeout = Some(Duration::from_secs(5));
+
+        let result: Result<()> = service.run(None, timeout, || panic!("decoder bug"));
+        assert!(matches!(result, Err(CbxError::Cancelled)));
+        assert_eq!(service.run(None, timeout, || Ok(1)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_crash_guard_skips_blocked_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir, 1, 8);
+        let timeout = Some(Duration::from_secs(5));
+        let book = dir.path().join("Broken.cbz");
+
+        // Breadcrumbs left behind by three crashed attempts
+        let crashes = CrashGuard::new(dir.path().join("crumbs"), 3);
+        for _ in 0..3 {
+            std::mem::forget(crashes.begin(&book).unwrap());
+        }
+
+        let result = service.run(Some(&book), timeout, || Ok(()));
+        assert!(matches!(result, Err(CbxError::CrashGuard(3))));
+        let result = service.run_inline(Some(&book), timeout, || Ok(()));
+        assert!(matches!(result, Err(CbxError::CrashGuard(3))));
+
+        // Completed requests clear their breadcrumb
+        let other = dir.path().join("Other.cbz");
+        service.run(Some(&other), timeout, || Ok(())).unwrap();
+        assert_eq!(crashes.unfinished_attempts(&other), 0);
+    }
+}
@@ -0,0 +1,430 @@
+//! Typed view of the configuration key
+//!
+//! Every value under HKCU\Software\CBXShell-rs\{GUID} that shapes a thumbnail
+//! is a field here. A thumbnail request loads them once through a single key
+//! handle and carries the struct, instead of reopening the key per value.
+//! Per-archive data (cover overrides, `.cbxpass` files) stays in
+//! `archive::config`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+
+use crate::archive::config::{self, ConfigKey, ConfigSink, ConfigSource};
+use crate::archive::{
+    CoverStrategy, CoverVerification, ListOptions, NaturalSortMode, SortKey, SortOrder,
+};
+use crate::image_processor::decoder::DecodeOptions;
+use crate::image_processor::thumbnail::ResizeFilter;
+
+/// All settings read from the configuration key
+///
+/// Fields hold decoded values with the defaults of the `archive::config`
+/// readers applied; see those for the registry encoding of each.
+/// `Debug` shows whether a password is set, never the password.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Settings {
+    /// `Enabled`: master thumbnail switch
+    pub enabled: bool,
+    /// `NoSort` (inverted): pick the first image in natural order
+    pub sort: bool,
+    /// `NaturalSortMode`: comparison used when sorting
+    pub natural_sort_mode: NaturalSortMode,
+    /// `SortBy`: full path or file name only
+    pub sort_by: SortKey,
+    /// `MaxArchiveSizeMB`: skip larger archives (0 = unlimited)
+    pub max_archive_size_mb: u32,
+    /// `CoverStrategy`: strategy for extensions without their own
+    pub cover_strategy: CoverStrategy,
+    /// `CoverStrategyByExtension`: `extension=strategy` list
+    pub cover_strategy_by_extension: String,
+    /// `ForceThumbnailSize`: fixed thumbnail size (0 = Explorer's)
+    pub force_thumbnail_size: u32,
+    /// `MaxThumbnailDim`: upper bound on the thumbnail size (0 = none)
+    pub max_thumbnail_dim: u32,
+    /// `AllowUpscaleTo`: size up to which small covers are enlarged
+    pub allow_upscale_to: Option<u32>,
+    /// `GdiHalftone`: resize filter
+    pub resize_filter: ResizeFilter,
+    /// `Grayscale`: resize gray covers in a single luma channel
+    pub grayscale: bool,
+    /// `BookEffect`: frame thumbnails with a border and drop shadow
+    pub book_effect: bool,
+    /// `DecodeTimeoutMs`: decode watchdog (0 = none)
+    pub decode_timeout_ms: u32,
+    /// `VerifyFirstImage`: checks on first-image candidates
+    pub verify_first_image: CoverVerification,
+    /// `MaxNonImageScan`: non-image files scanned before giving up (0 = all)
+    pub max_non_image_scan: u32,
+    /// `NextImageOnMissingCodec`: fall back to later pages
+    pub next_image_on_missing_codec: bool,
+    /// `LenientDecode`: render truncated covers partially
+    pub lenient_decode: bool,
+    /// `CoverSkipStems`: page names passed over during cover selection
+    pub cover_skip_stems: Vec<String>,
+    /// `EmbeddedPreview` and `EmbeddedPreviewNames`: preview files looked up
+    /// before cover selection (empty = lookup disabled)
+    pub embedded_preview_names: Vec<String>,
+    /// `EventLog`: report failures to the Windows Event Log
+    pub event_log: bool,
+    /// `WarmCodecs`: load OS codecs on the first thumbnail request
+    pub warm_codecs: bool,
+    /// `Password`: decrypted password for encrypted archives
+    pub password: Option<String>,
+}
+
+impl Settings {
+    /// Read all settings through one handle on the configuration key
+    pub fn load() -> Self {
+        Self::load_from(&ConfigKey::open())
+    }
+
+    /// Read all settings from an explicit configuration source
+    pub fn load_from(source: &dyn ConfigSource) -> Self {
+        Self {
+            enabled: config::thumbnails_enabled_from(source),
+            sort: config::should_sort_images_from(source),
+            natural_sort_mode: config::natural_sort_mode_from(source),
+            sort_by: config::sort_by_from(source),
+            max_archive_size_mb: config::max_archive_size_mb_from(source),
+            cover_strategy: source
+                .get_dword(config::COVER_STRATEGY_VALUE)
+                .map(CoverStrategy::from_registry_value)
+                .unwrap_or_default(),
+            cover_strategy_by_extension: source
+                .get_string(config::COVER_STRATEGY_BY_EXTENSION_VALUE)
+                .unwrap_or_default(),
+            force_thumbnail_size: config::force_thumbnail_size_from(source),
+            max_thumbnail_dim: config::max_thumbnail_dim_from(source),
+            allow_upscale_to: config::allow_upscale_to_from(source),
+            resize_filter: config::resize_filter_from(source),
+            grayscale: config::grayscale_from(source),
+            book_effect: config::book_effect_from(source),
+            decode_timeout_ms: config::decode_timeout_ms_from(source),
+            verify_first_image: config::verify_first_image_from(source),
+            max_non_image_scan: config::max_non_image_scan_from(source),
+            next_image_on_missing_codec: config::next_image_on_missing_codec_from(source),
+            lenient_decode: config::lenient_decode_from(source),
+            cover_skip_stems: config::cover_skip_stems_from(source),
+            embedded_preview_names: config::embedded_preview_names_from(source),
+            event_log: config::event_log_enabled_from(source),
+            warm_codecs: config::warm_codecs_enabled_from(source),
+            password: config::password_from(source),
+        }
+    }
+
+    /// Write all settings to the configuration key, creating it if needed
+    pub fn save(&self) -> io::Result<()> {
+        self.save_to(&mut ConfigKey::create()?)
+    }
+
+    /// Write all settings to an explicit destination
+    ///
+    /// Values are written in their registry encoding, so `load_from` reads
+    /// back the same settings. `Lanczos3` has no registry value and is saved
+    /// as the default filter. The password is left alone; it is written
+    /// outside the settings, encrypted with DPAPI or as plain text.
+    pub fn save_to(&self, sink: &mut dyn ConfigSink) -> io::Result<()> {
+        sink.set_dword(config::ENABLED_VALUE, u32::from(self.enabled))?;
+        sink.set_dword(config::NO_SORT_VALUE, u32::from(!self.sort))?;
+        sink.set_dword(
+            config::NATURAL_SORT_MODE_VALUE,
+            self.natural_sort_mode.registry_value(),
+        )?;
+        sink.set_dword(config::SORT_BY_VALUE, self.sort_by.registry_value())?;
+        sink.set_dword(config::MAX_ARCHIVE_SIZE_VALUE, self.max_archive_size_mb)?;
+        sink.set_dword(
+            config::COVER_STRATEGY_VALUE,
+            self.cover_strategy.registry_value(),
+        )?;
+        sink.set_string(
+            config::COVER_STRATEGY_BY_EXTENSION_VALUE,
+            &self.cover_strategy_by_extension,
+        )?;
+        sink.set_dword(
+            config::FORCE_THUMBNAIL_SIZE_VALUE,
+            self.force_thumbnail_size,
+        )?;
+        sink.set_dword(config::MAX_THUMBNAIL_DIM_VALUE, self.max_thumbnail_dim)?;
+        sink.set_dword(
+            config::ALLOW_UPSCALE_TO_VALUE,
+            self.allow_upscale_to.unwrap_or(0),
+        )?;
+        sink.set_dword(
+            config::GDI_HALFTONE_VALUE,
+            u32::from(self.resize_filter == ResizeFilter::GdiHalftone),
+        )?;
+        sink.set_dword(config::GRAYSCALE_VALUE, u32::from(self.grayscale))?;
+        sink.set_dword(config::BOOK_EFFECT_VALUE, u32::from(self.book_effect))?;
+        sink.set_dword(config::DECODE_TIMEOUT_VALUE, self.decode_timeout_ms)?;
+        sink.set_dword(
+            config::VERIFY_FIRST_IMAGE_VALUE,
+            self.verify_first_image.registry_value(),
+        )?;
+        sink.set_dword(config::MAX_NON_IMAGE_SCAN_VALUE, self.max_non_image_scan)?;
+        sink.set_dword(
+            config::NEXT_IMAGE_ON_MISSING_CODEC_VALUE,
+            u32::from(self.next_image_on_missing_codec),
+        )?;
+        sink.set_dword(config::LENIENT_DECODE_VALUE, u32::from(self.lenient_decode))?;
+        sink.set_string(
+            config::COVER_SKIP_STEMS_VALUE,
+            &self.cover_skip_stems.join(";"),
+        )?;
+        sink.set_dword(
+            config::EMBEDDED_PREVIEW_VALUE,
+            u32::from(!self.embedded_preview_names.is_empty()),
+        )?;
+        if !self.embedded_preview_names.is_empty() {
+            sink.set_string(
+                config::EMBEDDED_PREVIEW_NAMES_VALUE,
+                &self.embedded_preview_names.join(";"),
+            )?;
+        }
+        sink.set_dword(config::EVENT_LOG_VALUE, u32::from(self.event_log))?;
+        sink.set_dword(config::WARM_CODECS_VALUE, u32::from(self.warm_codecs))
+    }
+
+    /// Order of entry names when sorting is enabled
+    pub fn sort_order(&self) -> SortOrder {
+        SortOrder {
+            mode: self.natural_sort_mode,
+            sort_by: self.sort_by,
+        }
+    }
+
+    /// Sort order and scan limit archives are opened with
+    pub fn list_options(&self) -> ListOptions {
+        ListOptions {
+            order: self.sort_order(),
+            max_non_image_scan: self.max_non_image_scan,
+        }
+    }
+
+    /// Decode watchdog and leniency for covers
+    pub fn decode_options(&self) -> DecodeOptions {
+        DecodeOptions {
+            lenient: self.lenient_decode,
+            timeout_ms: self.decode_timeout_ms,
+        }
+    }
+
+    /// Password for the encrypted archive at `path`
+    ///
+    /// A `.cbxpass` file in the archive's folder wins over `Password`.
+    /// Streams carry no folder, so `None` gets the configured password.
+    pub fn archive_password_for(&self, path: Option<&Path>) -> Option<String> {
+        path.and_then(Path::parent)
+            .and_then(config::folder_password)
+            .or_else(|| self.password.clone())
+    }
+
+    /// Cover selection strategy for the archive at `path`
+    pub fn cover_strategy_for(&self, path: Option<&Path>) -> CoverStrategy {
+        config::resolve_cover_strategy(&self.cover_strategy_by_extension, self.cover_strategy, path)
+    }
+
+    /// Fingerprint of everything that picks and decodes the cover of the
+    /// archive at `path`, including the user's cover override
+    ///
+    /// Part of the cover cache key: a cover decoded under other settings is
+    /// not reused.
+    pub fn cover_selection(&self, path: Option<&Path>, user_override: Option<&str>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.sort.hash(&mut hasher);
+        self.natural_sort_mode.registry_value().hash(&mut hasher);
+        self.sort_by.registry_value().hash(&mut hasher);
+        self.cover_strategy_for(path)
+            .registry_value()
+            .hash(&mut hasher);
+        self.verify_first_image.registry_value().hash(&mut hasher);
+        self.max_non_image_scan.hash(&mut hasher);
+        self.next_image_on_missing_codec.hash(&mut hasher);
+        self.lenient_decode.hash(&mut hasher);
+        self.cover_skip_stems.hash(&mut hasher);
+        self.embedded_preview_names.hash(&mut hasher);
+        user_override.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl fmt::Debug for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Settings")
+            .field("enabled", &self.enabled)
+            .field("sort", &self.sort)
+            .field("natural_sort_mode", &self.natural_sort_mode)
+            .field("sort_by", &self.sort_by)
+            .field("max_archive_size_mb", &self.max_archive_size_mb)
+            .field("cover_strategy", &self.cover_strategy)
+            .field(
+                "cover_strategy_by_extension",
+                &self.cover_strategy_by_extension,
+            )
+            .field("force_thumbnail_size", &self.force_thumbnail_size)
+            .field("max_thumbnail_dim", &self.max_thumbnail_dim)
+            .field("allow_upscale_to", &self.allow_upscale_to)
+            .field("resize_filter", &self.resize_filter)
+            .field("grayscale", &self.grayscale)
+            .field("book_effect", &self.book_effect)
+            .field("decode_timeout_ms", &self.decode_timeout_ms)
+            .field("verify_first_image", &self.verify_first_image)
+            .field("max_non_image_scan", &self.max_non_image_scan)
+            .field(
+                "next_image_on_missing_codec",
+                &self.next_image_on_missing_codec,
+            )
+            .field("lenient_decode", &self.lenient_decode)
+            .field("cover_skip_stems", &self.cover_skip_stems)
+            .field("embedded_preview_names", &self.embedded_preview_names)
+            .field("event_log", &self.event_log)
+            .field("warm_codecs", &self.warm_codecs)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::config::MemoryConfigSource;
+
+    #[test]
+    fn test_load_defaults() {
+        let settings = Settings::load_from(&MemoryConfigSource::default());
+        assert!(settings.enabled);
+        assert!(!settings.sort);
+        assert_eq!(settings.decode_timeout_ms, 5000);
+        assert_eq!(settings.max_non_image_scan, 500);
+        assert_eq!(settings.allow_upscale_to, None);
+        assert_eq!(settings.resize_filter, ResizeFilter::Triangle);
+        assert!(settings.embedded_preview_names.is_empty());
+        assert_eq!(
+            settings.cover_strategy_for(Some(Path::new("book.epub"))),
+            CoverStrategy::Opf
+        );
+    }
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let mut sink = MemoryConfigSource::default();
+        let defaults = Settings::load_from(&sink);
+        defaults.save_to(&mut sink).unwrap();
+        assert_eq!(Settings::load_from(&sink), defaults);
+
+        let settings = Settings {
+            enabled: false,
+            sort: true,
+            natural_sort_mode: NaturalSortMode::Windows,
+            sort_by: SortKey::BaseName,
+            max_archive_size_mb: 300,
+            cover_strategy: CoverStrategy::Portrait,
+            cover_strategy_by_extension: "cbz=largest".to_string(),
+            force_thumbnail_size: 128,
+            max_thumbnail_dim: 512,
+            allow_upscale_to: Some(256),
+            resize_filter: ResizeFilter::GdiHalftone,
+            grayscale: true,
+            book_effect: true,
+            decode_timeout_ms: 0,
+            verify_first_image: CoverVerification::Decode,
+            max_non_image_scan: 0,
+            next_image_on_missing_codec: true,
+            lenient_decode: true,
+            cover_skip_stems: vec!["intro".to_string(), "toc".to_string()],
+            embedded_preview_names: vec!["thumb.png".to_string()],
+            event_log: true,
+            warm_codecs: true,
+            password: None,
+        };
+        let mut sink = MemoryConfigSource::default();
+        settings.save_to(&mut sink).unwrap();
+        let loaded = Settings::load_from(&sink);
+        assert_eq!(loaded, settings);
+        assert_eq!(
+            loaded.cover_strategy_for(Some(Path::new("a.CBZ"))),
+            CoverStrategy::Largest
+        );
+        assert_eq!(
+            loaded.cover_strategy_for(Some(Path::new("a.cbr"))),
+            CoverStrategy::Portrait
+        );
+
+        // NoSort keeps its inverted encoding
+        assert_eq!(sink.get_dword(config::NO_SORT_VALUE), Some(0));
+    }
+
+    #[test]
+    fn test_archive_password_for() {
+        let settings = Settings {
+            password: Some("configured".to_string()),
+            ..Settings::load_from(&MemoryConfigSource::default())
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let book = dir.path().join("Book.cb7");
+        assert_eq!(
+            settings.archive_password_for(Some(&book)).as_deref(),
+            Some("configured")
+        );
+        assert_eq!(
+            settings.archive_password_for(None).as_deref(),
+            Some("configured")
+        );
+
+        std::fs::write(dir.path().join(config::PASSWORD_FILE_NAME), "series\n").unwrap();
+        assert_eq!(
+            settings.archive_password_for(Some(&book)).as_deref(),
+            Some("series")
+        );
+    }
+
+    #[test]
+    fn test_debug_redacts_password() {
+        let settings = Settings {
+            password: Some("hunter2".to_string()),
+            ..Settings::load_from(&MemoryConfigSource::default())
+        };
+
+        let debug = format!("{:?}", settings);
+        assert!(!debug.contains("hunter2"));
+        assert!(debug.contains("password: Some(\"<redacted>\")"));
+        assert!(debug.contains("warm_codecs: false"));
+    }
+
+    #[test]
+    fn test_cover_selection() {
+        let settings = Settings::load_from(&MemoryConfigSource::default());
+        let book = Some(Path::new("a.cbz"));
+        let selection = settings.cover_selection(book, None);
+        assert_eq!(settings.clone().cover_selection(book, None), selection);
+
+        // Whatever changes which page is picked changes the fingerprint
+        assert_ne!(settings.cover_selection(book, Some("05.jpg")), selection);
+        let changed = [
+            Settings {
+                sort: true,
+                ..settings.clone()
+            },
+            Settings {
+                cover_strategy: CoverStrategy::Largest,
+                ..settings.clone()
+            },
+            Settings {
+                cover_skip_stems: vec!["intro".to_string()],
+                ..settings.clone()
+            },
+        ];
+        for other in changed {
+            assert_ne!(other.cover_selection(book, None), selection);
+        }
+
+        // Sizing doesn't; the cached decode is resized per request
+        let resized = Settings {
+            max_thumbnail_dim: 128,
+            ..settings.clone()
+        };
+        assert_eq!(resized.cover_selection(book, None), selection);
+    }
+}
@@ -0,0 +1,165 @@
+//! Scoped per-thread COM initialization
+//!
+//! `ScopedCom` pairs `CoInitializeEx` with `CoUninitialize` so an early return
+//! or panic cannot leave a thread initialized. Explorer's thumbnail threads
+//! already have an apartment; nesting a guard there only bumps COM's
+//! per-thread init count.
+
+use std::marker::PhantomData;
+use windows::core::HRESULT;
+
+/// RAII guard initializing COM (apartment-threaded) for the current thread
+///
+/// `S_OK` and `S_FALSE` (already initialized in the same mode) are both
+/// balanced with `CoUninitialize` on drop. `RPC_E_CHANGED_MODE` means the
+/// thread already belongs to a multithreaded apartment owned by someone else;
+/// COM is still usable there, but the guard must not uninitialize it.
+#[derive(Debug)]
+pub struct ScopedCom {
+    /// Whether this guard's `CoInitializeEx` call succeeded and must be balanced
+    initialized: bool,
+    /// COM initialization is per-thread, so the guard must stay on its thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ScopedCom {
+    /// Initialize COM for the current thread until the guard is dropped
+    pub fn new() -> Self {
+        Self::from_hresult(initialize())
+    }
+
+    /// Wrap the `HRESULT` of a `CoInitializeEx` call
+    fn from_hresult(hr: HRESULT) -> Self {
+        if hr.is_err() {
+            tracing::debug!("COM already initialized in another mode or unavailable: {hr:?}");
+        }
+
+        Self {
+            initialized: hr.is_ok(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Whether this guard initialized COM and will uninitialize it on drop
+    #[cfg(test)]
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+}
+
+impl Default for ScopedCom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ScopedCom {
+    fn drop(&mut self) {
+        if self.initialized {
+            uninitialize();
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn initialize() -> HRESULT {
+    use windows::Win32::System::Com::{CoInitializeEx, COINIT_APARTMENTTHREADED};
+
+    // UNAVOIDABLE UNSAFE: COM runtime FFI (ole32.dll)
+    // Safety: no reserved pointer is passed; the matching CoUninitialize is
+    // issued by Drop on the same thread (the guard is !Send)
+    match unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) } {
+        // S_OK and S_FALSE both map to Ok and are balanced alike
+        Ok(()) => windows::Win32::Foundation::S_OK,
+        Err(e) => e.code(),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn initialize() -> HRESULT {
+    windows::Win32::Foundation::E_NOTIMPL
+}
+
+#[cfg(target_os = "windows")]
+fn uninitialize() {
+    // UNAVOIDABLE UNSAFE: COM runtime FFI (ole32.dll)
+    // Safety: only called for a successful CoInitializeEx on this thread
+    unsafe { windows::Win32::System::Com::CoUninitialize() }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn uninitialize() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::Win32::Foundation::{E_OUTOFMEMORY, RPC_E_CHANGED_MODE, S_FALSE, S_OK};
+
+    #[test]
+    fn test_changed_mode_is_not_balanced() {
+        let guard = ScopedCom::from_hresult(RPC_E_CHANGED_MODE);
+        assert!(!guard.is_initialized());
+
+        let guard = ScopedCom::from_hresult(E_OUTOFMEMORY);
+        assert!(!guard.is_initialized());
+    }
+
+    #[test]
+    fn test_already_initialized_is_balanced() {
+        // Forget the guards so no unmatched CoUninitialize runs on the test thread
+        for hr in [S_OK, S_FALSE] {
+            let guard = ScopedCom::from_hresult(hr);
+            assert!(guard.is_initialized());
+            std::mem::forget(guard);
+        }
+    }
+}
+
+#[cfg(all(test, windows, feature = "e2e-windows"))]
+mod e2e_tests {
+    use super::*;
+    use windows::Win32::Graphics::Imaging::{CLSID_WICImagingFactory, IWICImagingFactory};
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
+        COINIT_MULTITHREADED,
+    };
+
+    fn com_usable() -> bool {
+        let factory: windows::core::Result<IWICImagingFactory> =
+            unsafe { CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER) };
+        factory.is_ok()
+    }
+
+    #[test]
+    #[ignore = "requires Windows COM runtime"]
+    fn test_nested_guards_keep_apartment() {
+        std::thread::spawn(|| {
+            let outer = ScopedCom::new();
+            {
+                // S_FALSE: already initialized in the same mode
+                let inner = ScopedCom::new();
+                assert!(inner.is_initialized());
+            }
+            assert!(outer.is_initialized());
+            assert!(com_usable(), "inner drop must not tear down the apartment");
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    #[ignore = "requires Windows COM runtime"]
+    fn test_guard_leaves_foreign_mta_alone() {
+        std::thread::spawn(|| unsafe {
+            CoInitializeEx(None, COINIT_MULTITHREADED).unwrap();
+            {
+                let guard = ScopedCom::new();
+                assert!(!guard.is_initialized());
+            }
+            assert!(com_usable(), "guard must not uninitialize the MTA");
+            CoUninitialize();
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -0,0 +1,331 @@
+//! Crash guard for files that take down the thumbnail host
+//!
+//! A malformed archive that crashes the decoder kills Explorer (or the
+//! isolated thumbnail host) under `panic = "abort"`, and Explorer asks for the
+//! same thumbnail again as soon as it restarts. To break the loop, a
+//! breadcrumb naming the file is written before the archive is opened and
+//! removed once extraction returns, successfully or not. A breadcrumb that
+//! survives means the process died mid-extraction; after `CRASH_LIMIT` such
+//! attempts the file is skipped and Explorer shows its default icon.
+//!
+//! Breadcrumbs live in `%TEMP%\cbxshell_breadcrumbs`, one per file, and
+//! record the file's modification time: replacing the file retries it.
+//! Deleting the folder clears every skip.
+//!
+//! Once a file's extraction has completed in this process it is not guarded
+//! again until it changes: the risky part, decoding its cover, already
+//! succeeded, and Explorer asking for the same file at other sizes then costs
+//! no breadcrumb I/O.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+/// Unfinished attempts after which a file is skipped
+pub const CRASH_LIMIT: u32 = 3;
+
+const BREADCRUMB_DIR: &str = "cbxshell_breadcrumbs";
+const BREADCRUMB_EXTENSION: &str = "crumb";
+
+/// Completed files remembered before the set starts over
+const COMPLETED_CAPACITY: usize = 1024;
+
+/// Files, with their modification times, that completed in this process
+type Completed = Arc<Mutex<HashSet<(PathBuf, u64)>>>;
+
+/// Breadcrumb store in a directory
+pub struct CrashGuard {
+    dir: PathBuf,
+    limit: u32,
+    completed: Completed,
+}
+
+/// Record of an attempt in progress; removes its breadcrumb when dropped
+pub struct Breadcrumb {
+    file: Option<PathBuf>,
+    /// Marked completed on drop, unless the attempt is unwinding from a panic
+    completion: Option<(Completed, PathBuf, u64)>,
+}
+
+impl Drop for Breadcrumb {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.take() {
+            let _ = fs::remove_file(file);
+        }
+        if std::thread::panicking() {
+            return;
+        }
+        if let Some((completed, path, modified)) = self.completion.take() {
+            if let Ok(mut completed) = completed.lock() {
+                if completed.len() >= COMPLETED_CAPACITY {
+                    completed.clear();
+                }
+                completed.insert((path, modified));
+            }
+        }
+    }
+}
+
+/// Parsed breadcrumb contents
+struct Record {
+    attempts: u32,
+    modified: u64,
+    path: PathBuf,
+}
+
+impl Record {
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let attempts = lines.next()?.parse().ok()?;
+        let modified = lines.next()?.parse().ok()?;
+        let path = PathBuf::from(lines.next()?);
+        Some(Self {
+            attempts,
+            modified,
+            path,
+        })
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n",
+            self.attempts,
+            self.modified,
+            self.path.display()
+        )
+    }
+}
+
+impl CrashGuard {
+    /// Guard storing breadcrumbs in `dir`, skipping files after `limit` crashes
+    pub fn new(dir: PathBuf, limit: u32) -> Self {
+        Self {
+            dir,
+            limit,
+            completed: Arc::default(),
+        }
+    }
+
+    /// Guard used by the shell extension, under the temp directory
+    pub fn in_temp_dir() -> Self {
+        Self::new(std::env::temp_dir().join(BREADCRUMB_DIR), CRASH_LIMIT)
+    }
+
+    /// Crashed attempts after which a file is skipped
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Record an attempt at `path`
+    ///
+    /// Returns `None` when earlier attempts crashed `limit` times and the file
+    /// should be skipped. A breadcrumb that cannot be written doesn't block
+    /// extraction; the file just isn't guarded. Nothing is written for a file
+    /// that already completed in this process.
+    pub fn begin(&self, path: &Path) -> Option<Breadcrumb> {
+        let modified = modified_secs(path);
+        let key = (path.to_path_buf(), modified);
+        if self
+            .completed
+            .lock()
+            .is_ok_and(|completed| completed.contains(&key))
+        {
+            return Some(Breadcrumb {
+                file: None,
+                completion: None,
+            });
+        }
+
+        let attempts = self
+            .read(path)
+            .filter(|record| record.modified == modified)
+            .map(|record| record.attempts)
+            .unwrap_or(0);
+        if attempts >= self.limit {
+            return None;
+        }
+
+        let record = Record {
+            attempts: attempts + 1,
+            modified,
+            path: path.to_path_buf(),
+        };
+        let file = self.breadcrumb_file(path);
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&file, record.render()))
+            .is_ok();
+
+        Some(Breadcrumb {
+            file: written.then_some(file),
+            completion: Some((Arc::clone(&self.completed), key.0, key.1)),
+        })
+    }
+
+    /// Attempts at `path` that never completed
+    #[cfg(test)]
+    pub fn unfinished_attempts(&self, path: &Path) -> u32 {
+        self.read(path).map(|record| record.attempts).unwrap_or(0)
+    }
+
+    /// Files currently skipped because they crashed too often
+    pub fn blocked_files(&self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry.path().extension().and_then(|e| e.to_str()) == Some(BREADCRUMB_EXTENSION)
+            })
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .filter_map(|contents| Record::parse(&contents))
+            .filter(|record| record.attempts >= self.limit)
+            .map(|record| record.path)
+            .collect()
+    }
+
+    /// Log the files this guard skips (once, when the service starts)
+    pub fn log_blocked_files(&self) {
+        if !super::debug_log::ENABLED {
+            return;
+        }
+        for path in self.blocked_files() {
+            crate::debug_log!(
+                "Crash guard: skipping {:?} after {} unfinished attempts",
+                path,
+                self.limit
+            );
+        }
+    }
+
+    fn read(&self, path: &Path) -> Option<Record> {
+        let contents = fs::read_to_string(self.breadcrumb_file(path)).ok()?;
+        // Hash collisions: the breadcrumb belongs to another file
+        Record::parse(&contents).filter(|record| record.path == path)
+    }
+
+    fn breadcrumb_file(&self, path: &Path) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.{}", path_hash(path), BREADCRUMB_EXTENSION))
+    }
+}
+
+/// Modification time in whole seconds, 0 when unavailable
+fn modified_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// FNV-1a of the lowercased path
+///
+/// Stable across processes and builds, unlike `DefaultHasher`; Windows paths
+/// are case-insensitive.
+fn path_hash(path: &Path) -> u64 {
+    path.to_string_lossy()
+        .to_lowercase()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(dir: &tempfile::TempDir) -> CrashGuard {
+        CrashGuard::new(dir.path().join(BREADCRUMB_DIR), CRASH_LIMIT)
+    }
+
+    #[test]
+    fn test_breadcrumb_cleared_on_completion() {
+        let dir = tempfile::tempdir().unwrap();
+        let guard = guard(&dir);
+        let book = dir.path().join("Book.cbz");
+
+        let breadcrumb = guard.begin(&book).unwrap();
+        assert_eq!(guard.unfinished_attempts(&book), 1);
+        drop(breadcrumb);
+        assert_eq!(guard.unfinished_attempts(&book), 0);
+
+        // Completed attempts never add up to a skip
+        for _ in 0..CRASH_LIMIT + 1 {
+            assert!(guard.begin(&book).is_some());
+        }
+    }
+
+    #[test]
+    fn test_completed_file_is_not_guarded_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let guard = guard(&dir);
+        let book = dir.path().join("Book.cbz");
+        fs::write(&book, b"book").unwrap();
+
+        drop(guard.begin(&book).unwrap());
+        // No breadcrumb is written for the same file
+        let breadcrumb = guard.begin(&book).unwrap();
+        assert_eq!(guard.unfinished_attempts(&book), 0);
+        drop(breadcrumb);
+
+        // Other files are still guarded
+        let other = dir.path().join("Other.cbz");
+        let breadcrumb = guard.begin(&other).unwrap();
+        assert_eq!(guard.unfinished_attempts(&other), 1);
+        drop(breadcrumb);
+    }
+
+    #[test]
+    fn test_repeated_crashes_skip_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let guard = guard(&dir);
+        let book = dir.path().join("Broken.cbz");
+        let other = dir.path().join("Other.cbz");
+
+        // A crash leaves the breadcrumb behind
+        for attempt in 1..=CRASH_LIMIT {
+            std::mem::forget(guard.begin(&book).unwrap());
+            assert_eq!(guard.unfinished_attempts(&book), attempt);
+        }
+
+        assert!(guard.begin(&book).is_none());
+        assert!(guard.begin(&other).is_some());
+        assert_eq!(guard.blocked_files(), vec![book]);
+    }
+
+    #[test]
+    fn test_modified_file_is_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let guard = guard(&dir);
+        let book = dir.path().join("Broken.cbz");
+        fs::write(&book, b"broken").unwrap();
+
+        let stale = Record {
+            attempts: CRASH_LIMIT,
+            modified: modified_secs(&book) + 1,
+            path: book.clone(),
+        };
+        fs::create_dir_all(&guard.dir).unwrap();
+        fs::write(guard.breadcrumb_file(&book), stale.render()).unwrap();
+
+        assert!(guard.begin(&book).is_some());
+    }
+
+    #[test]
+    fn test_path_hash_ignores_case() {
+        assert_eq!(
+            path_hash(Path::new("C:\\Comics\\Book.CBZ")),
+            path_hash(Path::new("c:\\comics\\book.cbz"))
+        );
+        assert_ne!(
+            path_hash(Path::new("C:\\Comics\\Book 1.cbz")),
+            path_hash(Path::new("C:\\Comics\\Book 2.cbz"))
+        );
+    }
+}
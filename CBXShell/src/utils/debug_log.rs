@@ -2,16 +2,33 @@
 //!
 //! Provides file-based logging that persists across DLL loads/unloads
 //! to help diagnose why Windows Explorer may not be showing thumbnails.
+//!
+//! Every line is a file open and append, and lines carry archive paths, so
+//! the log is compiled in only for debug builds or with the `debug-log`
+//! feature. Otherwise the `debug_log!` macro expands to a branch on a false
+//! constant and neither formats nor touches the file system. `tracing`
+//! remains available for opt-in diagnostics either way.
 
+use std::ffi::OsString;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
 const DEBUG_LOG_FILENAME: &str = "cbxshell_debug.log";
 
-/// Global mutex to serialize log writes
-static LOG_MUTEX: Mutex<()> = Mutex::new(());
+/// Size past which the log is moved to `.old` and started afresh
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Writes between size checks, so most lines skip the metadata call
+const ROTATION_CHECK_INTERVAL: u32 = 256;
+
+/// Whether this build writes the debug log
+pub const ENABLED: bool = cfg!(any(debug_assertions, feature = "debug-log"));
+
+/// Global mutex to serialize log writes, counting writes left until the
+/// next size check
+static LOG_MUTEX: Mutex<u32> = Mutex::new(0);
 
 fn debug_log_path() -> PathBuf {
     if let Some(custom_path) = std::env::var_os("CBXSHELL_DEBUG_LOG_PATH") {
@@ -25,20 +42,48 @@ fn debug_log_path() -> PathBuf {
 ///
 /// This function is safe to call from any thread and will serialize writes.
 /// Errors are silently ignored to prevent logging from breaking functionality.
+/// Does nothing unless `ENABLED`; prefer `debug_log!`, which also skips
+/// formatting the message.
 pub fn debug_log(msg: &str) {
-    let Ok(_guard) = LOG_MUTEX.lock() else {
+    log_to(ENABLED, debug_log_path, msg);
+}
+
+/// Append `msg` to the log at `path()` if `enabled`
+///
+/// When disabled, neither takes the lock, resolves the path nor touches the
+/// file system.
+fn log_to(enabled: bool, path: impl FnOnce() -> PathBuf, msg: &str) {
+    if !enabled {
+        return;
+    }
+
+    let Ok(mut until_check) = LOG_MUTEX.lock() else {
         return;
     };
 
-    let path = debug_log_path();
+    write_line(&path(), msg, &mut until_check, MAX_LOG_BYTES);
+}
+
+/// Append one timestamped line to the log at `path`, rotating it first if it
+/// has grown past `max_bytes`
+///
+/// The size is checked on the first write and then every
+/// `ROTATION_CHECK_INTERVAL` writes, counted down in `until_check`.
+fn write_line(path: &Path, msg: &str, until_check: &mut u32, max_bytes: u64) {
     if let Some(parent) = path.parent() {
         let _ = std::fs::create_dir_all(parent);
     }
 
+    if *until_check == 0 {
+        rotate_if_larger(path, max_bytes);
+        *until_check = ROTATION_CHECK_INTERVAL;
+    }
+    *until_check -= 1;
+
     let _ = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&path)
+        .open(path)
         .and_then(|mut f| {
             use std::time::SystemTime;
 
@@ -51,14 +96,70 @@ pub fn debug_log(msg: &str) {
         });
 }
 
+/// Path the log is moved to on rotation (`cbxshell_debug.log.old`)
+fn rotated_log_path(path: &Path) -> PathBuf {
+    let mut rotated = OsString::from(path.as_os_str());
+    rotated.push(".old");
+    PathBuf::from(rotated)
+}
+
+/// Move the log at `path` to `.old`, replacing the previous one, once it
+/// exceeds `max_bytes`
+fn rotate_if_larger(path: &Path, max_bytes: u64) {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return;
+    };
+    if metadata.len() <= max_bytes {
+        return;
+    }
+
+    let rotated = rotated_log_path(path);
+    // Windows rename does not replace an existing file
+    let _ = std::fs::remove_file(&rotated);
+    let _ = std::fs::rename(path, &rotated);
+}
+
+/// Write the final log line and flush logging on DLL unload
+///
+/// `debug_log` opens and appends per call, so earlier lines are already
+/// durable; this writes `final_msg` and flushes stderr, where the tracing
+/// subscriber writes. Uses `try_lock` and skips the line if another write
+/// holds the lock, because during process termination the thread holding it
+/// may already be gone, and blocking inside DllMain would hang the process.
+pub fn shutdown_logging(final_msg: &str) {
+    if ENABLED {
+        shutdown_log_to(&LOG_MUTEX, &debug_log_path(), final_msg);
+    }
+
+    let _ = std::io::stderr().flush();
+    let _ = std::io::stdout().flush();
+}
+
+/// Append `final_msg` to the log at `path` unless `lock` is held
+fn shutdown_log_to(lock: &Mutex<u32>, path: &Path, final_msg: &str) {
+    if let Ok(mut until_check) = lock.try_lock() {
+        write_line(path, final_msg, &mut until_check, MAX_LOG_BYTES);
+    }
+}
+
+/// Log a formatted debug message, compiled out unless logging is `ENABLED`
+#[macro_export]
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        if $crate::utils::debug_log::ENABLED {
+            $crate::utils::debug_log::debug_log(&format!($($arg)*));
+        }
+    };
+}
+
 /// Log method entry with parameters
 #[macro_export]
 macro_rules! log_entry {
     ($method:expr) => {
-        $crate::utils::debug_log::debug_log(&format!("[ENTRY] {}", $method));
+        $crate::debug_log!("[ENTRY] {}", $method);
     };
     ($method:expr, $($arg:tt)*) => {
-        $crate::utils::debug_log::debug_log(&format!("[ENTRY] {} - {}", $method, format!($($arg)*)));
+        $crate::debug_log!("[ENTRY] {} - {}", $method, format!($($arg)*));
     };
 }
 
@@ -66,10 +167,10 @@ macro_rules! log_entry {
 #[macro_export]
 macro_rules! log_success {
     ($method:expr) => {
-        $crate::utils::debug_log::debug_log(&format!("[SUCCESS] {}", $method));
+        $crate::debug_log!("[SUCCESS] {}", $method);
     };
     ($method:expr, $($arg:tt)*) => {
-        $crate::utils::debug_log::debug_log(&format!("[SUCCESS] {} - {}", $method, format!($($arg)*)));
+        $crate::debug_log!("[SUCCESS] {} - {}", $method, format!($($arg)*));
     };
 }
 
@@ -77,7 +178,7 @@ macro_rules! log_success {
 #[macro_export]
 macro_rules! log_error {
     ($method:expr, $error:expr) => {
-        $crate::utils::debug_log::debug_log(&format!("[ERROR] {} - {}", $method, $error));
+        $crate::debug_log!("[ERROR] {} - {}", $method, $error);
     };
 }
 
@@ -95,6 +196,7 @@ mod tests {
     static TEST_LOG_MUTEX: Mutex<()> = Mutex::new(());
 
     #[test]
+    #[cfg_attr(not(any(debug_assertions, feature = "debug-log")), ignore)]
     fn test_debug_log_basic() {
         let _guard = TEST_LOG_MUTEX.lock().unwrap();
         clear_debug_log();
@@ -105,6 +207,132 @@ mod tests {
     }
 
     #[test]
+    fn test_shutdown_logging_writes_final_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEBUG_LOG_FILENAME);
+        let lock = Mutex::new(0);
+        write_line(
+            &path,
+            "Work before detach",
+            &mut lock.lock().unwrap(),
+            MAX_LOG_BYTES,
+        );
+
+        // Simulates the DLL_PROCESS_DETACH path
+        shutdown_log_to(&lock, &path, "===== DLL_PROCESS_DETACH - test =====");
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let last = contents.lines().last().unwrap();
+        assert!(last.ends_with("===== DLL_PROCESS_DETACH - test ====="));
+        assert!(contents.contains("Work before detach"));
+    }
+
+    #[test]
+    fn test_shutdown_logging_skips_held_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEBUG_LOG_FILENAME);
+        let lock = Mutex::new(0);
+
+        // A writer that never releases the lock must not block detach
+        let _held = lock.lock().unwrap();
+        shutdown_log_to(&lock, &path, "Dropped");
+
+        assert!(!path.exists());
+    }
+
+    /// Release build without `debug-log`: run with `cargo test --release`
+    #[test]
+    #[cfg(not(any(debug_assertions, feature = "debug-log")))]
+    fn test_disabled_log_creates_no_file() {
+        let _guard = TEST_LOG_MUTEX.lock().unwrap();
+        clear_debug_log();
+
+        debug_log("Direct call");
+        crate::debug_log!("Macro call {}", 1);
+        shutdown_logging("===== DLL_PROCESS_DETACH - test =====");
+
+        assert!(!ENABLED);
+        assert!(!debug_log_path().exists());
+    }
+
+    #[test]
+    fn test_disabled_log_does_no_io() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEBUG_LOG_FILENAME);
+
+        // The path is never even resolved, let alone created
+        log_to(
+            false,
+            || panic!("disabled log resolved its path"),
+            "Dropped",
+        );
+        assert!(!path.exists());
+
+        log_to(true, || path.clone(), "Kept");
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("Kept"));
+        assert!(!contents.contains("Dropped"));
+    }
+
+    #[test]
+    fn test_log_rotates_past_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEBUG_LOG_FILENAME);
+        let rotated = rotated_log_path(&path);
+        let max_bytes = 4096;
+        let mut until_check = 0;
+
+        let lines = 3 * ROTATION_CHECK_INTERVAL;
+        for i in 0..lines {
+            write_line(
+                &path,
+                &format!("Line {:04}", i),
+                &mut until_check,
+                max_bytes,
+            );
+        }
+
+        // Rotation happened, and the size stays within one check interval
+        assert!(rotated.exists());
+        let current = std::fs::read_to_string(&path).unwrap();
+        assert!(current.lines().count() <= ROTATION_CHECK_INTERVAL as usize);
+
+        // The newest lines survive: the latest in the live log, the ones just
+        // before it in `.old`
+        let last = format!("Line {:04}", lines - 1);
+        assert!(current.lines().last().unwrap().ends_with(&last));
+        let first_current = current.lines().next().unwrap();
+        let previous = std::fs::read_to_string(&rotated).unwrap();
+        let last_previous = previous.lines().last().unwrap();
+        let index = |line: &str| -> u32 { line[line.len() - 4..].parse().unwrap() };
+        assert_eq!(index(last_previous) + 1, index(first_current));
+    }
+
+    #[test]
+    fn test_log_below_threshold_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEBUG_LOG_FILENAME);
+        let mut until_check = 0;
+
+        for i in 0..ROTATION_CHECK_INTERVAL + 1 {
+            write_line(
+                &path,
+                &format!("Line {}", i),
+                &mut until_check,
+                MAX_LOG_BYTES,
+            );
+        }
+
+        assert!(!rotated_log_path(&path).exists());
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents.lines().count(),
+            ROTATION_CHECK_INTERVAL as usize + 1
+        );
+    }
+
+    #[test]
+    #[cfg_attr(not(any(debug_assertions, feature = "debug-log")), ignore)]
     fn test_debug_log_concurrent() {
         use std::thread;
 
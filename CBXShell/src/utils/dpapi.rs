@@ -0,0 +1,54 @@
+//! Per-user secret storage with DPAPI
+//!
+//! The archive password can be kept in the registry encrypted with
+//! `CryptProtectData` (for example by PowerShell's `ProtectedData.Protect`
+//! with `CurrentUser` scope), so only the same Windows user on the same
+//! machine can read it back. Copying the registry value elsewhere yields
+//! nothing usable.
+
+/// Decrypt a `CryptProtectData` blob, `None` if it isn't this user's
+#[cfg(windows)]
+pub fn unprotect(data: &[u8]) -> Option<Vec<u8>> {
+    use windows::Win32::Security::Cryptography::{
+        CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+
+    // UNAVOIDABLE UNSAFE: DPAPI is Windows FFI
+    // Safety guarantees:
+    // - input points at `data`, which outlives the call and is only read
+    // - output is allocated by DPAPI and released with LocalFree in take_blob
+    unsafe {
+        CryptUnprotectData(
+            &input,
+            None,
+            None,
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )
+        .ok()?;
+        Some(take_blob(output))
+    }
+}
+
+/// Copy a DPAPI output blob and free the original
+#[cfg(windows)]
+unsafe fn take_blob(blob: windows::Win32::Security::Cryptography::CRYPT_INTEGER_BLOB) -> Vec<u8> {
+    use windows::Win32::Foundation::{LocalFree, HLOCAL};
+
+    let bytes = std::slice::from_raw_parts(blob.pbData, blob.cbData as usize).to_vec();
+    let _ = LocalFree(HLOCAL(blob.pbData as _));
+    bytes
+}
+
+#[cfg(not(windows))]
+pub fn unprotect(_data: &[u8]) -> Option<Vec<u8>> {
+    None
+}
@@ -19,21 +19,148 @@ pub enum CbxError {
     #[error("Registry error: {0}")]
     Registry(String),
 
-    #[error("No image found in archive")]
-    NoImageFound,
+    #[error("No images found in archive")]
+    NoImages,
+
+    #[error("Archive is password protected")]
+    PasswordProtected,
+
+    /// The file ends before the archive's directory (e.g. a ZIP without its
+    /// end of central directory record)
+    #[error("Archive is truncated: {0}")]
+    Truncated(String),
+
+    /// A truncated archive modified seconds ago, most likely still being
+    /// written by a download or extraction
+    #[error("Archive is still being written")]
+    StillWriting,
+
+    /// The image needs an OS codec (e.g. AVIF) that is not installed
+    #[error("Image processing error: {0} codec not installed")]
+    CodecMissing(String),
 
     #[error("Unsupported format: {0}")]
     UnsupportedFormat(String),
 
     #[error("Invalid file path")]
     InvalidPath,
+
+    #[error("Archive too large: {size} bytes exceeds {limit_mb} MB limit")]
+    ArchiveTooLarge { size: u64, limit_mb: u32 },
+
+    #[error("Thumbnails are disabled")]
+    Disabled,
+
+    /// Earlier attempts at this file crashed the host (see `crash_guard`)
+    #[error("Skipped after {0} crashed attempts")]
+    CrashGuard(u32),
+
+    /// Too many thumbnail requests are already waiting (see `service`)
+    #[error("Thumbnail service busy")]
+    Busy,
+
+    #[error("Thumbnail request timed out")]
+    TimedOut,
+
+    #[error("Thumbnail request cancelled")]
+    Cancelled,
+
+    /// The file failed moments ago and is not retried yet (see `failure_cache`)
+    #[error("Skipped after a recent failure")]
+    RecentlyFailed {
+        kind: ErrorKind,
+        hint: Option<String>,
+    },
+}
+
+/// Stable failure category of a `CbxError`
+///
+/// Messages are meant for people and may change; library consumers branch on
+/// the kind instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Format or feature this build cannot handle
+    Unsupported,
+    /// Archive is damaged, truncated or not an archive at all
+    Corrupt,
+    /// Archive or entry needs a password
+    Encrypted,
+    /// Archive has nothing to show
+    NoImages,
+    /// An image was found but could not be decoded or scaled
+    DecodeFailed,
+    /// File system, registry or OS failure
+    Io,
+    /// Archive exceeds the configured size limit
+    TooLarge,
+    /// Thumbnails are switched off by the user
+    Disabled,
+}
+
+impl CbxError {
+    /// Failure category, for matching without inspecting messages
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            CbxError::Archive(_) | CbxError::Truncated(_) => ErrorKind::Corrupt,
+            CbxError::Image(_) | CbxError::CodecMissing(_) => ErrorKind::DecodeFailed,
+            CbxError::Io(_) | CbxError::Windows(_) | CbxError::Registry(_) => ErrorKind::Io,
+            CbxError::InvalidPath => ErrorKind::Io,
+            CbxError::NoImages => ErrorKind::NoImages,
+            CbxError::PasswordProtected => ErrorKind::Encrypted,
+            CbxError::UnsupportedFormat(_) => ErrorKind::Unsupported,
+            CbxError::ArchiveTooLarge { .. } => ErrorKind::TooLarge,
+            CbxError::Disabled => ErrorKind::Disabled,
+            // A file that keeps crashing the decoder is malformed
+            CbxError::CrashGuard(_) => ErrorKind::Corrupt,
+            CbxError::Busy | CbxError::Cancelled => ErrorKind::Io,
+            // Transient: the file will be complete shortly
+            CbxError::StillWriting => ErrorKind::Io,
+            // Covers too slow to decode, like DecodeTimeoutMs
+            CbxError::TimedOut => ErrorKind::DecodeFailed,
+            CbxError::RecentlyFailed { kind, .. } => *kind,
+        }
+    }
+
+    /// Why no thumbnail was produced, for failures the user can act on
+    ///
+    /// Shown in the tooltip. Transient and unexplained failures (I/O, corrupt
+    /// archives, generic decode errors) return `None`, and so does `Disabled`:
+    /// turning thumbnails off is the user's own choice.
+    pub fn thumbnail_hint(&self) -> Option<String> {
+        if let CbxError::RecentlyFailed { hint, .. } = self {
+            return hint.clone();
+        }
+        let hint = match (self.kind(), self) {
+            (_, CbxError::CodecMissing(codec)) => format!(
+                "{} codec not installed (install the {} Image Extension from the Microsoft Store)",
+                codec, codec
+            ),
+            (_, CbxError::StillWriting) => "archive is still being written".to_string(),
+            (ErrorKind::Encrypted, _) => "archive is password protected".to_string(),
+            (ErrorKind::TooLarge, _) => {
+                "archive exceeds the size limit set in CBXManager".to_string()
+            }
+            (ErrorKind::Unsupported, _) => format!("{}", self),
+            (ErrorKind::NoImages, _) => "archive contains no images".to_string(),
+            _ => return None,
+        };
+        Some(format!("Thumbnail unavailable: {}", hint))
+    }
 }
 
 impl From<CbxError> for HRESULT {
     fn from(err: CbxError) -> HRESULT {
         match err {
-            CbxError::NoImageFound => windows::Win32::Foundation::E_FAIL,
             CbxError::InvalidPath => windows::Win32::Foundation::E_INVALIDARG,
+            // Not a failure: the archive simply has nothing to show
+            CbxError::NoImages => windows::Win32::UI::Shell::WTS_E_FAILEDEXTRACTION,
+            CbxError::PasswordProtected => windows::Win32::UI::Shell::WTS_E_FAILEDEXTRACTION,
+            // Explorer shows the default icon when the provider fails
+            CbxError::ArchiveTooLarge { .. } => windows::Win32::Foundation::E_FAIL,
+            CbxError::Disabled => windows::Win32::Foundation::E_FAIL,
+            CbxError::RecentlyFailed { .. } => windows::Win32::Foundation::E_FAIL,
+            // Tells the thumbnail cache to ask again later
+            CbxError::StillWriting => windows::Win32::UI::Shell::WTS_E_EXTRACTIONPENDING,
             CbxError::Windows(e) => e.code(),
             _ => windows::Win32::Foundation::E_FAIL,
         }
@@ -41,3 +168,102 @@ impl From<CbxError> for HRESULT {
 }
 
 pub type Result<T> = std::result::Result<T, CbxError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind() {
+        let cases = [
+            (
+                CbxError::Archive("Invalid ZIP archive".to_string()),
+                ErrorKind::Corrupt,
+            ),
+            (
+                CbxError::Image("Failed to decode".to_string()),
+                ErrorKind::DecodeFailed,
+            ),
+            (
+                CbxError::CodecMissing("AVIF".to_string()),
+                ErrorKind::DecodeFailed,
+            ),
+            (
+                CbxError::Io(std::io::Error::from(std::io::ErrorKind::NotFound)),
+                ErrorKind::Io,
+            ),
+            (CbxError::NoImages, ErrorKind::NoImages),
+            (CbxError::PasswordProtected, ErrorKind::Encrypted),
+            (
+                CbxError::UnsupportedFormat("ARJ".to_string()),
+                ErrorKind::Unsupported,
+            ),
+            (
+                CbxError::ArchiveTooLarge {
+                    size: 2 << 30,
+                    limit_mb: 1024,
+                },
+                ErrorKind::TooLarge,
+            ),
+            (CbxError::Disabled, ErrorKind::Disabled),
+            (CbxError::CrashGuard(3), ErrorKind::Corrupt),
+            (CbxError::Busy, ErrorKind::Io),
+            (CbxError::TimedOut, ErrorKind::DecodeFailed),
+            (CbxError::Cancelled, ErrorKind::Io),
+            (
+                CbxError::Truncated("no central directory".to_string()),
+                ErrorKind::Corrupt,
+            ),
+            (CbxError::StillWriting, ErrorKind::Io),
+            (
+                CbxError::RecentlyFailed {
+                    kind: ErrorKind::Encrypted,
+                    hint: None,
+                },
+                ErrorKind::Encrypted,
+            ),
+        ];
+
+        for (error, kind) in cases {
+            assert_eq!(error.kind(), kind, "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_thumbnail_hint() {
+        let hint = CbxError::CodecMissing("AVIF".to_string())
+            .thumbnail_hint()
+            .unwrap();
+        assert!(hint.starts_with("Thumbnail unavailable: AVIF codec not installed"));
+
+        assert!(CbxError::PasswordProtected
+            .thumbnail_hint()
+            .unwrap()
+            .contains("password protected"));
+        assert!(CbxError::NoImages.thumbnail_hint().is_some());
+
+        // Nothing the user can do about these
+        assert_eq!(CbxError::Archive("bad".to_string()).thumbnail_hint(), None);
+        assert_eq!(CbxError::Image("bad".to_string()).thumbnail_hint(), None);
+        assert_eq!(CbxError::TimedOut.thumbnail_hint(), None);
+        // Nothing to explain
+        assert_eq!(CbxError::Disabled.thumbnail_hint(), None);
+    }
+
+    #[test]
+    fn test_kind_of_archive_errors() {
+        let kind_of = |data: Vec<u8>| {
+            crate::archive::open_archive_from_stream(std::io::Cursor::new(data))
+                .err()
+                .map(|e| e.kind())
+        };
+
+        // Unknown magic bytes
+        assert_eq!(kind_of(vec![0u8; 32]), Some(ErrorKind::Unsupported));
+
+        // ZIP signature followed by garbage
+        let mut truncated = b"PK\x03\x04".to_vec();
+        truncated.resize(64, 0xAB);
+        assert_eq!(kind_of(truncated), Some(ErrorKind::Corrupt));
+    }
+}
@@ -0,0 +1,128 @@
+//! Optional Windows Event Log reporting
+//!
+//! Thumbnail failures are otherwise silent (Explorer just shows a generic
+//! icon), which makes them hard to diagnose across many machines. When the
+//! `EventLog` registry flag is set, extraction and decode failures are written
+//! to the Application log under the "CBXShell" source.
+//!
+//! Off by default: a folder of broken archives would otherwise flood the log.
+
+use std::path::Path;
+
+use super::error::CbxError;
+
+/// Event source name (registered by `DllRegisterServer`)
+pub const EVENT_SOURCE: &str = "CBXShell";
+
+/// Event ID used for all CBXShell events
+const EVENT_ID: u32 = 1000;
+
+/// Severity of an event log entry
+///
+/// Failures are the only events written, always as warnings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventLevel {
+    Warning,
+}
+
+/// Format the event message for a failed thumbnail
+///
+/// Only the file name is included; full paths can leak user names into logs
+/// that are collected centrally.
+pub fn format_failure(path: &Path, error: &CbxError) -> String {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_else(|| path.to_string_lossy());
+
+    format!(
+        "Thumbnail extraction failed for \"{}\": {}",
+        file_name, error
+    )
+}
+
+/// Write an event
+///
+/// Callers check `Settings::event_log` first. Errors are ignored: reporting
+/// must never turn into a thumbnail failure.
+pub fn report_event(level: EventLevel, msg: &str) {
+    write_event(level, msg);
+}
+
+/// Report an extraction/decode failure as a warning event
+pub fn report_failure(path: &Path, error: &CbxError) {
+    report_event(EventLevel::Warning, &format_failure(path, error));
+}
+
+#[cfg(windows)]
+fn write_event(level: EventLevel, msg: &str) {
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::Foundation::PSID;
+    use windows::Win32::System::EventLog::{
+        DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_WARNING_TYPE,
+    };
+
+    let event_type = match level {
+        EventLevel::Warning => EVENTLOG_WARNING_TYPE,
+    };
+    let source = HSTRING::from(EVENT_SOURCE);
+    let message = HSTRING::from(msg);
+
+    // UNAVOIDABLE UNSAFE: Event Log API is Windows FFI
+    // Safety guarantees:
+    // - source and message are null-terminated and outlive the calls
+    // - The event source handle is deregistered exactly once
+    unsafe {
+        let Ok(handle) = RegisterEventSourceW(PCWSTR::null(), &source) else {
+            return;
+        };
+
+        let strings = [PCWSTR(message.as_ptr())];
+        let _ = ReportEventW(
+            handle,
+            event_type,
+            0,
+            EVENT_ID,
+            PSID::default(),
+            0,
+            Some(&strings),
+            None,
+        );
+
+        let _ = DeregisterEventSource(handle);
+    }
+}
+
+#[cfg(not(windows))]
+fn write_event(level: EventLevel, msg: &str) {
+    tracing::debug!("Event log ({:?}, id {}): {}", level, EVENT_ID, msg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_failure_uses_file_name() {
+        let error = CbxError::Image("Failed to decode image: truncated".to_string());
+        let path = Path::new("Users").join("alice").join("Book 01.cbz");
+
+        assert_eq!(
+            format_failure(&path, &error),
+            "Thumbnail extraction failed for \"Book 01.cbz\": \
+             Image processing error: Failed to decode image: truncated"
+        );
+    }
+
+    #[test]
+    fn test_format_failure_relative_name() {
+        let message = format_failure(Path::new("book.cbr"), &CbxError::NoImages);
+        assert_eq!(
+            message,
+            format!(
+                "Thumbnail extraction failed for \"book.cbr\": {}",
+                CbxError::NoImages
+            )
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Recent hard failures, so hopeless files are not retried at once
+//!
+//! Explorer asks again for a thumbnail it didn't get (scrolling, switching
+//! views, refreshing the details pane). A corrupt or unsupported archive
+//! fails the same way every time, so its failure is remembered for
+//! `FAILURE_COOLDOWN` and repeated requests fail immediately without opening
+//! the archive again.
+//!
+//! Entries are keyed by file path and last-modified time like the cover
+//! cache: a rewritten archive misses and is tried again. Transient failures
+//! (I/O, busy, timed out) are never recorded, and neither is a truncated
+//! archive modified within `WRITE_SETTLE_TIME`: that is a file still being
+//! downloaded or extracted, not a broken one.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::image_processor::cover_cache::CoverKey;
+use crate::utils::error::{CbxError, ErrorKind};
+
+/// How long a failed file is skipped
+pub const FAILURE_COOLDOWN: Duration = Duration::from_secs(120);
+
+/// Failed files remembered by the process-wide cache
+pub const FAILURE_CACHE_CAPACITY: usize = 256;
+
+/// Truncated files modified more recently than this count as still being written
+pub const WRITE_SETTLE_TIME: Duration = Duration::from_secs(10);
+
+/// Whether `error` will recur on the unchanged file
+///
+/// Damaged, encrypted, unsupported or image-less archives and undecodable
+/// covers qualify. The crash guard has its own bookkeeping.
+pub fn is_hard_failure(error: &CbxError) -> bool {
+    matches!(
+        error,
+        CbxError::Archive(_)
+            | CbxError::Image(_)
+            | CbxError::CodecMissing(_)
+            | CbxError::NoImages
+            | CbxError::PasswordProtected
+            | CbxError::Truncated(_)
+            | CbxError::UnsupportedFormat(_)
+    )
+}
+
+/// Turn a truncated-archive `error` into `CbxError::StillWriting` when the
+/// file was modified less than `WRITE_SETTLE_TIME` before `now`
+///
+/// A modification time in the future (clock skew) counts as recent.
+pub fn settle_partial_write(error: CbxError, modified: SystemTime, now: SystemTime) -> CbxError {
+    let recent = now
+        .duration_since(modified)
+        .map_or(true, |age| age < WRITE_SETTLE_TIME);
+    match error {
+        CbxError::Truncated(detail) if recent => {
+            tracing::info!(
+                "Truncated archive was just modified, retry later: {}",
+                detail
+            );
+            CbxError::StillWriting
+        }
+        error => error,
+    }
+}
+
+struct Failure {
+    modified: SystemTime,
+    failed_at: Instant,
+    kind: ErrorKind,
+    hint: Option<String>,
+}
+
+/// Size-bounded cache of recent hard failures
+pub struct FailureCache {
+    capacity: usize,
+    cooldown: Duration,
+    entries: HashMap<PathBuf, Failure>,
+    /// Paths, oldest failure first
+    order: VecDeque<PathBuf>,
+}
+
+impl FailureCache {
+    /// Remember at most `capacity` files, each for `cooldown`
+    pub fn new(capacity: usize, cooldown: Duration) -> Self {
+        Self {
+            capacity,
+            cooldown,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// The recorded failure of `key` if it is still cooling down at `now`
+    ///
+    /// Expired entries and entries for another modification time are dropped.
+    pub fn check(&mut self, key: &CoverKey, now: Instant) -> Option<CbxError> {
+        let failure = self.entries.get(key.path())?;
+        let cooling = failure.modified == key.modified()
+            && now.saturating_duration_since(failure.failed_at) < self.cooldown;
+        if !cooling {
+            self.remove(key);
+            return None;
+        }
+        Some(CbxError::RecentlyFailed {
+            kind: failure.kind,
+            hint: failure.hint.clone(),
+        })
+    }
+
+    /// Record a failure of `key` at `now`, evicting the oldest beyond capacity
+    ///
+    /// Only hard failures are kept; others are ignored.
+    pub fn record(&mut self, key: CoverKey, error: &CbxError, now: Instant) {
+        if !is_hard_failure(error) || self.capacity == 0 {
+            return;
+        }
+        self.remove(&key);
+        while self.order.len() >= self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+
+        self.order.push_back(key.path().to_path_buf());
+        self.entries.insert(
+            key.path().to_path_buf(),
+            Failure {
+                modified: key.modified(),
+                failed_at: now,
+                kind: error.kind(),
+                hint: error.thumbnail_hint(),
+            },
+        );
+    }
+
+    /// Number of remembered files
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn remove(&mut self, key: &CoverKey) {
+        if self.entries.remove(key.path()).is_some() {
+            self.order.retain(|path| path != key.path());
+        }
+    }
+}
+
+fn global() -> &'static Mutex<FailureCache> {
+    static CACHE: OnceLock<Mutex<FailureCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(FailureCache::new(FAILURE_CACHE_CAPACITY, FAILURE_COOLDOWN)))
+}
+
+/// The recent failure of `key` in the process-wide cache, if any
+pub fn lookup(key: &CoverKey) -> Option<CbxError> {
+    global().lock().ok()?.check(key, Instant::now())
+}
+
+/// Record a failure of `key` in the process-wide cache
+pub fn record(key: CoverKey, error: &CbxError) {
+    if let Ok(mut cache) = global().lock() {
+        cache.record(key, error, Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &str, secs: u64) -> CoverKey {
+        CoverKey::new(
+            PathBuf::from(path),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+        )
+    }
+
+    #[test]
+    fn test_records_hard_failures_only() {
+        let now = Instant::now();
+        let mut cache = FailureCache::new(8, Duration::from_secs(60));
+        assert!(cache.check(&key("a.cbz", 1), now).is_none());
+
+        cache.record(key("a.cbz", 1), &CbxError::NoImages, now);
+        let error = cache.check(&key("a.cbz", 1), now).unwrap();
+        assert_eq!(error.kind(), ErrorKind::NoImages);
+        assert_eq!(
+            error.thumbnail_hint().as_deref(),
+            Some("Thumbnail unavailable: archive contains no images")
+        );
+
+        // Transient failures are retried
+        cache.record(key("b.cbz", 1), &CbxError::Busy, now);
+        cache.record(key("c.cbz", 1), &CbxError::TimedOut, now);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_cooldown_expires() {
+        let now = Instant::now();
+        let mut cache = FailureCache::new(8, Duration::from_secs(60));
+        cache.record(key("a.cbz", 1), &CbxError::Archive("bad".to_string()), now);
+
+        let later = now + Duration::from_secs(59);
+        assert!(cache.check(&key("a.cbz", 1), later).is_some());
+        let expired = now + Duration::from_secs(60);
+        assert!(cache.check(&key("a.cbz", 1), expired).is_none());
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_modified_time_invalidates() {
+        let now = Instant::now();
+        let mut cache = FailureCache::new(8, Duration::from_secs(60));
+        cache.record(key("a.cbz", 1), &CbxError::PasswordProtected, now);
+
+        // Edited since the failure: tried again, and the entry is gone
+        assert!(cache.check(&key("a.cbz", 2), now).is_none());
+        assert!(cache.check(&key("a.cbz", 1), now).is_none());
+    }
+
+    #[test]
+    fn test_fresh_truncated_zip_is_retried() {
+        use std::io::Write;
+        use zip::write::{FileOptions, ZipWriter};
+
+        let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.start_file("01.jpg", FileOptions::default()).unwrap();
+        zip.write_all(&[0xAB; 4096]).unwrap();
+        let zip_data = zip.finish().unwrap().into_inner();
+        let file = tempfile::Builder::new().suffix(".cbz").tempfile().unwrap();
+        std::fs::write(file.path(), &zip_data[..zip_data.len() / 2]).unwrap();
+
+        let open_error = || crate::archive::open_archive(file.path()).err().unwrap();
+        let key = CoverKey::for_file(file.path()).unwrap();
+        let now = Instant::now();
+        let mut cache = FailureCache::new(8, Duration::from_secs(60));
+
+        // Modified seconds ago: still being written, so not recorded
+        let fresh = key.modified() + Duration::from_secs(2);
+        let error = settle_partial_write(open_error(), key.modified(), fresh);
+        assert!(matches!(error, CbxError::StillWriting), "{}", error);
+        cache.record(key.clone(), &error, now);
+        assert!(cache.check(&key, now).is_none());
+
+        // Truncated long ago: broken for good
+        let old = key.modified() + Duration::from_secs(3600);
+        let error = settle_partial_write(open_error(), key.modified(), old);
+        assert!(matches!(error, CbxError::Truncated(_)), "{}", error);
+        cache.record(key.clone(), &error, now);
+        assert_eq!(cache.check(&key, now).unwrap().kind(), ErrorKind::Corrupt);
+
+        // Other failures are left alone however recent
+        let error = settle_partial_write(CbxError::NoImages, key.modified(), fresh);
+        assert!(matches!(error, CbxError::NoImages));
+    }
+
+    #[test]
+    fn test_unopenable_file_is_retried() {
+        // A file that can't be opened (locked, missing) is not a broken archive
+        let dir = tempfile::tempdir().unwrap();
+        let now = Instant::now();
+        let mut cache = FailureCache::new(8, Duration::from_secs(60));
+        for name in ["gone.cbz", "gone.cb7"] {
+            let error = crate::archive::open_archive(&dir.path().join(name))
+                .err()
+                .unwrap();
+            assert!(matches!(error, CbxError::Io(_)), "{}", error);
+            cache.record(key(name, 1), &error, now);
+        }
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_bounded_by_capacity() {
+        let now = Instant::now();
+        let mut cache = FailureCache::new(2, Duration::from_secs(60));
+        for path in ["a.cbz", "b.cbz", "c.cbz"] {
+            cache.record(key(path, 1), &CbxError::NoImages, now);
+        }
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.check(&key("a.cbz", 1), now).is_none());
+        assert!(cache.check(&key("c.cbz", 1), now).is_some());
+    }
+}
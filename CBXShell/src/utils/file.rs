@@ -1,13 +1,15 @@
 use crate::archive::ArchiveType;
 use crate::utils::error::{CbxError, Result};
 ///! File system utility functions
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use widestring::U16CString;
 use windows::core::PCWSTR;
 use windows::Win32::Foundation::FILETIME;
 use windows::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
 use windows::Win32::Storage::FileSystem::{
-    CreateFileW, GetFileTime, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, OPEN_EXISTING,
+    CreateFileW, GetFileAttributesW, GetFileTime, FILE_ATTRIBUTE_NORMAL,
+    FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS, FILE_ATTRIBUTE_RECALL_ON_OPEN, FILE_SHARE_READ,
+    INVALID_FILE_ATTRIBUTES, OPEN_EXISTING,
 };
 
 /// Get the last modified time of a file
@@ -108,6 +110,131 @@ pub fn detect_archive_type(path: &Path) -> Result<ArchiveType> {
         .ok_or_else(|| CbxError::UnsupportedFormat(extension.to_string()))
 }
 
+/// Longest plain (non-verbatim) Windows path
+const MAX_PLAIN_PATH_LEN: usize = 260;
+
+/// Resolve symlinks and junctions in an archive path
+///
+/// Falls back to the original path if canonicalization fails (unreachable
+/// shares, paths the file system cannot resolve). On Windows `canonicalize`
+/// returns a verbatim `\\?\` path; it is turned back into a plain path when it
+/// fits in `MAX_PLAIN_PATH_LEN`, since not every archive backend accepts the prefix.
+pub fn resolve_archive_path(path: &Path) -> PathBuf {
+    match std::fs::canonicalize(path) {
+        Ok(resolved) => {
+            let plain = resolved.to_str().and_then(strip_verbatim_prefix);
+            match plain {
+                Some(plain) if plain.len() < MAX_PLAIN_PATH_LEN => PathBuf::from(plain),
+                _ => resolved,
+            }
+        }
+        Err(e) => {
+            tracing::debug!("Failed to canonicalize {:?}, using as is: {}", path, e);
+            path.to_path_buf()
+        }
+    }
+}
+
+/// Convert a verbatim `\\?\C:\...` or `\\?\UNC\...` path to its plain form
+///
+/// Returns `None` for paths without a drive or UNC verbatim prefix.
+fn strip_verbatim_prefix(path: &str) -> Option<String> {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        return Some(format!(r"\\{}", rest));
+    }
+
+    let rest = path.strip_prefix(r"\\?\")?;
+    let bytes = rest.as_bytes();
+    let is_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    is_drive.then(|| rest.to_string())
+}
+
+/// Win32 error codes for a file held open or locked by another process
+const ERROR_SHARING_VIOLATION: i32 = 32;
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+/// Attempts made by `open_file_with_retry` while a file is locked
+const LOCKED_OPEN_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry of a locked file; doubles on each retry
+const LOCKED_OPEN_BACKOFF: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Whether an open failed because another process holds the file
+///
+/// Antivirus scanners briefly open new downloads exclusively, which shows up
+/// as a sharing or lock violation rather than a real error.
+pub fn is_lock_violation(error: &std::io::Error) -> bool {
+    matches!(
+        error.raw_os_error(),
+        Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION)
+    )
+}
+
+/// Open a file for reading, retrying briefly while another process locks it
+///
+/// Other errors are returned immediately; after the last attempt the lock
+/// violation itself is returned.
+pub fn open_file_with_retry(path: &Path) -> std::io::Result<std::fs::File> {
+    retry_while_locked(
+        || std::fs::File::open(path),
+        LOCKED_OPEN_ATTEMPTS,
+        LOCKED_OPEN_BACKOFF,
+        path,
+    )
+}
+
+fn retry_while_locked<T, F>(
+    mut open: F,
+    attempts: u32,
+    backoff: std::time::Duration,
+    path: &Path,
+) -> std::io::Result<T>
+where
+    F: FnMut() -> std::io::Result<T>,
+{
+    let mut delay = backoff;
+    let mut attempt = 1;
+
+    loop {
+        match open() {
+            Err(e) if attempt < attempts && is_lock_violation(&e) => {
+                tracing::info!(
+                    "{:?} is locked by another process (attempt {}/{}), retrying in {:?}: {}",
+                    path,
+                    attempt,
+                    attempts,
+                    delay,
+                    e
+                );
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Attributes the cloud files API sets on placeholders whose data is not local
+const CLOUD_RECALL_ATTRIBUTES: u32 =
+    FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS.0 | FILE_ATTRIBUTE_RECALL_ON_OPEN.0;
+
+/// Whether `attributes` mark a cloud placeholder (an online-only OneDrive file)
+pub fn is_cloud_placeholder_attributes(attributes: u32) -> bool {
+    attributes != INVALID_FILE_ATTRIBUTES && attributes & CLOUD_RECALL_ATTRIBUTES != 0
+}
+
+/// Whether `path` is a cloud placeholder that opening would download in full
+///
+/// Only the attributes are read, which never triggers hydration.
+pub fn is_cloud_placeholder(path: &Path) -> bool {
+    let Ok(wide_path) = U16CString::from_os_str(path.as_os_str()) else {
+        return false;
+    };
+    let attributes = unsafe { GetFileAttributesW(PCWSTR(wide_path.as_ptr())) };
+    is_cloud_placeholder_attributes(attributes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,6 +242,27 @@ mod tests {
     use std::io::Write;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_is_cloud_placeholder_attributes() {
+        const FILE_ATTRIBUTE_ARCHIVE: u32 = 0x20;
+        const FILE_ATTRIBUTE_PINNED: u32 = 0x80000;
+
+        // Online-only and "download on open" placeholders
+        assert!(is_cloud_placeholder_attributes(
+            FILE_ATTRIBUTE_ARCHIVE | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS.0
+        ));
+        assert!(is_cloud_placeholder_attributes(
+            FILE_ATTRIBUTE_RECALL_ON_OPEN.0
+        ));
+
+        // Local and always-available files are opened directly
+        assert!(!is_cloud_placeholder_attributes(FILE_ATTRIBUTE_ARCHIVE));
+        assert!(!is_cloud_placeholder_attributes(
+            FILE_ATTRIBUTE_ARCHIVE | FILE_ATTRIBUTE_PINNED
+        ));
+        assert!(!is_cloud_placeholder_attributes(INVALID_FILE_ATTRIBUTES));
+    }
+
     #[test]
     fn test_get_file_modified_time() {
         // Create a temporary file
@@ -229,4 +377,99 @@ mod tests {
             ArchiveType::SevenZip
         );
     }
+
+    #[test]
+    fn test_strip_verbatim_prefix() {
+        assert_eq!(
+            strip_verbatim_prefix(r"\\?\C:\Comics\book.cbz").as_deref(),
+            Some(r"C:\Comics\book.cbz")
+        );
+        assert_eq!(
+            strip_verbatim_prefix(r"\\?\UNC\server\share\book.cbr").as_deref(),
+            Some(r"\\server\share\book.cbr")
+        );
+        assert_eq!(strip_verbatim_prefix(r"\\?\Volume{1234}\book.cbz"), None);
+        assert_eq!(strip_verbatim_prefix(r"C:\Comics\book.cbz"), None);
+    }
+
+    #[test]
+    fn test_resolve_archive_path_falls_back_when_missing() {
+        let missing = Path::new("no-such-dir").join("book.cbz");
+        assert_eq!(resolve_archive_path(&missing), missing);
+    }
+
+    #[test]
+    fn test_retry_while_locked_recovers() {
+        let mut calls = 0;
+        let result = retry_while_locked(
+            || {
+                calls += 1;
+                if calls < 3 {
+                    Err(std::io::Error::from_raw_os_error(ERROR_SHARING_VIOLATION))
+                } else {
+                    Ok(calls)
+                }
+            },
+            3,
+            std::time::Duration::from_millis(1),
+            Path::new("book.cbz"),
+        );
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_retry_while_locked_gives_up() {
+        let mut calls = 0;
+        let result: std::io::Result<()> = retry_while_locked(
+            || {
+                calls += 1;
+                Err(std::io::Error::from_raw_os_error(ERROR_LOCK_VIOLATION))
+            },
+            3,
+            std::time::Duration::from_millis(1),
+            Path::new("book.cbz"),
+        );
+        assert!(is_lock_violation(&result.unwrap_err()));
+        assert_eq!(calls, 3);
+
+        // Other errors are not retried
+        let mut calls = 0;
+        let result: std::io::Result<()> = retry_while_locked(
+            || {
+                calls += 1;
+                Err(std::io::ErrorKind::NotFound.into())
+            },
+            3,
+            std::time::Duration::from_millis(1),
+            Path::new("book.cbz"),
+        );
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(calls, 1);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_open_file_with_retry_waits_for_exclusive_lock() {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("locked.cbz");
+        std::fs::write(&path, b"archive").unwrap();
+
+        // Share mode 0: every other open fails with ERROR_SHARING_VIOLATION
+        let lock = std::fs::OpenOptions::new()
+            .read(true)
+            .share_mode(0)
+            .open(&path)
+            .unwrap();
+        let err = std::fs::File::open(&path).unwrap_err();
+        assert!(is_lock_violation(&err), "{}", err);
+
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            drop(lock);
+        });
+        assert!(open_file_with_retry(&path).is_ok());
+        release.join().unwrap();
+    }
 }
@@ -1,3 +1,8 @@
+pub mod com;
+pub mod crash_guard;
 pub mod debug_log;
+pub mod dpapi;
 pub mod error;
+pub mod event_log;
+pub mod failure_cache;
 pub mod file;
@@ -0,0 +1,67 @@
+//! Integration test for PNG cover output
+//! Verifies that the returned bytes are a PNG of the scaled cover
+
+use std::io::Write;
+use std::path::Path;
+
+use cbxshell::cover_png;
+use image::{ImageFormat, Rgba, RgbaImage};
+use tempfile::Builder;
+use zip::write::{FileOptions, ZipWriter};
+
+fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+    let img = RgbaImage::from_pixel(width, height, Rgba([0, 128, 255, 255]));
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut buffer, ImageFormat::Png).unwrap();
+    buffer.into_inner()
+}
+
+fn write_cbz(path: &Path, entries: &[(&str, &[u8])]) {
+    let mut zip = ZipWriter::new(std::fs::File::create(path).unwrap());
+    for (name, data) in entries {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(data).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+#[test]
+fn test_cover_png_is_scaled_png() {
+    let temp_file = Builder::new()
+        .prefix("test_cover_png_")
+        .suffix(".cbz")
+        .tempfile()
+        .unwrap();
+    write_cbz(
+        temp_file.path(),
+        &[
+            ("page1.png", &png_bytes(400, 600)),
+            ("page2.png", &png_bytes(600, 400)),
+        ],
+    );
+
+    let bytes = cover_png(temp_file.path(), 150).unwrap();
+    assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Png);
+
+    let decoded = image::load_from_memory_with_format(&bytes, ImageFormat::Png).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (100, 150));
+}
+
+#[test]
+fn test_cover_png_never_upscales() {
+    let temp_file = Builder::new()
+        .prefix("test_cover_png_small_")
+        .suffix(".cbz")
+        .tempfile()
+        .unwrap();
+    write_cbz(temp_file.path(), &[("cover.png", &png_bytes(20, 30))]);
+
+    let bytes = cover_png(temp_file.path(), 256).unwrap();
+    let decoded = image::load_from_memory(&bytes).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (20, 30));
+}
+
+#[test]
+fn test_cover_png_missing_file() {
+    assert!(cover_png(Path::new("does_not_exist.cbz"), 256).is_err());
+}
@@ -0,0 +1,47 @@
+//! Integration test for opening archives held in memory
+//! Verifies that a CBZ read from a byte buffer yields its cover
+
+use std::io::Write;
+
+use cbxshell::{open_archive_from_bytes, ArchiveType, CbxError, ImageFormat};
+use image::{Rgba, RgbaImage};
+use zip::write::{FileOptions, ZipWriter};
+
+fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+    let img = RgbaImage::from_pixel(width, height, Rgba([0, 128, 255, 255]));
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut buffer, image::ImageFormat::Png).unwrap();
+    buffer.into_inner()
+}
+
+fn cbz_bytes(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, data) in entries {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(data).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_open_cbz_from_bytes() {
+    let cover = png_bytes(40, 60);
+    let data = cbz_bytes(&[
+        ("page2.png", &png_bytes(60, 40)),
+        ("page1.png", &cover),
+        ("info.txt", b"not an image"),
+    ]);
+
+    let archive = open_archive_from_bytes(data).unwrap();
+    assert_eq!(archive.archive_type(), ArchiveType::Zip);
+
+    let (bytes, format) = archive.read_cover(true).unwrap();
+    assert_eq!(format, ImageFormat::Png);
+    assert_eq!(bytes, cover);
+}
+
+#[test]
+fn test_open_garbage_from_bytes_fails() {
+    let result = open_archive_from_bytes(b"definitely not an archive".to_vec());
+    assert!(matches!(result, Err(CbxError::UnsupportedFormat(_))));
+}
@@ -0,0 +1,28 @@
+//! Integration test for the supported format lists
+//! Verifies that the formats every build handles are always reported
+
+use cbxshell::{supported_archive_types, supported_image_formats, ArchiveType, ImageFormat};
+
+#[test]
+fn test_base_archive_types_present() {
+    let types = supported_archive_types();
+    for archive_type in [ArchiveType::Zip, ArchiveType::Rar, ArchiveType::SevenZip] {
+        assert!(types.contains(&archive_type), "missing {:?}", archive_type);
+    }
+}
+
+#[test]
+fn test_base_image_formats_present() {
+    let formats = supported_image_formats();
+    for format in [
+        ImageFormat::Jpeg,
+        ImageFormat::Png,
+        ImageFormat::Gif,
+        ImageFormat::Bmp,
+        ImageFormat::Tiff,
+        ImageFormat::Ico,
+        ImageFormat::WebP,
+    ] {
+        assert!(formats.contains(&format), "missing {:?}", format);
+    }
+}
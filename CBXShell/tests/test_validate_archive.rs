@@ -0,0 +1,111 @@
+//! Integration test for archive validation
+//! Verifies that usable, image-less and encrypted archives are told apart
+
+use std::io::Write;
+use std::path::Path;
+
+use cbxshell::{validate_archive, ArchiveType, CbxError};
+use tempfile::Builder;
+use zip::write::{FileOptions, ZipWriter};
+use zip::CompressionMethod;
+
+/// PNG signature; validation never decodes entries
+const PNG_MAGIC: &[u8] = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+    let mut zip = ZipWriter::new(std::fs::File::create(path).unwrap());
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    for (name, data) in entries {
+        zip.start_file(*name, options).unwrap();
+        zip.write_all(data).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+/// Set the "encrypted" general purpose flag on every entry
+fn mark_encrypted(path: &Path) {
+    let mut data = std::fs::read(path).unwrap();
+    for i in 0..data.len().saturating_sub(10) {
+        match &data[i..i + 4] {
+            b"PK\x03\x04" => data[i + 6] |= 1,
+            b"PK\x01\x02" => data[i + 8] |= 1,
+            _ => {}
+        }
+    }
+    std::fs::write(path, data).unwrap();
+}
+
+fn temp_cbz(prefix: &str) -> tempfile::NamedTempFile {
+    Builder::new()
+        .prefix(prefix)
+        .suffix(".cbz")
+        .tempfile()
+        .unwrap()
+}
+
+#[test]
+fn test_validate_cbz() {
+    let file = temp_cbz("validate_ok_");
+    write_zip(
+        file.path(),
+        &[
+            ("ComicInfo.xml", b"<ComicInfo/>"),
+            ("page1.png", PNG_MAGIC),
+            ("page2.png", PNG_MAGIC),
+        ],
+    );
+
+    let metadata = validate_archive(file.path()).unwrap();
+    assert_eq!(metadata.archive_type, ArchiveType::Zip);
+    assert_eq!(metadata.total_files, 3);
+    assert_eq!(metadata.image_count, 2);
+}
+
+#[test]
+fn test_validate_imageless_zip() {
+    let file = temp_cbz("validate_no_images_");
+    write_zip(
+        file.path(),
+        &[("ComicInfo.xml", b"<ComicInfo/>"), ("readme.txt", b"text")],
+    );
+
+    assert!(matches!(
+        validate_archive(file.path()),
+        Err(CbxError::NoImages)
+    ));
+}
+
+#[test]
+fn test_validate_encrypted_zip() {
+    let file = temp_cbz("validate_encrypted_");
+    write_zip(file.path(), &[("page1.png", PNG_MAGIC)]);
+    mark_encrypted(file.path());
+
+    assert!(matches!(
+        validate_archive(file.path()),
+        Err(CbxError::PasswordProtected)
+    ));
+}
+
+#[test]
+fn test_validate_not_an_archive() {
+    let file = temp_cbz("validate_garbage_");
+    std::fs::write(file.path(), b"not a zip file").unwrap();
+
+    assert!(validate_archive(file.path()).is_err());
+}
+
+#[test]
+fn test_validate_zip_named_rar() {
+    let file = Builder::new()
+        .prefix("validate_mislabeled_")
+        .suffix(".rar")
+        .tempfile()
+        .unwrap();
+    write_zip(file.path(), &[("page1.png", PNG_MAGIC)]);
+
+    // Opened by its header, not its extension
+    let metadata = validate_archive(file.path()).unwrap();
+    assert_eq!(metadata.archive_type, ArchiveType::Zip);
+    assert_eq!(metadata.image_count, 1);
+}